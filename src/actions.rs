@@ -0,0 +1,436 @@
+use crate::types::{Snippet, SshConfig};
+use egui::{Key, KeyboardShortcut, Modifiers};
+use std::collections::BTreeMap;
+
+// ============================================================================
+// 动作注册表与快捷键 (v0.4.0)
+// ============================================================================
+
+/// 应用内可触发的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    CommandPalette,
+    NewConnection,
+    NewLocalShell,
+    CloseTab,
+    ConnectSelected,
+    DisconnectSelected,
+    SaveConfig,
+    OpenFileBrowser,
+    OpenTransfers,
+    HistorySearch,
+    OpenSettings,
+    ClearTerminal,
+    ExportSession,
+    ToggleTerminalPassthrough,
+    Quit,
+}
+
+impl Action {
+    /// 所有动作（按显示顺序）
+    pub const ALL: [Action; 15] = [
+        Action::CommandPalette,
+        Action::NewConnection,
+        Action::NewLocalShell,
+        Action::CloseTab,
+        Action::ConnectSelected,
+        Action::DisconnectSelected,
+        Action::SaveConfig,
+        Action::OpenFileBrowser,
+        Action::OpenTransfers,
+        Action::HistorySearch,
+        Action::OpenSettings,
+        Action::ClearTerminal,
+        Action::ExportSession,
+        Action::ToggleTerminalPassthrough,
+        Action::Quit,
+    ];
+
+    /// 持久化使用的标识符
+    pub fn id(self) -> &'static str {
+        match self {
+            Action::CommandPalette => "command_palette",
+            Action::NewConnection => "new_connection",
+            Action::NewLocalShell => "new_local_shell",
+            Action::CloseTab => "close_tab",
+            Action::ConnectSelected => "connect_selected",
+            Action::DisconnectSelected => "disconnect_selected",
+            Action::SaveConfig => "save_config",
+            Action::OpenFileBrowser => "open_file_browser",
+            Action::OpenTransfers => "open_transfers",
+            Action::HistorySearch => "history_search",
+            Action::OpenSettings => "open_settings",
+            Action::ClearTerminal => "clear_terminal",
+            Action::ExportSession => "export_session",
+            Action::ToggleTerminalPassthrough => "toggle_terminal_passthrough",
+            Action::Quit => "quit",
+        }
+    }
+
+    /// 显示名称
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::CommandPalette => "命令面板",
+            Action::NewConnection => "新建连接",
+            Action::NewLocalShell => "新建本地终端",
+            Action::CloseTab => "关闭当前终端标签",
+            Action::ConnectSelected => "连接所选服务器",
+            Action::DisconnectSelected => "断开所选服务器",
+            Action::SaveConfig => "保存配置",
+            Action::OpenFileBrowser => "打开文件浏览器",
+            Action::OpenTransfers => "显示传输队列",
+            Action::HistorySearch => "搜索命令历史",
+            Action::OpenSettings => "打开设置",
+            Action::ClearTerminal => "清空终端",
+            Action::ExportSession => "导出终端会话",
+            Action::ToggleTerminalPassthrough => "切换终端快捷键直通",
+            Action::Quit => "退出",
+        }
+    }
+
+    /// 默认快捷键
+    pub fn default_binding(self) -> Option<KeyBinding> {
+        let binding = match self {
+            Action::CommandPalette => "Ctrl+Shift+P",
+            Action::NewConnection => "Ctrl+Shift+N",
+            Action::NewLocalShell => "Ctrl+Shift+T",
+            Action::CloseTab => "Ctrl+Shift+W",
+            Action::SaveConfig => "Ctrl+Shift+S",
+            Action::OpenFileBrowser => "Ctrl+Shift+F",
+            Action::HistorySearch => "Ctrl+Shift+R",
+            Action::OpenSettings => "Ctrl+Shift+Comma",
+            Action::ClearTerminal => "Ctrl+Shift+K",
+            Action::Quit => "Ctrl+Shift+Q",
+            Action::ConnectSelected
+            | Action::DisconnectSelected
+            | Action::OpenTransfers
+            | Action::ExportSession
+            | Action::ToggleTerminalPassthrough => return None,
+        };
+        KeyBinding::parse(binding)
+    }
+
+    pub fn from_id(id: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| a.id() == id)
+    }
+}
+
+/// 快捷键组合
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub key: Key,
+}
+
+impl KeyBinding {
+    /// 解析 `Ctrl+Shift+P` 形式的字符串（`Cmd` 视为 `Ctrl`）
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut binding = KeyBinding {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            key: Key::Escape,
+        };
+        let mut key = None;
+
+        for part in text.split('+').map(str::trim) {
+            match part.to_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" | "command" => binding.ctrl = true,
+                "shift" => binding.shift = true,
+                "alt" | "option" => binding.alt = true,
+                "" => return None,
+                _ => {
+                    if key.is_some() {
+                        return None;
+                    }
+                    key = Key::from_name(part)
+                        .or_else(|| Key::from_name(&part.to_uppercase()));
+                    key?;
+                }
+            }
+        }
+
+        binding.key = key?;
+        Some(binding)
+    }
+
+    /// 从按键事件创建
+    pub fn from_event(key: Key, modifiers: Modifiers) -> Self {
+        Self {
+            ctrl: modifiers.ctrl || modifiers.command,
+            shift: modifiers.shift,
+            alt: modifiers.alt,
+            key,
+        }
+    }
+
+    /// 转换为 egui 快捷键
+    pub fn to_shortcut(self) -> KeyboardShortcut {
+        let mut modifiers = Modifiers::NONE;
+        if self.ctrl {
+            modifiers = modifiers | Modifiers::COMMAND;
+        }
+        if self.shift {
+            modifiers = modifiers | Modifiers::SHIFT;
+        }
+        if self.alt {
+            modifiers = modifiers | Modifiers::ALT;
+        }
+        KeyboardShortcut::new(modifiers, self.key)
+    }
+
+    /// 终端直通模式下仍由应用处理的快捷键
+    ///
+    /// 只有带 Shift 或 Alt 的组合键才会被拦截，其余（如 Ctrl+R、Ctrl+C）交给 shell。
+    pub fn is_terminal_safe(self) -> bool {
+        self.shift || self.alt
+    }
+}
+
+impl std::fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        write!(f, "{}", self.key.name())
+    }
+}
+
+/// 快捷键冲突
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyConflict {
+    pub binding: KeyBinding,
+    pub actions: Vec<Action>,
+}
+
+/// 生效的快捷键映射（默认值 + 用户覆盖）
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Action, Option<KeyBinding>)>,
+}
+
+impl Keymap {
+    /// 根据设置中的覆盖项构建
+    ///
+    /// 覆盖项以动作 ID 为键；空字符串表示取消绑定，无法解析的值被忽略。
+    pub fn from_overrides(overrides: &BTreeMap<String, String>) -> Self {
+        let bindings = Action::ALL
+            .into_iter()
+            .map(|action| {
+                let binding = match overrides.get(action.id()) {
+                    Some(text) if text.trim().is_empty() => None,
+                    Some(text) => KeyBinding::parse(text).or_else(|| action.default_binding()),
+                    None => action.default_binding(),
+                };
+                (action, binding)
+            })
+            .collect();
+        Self { bindings }
+    }
+
+    /// 获取动作的快捷键
+    pub fn binding(&self, action: Action) -> Option<KeyBinding> {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .and_then(|(_, b)| *b)
+    }
+
+    /// 快捷键的显示文本（未绑定时为空）
+    pub fn binding_text(&self, action: Action) -> String {
+        self.binding(action).map(|b| b.to_string()).unwrap_or_default()
+    }
+
+    /// 所有已绑定的动作
+    pub fn bound(&self) -> impl Iterator<Item = (Action, KeyBinding)> + '_ {
+        self.bindings.iter().filter_map(|(a, b)| b.map(|b| (*a, b)))
+    }
+
+    /// 检测重复绑定
+    pub fn conflicts(&self) -> Vec<KeyConflict> {
+        let mut conflicts: Vec<KeyConflict> = Vec::new();
+        for (action, binding) in self.bound() {
+            match conflicts.iter_mut().find(|c| c.binding == binding) {
+                Some(conflict) => conflict.actions.push(action),
+                None => conflicts.push(KeyConflict {
+                    binding,
+                    actions: vec![action],
+                }),
+            }
+        }
+        conflicts.retain(|c| c.actions.len() > 1);
+        conflicts
+    }
+}
+
+// ============================================================================
+// 命令面板搜索
+// ============================================================================
+
+/// 命令面板条目
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteItem {
+    Action(Action),
+    Connection(usize),
+    Snippet(usize),
+}
+
+/// 命令面板搜索结果
+#[derive(Debug, Clone)]
+pub struct PaletteEntry {
+    pub item: PaletteItem,
+    pub label: String,
+    pub detail: String,
+}
+
+/// 模糊搜索所有动作、已保存连接与代码片段
+pub fn search_palette(
+    query: &str,
+    keymap: &Keymap,
+    connections: &[SshConfig],
+    snippets: &[Snippet],
+) -> Vec<PaletteEntry> {
+    use fuzzy_matcher::skim::SkimMatcherV2;
+    use fuzzy_matcher::FuzzyMatcher;
+
+    let mut entries: Vec<PaletteEntry> = Action::ALL
+        .into_iter()
+        .map(|action| PaletteEntry {
+            item: PaletteItem::Action(action),
+            label: action.label().to_string(),
+            detail: keymap.binding_text(action),
+        })
+        .collect();
+
+    entries.extend(connections.iter().enumerate().map(|(idx, conn)| PaletteEntry {
+        item: PaletteItem::Connection(idx),
+        label: format!("连接: {}", conn.name),
+        detail: format!("{}@{}:{}", conn.username, conn.host, conn.port),
+    }));
+
+    entries.extend(snippets.iter().enumerate().map(|(idx, snippet)| PaletteEntry {
+        item: PaletteItem::Snippet(idx),
+        label: format!("片段: {}", snippet.name),
+        detail: snippet.command.clone(),
+    }));
+
+    if query.trim().is_empty() {
+        return entries;
+    }
+
+    let matcher = SkimMatcherV2::default();
+    let mut scored: Vec<(PaletteEntry, i64)> = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let haystack = format!("{} {}", entry.label, entry.detail);
+            matcher
+                .fuzzy_match(&haystack, query)
+                .map(|score| (entry, score))
+        })
+        .collect();
+
+    scored.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(entry, _)| entry).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let binding = KeyBinding::parse("ctrl+shift+p").unwrap();
+        assert!(binding.ctrl && binding.shift && !binding.alt);
+        assert_eq!(binding.key, Key::P);
+        assert_eq!(binding.to_string(), "Ctrl+Shift+P");
+
+        assert_eq!(KeyBinding::parse("Ctrl+,").unwrap().to_string(), "Ctrl+Comma");
+        assert_eq!(KeyBinding::parse("F5").unwrap().key, Key::F5);
+        assert!(KeyBinding::parse("Ctrl+").is_none());
+        assert!(KeyBinding::parse("Ctrl+A+B").is_none());
+        assert!(KeyBinding::parse("Ctrl+Nope").is_none());
+    }
+
+    #[test]
+    fn test_action_ids_unique() {
+        for action in Action::ALL {
+            assert_eq!(Action::from_id(action.id()), Some(action));
+        }
+    }
+
+    #[test]
+    fn test_default_keymap_has_no_conflicts() {
+        let keymap = Keymap::from_overrides(&BTreeMap::new());
+        assert!(keymap.conflicts().is_empty());
+        assert_eq!(keymap.binding_text(Action::CommandPalette), "Ctrl+Shift+P");
+    }
+
+    #[test]
+    fn test_overrides_and_conflicts() {
+        let mut overrides = BTreeMap::new();
+        overrides.insert("history_search".to_string(), "Ctrl+Shift+P".to_string());
+        overrides.insert("quit".to_string(), String::new());
+        overrides.insert("save_config".to_string(), "garbage".to_string());
+
+        let keymap = Keymap::from_overrides(&overrides);
+        assert_eq!(keymap.binding(Action::Quit), None);
+        assert_eq!(keymap.binding_text(Action::SaveConfig), "Ctrl+Shift+S");
+
+        let conflicts = keymap.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts[0].actions,
+            vec![Action::CommandPalette, Action::HistorySearch]
+        );
+    }
+
+    #[test]
+    fn test_terminal_safe() {
+        assert!(!KeyBinding::parse("Ctrl+R").unwrap().is_terminal_safe());
+        assert!(KeyBinding::parse("Ctrl+Shift+R").unwrap().is_terminal_safe());
+        assert!(KeyBinding::parse("Alt+X").unwrap().is_terminal_safe());
+    }
+
+    #[test]
+    fn test_default_bindings_are_terminal_safe() {
+        for action in Action::ALL {
+            if let Some(binding) = action.default_binding() {
+                assert!(binding.is_terminal_safe(), "{} 的默认快捷键 {} 会被终端占用", action.id(), binding);
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_palette() {
+        let keymap = Keymap::from_overrides(&BTreeMap::new());
+        let connections = vec![SshConfig::new(
+            "prod-db".to_string(),
+            "10.0.0.5".to_string(),
+            22,
+            "admin".to_string(),
+        )];
+        let snippets = vec![Snippet {
+            name: "磁盘占用".to_string(),
+            command: "df -h".to_string(),
+        }];
+
+        let all = search_palette("", &keymap, &connections, &snippets);
+        assert_eq!(all.len(), Action::ALL.len() + 2);
+
+        let results = search_palette("proddb", &keymap, &connections, &snippets);
+        assert_eq!(results[0].item, PaletteItem::Connection(0));
+
+        let results = search_palette("df -h", &keymap, &connections, &snippets);
+        assert_eq!(results[0].item, PaletteItem::Snippet(0));
+
+        assert!(search_palette("zzzzqqq", &keymap, &connections, &snippets).is_empty());
+    }
+}
@@ -2,12 +2,23 @@ use eframe::egui;
 use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex as TokioMutex};
 
+use crate::actions::{Action, Keymap, PaletteItem};
 use crate::ai::{AiManager, GoogleProvider, OllamaProvider, OpenAiProvider};
 use crate::config::ConfigManager;
+use crate::pty::LocalPty;
+use crate::session::{run_session_reader, SessionBackend, SessionMessage, TabKind, TerminalTab};
 use crate::ssh::SshSession;
-use crate::state::{AiChannelMessage, AppState, SshMessage};
+use crate::telnet::TelnetSession;
+use crate::state::{
+    AiChannelMessage, AppState, ClipboardPrompt, ExportDialog, LinkRequest, PasteTarget, PendingPaste, SshMessage,
+};
+use crate::terminal::{is_risky_paste, parent_dir, resolve_path, ClipboardRequest, LinkTarget, TerminalBuffer};
 use crate::types::*;
 use crate::ui::panels;
+use crate::expect::ExpectRunner;
+use crate::export::{ExportFormat, Redactor};
+use crate::triggers::TriggerEffect;
+use crate::zmodem::ZmodemEvent;
 
 // ============================================================================
 // 主应用结构
@@ -18,7 +29,7 @@ pub struct App {
 }
 
 impl App {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         // 创建 Tokio 运行时
         let runtime =
             Arc::new(tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime"));
@@ -32,6 +43,9 @@ impl App {
             AppConfig::default()
         });
 
+        // 加载 CJK 后备字体，避免中文与日韩文字显示为方框
+        let cjk_font = crate::ui::fonts::install_cjk_font(&cc.egui_ctx, &config.settings.cjk_font_path);
+
         // 从配置加载连接
         let connections = config.connections.clone();
         let connection_count = connections.len();
@@ -52,6 +66,10 @@ impl App {
         let (sftp_msg_tx, sftp_msg_rx) = mpsc::unbounded_channel();
         let sftp_msg_rx = Arc::new(std::sync::Mutex::new(sftp_msg_rx));
 
+        // 创建交互式会话通信通道（会话线程 -> UI）
+        let (session_msg_tx, session_msg_rx) = mpsc::unbounded_channel();
+        let session_msg_rx = Arc::new(std::sync::Mutex::new(session_msg_rx));
+
         // 初始化 AI Manager
         let mut ai_manager = AiManager::new();
 
@@ -96,15 +114,33 @@ impl App {
             new_conn_password: String::new(),
             new_conn_use_key: false,
             new_conn_key_path: String::new(),
+            new_conn_color_scheme: None,
+            new_conn_protocol: Protocol::Ssh,
 
             ssh_sessions,
             connection_status,
 
-            terminal_output:
-                "Welcome to iShell v0.3.0! 🚀\nType commands after connecting to a server.\n\n"
-                    .to_string(),
+            terminal: {
+                let mut terminal = TerminalBuffer::log(config.settings.terminal_scrollback);
+                terminal.feed(
+                    "Welcome to iShell v0.3.0! 🚀\nType commands after connecting to a server.\n\n",
+                );
+                terminal
+            },
             command_input: String::new(),
 
+            tabs: Vec::new(),
+            active_tab: None,
+            next_tab_id: 0,
+            session_msg_tx,
+            session_msg_rx,
+            repaint_ctx: None,
+
+            clipboard_prompts: std::collections::VecDeque::new(),
+            export_dialog: None,
+            triggers: Default::default(),
+            pending_paste: None,
+
             ssh_msg_tx,
             ssh_msg_rx,
 
@@ -122,11 +158,20 @@ impl App {
             remote_current_path: "/".to_string(),
             local_current_path: std::env::current_dir().unwrap_or_default(),
             remote_files: Vec::new(),
+            remote_sort: Default::default(),
             local_files: Vec::new(),
             selected_remote_files: Vec::new(),
             selected_local_file: None,
-            sftp_progress: 0.0,
+            zmodem_progress: 0.0,
             sftp_status: String::new(),
+            transfers: load_transfers(),
+            show_transfers: false,
+            remote_edits: Default::default(),
+            editor_buffers: Vec::new(),
+            file_viewers: Vec::new(),
+            file_op_dialog: None,
+            remote_ids: std::collections::HashMap::new(),
+            remote_link_requests: std::collections::HashMap::new(),
 
             sftp_msg_tx,
             sftp_msg_rx,
@@ -139,8 +184,20 @@ impl App {
             show_history_search: false,
             history_search_query: String::new(),
 
+            show_command_palette: false,
+            command_palette_query: String::new(),
+            command_palette_selected: 0,
+            terminal_focused: false,
+            keybinding_capture: None,
+            new_snippet_name: String::new(),
+            new_snippet_command: String::new(),
+
             show_settings: false,
             settings_page: crate::types::SettingsPage::General,
+            color_scheme_status: String::new(),
+            theme_editor: None,
+            theme_editor_status: String::new(),
+            cjk_font,
 
             runtime,
             config,
@@ -152,47 +209,88 @@ impl App {
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // 应用主题
-        crate::theme::ThemeManager::apply(ctx, &self.state.config.settings.theme);
+        self.state.repaint_ctx.get_or_insert_with(|| ctx.clone());
+
+        // 应用主题（编辑主题时实时预览草稿）
+        match &self.state.theme_editor {
+            Some(draft) => crate::theme::ThemeManager::apply_custom(ctx, draft),
+            None => crate::theme::ThemeManager::apply(
+                ctx,
+                &self.state.config.settings.theme,
+                &self.state.config.custom_themes,
+            ),
+        }
 
         // 处理异步消息
         process_ssh_messages(&mut self.state);
         process_ai_messages(&mut self.state);
         process_sftp_messages(&mut self.state);
+        process_session_messages(&mut self.state);
+        if pump_zmodem_transfers(&mut self.state) {
+            ctx.request_repaint();
+        }
+        if continue_trigger_scans(&mut self.state, crate::triggers::TRIGGER_BUDGET) {
+            ctx.request_repaint();
+        }
+        if let Some(deadline) = tick_expect_scripts(&mut self.state) {
+            ctx.request_repaint_after(deadline.saturating_duration_since(std::time::Instant::now()));
+        }
+        pump_transfers(&mut self.state);
+        pump_remote_edits(&mut self.state);
+
+        // 全局快捷键
+        handle_shortcuts(&mut self.state, ctx);
+        let keymap = Keymap::from_overrides(&self.state.config.settings.keybindings);
 
         // Top menu bar
         egui::TopBottomPanel::top("menu").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
+                let menu_item = |ui: &mut egui::Ui, text: &str, action: Action| {
+                    ui.add(egui::Button::new(text).shortcut_text(keymap.binding_text(action)))
+                        .clicked()
+                };
+
                 ui.menu_button("📁 File", |ui| {
-                    if ui.button("➕ New Connection").clicked() {
+                    if menu_item(ui, "➕ New Connection", Action::NewConnection) {
                         self.state.show_new_connection = true;
                         ui.close_menu();
                     }
+                    if menu_item(ui, "🖥️ New Local Shell", Action::NewLocalShell) {
+                        open_local_tab(&mut self.state);
+                        ui.close_menu();
+                    }
                     ui.separator();
-                    if ui.button("💾 Save Config").clicked() {
+                    if menu_item(ui, "💾 Save Config", Action::SaveConfig) {
                         save_config(&mut self.state);
                         ui.close_menu();
                     }
                     ui.separator();
-                    if ui.button("🚪 Quit").clicked() {
-                        save_command_history(&self.state);
-                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    if menu_item(ui, "🚪 Quit", Action::Quit) {
+                        run_action(&mut self.state, ctx, Action::Quit);
                     }
                 });
 
                 ui.menu_button("🔧 Tools", |ui| {
-                    if ui.button("📁 File Browser").clicked() {
+                    if menu_item(ui, "🎯 Command Palette", Action::CommandPalette) {
+                        run_action(&mut self.state, ctx, Action::CommandPalette);
+                        ui.close_menu();
+                    }
+                    if menu_item(ui, "📁 File Browser", Action::OpenFileBrowser) {
                         self.state.show_file_browser = true;
                         ui.close_menu();
                     }
-                    if ui.button("🔍 Command History").clicked() {
+                    if menu_item(ui, "📦 Transfers", Action::OpenTransfers) {
+                        self.state.show_transfers = !self.state.show_transfers;
+                        ui.close_menu();
+                    }
+                    if menu_item(ui, "🔍 Command History", Action::HistorySearch) {
                         self.state.show_history_search = true;
                         ui.close_menu();
                     }
                     if ui.button("🔑 SSH Keys").clicked() {
                         ui.close_menu();
                     }
-                    if ui.button("⚙️ Settings").clicked() {
+                    if menu_item(ui, "⚙️ Settings", Action::OpenSettings) {
                         self.state.show_settings = true;
                         ui.close_menu();
                     }
@@ -222,6 +320,9 @@ impl eframe::App for App {
             });
         });
 
+        // 命令面板（在面板之前渲染，以便先消费导航按键）
+        crate::ui::command_palette::render_command_palette(&mut self.state, ctx);
+
         // Render panels using the new module structure
         panels::render_connections_panel(&mut self.state, ctx);
         panels::render_ai_panel(&mut self.state, ctx);
@@ -231,10 +332,19 @@ impl eframe::App for App {
         
         // Render file browser (v0.3.0)
         crate::ui::file_browser::render_file_browser(&mut self.state, ctx);
+        crate::ui::transfers::render_transfers_window(&mut self.state, ctx);
+        crate::ui::remote_edit::render_remote_edits(&mut self.state, ctx);
+        crate::ui::editor::render_file_viewers(&mut self.state, ctx);
+        crate::ui::file_ops::render_file_op_dialog(&mut self.state, ctx);
         
         // Render settings window (v0.3.0 - Phase 4)
         crate::ui::settings_panel::render_settings_window(&mut self.state, ctx);
 
+        // 剪贴板权限提示与粘贴确认
+        crate::ui::clipboard::render_clipboard_prompt(&mut self.state, ctx);
+        crate::ui::clipboard::render_paste_confirmation(&mut self.state, ctx);
+        crate::ui::export::render_export_dialog(&mut self.state, ctx);
+
 
         // 请求重绘
         ctx.request_repaint_after(std::time::Duration::from_millis(100));
@@ -243,6 +353,8 @@ impl eframe::App for App {
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
         // 保存命令历史
         save_command_history(&self.state);
+        save_transfers(&self.state);
+        self.state.remote_edits.close_all();
     }
 }
 
@@ -280,32 +392,784 @@ fn save_command_history(state: &AppState) {
     }
 }
 
+// 处理全局快捷键
+fn handle_shortcuts(state: &mut AppState, ctx: &egui::Context) {
+    // 录制快捷键时不触发任何动作
+    if state.keybinding_capture.is_some() {
+        return;
+    }
+
+    let keymap = Keymap::from_overrides(&state.config.settings.keybindings);
+    let passthrough = state.config.settings.terminal_passthrough && state.terminal_focused;
+
+    // 修饰键多的组合优先匹配，避免 Ctrl+R 抢先消费 Ctrl+Shift+R
+    let mut bound: Vec<_> = keymap.bound().collect();
+    bound.sort_by_key(|(_, b)| std::cmp::Reverse(b.ctrl as u8 + b.shift as u8 + b.alt as u8));
+
+    let mut triggered = Vec::new();
+    for (action, binding) in bound {
+        if passthrough && !binding.is_terminal_safe() {
+            continue;
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&binding.to_shortcut())) {
+            triggered.push(action);
+        }
+    }
+
+    for action in triggered {
+        run_action(state, ctx, action);
+    }
+}
+
+// 执行动作
+pub fn run_action(state: &mut AppState, ctx: &egui::Context, action: Action) {
+    match action {
+        Action::CommandPalette => {
+            state.show_command_palette = !state.show_command_palette;
+            state.command_palette_query.clear();
+            state.command_palette_selected = 0;
+        }
+        Action::NewConnection => state.show_new_connection = true,
+        Action::NewLocalShell => open_local_tab(state),
+        Action::CloseTab => {
+            if let Some(index) = state.active_tab {
+                close_tab(state, index);
+            }
+        }
+        Action::ConnectSelected => {
+            if let Some(idx) = state.selected_connection {
+                connect_ssh(state, idx);
+            }
+        }
+        Action::DisconnectSelected => {
+            if let Some(idx) = state.selected_connection {
+                disconnect_ssh(state, idx);
+            }
+        }
+        Action::SaveConfig => save_config(state),
+        Action::OpenFileBrowser => state.show_file_browser = true,
+        Action::OpenTransfers => state.show_transfers = !state.show_transfers,
+        Action::HistorySearch => state.show_history_search = !state.show_history_search,
+        Action::OpenSettings => state.show_settings = true,
+        Action::ClearTerminal => active_terminal_mut(state).clear(),
+        Action::ExportSession => open_export_dialog(state, None),
+        Action::ToggleTerminalPassthrough => {
+            let settings = &mut state.config.settings;
+            settings.terminal_passthrough = !settings.terminal_passthrough;
+        }
+        Action::Quit => {
+            save_command_history(state);
+            save_transfers(state);
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
+}
+
+// 执行命令面板中选中的条目
+pub fn run_palette_item(state: &mut AppState, ctx: &egui::Context, item: PaletteItem) {
+    state.show_command_palette = false;
+    match item {
+        PaletteItem::Action(Action::CommandPalette) => {}
+        PaletteItem::Action(action) => run_action(state, ctx, action),
+        PaletteItem::Connection(idx) => {
+            if idx < state.connections.len() {
+                state.selected_connection = Some(idx);
+                if state.connection_status[idx] != ConnectionStatus::Connected {
+                    connect_ssh(state, idx);
+                }
+            }
+        }
+        PaletteItem::Snippet(idx) => run_snippet(state, idx),
+    }
+}
+
+// 运行代码片段：可以执行时直接执行，否则填入命令输入框
+pub fn run_snippet(state: &mut AppState, index: usize) {
+    let Some(snippet) = state.config.snippets.get(index).cloned() else {
+        return;
+    };
+
+    if can_submit_command(state) {
+        submit_command(state, snippet.command);
+    } else {
+        state.command_input = snippet.command;
+    }
+}
+
+// 当前终端是否可以执行命令（活动标签在线，或控制台所选连接已连接）
+pub fn can_submit_command(state: &AppState) -> bool {
+    match state.active_tab.and_then(|i| state.tabs.get(i)) {
+        Some(tab) => tab.is_live(),
+        None => state
+            .selected_connection
+            .and_then(|i| state.connection_status.get(i))
+            .map(|s| *s == ConnectionStatus::Connected)
+            .unwrap_or(false),
+    }
+}
+
+// 提交命令：记录历史，发送到活动标签的 shell，控制台则通过 exec 执行
+pub fn submit_command(state: &mut AppState, command: String) {
+    let connection_name = match state.active_tab.and_then(|i| state.tabs.get(i)) {
+        Some(tab) => tab_connection_name(state, tab.kind),
+        None => state
+            .selected_connection
+            .and_then(|idx| state.connections.get(idx))
+            .map(|c| c.name.clone())
+            .unwrap_or_else(|| "本地".to_string()),
+    };
+    state.command_history.add(command.clone(), connection_name);
+
+    match state.active_tab.and_then(|i| state.tabs.get(i)) {
+        Some(tab) => {
+            if let Err(e) = tab.write(format!("{}\r", command).as_bytes()) {
+                state.terminal.feed(&format!("❌ Error: {}\n", e));
+            }
+        }
+        None => execute_ssh_command(state, command),
+    }
+}
+
+// 标签在命令历史中记录的连接名
+fn tab_connection_name(state: &AppState, kind: TabKind) -> String {
+    match kind {
+        TabKind::Remote { connection } => state
+            .connections
+            .get(connection)
+            .map(|c| c.name.clone())
+            .unwrap_or_else(|| "本地".to_string()),
+        TabKind::Local => "本地".to_string(),
+    }
+}
+
+// 当前显示的终端缓冲区
+pub fn active_terminal(state: &AppState) -> &TerminalBuffer {
+    match state.active_tab.and_then(|i| state.tabs.get(i)) {
+        Some(tab) => &tab.terminal,
+        None => &state.terminal,
+    }
+}
+
+pub fn active_terminal_mut(state: &mut AppState) -> &mut TerminalBuffer {
+    match state.active_tab.and_then(|i| state.tabs.get_mut(i)) {
+        Some(tab) => &mut tab.terminal,
+        None => &mut state.terminal,
+    }
+}
+
+// 新建终端标签并切换到该标签
+fn add_tab(state: &mut AppState, title: String, kind: TabKind) -> usize {
+    let id = state.next_tab_id;
+    state.next_tab_id += 1;
+
+    let mut tab = TerminalTab::new(id, title, kind, state.config.settings.terminal_scrollback);
+    // 新标签沿用当前终端区域的尺寸
+    let (cols, rows) = active_terminal(state).size();
+    tab.terminal.resize(cols, rows);
+
+    state.tabs.push(tab);
+    let index = state.tabs.len() - 1;
+    state.active_tab = Some(index);
+    index
+}
+
+// 为标签启动后台读取线程
+fn spawn_tab_reader(state: &AppState, index: usize) {
+    let tab = &state.tabs[index];
+    let (id, backend) = (tab.id, tab.backend.clone());
+    let tx = state.session_msg_tx.clone();
+    let repaint = state.repaint_ctx.clone();
+    std::thread::spawn(move || run_session_reader(id, backend, tx, repaint));
+}
+
+// 打开本地 shell 标签
+pub fn open_local_tab(state: &mut AppState) {
+    let index = add_tab(state, "🖥️ Local".to_string(), TabKind::Local);
+    let tab = &mut state.tabs[index];
+    let (cols, rows) = tab.terminal.size();
+
+    match LocalPty::spawn_shell(cols as u16, rows as u16) {
+        Ok(pty) => {
+            *tab.backend.lock().unwrap() = Some(Box::new(pty));
+            tab.status = ConnectionStatus::Connected;
+            spawn_tab_reader(state, index);
+        }
+        Err(e) => {
+            tab.status = ConnectionStatus::Error;
+            tab.terminal
+                .feed(&format!("❌ Failed to start local shell: {}\r\n", e));
+        }
+    }
+}
+
+// 为连接打开交互式会话标签（SSH 使用独立的会话，Telnet / 原始 TCP 直接连接）
+pub fn open_connection_tab(state: &mut AppState, connection: usize) {
+    let Some(config) = state.connections.get(connection).cloned() else {
+        return;
+    };
+    let auth = config.auth.clone();
+    if config.protocol == Protocol::Ssh && auth.is_none() {
+        state.terminal.feed("❌ No authentication method configured\n");
+        return;
+    }
+
+    let index = add_tab(state, config.name.clone(), TabKind::Remote { connection });
+    let tab = &mut state.tabs[index];
+    tab.terminal.feed(&match config.protocol {
+        Protocol::Ssh => format!(
+            "🔄 Connecting to {}@{}:{}...\r\n",
+            config.username, config.host, config.port
+        ),
+        protocol => format!(
+            "🔄 Connecting to {}:{} ({})...\r\n",
+            config.host,
+            config.port,
+            protocol.label()
+        ),
+    });
+
+    let (cols, rows) = tab.terminal.size();
+    let (id, backend) = (tab.id, tab.backend.clone());
+    let tx = state.session_msg_tx.clone();
+    let repaint = state.repaint_ctx.clone();
+
+    std::thread::spawn(move || {
+        let result: Result<Box<dyn SessionBackend>> = match config.protocol {
+            Protocol::Ssh => {
+                let session =
+                    SshSession::new(config.host.clone(), config.port, config.username.clone());
+                session
+                    .connect(auth.as_ref().unwrap())
+                    .and_then(|_| session.start_shell(cols as u32, rows as u32))
+                    .map(|shell| Box::new(shell) as Box<dyn SessionBackend>)
+            }
+            protocol => TelnetSession::connect(
+                &config.host,
+                config.port,
+                protocol == Protocol::Raw,
+                cols as u16,
+                rows as u16,
+            )
+            .map(|session| Box::new(session) as Box<dyn SessionBackend>),
+        };
+
+        match result {
+            Ok(session) => {
+                *backend.lock().unwrap() = Some(session);
+                let _ = tx.send(SessionMessage::Opened(id));
+                run_session_reader(id, backend, tx, repaint);
+            }
+            Err(e) => {
+                let _ = tx.send(SessionMessage::Error {
+                    tab: id,
+                    error: e.to_string(),
+                });
+            }
+        }
+    });
+}
+
+// 关闭终端标签
+pub fn close_tab(state: &mut AppState, index: usize) {
+    if index >= state.tabs.len() {
+        return;
+    }
+    let mut tab = state.tabs.remove(index);
+    tab.close();
+
+    state.active_tab = match state.active_tab {
+        Some(active) if active > index => Some(active - 1),
+        Some(active) if active == index => {
+            if state.tabs.is_empty() {
+                None
+            } else {
+                Some(index.min(state.tabs.len() - 1))
+            }
+        }
+        other => other,
+    };
+}
+
+// 处理交互式会话消息
+fn process_session_messages(state: &mut AppState) {
+    let messages: Vec<_> = {
+        let mut rx = state.session_msg_rx.lock().unwrap();
+        std::iter::from_fn(|| rx.try_recv().ok()).collect()
+    };
+    state.triggers.sync(&state.config.triggers);
+    for msg in messages {
+        let id = match &msg {
+            SessionMessage::Opened(id) | SessionMessage::Closed(id) => *id,
+            SessionMessage::Output { tab, .. }
+            | SessionMessage::Error { tab, .. }
+            | SessionMessage::ZmodemFiles { tab, .. } => *tab,
+        };
+        // 标签可能已被关闭
+        let Some(kind) = state.tabs.iter().find(|t| t.id == id).map(|t| t.kind) else {
+            continue;
+        };
+        let connection = tab_connection_name(state, kind);
+        let tab = state.tabs.iter_mut().find(|t| t.id == id).unwrap();
+
+        let mut clipboard_requests = Vec::new();
+        let mut finished_commands = Vec::new();
+        let mut zmodem_events = Vec::new();
+        let mut trigger_effects = Vec::new();
+        let mut auto_scripts = Vec::new();
+        match msg {
+            SessionMessage::Opened(_) => {
+                tab.status = ConnectionStatus::Connected;
+                // Telnet / 原始 TCP 的对端不一定是 shell，不注入脚本
+                let is_shell = match tab.kind {
+                    TabKind::Local => true,
+                    TabKind::Remote { connection } => state
+                        .connections
+                        .get(connection)
+                        .is_some_and(|c| c.protocol == Protocol::Ssh),
+                };
+                if is_shell && state.config.settings.shell_integration {
+                    let _ = tab.write(&crate::session::shell_integration_input());
+                }
+                if let TabKind::Remote { connection } = tab.kind {
+                    auto_scripts = state.connections.get(connection).map_or_else(Vec::new, |c| {
+                        (0..c.expect_scripts.len()).filter(|&i| c.expect_scripts[i].auto_start).collect()
+                    });
+                }
+            }
+            SessionMessage::Output { data, .. } => {
+                zmodem_events = tab.feed_output(&data, &state.local_current_path);
+                tab.flush_responses();
+                trigger_effects = crate::triggers::scan(
+                    &state.triggers,
+                    &mut tab.triggers,
+                    &mut tab.terminal,
+                    &connection,
+                    crate::triggers::TRIGGER_BUDGET,
+                );
+                clipboard_requests = tab.terminal.take_clipboard_requests();
+                finished_commands = tab.terminal.take_finished_commands();
+            }
+            SessionMessage::Closed(_) => {
+                tab.close();
+                tab.terminal.feed("\r\n\x1b[2m[会话已结束]\x1b[0m\r\n");
+            }
+            SessionMessage::Error { error, .. } => {
+                tab.close();
+                tab.status = ConnectionStatus::Error;
+                tab.terminal.feed(&format!("\r\n❌ Error: {}\r\n", error));
+            }
+            SessionMessage::ZmodemFiles { files, .. } => {
+                tab.zmodem_picking = false;
+                match tab.zmodem.as_mut() {
+                    // 远端先执行了 rz，正在等待文件
+                    Some(transfer) => transfer.provide_files(files),
+                    None => start_zmodem_upload(tab, files),
+                }
+            }
+        }
+
+        // Telnet / 原始 TCP 没有独立的控制会话，连接状态跟随标签
+        let (kind, status) = (tab.kind, tab.status);
+        if let TabKind::Remote { connection } = kind {
+            let session_only = state
+                .connections
+                .get(connection)
+                .is_some_and(|c| c.protocol != Protocol::Ssh);
+            if session_only {
+                if let Some(slot) = state.connection_status.get_mut(connection) {
+                    *slot = status;
+                }
+            }
+        }
+
+        // 命令退出码记入历史
+        if !finished_commands.is_empty() {
+            for (command, exit_code) in finished_commands {
+                state.command_history.record_exit(&command, &connection, exit_code);
+            }
+        }
+
+        for request in clipboard_requests {
+            handle_clipboard_request(state, id, request);
+        }
+        handle_zmodem_events(state, zmodem_events);
+        for effect in trigger_effects {
+            apply_trigger_effect(state, id, effect);
+        }
+        if let Some(index) = state.tabs.iter().position(|t| t.id == id) {
+            for script in auto_scripts {
+                run_expect_script(state, index, script);
+            }
+        }
+    }
+}
+
+// 在标签中运行所属连接的第 `script` 个 expect 脚本
+pub fn run_expect_script(state: &mut AppState, tab_index: usize, script: usize) {
+    let Some(tab) = state.tabs.get_mut(tab_index) else {
+        return;
+    };
+    let TabKind::Remote { connection } = tab.kind else {
+        return;
+    };
+    let Some(conn) = state.connections.get(connection) else {
+        return;
+    };
+    let Some(script) = conn.expect_scripts.get(script) else {
+        return;
+    };
+    match ExpectRunner::new(script, conn, std::time::Instant::now()) {
+        Ok(runner) => tab.start_expect(runner),
+        Err(e) => tab.log_expect(&format!("❌ 脚本 {}: {}", script.name, e)),
+    }
+}
+
+// 继续扫描因超出时间预算而推迟的输出行，仍有未扫描的行时返回 true
+//
+// 输出在一次突发后停止时不会再有 Output 消息，推迟的行由每帧在这里补上。
+fn continue_trigger_scans(state: &mut AppState, budget: std::time::Duration) -> bool {
+    state.triggers.sync(&state.config.triggers);
+    let pending: Vec<(u64, TabKind)> = state
+        .tabs
+        .iter()
+        .filter(|tab| tab.triggers.is_pending(&state.triggers, &tab.terminal))
+        .map(|tab| (tab.id, tab.kind))
+        .collect();
+    let mut remaining = false;
+    for (id, kind) in pending {
+        let connection = tab_connection_name(state, kind);
+        let Some(tab) = state.tabs.iter_mut().find(|t| t.id == id) else {
+            continue;
+        };
+        let effects = crate::triggers::scan(&state.triggers, &mut tab.triggers, &mut tab.terminal, &connection, budget);
+        remaining |= tab.triggers.is_pending(&state.triggers, &tab.terminal);
+        for effect in effects {
+            apply_trigger_effect(state, id, effect);
+        }
+    }
+    remaining
+}
+
+// 检查各标签 expect 脚本的超时，返回最近的超时时刻
+fn tick_expect_scripts(state: &mut AppState) -> Option<std::time::Instant> {
+    let now = std::time::Instant::now();
+    state
+        .tabs
+        .iter_mut()
+        .filter(|tab| !tab.expect.is_empty())
+        .filter_map(|tab| tab.tick_expect(now))
+        .min()
+}
+
+// 执行触发器动作
+fn apply_trigger_effect(state: &mut AppState, tab: u64, effect: TriggerEffect) {
+    let input = match effect {
+        TriggerEffect::Notify { rule, text } => {
+            crate::notify::desktop_notification(&format!("iShell · {}", rule), &text);
+            if let Some(ctx) = &state.repaint_ctx {
+                ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(
+                    egui::UserAttentionType::Informational,
+                ));
+            }
+            return;
+        }
+        TriggerEffect::Sound => return crate::notify::play_alert_sound(),
+        TriggerEffect::Reply(text) => text,
+        TriggerEffect::RunSnippet(name) => {
+            match state.config.snippets.iter().find(|s| s.name == name) {
+                Some(snippet) => snippet.command.clone(),
+                None => return eprintln!("Trigger snippet not found: {}", name),
+            }
+        }
+    };
+    if let Some(tab) = state.tabs.iter().find(|t| t.id == tab && t.is_live()) {
+        let _ = tab.write(format!("{}\r", input).as_bytes());
+    }
+}
+
+/// 每帧发送的 ZMODEM 数据上限，避免大文件上传阻塞界面
+const ZMODEM_FRAME_BUDGET: usize = 256 * 1024;
+
+// 推进正在上传的 ZMODEM 传输，有传输进行中时返回 true
+fn pump_zmodem_transfers(state: &mut AppState) -> bool {
+    let mut events = Vec::new();
+    let mut active = false;
+    for tab in &mut state.tabs {
+        if tab.zmodem.is_some() {
+            events.extend(tab.pump_zmodem(ZMODEM_FRAME_BUDGET));
+            active |= tab.zmodem.is_some();
+        }
+    }
+    handle_zmodem_events(state, events);
+    active
+}
+
+// ZMODEM 进度沿用 SFTP 的状态显示
+fn handle_zmodem_events(state: &mut AppState, events: Vec<ZmodemEvent>) {
+    for event in events {
+        match event {
+            ZmodemEvent::FileStarted { name, .. } => {
+                state.zmodem_progress = 0.0;
+                state.sftp_status = format!("ZMODEM: {}", name);
+            }
+            ZmodemEvent::Progress {
+                name,
+                transferred,
+                size: Some(size),
+            } if size > 0 => {
+                // 1.0 表示完成，传输中保持在 1.0 以下以显示进度条
+                state.zmodem_progress = (transferred as f32 / size as f32).min(0.999);
+                state.sftp_status = format!("ZMODEM: {}", name);
+            }
+            ZmodemEvent::Progress { name, transferred, .. } => {
+                state.sftp_status = format!("ZMODEM: {} ({} 字节)", name, transferred);
+            }
+            ZmodemEvent::FileCompleted { name, path } => {
+                state.zmodem_progress = 1.0;
+                state.sftp_status = if path.as_os_str().is_empty() {
+                    format!("ZMODEM: 已发送 {}", name)
+                } else {
+                    format!("ZMODEM: 已保存 {}", path.display())
+                };
+            }
+            ZmodemEvent::FileSkipped { name } => {
+                state.sftp_status = format!("ZMODEM: 已跳过 {}", name);
+            }
+            ZmodemEvent::Finished => {
+                state.zmodem_progress = 1.0;
+                if !state.sftp_status.starts_with("ZMODEM: 已") {
+                    state.sftp_status = "ZMODEM 传输完成".to_string();
+                }
+            }
+            ZmodemEvent::Error(error) => {
+                state.zmodem_progress = 0.0;
+                state.sftp_status = format!("❌ ZMODEM: {}", error);
+            }
+        }
+    }
+}
+
+// 在标签中执行远端 `rz`，启动后上传已选择的文件
+fn start_zmodem_upload(tab: &mut TerminalTab, files: Vec<std::path::PathBuf>) {
+    if files.is_empty() || !tab.is_live() || tab.zmodem.is_some() {
+        return;
+    }
+    tab.zmodem_upload = Some(files);
+    if let Err(e) = tab.write(b"rz\r") {
+        tab.zmodem_upload = None;
+        tab.terminal.feed(&format!("\r\n❌ ZMODEM: {}\r\n", e));
+    }
+}
+
+/// 在后台打开文件对话框选择 ZMODEM 上传的文件，结果以 [`SessionMessage::ZmodemFiles`] 送回
+///
+/// 对话框打开期间界面与会话输出照常处理，远端 `rz` 不会因等待而超时。
+pub fn pick_zmodem_files(state: &mut AppState, tab_index: usize) {
+    let Some(tab) = state.tabs.get_mut(tab_index).filter(|tab| !tab.zmodem_picking) else {
+        return;
+    };
+    tab.zmodem_picking = true;
+    let id = tab.id;
+    let tx = state.session_msg_tx.clone();
+    let repaint = state.repaint_ctx.clone();
+    state.runtime.spawn(async move {
+        let files = rfd::AsyncFileDialog::new()
+            .set_title("选择要通过 ZMODEM 上传的文件")
+            .pick_files()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .map(|file| file.path().to_path_buf())
+            .collect();
+        let _ = tx.send(SessionMessage::ZmodemFiles { tab: id, files });
+        if let Some(ctx) = repaint {
+            ctx.request_repaint();
+        }
+    });
+}
+
+// 标签对应的主机名，用于记录剪贴板权限
+fn tab_host(state: &AppState, tab: u64) -> String {
+    let kind = state.tabs.iter().find(|t| t.id == tab).map(|t| t.kind);
+    match kind {
+        Some(TabKind::Remote { connection }) => state
+            .connections
+            .get(connection)
+            .map(|c| c.host.clone())
+            .unwrap_or_default(),
+        _ => "localhost".to_string(),
+    }
+}
+
+// 处理 OSC 52 剪贴板请求：按主机权限执行、拒绝或等待用户确认
+fn handle_clipboard_request(state: &mut AppState, tab: u64, request: ClipboardRequest) {
+    let host = tab_host(state, tab);
+    let access = state
+        .config
+        .settings
+        .clipboard_access
+        .get(&host)
+        .copied()
+        .unwrap_or_default();
+    let allowed = match request {
+        ClipboardRequest::Set(_) => access.write,
+        ClipboardRequest::Get { .. } => access.read,
+    };
+
+    match allowed {
+        Some(true) => apply_clipboard_request(state, tab, request),
+        Some(false) => {}
+        None => queue_clipboard_prompt(state, ClipboardPrompt { tab, host, request }),
+    }
+}
+
+// 等待确认的剪贴板请求最多保留的数量
+const MAX_CLIPBOARD_PROMPTS: usize = 8;
+
+// 加入剪贴板权限提示队列
+//
+// 同一标签的同类请求合并为一个：写入只保留最新的内容，读取只保留第一个。
+// 远程程序反复发送请求时不会堆积提示。
+fn queue_clipboard_prompt(state: &mut AppState, prompt: ClipboardPrompt) {
+    let same_kind = |a: &ClipboardRequest, b: &ClipboardRequest| std::mem::discriminant(a) == std::mem::discriminant(b);
+    let full = state.clipboard_prompts.len() >= MAX_CLIPBOARD_PROMPTS;
+    let pending = state
+        .clipboard_prompts
+        .iter_mut()
+        .find(|p| p.tab == prompt.tab && same_kind(&p.request, &prompt.request));
+    match pending {
+        Some(pending) => {
+            if let ClipboardRequest::Set(_) = prompt.request {
+                pending.request = prompt.request;
+            }
+        }
+        None if full => {
+            eprintln!("Too many pending clipboard requests, ignoring request from {}", prompt.host);
+        }
+        None => state.clipboard_prompts.push_back(prompt),
+    }
+}
+
+// 执行已授权的剪贴板请求
+fn apply_clipboard_request(state: &mut AppState, tab: u64, request: ClipboardRequest) {
+    match request {
+        ClipboardRequest::Set(text) => {
+            if let Some(ctx) = &state.repaint_ctx {
+                ctx.copy_text(text);
+            }
+        }
+        ClipboardRequest::Get { selection } => {
+            use base64::Engine;
+
+            let text = read_clipboard().unwrap_or_default();
+            let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+            if let Some(tab) = state.tabs.iter().find(|t| t.id == tab) {
+                let _ = tab.write(format!("\x1b]52;{};{}\x07", selection, encoded).as_bytes());
+            }
+        }
+    }
+}
+
+// 回应剪贴板权限提示，`remember` 时保存为该主机的默认权限
+pub fn answer_clipboard_prompt(state: &mut AppState, prompt: ClipboardPrompt, allow: bool, remember: bool) {
+    if remember {
+        let access = state
+            .config
+            .settings
+            .clipboard_access
+            .entry(prompt.host.clone())
+            .or_default();
+        match prompt.request {
+            ClipboardRequest::Set(_) => access.write = Some(allow),
+            ClipboardRequest::Get { .. } => access.read = Some(allow),
+        }
+        if state.config.settings.auto_save_config {
+            save_config(state);
+        }
+    }
+    if allow {
+        apply_clipboard_request(state, prompt.tab, prompt.request);
+    }
+}
+
+// 读取系统剪贴板文本
+pub fn read_clipboard() -> Option<String> {
+    arboard::Clipboard::new().ok()?.get_text().ok()
+}
+
+// 粘贴文本：开启粘贴保护时，多行或包含 sudo 的内容需要先确认
+pub fn paste_text(state: &mut AppState, target: PasteTarget, text: String) {
+    if text.is_empty() {
+        return;
+    }
+    if state.config.settings.paste_protection && is_risky_paste(&text) {
+        state.pending_paste = Some(PendingPaste { target, text });
+    } else {
+        deliver_paste(state, target, text);
+    }
+}
+
+// 将粘贴内容发送到目标（终端标签按括号粘贴模式编码）
+pub fn deliver_paste(state: &mut AppState, target: PasteTarget, text: String) {
+    match target {
+        PasteTarget::Tab(id) => {
+            if let Some(tab) = state.tabs.iter().find(|t| t.id == id) {
+                if let Err(e) = tab.write(&tab.terminal.encode_paste(&text)) {
+                    state.terminal.feed(&format!("❌ Error: {}\n", e));
+                }
+            }
+        }
+        PasteTarget::CommandInput => {
+            let mut lines: Vec<&str> = text.lines().collect();
+            // 最后一行没有换行符时留在输入框中，不立即执行
+            let rest = if text.ends_with('\n') { "" } else { lines.pop().unwrap_or("") };
+            if can_submit_command(state) {
+                let first = std::mem::take(&mut state.command_input);
+                for (i, line) in lines.into_iter().enumerate() {
+                    let command = if i == 0 { format!("{}{}", first, line) } else { line.to_string() };
+                    if !command.trim().is_empty() {
+                        submit_command(state, command);
+                    }
+                }
+            } else {
+                for line in lines {
+                    state.command_input.push_str(line);
+                    state.command_input.push(' ');
+                }
+            }
+            state.command_input.push_str(rest);
+        }
+    }
+}
+
 // 处理 SSH 消息
 fn process_ssh_messages(state: &mut AppState) {
-    let mut rx = state.ssh_msg_rx.lock().unwrap();
-    while let Ok(msg) = rx.try_recv() {
+    let messages: Vec<_> = {
+        let mut rx = state.ssh_msg_rx.lock().unwrap();
+        std::iter::from_fn(|| rx.try_recv().ok()).collect()
+    };
+    for msg in messages {
         match msg {
             SshMessage::Connected(info) => {
-                state
-                    .terminal_output
-                    .push_str(&format!("✅ Connected: {}\n", info));
+                state.terminal.feed(&format!("✅ Connected: {}\n", info));
                 if let Some(idx) = state.selected_connection {
                     state.connection_status[idx] = ConnectionStatus::Connected;
+                    open_connection_tab(state, idx);
+                    let name = state.connections[idx].name.clone();
+                    state.transfers.reconnected(&name);
                 }
             }
             SshMessage::Disconnected => {
-                state.terminal_output.push_str("❌ Disconnected\n");
+                state.terminal.feed("❌ Disconnected\n");
                 if let Some(idx) = state.selected_connection {
                     state.connection_status[idx] = ConnectionStatus::Disconnected;
                 }
             }
             SshMessage::Output(output) => {
-                state.terminal_output.push_str(&output);
+                state.terminal.feed(&output);
             }
             SshMessage::Error(error) => {
-                state
-                    .terminal_output
-                    .push_str(&format!("❌ Error: {}\n", error));
+                state.terminal.feed(&format!("❌ Error: {}\n", error));
                 if let Some(idx) = state.selected_connection {
                     state.connection_status[idx] = ConnectionStatus::Error;
                 }
@@ -332,26 +1196,214 @@ fn process_ai_messages(state: &mut AppState) {
     }
 }
 
+// 传输队列文件（与命令历史放在同一目录）
+fn transfers_path() -> Option<std::path::PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("ishell").join("transfers.json"))
+}
+
+// 加载上次未完成的传输
+fn load_transfers() -> crate::transfer::TransferManager {
+    let Some(path) = transfers_path() else {
+        return Default::default();
+    };
+    match crate::transfer::TransferManager::load(&path) {
+        Ok(transfers) => transfers,
+        Err(e) => {
+            eprintln!("⚠️ Failed to load transfer queue: {}", e);
+            Default::default()
+        }
+    }
+}
+
+fn save_transfers(state: &AppState) {
+    if let Some(path) = transfers_path() {
+        if let Err(e) = state.transfers.save(&path) {
+            eprintln!("⚠️ Failed to save transfer queue: {}", e);
+        }
+    }
+}
+
+// 按名称找到已连接的 SSH 会话
+pub fn connected_session(state: &AppState, name: &str) -> Option<Arc<std::sync::Mutex<SshSession>>> {
+    let index = state.connections.iter().position(|c| c.name == name)?;
+    if state.connection_status.get(index) != Some(&ConnectionStatus::Connected) {
+        return None;
+    }
+    state.ssh_sessions.get(index)?.clone()
+}
+
+// 为排队中的传输启动工作线程，并在队列变化时保存
+fn pump_transfers(state: &mut AppState) {
+    let concurrency = state.config.settings.transfer_concurrency;
+    let ids = state.transfers.startable(concurrency, |name| connected_session(state, name).is_some());
+    for id in ids {
+        let Some(session) = state.transfers.get(id).and_then(|item| connected_session(state, &item.connection)) else {
+            continue;
+        };
+        if let Some((item, control)) = state.transfers.start(id) {
+            crate::transfer::spawn_worker(
+                item,
+                session,
+                control,
+                state.config.settings.transfer_chunk_kb * 1024,
+                state.config.settings.transfer_verify_tail,
+                state.sftp_msg_tx.clone(),
+            );
+        }
+    }
+    if state.transfers.take_dirty() {
+        save_transfers(state);
+    }
+}
+
+// 处理传输工作线程的更新
+fn apply_transfer_update(state: &mut AppState, update: crate::transfer::TransferUpdate) {
+    use crate::transfer::{TransferState, TransferUpdate};
+
+    let is_progress = matches!(update, TransferUpdate::Progress(_));
+    let Some(item) = state.transfers.apply(update).cloned() else {
+        return;
+    };
+    if is_progress {
+        return;
+    }
+    match &item.state {
+        TransferState::Completed => {
+            state.sftp_status = format!("✅ {} 传输完成", item.name());
+            // 上传到正在浏览的目录时刷新列表
+            let parent = crate::terminal::parent_dir(&item.remote_path);
+            if item.direction == crate::types::TransferDirection::Upload
+                && state.show_file_browser
+                && parent == state.remote_current_path
+            {
+                crate::ui::file_browser::request_file_list(state);
+            }
+        }
+        TransferState::Interrupted => {
+            state.sftp_status = format!("⚠️ 连接中断，重连后将续传 {}", item.name());
+        }
+        TransferState::Failed { error } => {
+            state.sftp_status = format!("❌ {}: {}", item.name(), error);
+        }
+        _ => {}
+    }
+}
+
+// 上传已在本地保存的远程编辑文件
+fn pump_remote_edits(state: &mut AppState) {
+    for edit in state.remote_edits.poll(std::time::Instant::now()) {
+        match connected_session(state, &edit.connection) {
+            Some(session) => crate::remote_edit::spawn_upload(session, edit, false, state.sftp_msg_tx.clone()),
+            None => {
+                state.remote_edits.apply(crate::remote_edit::EditUpdate::Failed {
+                    id: edit.id,
+                    error: format!("连接 {} 未连接", edit.connection),
+                });
+            }
+        }
+    }
+}
+
+// 处理远程编辑的下载/上传结果
+fn apply_edit_update(state: &mut AppState, update: crate::remote_edit::EditUpdate) {
+    use crate::remote_edit::{EditState, EditorBuffer, EditUpdate};
+
+    let downloaded = matches!(update, EditUpdate::Downloaded { .. });
+    let Some(edit) = state.remote_edits.apply(update).cloned() else {
+        return;
+    };
+    match &edit.state {
+        EditState::Watching if downloaded && edit.builtin => match EditorBuffer::load(&edit) {
+            Ok(buffer) => {
+                state.editor_buffers.retain(|b| b.edit_id != edit.id);
+                state.editor_buffers.push(buffer);
+            }
+            Err(e) => state.sftp_status = format!("❌ {}", e),
+        },
+        EditState::Watching if downloaded => {
+            match crate::remote_edit::launch_editor(&state.config.settings.external_editor, &edit.local_path) {
+                Ok(()) => state.sftp_status = format!("✏ 正在编辑 {}，保存后自动上传", edit.name()),
+                Err(e) => state.sftp_status = format!("❌ 无法启动编辑器: {}", e),
+            }
+        }
+        EditState::Watching => {
+            state.sftp_status = format!("✅ 已保存到远程: {}", edit.remote_path);
+            if state.show_file_browser && crate::terminal::parent_dir(&edit.remote_path) == state.remote_current_path {
+                crate::ui::file_browser::request_file_list(state);
+            }
+        }
+        EditState::Conflict { .. } => {
+            state.sftp_status = format!("⚠️ {} 在打开后已被修改，未上传", edit.remote_path);
+        }
+        EditState::Failed { error } => {
+            state.sftp_status = format!("❌ {}: {}", edit.name(), error);
+        }
+        EditState::Downloading | EditState::Uploading => {}
+    }
+}
+
 // 处理 SFTP 消息 (v0.3.0)
 fn process_sftp_messages(state: &mut AppState) {
-    let mut rx = state.sftp_msg_rx.lock().unwrap();
-    while let Ok(msg) = rx.try_recv() {
+    let messages: Vec<_> = {
+        let mut rx = state.sftp_msg_rx.lock().unwrap();
+        std::iter::from_fn(|| rx.try_recv().ok()).collect()
+    };
+    for msg in messages {
         match msg {
-            crate::types::SftpMessage::FileList(files) => {
+            crate::types::SftpMessage::Directory(path) => {
+                state.remote_current_path = path;
+            }
+            crate::types::SftpMessage::FileList(mut files) => {
+                state.remote_sort.sort(&mut files);
+                state.remote_link_requests.clear();
                 state.remote_files = files;
                 state.sftp_status = format!("已加载 {} 个文件", state.remote_files.len());
             }
-            crate::types::SftpMessage::Progress(progress) => {
-                state.sftp_progress = progress;
+            crate::types::SftpMessage::Status(status) => {
+                state.sftp_status = status;
+            }
+            crate::types::SftpMessage::Transfer(update) => {
+                apply_transfer_update(state, update);
+            }
+            crate::types::SftpMessage::Edit(update) => {
+                apply_edit_update(state, update);
+            }
+            crate::types::SftpMessage::Viewer(update) => {
+                let id = match &update {
+                    crate::editor::ViewerUpdate::Chunk { id, .. } | crate::editor::ViewerUpdate::Failed { id, .. } => *id,
+                };
+                if let Some(viewer) = state.file_viewers.iter_mut().find(|v| v.id == id) {
+                    viewer.apply(update);
+                }
+            }
+            crate::types::SftpMessage::Changed(status) => {
+                state.sftp_status = format!("✅ {}", status);
+                if state.show_file_browser {
+                    crate::ui::file_browser::request_file_list(state);
+                }
+            }
+            crate::types::SftpMessage::LinkResolved { path, target, is_dir } => {
+                let request = state.remote_link_requests.insert(path.clone(), LinkRequest::Done);
+                if let Some(entry) = state.remote_files.iter_mut().find(|e| e.path == path) {
+                    entry.link_target = target;
+                    entry.is_dir = is_dir;
+                    let entry = entry.clone();
+                    // 指向目录的链接排到目录中（选择按路径保存，不受影响）
+                    state.remote_sort.sort(&mut state.remote_files);
+                    if request == Some(LinkRequest::Pending { open: true }) {
+                        crate::ui::file_browser::open_entry(state, &entry);
+                    }
+                }
+            }
+            crate::types::SftpMessage::Ids { connection, names } => {
+                state.remote_ids.insert(connection, names);
             }
             crate::types::SftpMessage::Complete => {
-                state.sftp_progress = 1.0;
                 state.sftp_status = "操作完成".to_string();
                 // 刷新文件列表
                 // refresh_remote_files(state); // 注意：这需要在 UI 线程调用
             }
             crate::types::SftpMessage::Error(error) => {
-                state.sftp_progress = 0.0;
                 state.sftp_status = format!("❌ {}", error);
             }
         }
@@ -365,15 +1417,18 @@ pub fn connect_ssh(state: &mut AppState, index: usize) {
     }
 
     let conn = &state.connections[index];
+    if conn.protocol != Protocol::Ssh {
+        state.connection_status[index] = ConnectionStatus::Connecting;
+        open_connection_tab(state, index);
+        return;
+    }
     if conn.auth.is_none() {
-        state
-            .terminal_output
-            .push_str("❌ No authentication method configured\n");
+        state.terminal.feed("❌ No authentication method configured\n");
         return;
     }
 
     state.connection_status[index] = ConnectionStatus::Connecting;
-    state.terminal_output.push_str(&format!(
+    state.terminal.feed(&format!(
         "🔄 Connecting to {}@{}:{}...\n",
         conn.username, conn.host, conn.port
     ));
@@ -412,6 +1467,19 @@ pub fn connect_ssh(state: &mut AppState, index: usize) {
 
 // 断开 SSH 连接
 pub fn disconnect_ssh(state: &mut AppState, index: usize) {
+    if state.connections.get(index).is_some_and(|c| c.protocol != Protocol::Ssh) {
+        state.connection_status[index] = ConnectionStatus::Disconnected;
+    }
+
+    // 关闭该连接的会话标签
+    while let Some(tab_index) = state
+        .tabs
+        .iter()
+        .position(|t| t.kind == TabKind::Remote { connection: index })
+    {
+        close_tab(state, tab_index);
+    }
+
     if let Some(session) = &state.ssh_sessions[index] {
         let sess = session.lock().unwrap();
         let _ = sess.disconnect();
@@ -429,7 +1497,7 @@ pub fn execute_ssh_command(state: &mut AppState, command: String) {
             let session = session.clone();
             let tx = state.ssh_msg_tx.clone();
 
-            state.terminal_output.push_str(&format!("$ {}\n", command));
+            state.terminal.feed(&format!("$ {}\n", command));
 
             std::thread::spawn(move || {
                 let result = {
@@ -497,6 +1565,111 @@ pub fn send_ai_message(state: &mut AppState, user_message: String) {
 }
 
 // 更改 AI 提供商
+// 将命令块（命令、退出码与输出）发送给 AI 分析
+pub fn send_block_to_ai(state: &mut AppState, block: usize) {
+    let terminal = active_terminal(state);
+    let (Some(info), Some(output)) = (terminal.blocks().get(block), terminal.block_output(block)) else {
+        return;
+    };
+    let status = match info.exit_code {
+        Some(code) => format!("退出码 {}", code),
+        None => "退出码未知".to_string(),
+    };
+    let message = format!(
+        "以下是命令 `{}` 的输出（{}），请帮我分析：\n```\n{}\n```",
+        info.command, status, output
+    );
+    send_ai_message(state, message);
+}
+
+/// 打开终端中 Ctrl+点击的链接
+///
+/// URL 在浏览器中打开；SSH 标签中的路径在 SFTP 文件浏览器中打开所在目录，
+/// 本地标签中的路径交给系统默认程序。
+pub fn open_terminal_link(state: &mut AppState, ctx: &egui::Context, target: LinkTarget) {
+    let path = match target {
+        LinkTarget::Url(url) => {
+            ctx.open_url(egui::OpenUrl::new_tab(url));
+            return;
+        }
+        LinkTarget::Path { path, .. } => path,
+    };
+    // 控制台显示的是所选连接上执行命令的输出
+    let remote = match state.active_tab.and_then(|i| state.tabs.get(i)).map(|tab| tab.kind) {
+        Some(TabKind::Remote { connection }) => Some(connection),
+        Some(TabKind::Local) => None,
+        None => state
+            .selected_connection
+            .filter(|&i| state.connection_status.get(i) == Some(&ConnectionStatus::Connected)),
+    };
+    let cwd = active_terminal(state).working_directory().map(str::to_string);
+    match remote {
+        Some(connection) => {
+            if !matches!(state.ssh_sessions.get(connection), Some(Some(_))) {
+                state.sftp_status = "⚠️ 该连接不支持 SFTP，无法打开路径".to_string();
+                return;
+            }
+            let path = resolve_path(&path, cwd.as_deref(), None);
+            state.selected_connection = Some(connection);
+            state.remote_current_path = parent_dir(&path);
+            state.selected_remote_files = vec![path];
+            state.show_file_browser = true;
+            crate::ui::file_browser::request_file_list(state);
+        }
+        None => {
+            let home = dirs::home_dir().map(|h| h.to_string_lossy().to_string());
+            let path = resolve_path(&path, cwd.as_deref(), home.as_deref());
+            ctx.open_url(egui::OpenUrl::new_tab(format!("file://{}", path)));
+        }
+    }
+}
+
+/// 打开导出对话框，`block` 为 None 时导出整个会话
+pub fn open_export_dialog(state: &mut AppState, block: Option<usize>) {
+    state.export_dialog = Some(ExportDialog {
+        tab: state.active_tab.and_then(|i| state.tabs.get(i)).map(|t| t.id),
+        block,
+        format: ExportFormat::Html,
+        redact: true,
+        status: String::new(),
+    });
+}
+
+/// 按导出对话框的设置生成内容，返回（建议的文件名，内容）
+pub fn export_terminal(state: &AppState, dialog: &ExportDialog) -> Option<(String, String)> {
+    let (terminal, title, connection) = match dialog.tab {
+        Some(id) => {
+            let tab = state.tabs.iter().find(|t| t.id == id)?;
+            let connection = match tab.kind {
+                TabKind::Remote { connection } => Some(connection),
+                TabKind::Local => None,
+            };
+            (&tab.terminal, tab.display_title().to_string(), connection)
+        }
+        None => (&state.terminal, "console".to_string(), None),
+    };
+    let (range, title) = match dialog.block {
+        Some(block) => (
+            crate::export::block_range(terminal, block)?,
+            format!("{} - {}", title, terminal.blocks()[block].command),
+        ),
+        None => (crate::export::session_range(terminal), title),
+    };
+
+    let redactor = dialog
+        .redact
+        .then(|| Redactor::compile(&state.config.settings.export_redaction_patterns).0);
+    let palette = connection_palette(state, connection);
+    let content = crate::export::export(terminal, range, dialog.format, &palette, redactor.as_ref(), &title);
+
+    let stem: String = title
+        .chars()
+        .map(|c| if c.is_alphanumeric() || "-_.".contains(c) { c } else { '_' })
+        .take(60)
+        .collect();
+    Some((format!("{}.{}", stem, dialog.format.extension()), content))
+}
+
 pub fn change_ai_provider(state: &mut AppState, provider: AiProviderType) {
     let ai_manager = state.ai_manager.clone();
     let runtime = state.runtime.clone();
@@ -518,13 +1691,100 @@ pub fn save_config(state: &mut AppState) {
     }
 }
 
+// 当前终端使用的调色板：优先使用对应连接的配色方案，否则跟随主题
+pub fn active_terminal_palette(state: &AppState) -> crate::theme::TerminalPalette {
+    let connection = match state.active_tab.and_then(|i| state.tabs.get(i)) {
+        Some(tab) => match tab.kind {
+            TabKind::Remote { connection } => Some(connection),
+            TabKind::Local => None,
+        },
+        None => state.selected_connection,
+    };
+    connection_palette(state, connection)
+}
+
+// 连接的终端调色板（None 为本地或日志控制台）
+fn connection_palette(state: &AppState, connection: Option<usize>) -> crate::theme::TerminalPalette {
+    connection
+        .and_then(|idx| state.connections.get(idx))
+        .and_then(|conn| conn.color_scheme.as_deref())
+        .and_then(|name| crate::color_scheme::find_scheme(&state.config.color_schemes, name))
+        .map(|scheme| scheme.to_palette())
+        .unwrap_or_else(|| {
+            crate::theme::ThemeManager::terminal_palette(
+                &state.config.settings.theme,
+                &state.config.custom_themes,
+            )
+        })
+}
+
+// 导入终端配色方案文件
+pub fn import_color_scheme(state: &mut AppState, path: &std::path::Path) {
+    match crate::color_scheme::ColorScheme::import_file(path) {
+        Ok(scheme) => {
+            state.color_scheme_status = format!("✅ 已导入配色方案: {}", scheme.name);
+            state.config.color_schemes.retain(|s| s.name != scheme.name);
+            state.config.color_schemes.push(scheme);
+        }
+        Err(e) => {
+            state.color_scheme_status = format!("❌ 导入失败: {}", e);
+        }
+    }
+}
+
+// 保存主题编辑器中的草稿为命名主题，并切换到该主题
+pub fn save_custom_theme(state: &mut AppState) {
+    let Some(mut theme) = state.theme_editor.clone() else {
+        return;
+    };
+    theme.name = theme.name.trim().to_string();
+    if let Err(e) = theme.validate() {
+        state.theme_editor_status = format!("❌ {}", e);
+        return;
+    }
+
+    match state.config.custom_themes.iter_mut().find(|t| t.name == theme.name) {
+        Some(existing) => *existing = theme.clone(),
+        None => state.config.custom_themes.push(theme.clone()),
+    }
+    state.config.settings.theme = theme.name.clone();
+    state.theme_editor_status = format!("✅ 已保存主题: {}", theme.name);
+    state.theme_editor = None;
+}
+
+// 从 TOML 文件导入主题
+pub fn import_custom_theme(state: &mut AppState, path: &std::path::Path) {
+    match crate::theme::CustomTheme::load_from_file(path) {
+        Ok(theme) => {
+            state.theme_editor_status = format!("✅ 已导入主题: {}", theme.name);
+            state.config.custom_themes.retain(|t| t.name != theme.name);
+            state.config.custom_themes.push(theme);
+        }
+        Err(e) => {
+            state.theme_editor_status = format!("❌ 导入失败: {}", e);
+        }
+    }
+}
+
+// 所有可选的配色方案名称（内置 + 用户导入）
+pub fn color_scheme_names(state: &AppState) -> Vec<String> {
+    crate::color_scheme::ColorScheme::builtin()
+        .iter()
+        .chain(state.config.color_schemes.iter())
+        .map(|s| s.name.clone())
+        .collect()
+}
+
 // 创建新连接
 pub fn create_connection(state: &mut AppState) {
     if state.new_conn_name.is_empty() || state.new_conn_host.is_empty() {
         return;
     }
 
-    let port = state.new_conn_port.parse().unwrap_or(22);
+    let port = state
+        .new_conn_port
+        .parse()
+        .unwrap_or(state.new_conn_protocol.default_port());
     let mut config = SshConfig::new(
         state.new_conn_name.clone(),
         state.new_conn_host.clone(),
@@ -532,8 +1792,11 @@ pub fn create_connection(state: &mut AppState) {
         state.new_conn_user.clone(),
     );
 
-    // 设置认证方法
-    if state.new_conn_use_key {
+    // 设置认证方法（Telnet / 原始 TCP 在终端中交互登录）
+    config.protocol = state.new_conn_protocol;
+    if config.protocol != Protocol::Ssh {
+        config.auth = None;
+    } else if state.new_conn_use_key {
         config.auth = Some(AuthMethod::PrivateKey {
             key_path: std::path::PathBuf::from(&state.new_conn_key_path),
             passphrase: if state.new_conn_password.is_empty() {
@@ -545,6 +1808,7 @@ pub fn create_connection(state: &mut AppState) {
     } else {
         config.auth = Some(AuthMethod::Password(state.new_conn_password.clone()));
     }
+    config.color_scheme = state.new_conn_color_scheme.clone();
 
     state.connections.push(config);
     state.ssh_sessions.push(None);
@@ -561,6 +1825,8 @@ pub fn create_connection(state: &mut AppState) {
     state.new_conn_password.clear();
     state.new_conn_use_key = false;
     state.new_conn_key_path.clear();
+    state.new_conn_color_scheme = None;
+    state.new_conn_protocol = Protocol::Ssh;
 
     state.show_new_connection = false;
 }
@@ -572,7 +1838,6 @@ pub fn create_connection(state: &mut AppState) {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::PathBuf;
 
     // Helper to create a testable app instance
     fn create_test_app() -> App {
@@ -599,6 +1864,9 @@ mod tests {
         let (sftp_msg_tx, sftp_msg_rx) = mpsc::unbounded_channel();
         let sftp_msg_rx = Arc::new(std::sync::Mutex::new(sftp_msg_rx));
 
+        let (session_msg_tx, session_msg_rx) = mpsc::unbounded_channel();
+        let session_msg_rx = Arc::new(std::sync::Mutex::new(session_msg_rx));
+
         let ai_manager = Arc::new(TokioMutex::new(Some(AiManager::new())));
 
         let state = AppState {
@@ -615,13 +1883,27 @@ mod tests {
             new_conn_password: String::new(),
             new_conn_use_key: false,
             new_conn_key_path: String::new(),
+            new_conn_color_scheme: None,
+            new_conn_protocol: Protocol::Ssh,
 
             ssh_sessions: Vec::new(),
             connection_status: Vec::new(),
 
-            terminal_output: String::new(),
+            terminal: TerminalBuffer::default(),
             command_input: String::new(),
 
+            tabs: Vec::new(),
+            active_tab: None,
+            next_tab_id: 0,
+            session_msg_tx,
+            session_msg_rx,
+            repaint_ctx: None,
+
+            clipboard_prompts: std::collections::VecDeque::new(),
+            export_dialog: None,
+            triggers: Default::default(),
+            pending_paste: None,
+
             ssh_msg_tx,
             ssh_msg_rx,
 
@@ -638,11 +1920,20 @@ mod tests {
             remote_current_path: "/".to_string(),
             local_current_path: std::env::current_dir().unwrap_or_default(),
             remote_files: Vec::new(),
+            remote_sort: Default::default(),
             local_files: Vec::new(),
             selected_remote_files: Vec::new(),
             selected_local_file: None,
-            sftp_progress: 0.0,
+            zmodem_progress: 0.0,
             sftp_status: String::new(),
+            transfers: Default::default(),
+            show_transfers: false,
+            remote_edits: Default::default(),
+            editor_buffers: Vec::new(),
+            file_viewers: Vec::new(),
+            file_op_dialog: None,
+            remote_ids: std::collections::HashMap::new(),
+            remote_link_requests: std::collections::HashMap::new(),
 
             sftp_msg_tx,
             sftp_msg_rx,
@@ -655,8 +1946,20 @@ mod tests {
             show_history_search: false,
             history_search_query: String::new(),
 
+            show_command_palette: false,
+            command_palette_query: String::new(),
+            command_palette_selected: 0,
+            terminal_focused: false,
+            keybinding_capture: None,
+            new_snippet_name: String::new(),
+            new_snippet_command: String::new(),
+
             show_settings: false,
             settings_page: SettingsPage::General,
+            color_scheme_status: String::new(),
+            theme_editor: None,
+            theme_editor_status: String::new(),
+            cjk_font: None,
 
             runtime,
         };
@@ -695,6 +1998,85 @@ mod tests {
         assert_eq!(app.state.connection_status.len(), 1);
     }
 
+    #[test]
+    fn test_run_action_and_snippets() {
+        let mut app = create_test_app();
+        let ctx = egui::Context::default();
+
+        run_action(&mut app.state, &ctx, Action::CommandPalette);
+        assert!(app.state.show_command_palette);
+
+        app.state.terminal.feed("some output\n");
+        run_action(&mut app.state, &ctx, Action::ClearTerminal);
+        assert_eq!(app.state.terminal.plain_text(), "");
+
+        assert!(app.state.config.settings.terminal_passthrough);
+        run_action(&mut app.state, &ctx, Action::ToggleTerminalPassthrough);
+        assert!(!app.state.config.settings.terminal_passthrough);
+
+        // 未连接时片段填入输入框
+        app.state.config.snippets.push(Snippet {
+            name: "uptime".to_string(),
+            command: "uptime".to_string(),
+        });
+        run_palette_item(&mut app.state, &ctx, PaletteItem::Snippet(0));
+        assert!(!app.state.show_command_palette);
+        assert_eq!(app.state.command_input, "uptime");
+    }
+
+    #[test]
+    fn test_save_custom_theme() {
+        let mut app = create_test_app();
+
+        app.state.theme_editor = Some(crate::theme::CustomTheme {
+            name: " Ocean ".to_string(),
+            ..Default::default()
+        });
+        save_custom_theme(&mut app.state);
+        assert!(app.state.theme_editor.is_none());
+        assert_eq!(app.state.config.settings.theme, "Ocean");
+        assert_eq!(app.state.config.custom_themes.len(), 1);
+
+        // 同名主题覆盖而不是重复添加
+        app.state.theme_editor = Some(crate::theme::CustomTheme {
+            name: "Ocean".to_string(),
+            widget_rounding: 8.0,
+            ..Default::default()
+        });
+        save_custom_theme(&mut app.state);
+        assert_eq!(app.state.config.custom_themes.len(), 1);
+        assert_eq!(app.state.config.custom_themes[0].widget_rounding, 8.0);
+
+        // 保留名称被拒绝，草稿保持打开
+        app.state.theme_editor = Some(crate::theme::CustomTheme {
+            name: "light".to_string(),
+            ..Default::default()
+        });
+        save_custom_theme(&mut app.state);
+        assert!(app.state.theme_editor.is_some());
+        assert_eq!(app.state.config.custom_themes.len(), 1);
+    }
+
+    #[test]
+    fn test_per_connection_color_scheme() {
+        let mut app = create_test_app();
+        let theme_palette = crate::theme::ThemeManager::terminal_palette("dark", &[]);
+        assert_eq!(active_terminal_palette(&app.state), theme_palette);
+
+        app.state.new_conn_name = "prod".to_string();
+        app.state.new_conn_host = "prod.example.com".to_string();
+        app.state.new_conn_color_scheme = Some("Production Red".to_string());
+        create_connection(&mut app.state);
+        app.state.selected_connection = Some(0);
+
+        assert_eq!(
+            app.state.connections[0].color_scheme.as_deref(),
+            Some("Production Red")
+        );
+        assert_ne!(active_terminal_palette(&app.state), theme_palette);
+        assert!(color_scheme_names(&app.state).contains(&"Production Red".to_string()));
+    }
+
     #[test]
     fn test_terminal_input_handling() {
         let mut app = create_test_app();
@@ -722,7 +2104,265 @@ mod tests {
         execute_ssh_command(&mut app.state, "echo hello".to_string());
 
         // Output format check
-        assert!(app.state.terminal_output.contains("$ echo hello"));
+        assert!(app.state.terminal.plain_text().contains("$ echo hello"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_local_shell_tabs() {
+        let mut app = create_test_app();
+        assert!(!can_submit_command(&app.state));
+
+        open_local_tab(&mut app.state);
+        open_local_tab(&mut app.state);
+        assert_eq!(app.state.tabs.len(), 2);
+        assert_eq!(app.state.active_tab, Some(1));
+        assert!(app.state.tabs.iter().all(|t| t.is_live()));
+        assert!(can_submit_command(&app.state));
+
+        // 本地标签使用主题调色板，清空作用于当前标签
+        let theme_palette = crate::theme::ThemeManager::terminal_palette("dark", &[]);
+        assert_eq!(active_terminal_palette(&app.state), theme_palette);
+        app.state.terminal.feed("console\n");
+        run_action(&mut app.state, &egui::Context::default(), Action::ClearTerminal);
+        assert!(app.state.terminal.plain_text().contains("console"));
+
+        close_tab(&mut app.state, 1);
+        assert_eq!(app.state.active_tab, Some(0));
+        run_action(&mut app.state, &egui::Context::default(), Action::CloseTab);
+        assert!(app.state.tabs.is_empty());
+        assert_eq!(app.state.active_tab, None);
+    }
+
+    #[test]
+    fn test_deferred_trigger_scan_continues_without_output() {
+        let mut app = create_test_app();
+        app.state.config.triggers = vec![crate::types::TriggerRule {
+            name: "build".to_string(),
+            pattern: "Build finished".to_string(),
+            connection: String::new(),
+            action: crate::types::TriggerAction::Highlight { color: [1, 2, 3] },
+            enabled: true,
+        }];
+        let index = add_tab(&mut app.state, "local".to_string(), TabKind::Local);
+
+        // 一次突发输出的扫描超出预算（匹配的行在末尾），之后不再有输出
+        let burst = format!("{}Build finished\r\n", "line\r\n".repeat(50));
+        app.state.tabs[index].terminal.feed(&burst);
+        let highlighted = |app: &App| {
+            let line = app.state.tabs[index].terminal.line(50).unwrap();
+            assert_eq!(line.text(), "Build finished");
+            line.cells[0].style.fg == crate::terminal::TermColor::Rgb(1, 2, 3)
+        };
+        assert!(continue_trigger_scans(&mut app.state, std::time::Duration::ZERO));
+        assert!(!highlighted(&app));
+
+        // 下一帧继续扫描
+        assert!(!continue_trigger_scans(&mut app.state, crate::triggers::TRIGGER_BUDGET));
+        assert!(highlighted(&app));
+    }
+
+    #[test]
+    fn test_zmodem_files_from_background_picker() {
+        let mut app = create_test_app();
+        let index = add_tab(&mut app.state, "local".to_string(), TabKind::Local);
+        let id = app.state.tabs[index].id;
+        app.state.tabs[index].zmodem = Some(crate::zmodem::ZmodemSession::awaiting_files());
+        app.state.tabs[index].zmodem_picking = true;
+
+        // 对话框取消时传输收到空列表并结束等待
+        let files = Vec::new();
+        app.state.session_msg_tx.send(SessionMessage::ZmodemFiles { tab: id, files }).unwrap();
+        process_session_messages(&mut app.state);
+        let tab = &app.state.tabs[index];
+        assert!(!tab.zmodem_picking);
+        assert!(!tab.zmodem.as_ref().unwrap().needs_files());
+    }
+
+    #[test]
+    fn test_osc52_clipboard_permissions() {
+        let mut app = create_test_app();
+        let ctx = egui::Context::default();
+        app.state.repaint_ctx = Some(ctx.clone());
+        app.state.config.settings.auto_save_config = false;
+        let index = add_tab(&mut app.state, "local".to_string(), TabKind::Local);
+        let id = app.state.tabs[index].id;
+
+        // 未记录权限时进入提示队列
+        let output = |data: &[u8]| SessionMessage::Output { tab: id, data: data.to_vec() };
+        app.state.session_msg_tx.send(output(b"\x1b]52;c;aGVsbG8=\x07")).unwrap();
+        process_session_messages(&mut app.state);
+        let prompt = app.state.clipboard_prompts.pop_front().unwrap();
+        assert_eq!(prompt.host, "localhost");
+        assert_eq!(prompt.request, ClipboardRequest::Set("hello".to_string()));
+
+        // 始终允许后，后续请求直接执行
+        answer_clipboard_prompt(&mut app.state, prompt, true, true);
+        assert_eq!(ctx.output(|o| o.copied_text.clone()), "hello");
+        assert_eq!(app.state.config.settings.clipboard_access["localhost"].write, Some(true));
+
+        app.state.session_msg_tx.send(output(b"\x1b]52;c;d29ybGQ=\x07")).unwrap();
+        process_session_messages(&mut app.state);
+        assert!(app.state.clipboard_prompts.is_empty());
+        assert_eq!(ctx.output(|o| o.copied_text.clone()), "world");
+
+        // 读取权限单独询问
+        app.state.session_msg_tx.send(output(b"\x1b]52;c;?\x07")).unwrap();
+        process_session_messages(&mut app.state);
+        assert_eq!(app.state.clipboard_prompts.len(), 1);
+
+        // 反复请求时同一标签的同类提示合并
+        app.state.config.settings.clipboard_access.clear();
+        app.state.clipboard_prompts.clear();
+        let flood = b"\x1b]52;c;?\x07".repeat(100);
+        app.state.session_msg_tx.send(output(&flood)).unwrap();
+        app.state.session_msg_tx.send(output(b"\x1b]52;c;YQ==\x07\x1b]52;c;Yg==\x07")).unwrap();
+        process_session_messages(&mut app.state);
+        assert_eq!(app.state.clipboard_prompts.len(), 2);
+        assert!(matches!(app.state.clipboard_prompts[0].request, ClipboardRequest::Get { .. }));
+        assert_eq!(app.state.clipboard_prompts[1].request, ClipboardRequest::Set("b".to_string()));
+
+        // 多个标签的提示总数有上限
+        for _ in 0..MAX_CLIPBOARD_PROMPTS {
+            let index = add_tab(&mut app.state, "local".to_string(), TabKind::Local);
+            let id = app.state.tabs[index].id;
+            let data = b"\x1b]52;c;?\x07".to_vec();
+            app.state.session_msg_tx.send(SessionMessage::Output { tab: id, data }).unwrap();
+        }
+        process_session_messages(&mut app.state);
+        assert_eq!(app.state.clipboard_prompts.len(), MAX_CLIPBOARD_PROMPTS);
+    }
+
+    #[test]
+    fn test_command_blocks_record_exit_codes() {
+        let mut app = create_test_app();
+        let index = add_tab(&mut app.state, "local".to_string(), TabKind::Local);
+        let id = app.state.tabs[index].id;
+        app.state.active_tab = Some(index);
+
+        let output = b"\x1b]133;A\x07$ \x1b]133;B\x07make\r\n\x1b]133;C\x07error\r\n\x1b]133;D;2\x07";
+        app.state
+            .session_msg_tx
+            .send(SessionMessage::Output { tab: id, data: output.to_vec() })
+            .unwrap();
+        process_session_messages(&mut app.state);
+
+        let entry = app.state.command_history.get_recent(1)[0];
+        assert_eq!(entry.command, "make");
+        assert_eq!(entry.connection, "本地");
+        assert_eq!(entry.exit_code, Some(2));
+        assert_eq!(active_terminal(&app.state).block_output(0).unwrap(), "error");
+    }
+
+    #[test]
+    fn test_export_session_and_block() {
+        let mut app = create_test_app();
+        let index = add_tab(&mut app.state, "local".to_string(), TabKind::Local);
+        let id = app.state.tabs[index].id;
+        app.state.active_tab = Some(index);
+
+        let output = b"welcome\r\n\x1b]133;A\x07$ \x1b]133;B\x07env\r\n\x1b]133;C\x07TOKEN=s3cr3t\r\n\x1b]133;D;0\x07";
+        app.state
+            .session_msg_tx
+            .send(SessionMessage::Output { tab: id, data: output.to_vec() })
+            .unwrap();
+        process_session_messages(&mut app.state);
+
+        open_export_dialog(&mut app.state, Some(0));
+        let mut dialog = app.state.export_dialog.clone().unwrap();
+        dialog.format = ExportFormat::PlainText;
+        let (file_name, content) = export_terminal(&app.state, &dialog).unwrap();
+        assert_eq!(file_name, "local_-_env.txt");
+        assert_eq!(content, "$ env\nTOKEN=[REDACTED]\n");
+
+        dialog.block = None;
+        dialog.redact = false;
+        let (_, content) = export_terminal(&app.state, &dialog).unwrap();
+        assert_eq!(content, "welcome\n$ env\nTOKEN=s3cr3t\n");
+    }
+
+    #[test]
+    fn test_open_remote_path_link_in_file_browser() {
+        let mut app = create_test_app();
+        app.state.new_conn_name = "web".to_string();
+        app.state.new_conn_host = "web".to_string();
+        create_connection(&mut app.state);
+        app.state.ssh_sessions[0] = Some(Arc::new(std::sync::Mutex::new(SshSession::new(
+            "web".to_string(),
+            22,
+            "root".to_string(),
+        ))));
+        let index = add_tab(&mut app.state, "web".to_string(), TabKind::Remote { connection: 0 });
+        app.state.active_tab = Some(index);
+        app.state.tabs[index].terminal.feed("\x1b]7;file://web/srv/app\x07");
+
+        let ctx = egui::Context::default();
+        let link = LinkTarget::Path {
+            path: "src/main.rs".to_string(),
+            line: Some(3),
+            column: None,
+        };
+        open_terminal_link(&mut app.state, &ctx, link);
+        assert!(app.state.show_file_browser);
+        assert_eq!(app.state.selected_connection, Some(0));
+        assert_eq!(app.state.remote_current_path, "/srv/app/src");
+        assert_eq!(app.state.selected_remote_files, vec!["/srv/app/src/main.rs".to_string()]);
+    }
+
+    #[test]
+    fn test_link_resolved_resorts_remote_files() {
+        let mut app = create_test_app();
+        let mut link = FileEntry::new("a-link".to_string(), "/srv/a-link".to_string(), false);
+        link.kind = FileKind::Symlink;
+        let mut files = vec![
+            FileEntry::new("z-dir".to_string(), "/srv/z-dir".to_string(), true),
+            link,
+            FileEntry::new("b.txt".to_string(), "/srv/b.txt".to_string(), false),
+        ];
+        app.state.remote_sort.sort(&mut files);
+        app.state.remote_files = files;
+        app.state.selected_remote_files = vec!["/srv/a-link".to_string()];
+
+        app.state
+            .sftp_msg_tx
+            .send(SftpMessage::LinkResolved {
+                path: "/srv/a-link".to_string(),
+                target: Some("/data".to_string()),
+                is_dir: true,
+            })
+            .unwrap();
+        process_sftp_messages(&mut app.state);
+        let names: Vec<_> = app.state.remote_files.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["a-link", "z-dir", "b.txt"]);
+        assert_eq!(app.state.selected_remote_files, vec!["/srv/a-link".to_string()]);
+    }
+
+    #[test]
+    fn test_paste_protection() {
+        let mut app = create_test_app();
+
+        paste_text(&mut app.state, PasteTarget::CommandInput, "ls -la".to_string());
+        assert_eq!(app.state.command_input, "ls -la");
+        assert!(app.state.pending_paste.is_none());
+
+        // 多行与 sudo 需要确认
+        app.state.command_input.clear();
+        paste_text(&mut app.state, PasteTarget::CommandInput, "cd /\nrm -rf tmp".to_string());
+        assert!(app.state.command_input.is_empty());
+        let pending = app.state.pending_paste.take().unwrap();
+        assert_eq!(pending.target, PasteTarget::CommandInput);
+
+        paste_text(&mut app.state, PasteTarget::CommandInput, "sudo reboot".to_string());
+        assert!(app.state.pending_paste.is_some());
+
+        // 确认后未连接时合并到输入框，而不是逐行执行
+        deliver_paste(&mut app.state, pending.target, pending.text);
+        assert_eq!(app.state.command_input, "cd / rm -rf tmp");
+
+        app.state.command_input.clear();
+        app.state.config.settings.paste_protection = false;
+        paste_text(&mut app.state, PasteTarget::CommandInput, "sudo reboot".to_string());
+        assert_eq!(app.state.command_input, "sudo reboot");
     }
 
     #[test]
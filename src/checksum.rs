@@ -0,0 +1,223 @@
+use crate::ssh::SshSession;
+use crate::types::Result;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+// ============================================================================
+// 传输后的完整性校验：比较本地与远程文件的哈希
+// ============================================================================
+
+/// 校验和算法，按优先顺序排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Md5,
+}
+
+impl ChecksumAlgorithm {
+    pub const ALL: [ChecksumAlgorithm; 2] = [ChecksumAlgorithm::Sha256, ChecksumAlgorithm::Md5];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Md5 => "md5",
+        }
+    }
+
+    /// 十六进制摘要的长度
+    fn hex_len(&self) -> usize {
+        match self {
+            ChecksumAlgorithm::Sha256 => 64,
+            ChecksumAlgorithm::Md5 => 32,
+        }
+    }
+
+    /// 远程主机上可能存在的命令（GNU coreutils 优先，其次是 BSD/macOS）
+    fn remote_commands(&self) -> &'static [&'static str] {
+        match self {
+            ChecksumAlgorithm::Sha256 => &["sha256sum", "shasum -a 256"],
+            ChecksumAlgorithm::Md5 => &["md5sum", "md5 -q"],
+        }
+    }
+}
+
+/// 一次校验的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksums {
+    pub algorithm: ChecksumAlgorithm,
+    pub local: String,
+    pub remote: String,
+}
+
+impl Checksums {
+    pub fn matches(&self) -> bool {
+        self.local == self.remote
+    }
+}
+
+/// 计算本地文件的摘要（小写十六进制）
+pub fn local_checksum(path: &Path, algorithm: ChecksumAlgorithm) -> Result<String> {
+    match algorithm {
+        ChecksumAlgorithm::Sha256 => digest_file::<Sha256>(path),
+        ChecksumAlgorithm::Md5 => digest_file::<Md5>(path),
+    }
+}
+
+fn digest_file<D: Digest + Write>(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = D::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hex(&hasher.finalize()))
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 通过 exec 通道在远程主机上计算摘要
+///
+/// libssh2 不支持 SFTP 的 `check-file` 扩展，因此依次尝试远程的
+/// `sha256sum`、`shasum`、`md5sum`、`md5`，使用第一个可用的算法。
+pub fn remote_checksum(session: &SshSession, path: &str) -> Result<(ChecksumAlgorithm, String)> {
+    let mut last_output = String::new();
+    for algorithm in ChecksumAlgorithm::ALL {
+        for command in algorithm.remote_commands() {
+            let output = session.execute_command(&format!("{} -- {}", command, shell_quote(path)))?;
+            if let Some(digest) = parse_checksum_output(&output, algorithm) {
+                return Ok((algorithm, digest));
+            }
+            last_output = output;
+        }
+    }
+    Err(format!(
+        "远程主机无法计算校验和（需要 sha256sum 或 md5sum）: {}",
+        last_output.trim()
+    )
+    .into())
+}
+
+/// 本地文件在各算法下的摘要
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalChecksums {
+    pub sha256: String,
+    pub md5: String,
+}
+
+impl LocalChecksums {
+    /// 只读取一遍文件，同时计算所有算法的摘要
+    pub fn compute(path: &Path) -> Result<Self> {
+        let mut file = std::fs::File::open(path)?;
+        let mut hashers = (Sha256::new(), Md5::new());
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = std::io::Read::read(&mut file, &mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hashers.0.update(&buf[..n]);
+            hashers.1.update(&buf[..n]);
+        }
+        Ok(Self {
+            sha256: hex(&hashers.0.finalize()),
+            md5: hex(&hashers.1.finalize()),
+        })
+    }
+
+    pub fn get(&self, algorithm: ChecksumAlgorithm) -> &str {
+        match algorithm {
+            ChecksumAlgorithm::Sha256 => &self.sha256,
+            ChecksumAlgorithm::Md5 => &self.md5,
+        }
+    }
+}
+
+/// 比较本地与远程文件的摘要
+///
+/// 远程可用的算法事先未知，先在不持有会话锁的情况下计算本地文件的全部摘要，
+/// 只在远程执行校验命令时锁定会话，避免大文件哈希期间阻塞同一连接上的其他操作。
+pub fn compare(session: &Mutex<SshSession>, local: &Path, remote: &str) -> Result<Checksums> {
+    let local = LocalChecksums::compute(local)?;
+    let (algorithm, remote) = remote_checksum(&session.lock().unwrap(), remote)?;
+    Ok(Checksums {
+        algorithm,
+        local: local.get(algorithm).to_string(),
+        remote,
+    })
+}
+
+/// 从 `sha256sum`/`md5sum` 的输出中取出摘要
+///
+/// 文件名含特殊字符时 coreutils 会在行首加 `\`；`md5 -q` 只输出摘要。
+pub fn parse_checksum_output(output: &str, algorithm: ChecksumAlgorithm) -> Option<String> {
+    let line = output.lines().next()?.trim();
+    let digest = line.strip_prefix('\\').unwrap_or(line).split_whitespace().next()?;
+    (digest.len() == algorithm.hex_len() && digest.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| digest.to_ascii_lowercase())
+}
+
+/// 用单引号包裹，作为 POSIX shell 的一个参数
+pub fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_checksum_output() {
+        let sha = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let output = format!("{}  /srv/app.tar.gz\n", sha);
+        assert_eq!(
+            parse_checksum_output(&output, ChecksumAlgorithm::Sha256).as_deref(),
+            Some(sha)
+        );
+        // 文件名需要转义时行首带反斜杠
+        let output = format!("\\{}  /srv/a\\nb\n", sha.to_uppercase());
+        assert_eq!(
+            parse_checksum_output(&output, ChecksumAlgorithm::Sha256).as_deref(),
+            Some(sha)
+        );
+
+        let md5 = "d41d8cd98f00b204e9800998ecf8427e";
+        assert_eq!(parse_checksum_output(md5, ChecksumAlgorithm::Md5).as_deref(), Some(md5));
+        assert_eq!(parse_checksum_output(md5, ChecksumAlgorithm::Sha256), None);
+
+        // 命令不存在时只有 stderr
+        let missing = "\n[stderr]:\nsh: sha256sum: command not found\n";
+        assert_eq!(parse_checksum_output(missing, ChecksumAlgorithm::Sha256), None);
+        assert_eq!(parse_checksum_output("", ChecksumAlgorithm::Md5), None);
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/srv/app.tar.gz"), "'/srv/app.tar.gz'");
+        assert_eq!(shell_quote("/tmp/it's here"), r"'/tmp/it'\''s here'");
+        assert_eq!(shell_quote("$(rm -rf ~)"), "'$(rm -rf ~)'");
+    }
+
+    #[test]
+    fn test_local_checksum() {
+        let path = std::env::temp_dir().join(format!("checksum_{}", rand::random::<u32>()));
+        std::fs::write(&path, b"abc").unwrap();
+        let sha = local_checksum(&path, ChecksumAlgorithm::Sha256).unwrap();
+        let md5 = local_checksum(&path, ChecksumAlgorithm::Md5).unwrap();
+        let all = LocalChecksums::compute(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(all.get(ChecksumAlgorithm::Sha256), sha);
+        assert_eq!(all.get(ChecksumAlgorithm::Md5), md5);
+
+        assert_eq!(sha, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(md5, "900150983cd24fb0d6963f7d28e17f72");
+        let checksums = Checksums {
+            algorithm: ChecksumAlgorithm::Md5,
+            local: md5.clone(),
+            remote: md5,
+        };
+        assert!(checksums.matches());
+    }
+}
@@ -0,0 +1,501 @@
+use crate::theme::TerminalPalette;
+use crate::types::Result;
+use egui::Color32;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::path::Path;
+
+// ============================================================================
+// 终端配色方案 (v0.4.0)
+// ============================================================================
+//
+// 支持导入 iTerm2 (.itermcolors)、Windows Terminal (JSON)、
+// base16 (YAML) 与 Alacritty (TOML) 配色方案。
+
+/// RGB 颜色，序列化为 `#rrggbb`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RgbColor(pub u8, pub u8, pub u8);
+
+impl RgbColor {
+    /// 解析 `#rrggbb`、`0xrrggbb`、`rrggbb` 或 `#rgb`
+    pub fn parse_hex(value: &str) -> Option<Self> {
+        let value = value.trim().trim_matches('"').trim_matches('\'');
+        let hex = value
+            .strip_prefix('#')
+            .or_else(|| value.strip_prefix("0x"))
+            .or_else(|| value.strip_prefix("0X"))
+            .unwrap_or(value);
+
+        let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+        match hex.len() {
+            6 => Some(Self(
+                channel(&hex[0..2])?,
+                channel(&hex[2..4])?,
+                channel(&hex[4..6])?,
+            )),
+            3 => {
+                let c = |i: usize| channel(&hex[i..i + 1]).map(|v| v * 17);
+                Some(Self(c(0)?, c(1)?, c(2)?))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+
+    pub fn to_color32(self) -> Color32 {
+        Color32::from_rgb(self.0, self.1, self.2)
+    }
+
+    pub fn from_color32(color: Color32) -> Self {
+        Self(color.r(), color.g(), color.b())
+    }
+}
+
+impl Serialize for RgbColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for RgbColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        RgbColor::parse_hex(&value)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid color: {}", value)))
+    }
+}
+
+/// 终端配色方案
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorScheme {
+    pub name: String,
+    pub foreground: RgbColor,
+    pub background: RgbColor,
+    pub cursor: RgbColor,
+    /// ANSI 0-15
+    pub ansi: [RgbColor; 16],
+}
+
+/// 配色方案文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemeFormat {
+    ITerm2,
+    WindowsTerminal,
+    Base16,
+    Alacritty,
+}
+
+impl SchemeFormat {
+    /// 根据文件扩展名判断格式
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "itermcolors" => Some(SchemeFormat::ITerm2),
+            "json" => Some(SchemeFormat::WindowsTerminal),
+            "yaml" | "yml" => Some(SchemeFormat::Base16),
+            "toml" => Some(SchemeFormat::Alacritty),
+            _ => None,
+        }
+    }
+}
+
+/// Windows Terminal / Alacritty 中 ANSI 0-7 的颜色名称
+const ANSI_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "purple", "cyan", "white"];
+
+impl ColorScheme {
+    /// 从调色板创建配色方案
+    pub fn from_palette(name: &str, palette: &TerminalPalette) -> Self {
+        let mut ansi = [RgbColor::default(); 16];
+        for (dst, src) in ansi.iter_mut().zip(palette.ansi.iter()) {
+            *dst = RgbColor::from_color32(*src);
+        }
+        Self {
+            name: name.to_string(),
+            foreground: RgbColor::from_color32(palette.foreground),
+            background: RgbColor::from_color32(palette.background),
+            cursor: RgbColor::from_color32(palette.cursor),
+            ansi,
+        }
+    }
+
+    /// 转换为终端调色板
+    pub fn to_palette(&self) -> TerminalPalette {
+        let mut ansi = [Color32::BLACK; 16];
+        for (dst, src) in ansi.iter_mut().zip(self.ansi.iter()) {
+            *dst = src.to_color32();
+        }
+        TerminalPalette {
+            foreground: self.foreground.to_color32(),
+            background: self.background.to_color32(),
+            cursor: self.cursor.to_color32(),
+            ansi,
+        }
+    }
+
+    /// 内置配色方案
+    pub fn builtin() -> Vec<ColorScheme> {
+        let dark = TerminalPalette::dark();
+        let production = TerminalPalette {
+            background: Color32::from_rgb(48, 10, 10),
+            foreground: Color32::from_rgb(240, 214, 214),
+            cursor: Color32::from_rgb(255, 90, 90),
+            ..dark
+        };
+        vec![
+            ColorScheme::from_palette("iShell Dark", &dark),
+            ColorScheme::from_palette("iShell Light", &TerminalPalette::light()),
+            ColorScheme::from_palette("Production Red", &production),
+        ]
+    }
+
+    /// 从文件导入配色方案（按扩展名识别格式）
+    pub fn import_file(path: &Path) -> Result<ColorScheme> {
+        let format = SchemeFormat::from_path(path).ok_or("Unsupported color scheme format")?;
+        let default_name = path
+            .file_stem()
+            .and_then(|n| n.to_str())
+            .unwrap_or("Imported")
+            .to_string();
+
+        match format {
+            SchemeFormat::ITerm2 => {
+                let bytes = std::fs::read(path)?;
+                Self::from_iterm(&bytes, &default_name)
+            }
+            SchemeFormat::WindowsTerminal => {
+                Self::from_windows_terminal(&std::fs::read_to_string(path)?, &default_name)
+            }
+            SchemeFormat::Base16 => Self::from_base16(&std::fs::read_to_string(path)?, &default_name),
+            SchemeFormat::Alacritty => {
+                Self::from_alacritty(&std::fs::read_to_string(path)?, &default_name)
+            }
+        }
+    }
+
+    /// 解析 iTerm2 `.itermcolors` plist
+    pub fn from_iterm(bytes: &[u8], name: &str) -> Result<ColorScheme> {
+        let value = plist::Value::from_reader(std::io::Cursor::new(bytes))?;
+        let dict = value.as_dictionary().ok_or("Invalid itermcolors file")?;
+
+        let color = |key: &str| -> Result<RgbColor> {
+            let entry = dict
+                .get(key)
+                .and_then(|v| v.as_dictionary())
+                .ok_or_else(|| format!("Missing color: {}", key))?;
+            let component = |c: &str| {
+                entry
+                    .get(c)
+                    .and_then(|v| v.as_real())
+                    .map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+                    .unwrap_or(0)
+            };
+            Ok(RgbColor(
+                component("Red Component"),
+                component("Green Component"),
+                component("Blue Component"),
+            ))
+        };
+
+        let mut ansi = [RgbColor::default(); 16];
+        for (i, slot) in ansi.iter_mut().enumerate() {
+            *slot = color(&format!("Ansi {} Color", i))?;
+        }
+        let foreground = color("Foreground Color")?;
+        let background = color("Background Color")?;
+        let cursor = color("Cursor Color").unwrap_or(foreground);
+
+        Ok(ColorScheme {
+            name: name.to_string(),
+            foreground,
+            background,
+            cursor,
+            ansi,
+        })
+    }
+
+    /// 解析 Windows Terminal 配色方案 JSON
+    ///
+    /// 支持单个方案对象，或包含 `schemes` 数组的完整 settings.json（取第一个）。
+    pub fn from_windows_terminal(content: &str, name: &str) -> Result<ColorScheme> {
+        let value: serde_json::Value = serde_json::from_str(content)?;
+        let scheme = match value.get("schemes").and_then(|s| s.as_array()) {
+            Some(schemes) => schemes.first().ok_or("No schemes found")?,
+            None => &value,
+        };
+
+        let color = |key: &str| -> Result<RgbColor> {
+            scheme
+                .get(key)
+                .and_then(|v| v.as_str())
+                .and_then(RgbColor::parse_hex)
+                .ok_or_else(|| format!("Missing color: {}", key).into())
+        };
+
+        let mut ansi = [RgbColor::default(); 16];
+        for (i, base) in ANSI_NAMES.iter().enumerate() {
+            ansi[i] = color(base)?;
+            let mut chars = base.chars();
+            let bright = format!(
+                "bright{}{}",
+                chars.next().unwrap().to_ascii_uppercase(),
+                chars.as_str()
+            );
+            ansi[i + 8] = color(&bright)?;
+        }
+        let foreground = color("foreground")?;
+
+        Ok(ColorScheme {
+            name: scheme
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or(name)
+                .to_string(),
+            foreground,
+            background: color("background")?,
+            cursor: color("cursorColor").unwrap_or(foreground),
+            ansi,
+        })
+    }
+
+    /// 解析 base16 YAML 方案（`base00` - `base0F`）
+    pub fn from_base16(content: &str, name: &str) -> Result<ColorScheme> {
+        let mut scheme_name = name.to_string();
+        let mut base = [None; 16];
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim();
+            let value = value.trim().trim_matches('"').trim_matches('\'');
+            if key == "scheme" && !value.is_empty() {
+                scheme_name = value.to_string();
+            } else if let Some(index) = key.strip_prefix("base") {
+                if let Ok(i) = usize::from_str_radix(index, 16) {
+                    if i < 16 {
+                        base[i] = RgbColor::parse_hex(value);
+                    }
+                }
+            }
+        }
+
+        let b = |i: usize| base[i].ok_or_else(|| format!("Missing color: base{:02X}", i));
+
+        // 标准 base16-shell 映射
+        let mapping = [
+            0x00, 0x08, 0x0B, 0x0A, 0x0D, 0x0E, 0x0C, 0x05, 0x03, 0x08, 0x0B, 0x0A, 0x0D, 0x0E,
+            0x0C, 0x07,
+        ];
+        let mut ansi = [RgbColor::default(); 16];
+        for (slot, index) in ansi.iter_mut().zip(mapping) {
+            *slot = b(index)?;
+        }
+
+        Ok(ColorScheme {
+            name: scheme_name,
+            foreground: b(0x05)?,
+            background: b(0x00)?,
+            cursor: b(0x05)?,
+            ansi,
+        })
+    }
+
+    /// 解析 Alacritty TOML 配色（`[colors.primary]`、`[colors.normal]`、`[colors.bright]`）
+    pub fn from_alacritty(content: &str, name: &str) -> Result<ColorScheme> {
+        let value: toml::Value = toml::from_str(content)?;
+        let colors = value.get("colors").ok_or("Missing [colors] section")?;
+
+        let color = |section: &str, key: &str| -> Result<RgbColor> {
+            colors
+                .get(section)
+                .and_then(|s| s.get(key))
+                .and_then(|v| v.as_str())
+                .and_then(RgbColor::parse_hex)
+                .ok_or_else(|| format!("Missing color: colors.{}.{}", section, key).into())
+        };
+
+        let mut ansi = [RgbColor::default(); 16];
+        for (i, base) in ANSI_NAMES.iter().enumerate() {
+            // Alacritty 使用 magenta 而非 purple
+            let key = if *base == "purple" { "magenta" } else { base };
+            ansi[i] = color("normal", key)?;
+            ansi[i + 8] = color("bright", key)?;
+        }
+        let foreground = color("primary", "foreground")?;
+
+        Ok(ColorScheme {
+            name: name.to_string(),
+            foreground,
+            background: color("primary", "background")?,
+            cursor: color("cursor", "cursor").unwrap_or(foreground),
+            ansi,
+        })
+    }
+}
+
+/// 按名称查找配色方案（先查用户方案，再查内置方案）
+pub fn find_scheme(schemes: &[ColorScheme], name: &str) -> Option<ColorScheme> {
+    schemes
+        .iter()
+        .find(|s| s.name == name)
+        .cloned()
+        .or_else(|| ColorScheme::builtin().into_iter().find(|s| s.name == name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(RgbColor::parse_hex("#ff8000"), Some(RgbColor(255, 128, 0)));
+        assert_eq!(RgbColor::parse_hex("0x0A0b0C"), Some(RgbColor(10, 11, 12)));
+        assert_eq!(RgbColor::parse_hex("\"181818\""), Some(RgbColor(24, 24, 24)));
+        assert_eq!(RgbColor::parse_hex("#fff"), Some(RgbColor(255, 255, 255)));
+        assert_eq!(RgbColor::parse_hex("nope"), None);
+    }
+
+    #[test]
+    fn test_palette_roundtrip() {
+        let palette = TerminalPalette::dark();
+        let scheme = ColorScheme::from_palette("test", &palette);
+        assert_eq!(scheme.to_palette(), palette);
+    }
+
+    #[test]
+    fn test_windows_terminal() {
+        let json = r##"{
+            "name": "Campbell",
+            "foreground": "#CCCCCC", "background": "#0C0C0C", "cursorColor": "#FFFFFF",
+            "black": "#0C0C0C", "red": "#C50F1F", "green": "#13A10E", "yellow": "#C19C00",
+            "blue": "#0037DA", "purple": "#881798", "cyan": "#3A96DD", "white": "#CCCCCC",
+            "brightBlack": "#767676", "brightRed": "#E74856", "brightGreen": "#16C60C",
+            "brightYellow": "#F9F1A5", "brightBlue": "#3B78FF", "brightPurple": "#B4009E",
+            "brightCyan": "#61D6D6", "brightWhite": "#F2F2F2"
+        }"##;
+        let scheme = ColorScheme::from_windows_terminal(json, "fallback").unwrap();
+        assert_eq!(scheme.name, "Campbell");
+        assert_eq!(scheme.ansi[1], RgbColor(0xC5, 0x0F, 0x1F));
+        assert_eq!(scheme.ansi[13], RgbColor(0xB4, 0x00, 0x9E));
+        assert_eq!(scheme.cursor, RgbColor(255, 255, 255));
+    }
+
+    #[test]
+    fn test_base16() {
+        let yaml = r#"
+scheme: "Default Dark"
+author: "Chris Kempson"
+base00: "181818"
+base01: "282828"
+base02: "383838"
+base03: "585858"
+base04: "b8b8b8"
+base05: "d8d8d8"
+base06: "e8e8e8"
+base07: "f8f8f8"
+base08: "ab4642"
+base09: "dc9656"
+base0A: "f7ca88"
+base0B: "a1b56c"
+base0C: "86c1b9"
+base0D: "7cafc2"
+base0E: "ba8baf"
+base0F: "a16946"
+"#;
+        let scheme = ColorScheme::from_base16(yaml, "fallback").unwrap();
+        assert_eq!(scheme.name, "Default Dark");
+        assert_eq!(scheme.background, RgbColor(0x18, 0x18, 0x18));
+        assert_eq!(scheme.ansi[1], RgbColor(0xab, 0x46, 0x42));
+        assert_eq!(scheme.ansi[8], RgbColor(0x58, 0x58, 0x58));
+        assert_eq!(scheme.ansi[15], RgbColor(0xf8, 0xf8, 0xf8));
+    }
+
+    #[test]
+    fn test_alacritty() {
+        let toml = r##"
+[colors.primary]
+background = "#1d1f21"
+foreground = "#c5c8c6"
+
+[colors.cursor]
+cursor = "#ffffff"
+
+[colors.normal]
+black = "#1d1f21"
+red = "#cc6666"
+green = "#b5bd68"
+yellow = "#f0c674"
+blue = "#81a2be"
+magenta = "#b294bb"
+cyan = "#8abeb7"
+white = "#c5c8c6"
+
+[colors.bright]
+black = "#666666"
+red = "#d54e53"
+green = "#b9ca4a"
+yellow = "#e7c547"
+blue = "#7aa6da"
+magenta = "#c397d8"
+cyan = "#70c0b1"
+white = "#eaeaea"
+"##;
+        let scheme = ColorScheme::from_alacritty(toml, "tomorrow").unwrap();
+        assert_eq!(scheme.name, "tomorrow");
+        assert_eq!(scheme.ansi[5], RgbColor(0xb2, 0x94, 0xbb));
+        assert_eq!(scheme.ansi[8], RgbColor(0x66, 0x66, 0x66));
+        assert_eq!(scheme.background, RgbColor(0x1d, 0x1f, 0x21));
+    }
+
+    #[test]
+    fn test_iterm() {
+        let mut entries = String::new();
+        let mut push = |key: &str, r: f64, g: f64, b: f64| {
+            entries.push_str(&format!(
+                "<key>{}</key><dict>\
+                 <key>Blue Component</key><real>{}</real>\
+                 <key>Green Component</key><real>{}</real>\
+                 <key>Red Component</key><real>{}</real></dict>",
+                key, b, g, r
+            ));
+        };
+        for i in 0..16 {
+            push(&format!("Ansi {} Color", i), i as f64 / 15.0, 0.0, 0.0);
+        }
+        push("Foreground Color", 1.0, 1.0, 1.0);
+        push("Background Color", 0.0, 0.0, 0.0);
+        let plist = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict>{}</dict></plist>"#,
+            entries
+        );
+
+        let scheme = ColorScheme::from_iterm(plist.as_bytes(), "iterm").unwrap();
+        assert_eq!(scheme.ansi[15], RgbColor(255, 0, 0));
+        assert_eq!(scheme.foreground, RgbColor(255, 255, 255));
+        // 缺少光标颜色时使用前景色
+        assert_eq!(scheme.cursor, scheme.foreground);
+    }
+
+    #[test]
+    fn test_find_scheme() {
+        assert!(find_scheme(&[], "Production Red").is_some());
+        assert!(find_scheme(&[], "missing").is_none());
+
+        let mut custom = ColorScheme::builtin()[0].clone();
+        custom.name = "Mine".to_string();
+        assert_eq!(find_scheme(&[custom.clone()], "Mine"), Some(custom));
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let scheme = ColorScheme::builtin().remove(2);
+        let text = toml::to_string(&scheme).unwrap();
+        assert!(text.contains("#300a0a"));
+        let parsed: ColorScheme = toml::from_str(&text).unwrap();
+        assert_eq!(parsed, scheme);
+    }
+}
@@ -102,6 +102,14 @@ impl ConfigManager {
 
     /// 加密连接配置
     pub fn encrypt_connection(&self, conn: &mut SshConfig) -> Result<()> {
+        for secret in &mut conn.secrets {
+            secret.value_encrypted = if secret.value.is_empty() {
+                None
+            } else {
+                Some(self.encryptor.encrypt(&secret.value)?)
+            };
+        }
+
         if let Some(auth) = &conn.auth {
             match auth {
                 AuthMethod::Password(password) => {
@@ -127,6 +135,12 @@ impl ConfigManager {
 
     /// 解密连接配置
     pub fn decrypt_connection(&self, conn: &mut SshConfig) -> Result<()> {
+        for secret in &mut conn.secrets {
+            if let Some(encrypted) = &secret.value_encrypted {
+                secret.value = self.encryptor.decrypt(encrypted)?;
+            }
+        }
+
         // 尝试解密密码
         if let Some(encrypted) = &conn.password_encrypted {
             let password = self.encryptor.decrypt(encrypted)?;
@@ -196,6 +210,7 @@ impl ConfigManager {
             conn.password_encrypted = None;
             conn.key_passphrase_encrypted = None;
             conn.auth = None;
+            conn.secrets.clear();
         }
 
         safe_config.ai.openai.api_key = None;
@@ -296,4 +311,86 @@ mod tests {
             panic!("Expected password auth");
         }
     }
+
+    #[test]
+    fn test_trigger_rules_roundtrip() {
+        let manager = get_test_manager();
+        let mut config = AppConfig {
+            triggers: vec![
+                TriggerRule {
+                    name: "错误".to_string(),
+                    pattern: "ERROR|FATAL".to_string(),
+                    connection: String::new(),
+                    action: TriggerAction::Highlight { color: [255, 0, 0] },
+                    enabled: true,
+                },
+                TriggerRule {
+                    name: "确认".to_string(),
+                    pattern: r"\(y/n\)".to_string(),
+                    connection: "prod".to_string(),
+                    action: TriggerAction::AutoReply { text: "y".to_string() },
+                    enabled: false,
+                },
+                TriggerRule {
+                    name: "完成".to_string(),
+                    pattern: "Build finished".to_string(),
+                    connection: String::new(),
+                    action: TriggerAction::Notify,
+                    enabled: true,
+                },
+            ],
+            ..AppConfig::default()
+        };
+
+        manager.save_config(&mut config).unwrap();
+        let loaded = manager.load_config().unwrap();
+        assert_eq!(loaded.triggers, config.triggers);
+    }
+
+    #[test]
+    fn test_expect_scripts_and_secrets_roundtrip() {
+        let manager = get_test_manager();
+        let mut conn = SshConfig::new("db".to_string(), "db".to_string(), 22, "ops".to_string());
+        conn.auth = Some(AuthMethod::Password("login-pw".to_string()));
+        conn.secrets.push(StoredSecret {
+            name: "sudo".to_string(),
+            value: "s3cr3t".to_string(),
+            value_encrypted: None,
+        });
+        conn.expect_scripts.push(ExpectScript {
+            name: "sudo".to_string(),
+            auto_start: true,
+            steps: vec![ExpectStep {
+                branches: vec![
+                    ExpectBranch {
+                        pattern: r"\[sudo\] password".to_string(),
+                        send: Some(ExpectSend::Secret { name: "sudo".to_string() }),
+                        next: ExpectNext::Repeat,
+                    },
+                    ExpectBranch {
+                        pattern: r"\(yes/no\)".to_string(),
+                        send: Some(ExpectSend::Line { text: "yes".to_string() }),
+                        next: ExpectNext::Goto { step: 1 },
+                    },
+                ],
+                timeout_secs: 0,
+                on_timeout: ExpectNext::Fail,
+            }],
+        });
+        let mut config = AppConfig {
+            connections: vec![conn],
+            ..AppConfig::default()
+        };
+
+        manager.save_config(&mut config).unwrap();
+        let content = std::fs::read_to_string(manager.config_path()).unwrap();
+        assert!(!content.contains("s3cr3t"));
+
+        let loaded = manager.load_config().unwrap();
+        let conn = &loaded.connections[0];
+        assert_eq!(conn.expect_scripts, config.connections[0].expect_scripts);
+        assert_eq!(conn.secret("sudo"), Some("s3cr3t"));
+        assert_eq!(conn.secret(LOGIN_PASSWORD_SECRET), Some("login-pw"));
+        assert_eq!(conn.secret("enable"), None);
+    }
 }
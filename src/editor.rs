@@ -0,0 +1,1369 @@
+use crate::ssh::SshSession;
+use crate::types::{Result, SftpMessage};
+use encoding_rs::Encoding;
+use regex::{NoExpand, Regex, RegexBuilder};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+// ============================================================================
+// 内置编辑器：编码与换行符检测、语法高亮、查找替换、大文件分页查看
+// ============================================================================
+
+/// 超过此大小的文件以只读方式分页查看
+pub const MAX_EDIT_SIZE: u64 = 1024 * 1024;
+
+/// 只读查看时每页读取的字节数
+pub const VIEW_CHUNK: u64 = 256 * 1024;
+
+/// 判断编码时检查的字节数
+const SNIFF_LEN: usize = 8 * 1024;
+
+/// 文本编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    /// 带 BOM 的 UTF-8
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Gb18030,
+    Big5,
+    ShiftJis,
+    EucKr,
+    Windows1252,
+}
+
+impl TextEncoding {
+    pub const ALL: [TextEncoding; 9] = [
+        TextEncoding::Utf8,
+        TextEncoding::Utf8Bom,
+        TextEncoding::Utf16Le,
+        TextEncoding::Utf16Be,
+        TextEncoding::Gb18030,
+        TextEncoding::Big5,
+        TextEncoding::ShiftJis,
+        TextEncoding::EucKr,
+        TextEncoding::Windows1252,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "UTF-8",
+            TextEncoding::Utf8Bom => "UTF-8 BOM",
+            TextEncoding::Utf16Le => "UTF-16 LE",
+            TextEncoding::Utf16Be => "UTF-16 BE",
+            TextEncoding::Gb18030 => "GBK/GB18030",
+            TextEncoding::Big5 => "Big5",
+            TextEncoding::ShiftJis => "Shift_JIS",
+            TextEncoding::EucKr => "EUC-KR",
+            TextEncoding::Windows1252 => "Latin-1",
+        }
+    }
+
+    fn encoding(&self) -> &'static Encoding {
+        match self {
+            TextEncoding::Utf8 | TextEncoding::Utf8Bom => encoding_rs::UTF_8,
+            TextEncoding::Utf16Le => encoding_rs::UTF_16LE,
+            TextEncoding::Utf16Be => encoding_rs::UTF_16BE,
+            TextEncoding::Gb18030 => encoding_rs::GB18030,
+            TextEncoding::Big5 => encoding_rs::BIG5,
+            TextEncoding::ShiftJis => encoding_rs::SHIFT_JIS,
+            TextEncoding::EucKr => encoding_rs::EUC_KR,
+            TextEncoding::Windows1252 => encoding_rs::WINDOWS_1252,
+        }
+    }
+
+    fn bom(&self) -> &'static [u8] {
+        match self {
+            TextEncoding::Utf8Bom => &[0xef, 0xbb, 0xbf],
+            TextEncoding::Utf16Le => &[0xff, 0xfe],
+            TextEncoding::Utf16Be => &[0xfe, 0xff],
+            _ => &[],
+        }
+    }
+
+    fn is_utf16(&self) -> bool {
+        matches!(self, TextEncoding::Utf16Le | TextEncoding::Utf16Be)
+    }
+
+    /// 根据 BOM 和内容猜测编码
+    ///
+    /// 有 BOM 时以 BOM 为准；合法的 UTF-8 视为 UTF-8；
+    /// 否则依次尝试 GB18030 和 Latin-1（后者总能解码）。
+    pub fn detect(bytes: &[u8]) -> TextEncoding {
+        for encoding in [TextEncoding::Utf8Bom, TextEncoding::Utf16Le, TextEncoding::Utf16Be] {
+            if bytes.starts_with(encoding.bom()) {
+                return encoding;
+            }
+        }
+        // 只检查开头，末尾可能截断在多字节字符中间
+        let sample = &bytes[..bytes.len().min(SNIFF_LEN)];
+        let valid_utf8 = match std::str::from_utf8(sample) {
+            Ok(_) => true,
+            Err(e) => e.error_len().is_none() && sample.len() < bytes.len(),
+        };
+        if valid_utf8 {
+            TextEncoding::Utf8
+        } else if encoding_rs::GB18030
+            .decode_without_bom_handling_and_without_replacement(sample)
+            .is_some()
+        {
+            TextEncoding::Gb18030
+        } else {
+            TextEncoding::Windows1252
+        }
+    }
+
+    /// 解码（去掉 BOM），返回文本以及是否有无法解码的字节
+    pub fn decode(&self, bytes: &[u8]) -> (String, bool) {
+        let bytes = bytes.strip_prefix(self.bom()).unwrap_or(bytes);
+        let (text, had_errors) = self.encoding().decode_without_bom_handling(bytes);
+        (text.into_owned(), had_errors)
+    }
+
+    /// 编码（加上 BOM），文本中有该编码无法表示的字符时返回错误
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        let mut bytes = self.bom().to_vec();
+        match self {
+            // encoding_rs 按 WHATWG 规范不提供 UTF-16 编码器
+            TextEncoding::Utf16Le => bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes)),
+            TextEncoding::Utf16Be => bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes)),
+            _ => {
+                let (encoded, _, unmappable) = self.encoding().encode(text);
+                if unmappable {
+                    return Err(format!("文本中有 {} 无法表示的字符", self.label()).into());
+                }
+                bytes.extend_from_slice(&encoded);
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+/// 换行符
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineEnding {
+    pub const ALL: [LineEnding; 3] = [LineEnding::Lf, LineEnding::CrLf, LineEnding::Cr];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+            LineEnding::Cr => "CR",
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// 出现最多的换行符，没有换行时为 LF；第二个值表示是否混用
+    pub fn detect(text: &str) -> (LineEnding, bool) {
+        let crlf = text.matches("\r\n").count();
+        let cr = text.matches('\r').count() - crlf;
+        let lf = text.matches('\n').count() - crlf;
+        let mixed = [lf, crlf, cr].iter().filter(|&&n| n > 0).count() > 1;
+        let ending = if crlf > lf && crlf >= cr {
+            LineEnding::CrLf
+        } else if cr > lf && cr > crlf {
+            LineEnding::Cr
+        } else {
+            LineEnding::Lf
+        };
+        (ending, mixed)
+    }
+}
+
+/// 统一为 `\n`
+fn normalize_line_endings(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// 解码后的文本文件，编辑时换行符统一为 `\n`，保存时还原
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub text: String,
+    pub encoding: TextEncoding,
+    pub line_ending: LineEnding,
+    /// 原文件混用了多种换行符（保存时会统一）
+    pub mixed_line_endings: bool,
+    /// 有无法按当前编码解码的字节，保存会破坏原内容
+    pub lossy: bool,
+}
+
+impl Document {
+    /// 自动检测编码
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        Self::decode_as(bytes, TextEncoding::detect(bytes))
+    }
+
+    /// 按指定编码解码，疑似二进制文件时返回错误
+    pub fn decode_as(bytes: &[u8], encoding: TextEncoding) -> Result<Self> {
+        if !encoding.is_utf16() && bytes[..bytes.len().min(SNIFF_LEN)].contains(&0) {
+            return Err("疑似二进制文件，无法作为文本打开".into());
+        }
+        let (text, lossy) = encoding.decode(bytes);
+        let (line_ending, mixed_line_endings) = LineEnding::detect(&text);
+        Ok(Self {
+            text: normalize_line_endings(&text),
+            encoding,
+            line_ending,
+            mixed_line_endings,
+            lossy,
+        })
+    }
+
+    /// 按原编码和换行符编码
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let text = match self.line_ending {
+            LineEnding::Lf => std::borrow::Cow::Borrowed(self.text.as_str()),
+            ending => std::borrow::Cow::Owned(self.text.replace('\n', ending.as_str())),
+        };
+        self.encoding.encode(&text)
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.text.lines().count().max(1)
+    }
+}
+
+// ============================================================================
+// 语法高亮
+// ============================================================================
+
+/// 高亮语言，按扩展名或文件名识别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    Plain,
+    Rust,
+    Python,
+    Shell,
+    JavaScript,
+    C,
+    Go,
+    Java,
+    Sql,
+    Json,
+    Yaml,
+    Toml,
+    /// ini、conf、env 等键值配置
+    Config,
+    Markup,
+}
+
+impl Language {
+    pub const ALL: [Language; 14] = [
+        Language::Plain,
+        Language::Rust,
+        Language::Python,
+        Language::Shell,
+        Language::JavaScript,
+        Language::C,
+        Language::Go,
+        Language::Java,
+        Language::Sql,
+        Language::Json,
+        Language::Yaml,
+        Language::Toml,
+        Language::Config,
+        Language::Markup,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Language::Plain => "纯文本",
+            Language::Rust => "Rust",
+            Language::Python => "Python",
+            Language::Shell => "Shell",
+            Language::JavaScript => "JavaScript",
+            Language::C => "C/C++",
+            Language::Go => "Go",
+            Language::Java => "Java",
+            Language::Sql => "SQL",
+            Language::Json => "JSON",
+            Language::Yaml => "YAML",
+            Language::Toml => "TOML",
+            Language::Config => "配置文件",
+            Language::Markup => "HTML/XML",
+        }
+    }
+
+    pub fn from_path(path: &str) -> Language {
+        let name = path.rsplit('/').next().unwrap_or(path).to_ascii_lowercase();
+        match name.as_str() {
+            "dockerfile" | "makefile" | ".bashrc" | ".profile" | ".zshrc" | ".bash_profile" => return Language::Shell,
+            ".env" | ".gitconfig" | ".editorconfig" => return Language::Config,
+            _ => {}
+        }
+        let Some((_, extension)) = name.rsplit_once('.') else {
+            return Language::Plain;
+        };
+        match extension {
+            "rs" => Language::Rust,
+            "py" | "pyw" => Language::Python,
+            "sh" | "bash" | "zsh" | "ksh" => Language::Shell,
+            "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" => Language::JavaScript,
+            "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" => Language::C,
+            "go" => Language::Go,
+            "java" | "kt" | "kts" | "scala" => Language::Java,
+            "sql" => Language::Sql,
+            "json" => Language::Json,
+            "yml" | "yaml" => Language::Yaml,
+            "toml" => Language::Toml,
+            "ini" | "conf" | "cfg" | "cnf" | "properties" | "env" | "service" => Language::Config,
+            "html" | "htm" | "xml" | "svg" | "xhtml" | "plist" => Language::Markup,
+            _ => Language::Plain,
+        }
+    }
+
+    fn syntax(&self) -> Syntax {
+        type Comments = (&'static [&'static str], Option<(&'static str, &'static str)>);
+        const C_COMMENTS: Comments = (&["//"], Some(("/*", "*/")));
+        let (keywords, (line_comments, block_comment), quotes): (&[&str], Comments, &[char]) = match self {
+            Language::Plain => (&[], (&[], None), &[]),
+            Language::Rust => (
+                &[
+                    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+                    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+                    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe",
+                    "use", "where", "while",
+                ],
+                C_COMMENTS,
+                &['"'],
+            ),
+            Language::Python => (
+                &[
+                    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif",
+                    "else", "except", "False", "finally", "for", "from", "global", "if", "import", "in", "is",
+                    "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return", "True", "try", "while",
+                    "with", "yield",
+                ],
+                (&["#"], None),
+                &['"', '\''],
+            ),
+            Language::Shell => (
+                &[
+                    "case",
+                    "do",
+                    "done",
+                    "elif",
+                    "else",
+                    "esac",
+                    "exit",
+                    "export",
+                    "fi",
+                    "for",
+                    "function",
+                    "if",
+                    "in",
+                    "local",
+                    "return",
+                    "then",
+                    "until",
+                    "while",
+                    "FROM",
+                    "RUN",
+                    "CMD",
+                    "COPY",
+                    "ENV",
+                    "WORKDIR",
+                    "ENTRYPOINT",
+                    "EXPOSE",
+                ],
+                (&["#"], None),
+                &['"', '\''],
+            ),
+            Language::JavaScript => (
+                &[
+                    "async",
+                    "await",
+                    "break",
+                    "case",
+                    "catch",
+                    "class",
+                    "const",
+                    "continue",
+                    "default",
+                    "else",
+                    "export",
+                    "extends",
+                    "false",
+                    "finally",
+                    "for",
+                    "from",
+                    "function",
+                    "if",
+                    "import",
+                    "in",
+                    "instanceof",
+                    "interface",
+                    "let",
+                    "new",
+                    "null",
+                    "return",
+                    "switch",
+                    "this",
+                    "throw",
+                    "true",
+                    "try",
+                    "type",
+                    "typeof",
+                    "undefined",
+                    "var",
+                    "while",
+                ],
+                C_COMMENTS,
+                &['"', '\'', '`'],
+            ),
+            Language::C => (
+                &[
+                    "auto",
+                    "bool",
+                    "break",
+                    "case",
+                    "char",
+                    "class",
+                    "const",
+                    "continue",
+                    "default",
+                    "do",
+                    "double",
+                    "else",
+                    "enum",
+                    "extern",
+                    "float",
+                    "for",
+                    "if",
+                    "include",
+                    "define",
+                    "int",
+                    "long",
+                    "namespace",
+                    "nullptr",
+                    "return",
+                    "short",
+                    "signed",
+                    "sizeof",
+                    "static",
+                    "struct",
+                    "switch",
+                    "template",
+                    "typedef",
+                    "union",
+                    "unsigned",
+                    "void",
+                    "while",
+                ],
+                C_COMMENTS,
+                &['"', '\''],
+            ),
+            Language::Go => (
+                &[
+                    "break",
+                    "case",
+                    "chan",
+                    "const",
+                    "continue",
+                    "default",
+                    "defer",
+                    "else",
+                    "false",
+                    "for",
+                    "func",
+                    "go",
+                    "if",
+                    "import",
+                    "interface",
+                    "map",
+                    "nil",
+                    "package",
+                    "range",
+                    "return",
+                    "select",
+                    "struct",
+                    "switch",
+                    "true",
+                    "type",
+                    "var",
+                ],
+                C_COMMENTS,
+                &['"', '\'', '`'],
+            ),
+            Language::Java => (
+                &[
+                    "abstract",
+                    "class",
+                    "else",
+                    "extends",
+                    "false",
+                    "final",
+                    "for",
+                    "fun",
+                    "if",
+                    "implements",
+                    "import",
+                    "interface",
+                    "new",
+                    "null",
+                    "object",
+                    "package",
+                    "private",
+                    "protected",
+                    "public",
+                    "return",
+                    "static",
+                    "this",
+                    "throw",
+                    "throws",
+                    "true",
+                    "try",
+                    "catch",
+                    "val",
+                    "var",
+                    "void",
+                    "while",
+                ],
+                C_COMMENTS,
+                &['"', '\''],
+            ),
+            Language::Sql => (
+                &[
+                    "add", "alter", "and", "as", "asc", "by", "create", "delete", "desc", "distinct", "drop", "from",
+                    "group", "having", "index", "insert", "into", "join", "key", "left", "limit", "not", "null", "on",
+                    "or", "order", "primary", "select", "set", "table", "update", "values", "where",
+                ],
+                (&["--"], Some(("/*", "*/"))),
+                &['"', '\''],
+            ),
+            Language::Json => (&["true", "false", "null"], (&[], None), &['"']),
+            Language::Yaml => (&["true", "false", "null", "yes", "no"], (&["#"], None), &['"', '\'']),
+            Language::Toml => (&["true", "false"], (&["#"], None), &['"', '\'']),
+            Language::Config => (
+                &["on", "off", "true", "false", "yes", "no"],
+                (&["#", ";"], None),
+                &['"', '\''],
+            ),
+            Language::Markup => (&[], (&[], Some(("<!--", "-->"))), &['"', '\'']),
+        };
+        Syntax {
+            keywords,
+            line_comments,
+            block_comment,
+            quotes,
+            ignore_case: *self == Language::Sql,
+            keys: matches!(self, Language::Yaml | Language::Toml | Language::Config),
+            tags: *self == Language::Markup,
+        }
+    }
+}
+
+struct Syntax {
+    keywords: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    ignore_case: bool,
+    /// 行首的 `键 =`、`键:` 和 `[节]`
+    keys: bool,
+    /// `<标签`
+    tags: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Keyword,
+    String,
+    Number,
+    Comment,
+    /// 配置文件的键和节名、标记语言的标签名
+    Key,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub range: Range<usize>,
+    pub kind: TokenKind,
+}
+
+/// 把文本切分为需要着色的片段（未列出的部分为普通文本）
+pub fn highlight(text: &str, language: Language) -> Vec<Token> {
+    let syntax = language.syntax();
+    let mut tokens = Vec::new();
+    if language == Language::Plain {
+        return tokens;
+    }
+    let bytes = text.as_bytes();
+    let is_word = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let mut line_start = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &text[i..];
+        let b = bytes[i];
+        let after_space = i == 0 || bytes[i - 1].is_ascii_whitespace();
+
+        if b == b'\n' {
+            line_start = true;
+            i += 1;
+            continue;
+        }
+        if b == b' ' || b == b'\t' || b == b'\r' {
+            i += 1;
+            continue;
+        }
+
+        // 注释：单字符的 `#`/`;` 只在行首或空白后生效，避免误伤 `${#x}`、`a;b`
+        if let Some((open, close)) = syntax.block_comment {
+            if let Some(body) = rest.strip_prefix(open) {
+                let end = body
+                    .find(close)
+                    .map_or(bytes.len(), |n| i + open.len() + n + close.len());
+                tokens.push(Token {
+                    range: i..end,
+                    kind: TokenKind::Comment,
+                });
+                i = end;
+                line_start = false;
+                continue;
+            }
+        }
+        if syntax
+            .line_comments
+            .iter()
+            .any(|marker| rest.starts_with(marker) && (marker.len() > 1 || after_space))
+        {
+            let end = rest.find('\n').map_or(bytes.len(), |n| i + n);
+            tokens.push(Token {
+                range: i..end,
+                kind: TokenKind::Comment,
+            });
+            i = end;
+            continue;
+        }
+
+        if syntax.keys && line_start {
+            if let Some(len) = key_len(rest) {
+                tokens.push(Token {
+                    range: i..i + len,
+                    kind: TokenKind::Key,
+                });
+                i += len;
+                line_start = false;
+                continue;
+            }
+        }
+        line_start = false;
+
+        if syntax.tags && b == b'<' {
+            let name_start = i + 1 + usize::from(bytes.get(i + 1) == Some(&b'/'));
+            let name_end = (name_start..bytes.len())
+                .find(|&j| !(is_word(bytes[j]) || matches!(bytes[j], b'-' | b':' | b'.')))
+                .unwrap_or(bytes.len());
+            if name_end > name_start {
+                tokens.push(Token {
+                    range: i..name_end,
+                    kind: TokenKind::Key,
+                });
+                i = name_end;
+                continue;
+            }
+        }
+
+        if syntax.quotes.contains(&(b as char)) {
+            // 反引号字符串可以跨行，其余字符串到行尾为止
+            let multiline = b == b'`';
+            let mut j = i + 1;
+            while j < bytes.len() && bytes[j] != b {
+                if bytes[j] == b'\\' {
+                    j += 1;
+                } else if bytes[j] == b'\n' && !multiline {
+                    break;
+                }
+                j += 1;
+            }
+            let end = (j + 1).min(bytes.len());
+            tokens.push(Token {
+                range: i..end,
+                kind: TokenKind::String,
+            });
+            i = end;
+            continue;
+        }
+
+        if is_word(b) {
+            let end = (i..bytes.len()).find(|&j| !is_word(bytes[j])).unwrap_or(bytes.len());
+            let word = &text[i..end];
+            let after_word = i == 0 || !is_word(bytes[i - 1]);
+            if b.is_ascii_digit() && after_word {
+                // 小数部分
+                let end = (end..bytes.len())
+                    .find(|&j| !(is_word(bytes[j]) || bytes[j] == b'.'))
+                    .unwrap_or(bytes.len());
+                tokens.push(Token {
+                    range: i..end,
+                    kind: TokenKind::Number,
+                });
+                i = end;
+                continue;
+            }
+            let keyword = if syntax.ignore_case {
+                syntax.keywords.iter().any(|k| k.eq_ignore_ascii_case(word))
+            } else {
+                syntax.keywords.contains(&word)
+            };
+            if keyword {
+                tokens.push(Token {
+                    range: i..end,
+                    kind: TokenKind::Keyword,
+                });
+            }
+            i = end;
+            continue;
+        }
+
+        // 其他字符（含多字节字符）
+        i += rest.chars().next().map_or(1, char::len_utf8);
+    }
+    tokens
+}
+
+/// 行首的节名 `[section]` 或键名（后面跟 `=` 或 `:`）的长度
+fn key_len(line: &str) -> Option<usize> {
+    let line = line.split('\n').next().unwrap_or(line);
+    if line.starts_with('[') {
+        return line.find(']').map(|n| n + 1);
+    }
+    let key_end = line
+        .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '"' | '\'')))
+        .unwrap_or(line.len());
+    if key_end == 0 {
+        return None;
+    }
+    let rest = line[key_end..].trim_start_matches([' ', '\t']);
+    (rest.starts_with('=') || rest.starts_with(':')).then_some(key_end)
+}
+
+// ============================================================================
+// 查找与替换
+// ============================================================================
+
+/// 查找选项
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FindOptions {
+    pub case_sensitive: bool,
+    /// 按正则表达式查找，替换文本中可用 `$1` 引用分组
+    pub regex: bool,
+}
+
+/// 编译好的查找条件
+#[derive(Debug, Clone)]
+pub struct Finder {
+    regex: Regex,
+    expand: bool,
+}
+
+impl Finder {
+    pub fn new(query: &str, options: FindOptions) -> Result<Self> {
+        let pattern = if options.regex {
+            query.to_string()
+        } else {
+            regex::escape(query)
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(!options.case_sensitive)
+            .multi_line(true)
+            .build()?;
+        Ok(Self {
+            regex,
+            expand: options.regex,
+        })
+    }
+
+    /// 所有匹配（忽略空匹配）
+    pub fn matches(&self, text: &str) -> Vec<Range<usize>> {
+        self.regex
+            .find_iter(text)
+            .filter(|m| !m.is_empty())
+            .map(|m| m.range())
+            .collect()
+    }
+
+    /// 从 `from` 开始的下一个匹配，到末尾后从头查找
+    pub fn next(&self, text: &str, from: usize) -> Option<Range<usize>> {
+        let matches = self.matches(text);
+        matches
+            .iter()
+            .find(|m| m.start >= from)
+            .or_else(|| matches.first())
+            .cloned()
+    }
+
+    /// 在 `from` 之前的上一个匹配，到开头后从末尾查找
+    pub fn previous(&self, text: &str, from: usize) -> Option<Range<usize>> {
+        let matches = self.matches(text);
+        matches
+            .iter()
+            .rev()
+            .find(|m| m.end <= from)
+            .or_else(|| matches.last())
+            .cloned()
+    }
+
+    /// 替换从 `from` 开始的下一个匹配，返回替换后文本的范围
+    pub fn replace_next(&self, text: &mut String, from: usize, replacement: &str) -> Option<Range<usize>> {
+        let found = self.next(text, from)?;
+        let replaced = if self.expand {
+            self.regex.replace(&text[found.clone()], replacement).into_owned()
+        } else {
+            replacement.to_string()
+        };
+        text.replace_range(found.clone(), &replaced);
+        Some(found.start..found.start + replaced.len())
+    }
+
+    /// 替换全部匹配，返回替换的个数
+    pub fn replace_all(&self, text: &mut String, replacement: &str) -> usize {
+        let count = self.matches(text).len();
+        if count > 0 {
+            let replaced = if self.expand {
+                self.regex.replace_all(text, replacement)
+            } else {
+                self.regex.replace_all(text, NoExpand(replacement))
+            };
+            *text = replaced.into_owned();
+        }
+        count
+    }
+}
+
+/// 查找栏的输入和当前选中的匹配
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FindState {
+    pub visible: bool,
+    pub query: String,
+    pub replacement: String,
+    pub options: FindOptions,
+    /// 当前选中的匹配（字节范围）
+    pub current: Option<Range<usize>>,
+    /// 匹配个数或正则错误
+    pub message: String,
+}
+
+impl FindState {
+    pub fn finder(&mut self) -> Option<Finder> {
+        if self.query.is_empty() {
+            self.message.clear();
+            return None;
+        }
+        match Finder::new(&self.query, self.options) {
+            Ok(finder) => Some(finder),
+            Err(e) => {
+                self.message = format!("❌ {}", e);
+                None
+            }
+        }
+    }
+
+    /// 选中下一个（`backward` 为 true 时上一个）匹配
+    pub fn find(&mut self, text: &str, backward: bool) -> Option<Range<usize>> {
+        let finder = self.finder()?;
+        let found = match (&self.current, backward) {
+            (Some(current), false) => finder.next(text, current.end),
+            (Some(current), true) => finder.previous(text, current.start),
+            (None, false) => finder.next(text, 0),
+            (None, true) => finder.previous(text, text.len()),
+        };
+        self.message = match &found {
+            Some(_) => format!("{} 个匹配", finder.matches(text).len()),
+            None => "无匹配".to_string(),
+        };
+        self.current = found.clone();
+        found
+    }
+
+    /// 替换当前匹配并选中下一个，返回是否有替换
+    pub fn replace(&mut self, text: &mut String) -> bool {
+        let Some(finder) = self.finder() else {
+            return false;
+        };
+        let from = self.current.as_ref().map_or(0, |current| current.start);
+        let Some(replaced) = finder.replace_next(text, from, &self.replacement) else {
+            self.message = "无匹配".to_string();
+            return false;
+        };
+        self.current = Some(replaced.end..replaced.end);
+        self.find(text, false);
+        true
+    }
+
+    pub fn replace_all(&mut self, text: &mut String) -> usize {
+        let Some(finder) = self.finder() else {
+            return 0;
+        };
+        let count = finder.replace_all(text, &self.replacement);
+        self.current = None;
+        self.message = format!("已替换 {} 处", count);
+        count
+    }
+}
+
+// ============================================================================
+// 大文件只读分页查看
+// ============================================================================
+
+/// 后台读取线程发回的结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViewerUpdate {
+    Chunk {
+        id: u64,
+        offset: u64,
+        size: u64,
+        data: Vec<u8>,
+    },
+    Failed {
+        id: u64,
+        error: String,
+    },
+}
+
+/// 要读取的一页
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PageRequest {
+    offset: u64,
+    first_line: Option<usize>,
+    /// 起点可能在一行中间，显示时从下一行开始
+    align: bool,
+}
+
+/// 只读查看的远程大文件，每次只保存一页
+#[derive(Debug, Clone)]
+pub struct FileViewer {
+    pub id: u64,
+    pub connection: String,
+    pub remote_path: String,
+    pub size: u64,
+    pub language: Language,
+    /// 第一页检测到的编码
+    pub encoding: Option<TextEncoding>,
+    /// 当前页在文件中的起点
+    pub offset: u64,
+    /// 当前页首行的行号，跳转到末尾后未知
+    pub first_line: Option<usize>,
+    /// 当前页的内容（换行符已统一）
+    pub text: String,
+    /// 当前页对应的字节数
+    pub page_len: u64,
+    pub error: Option<String>,
+    pub find: FindState,
+    history: Vec<PageRequest>,
+    pending: Option<PageRequest>,
+}
+
+impl FileViewer {
+    pub fn new(id: u64, connection: &str, remote_path: &str, size: u64) -> Self {
+        Self {
+            id,
+            connection: connection.to_string(),
+            remote_path: remote_path.to_string(),
+            size,
+            language: Language::from_path(remote_path),
+            encoding: None,
+            offset: 0,
+            first_line: Some(1),
+            text: String::new(),
+            page_len: 0,
+            error: None,
+            find: FindState::default(),
+            history: Vec::new(),
+            pending: Some(PageRequest {
+                offset: 0,
+                first_line: Some(1),
+                align: false,
+            }),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.remote_path.rsplit('/').next().unwrap_or(&self.remote_path)
+    }
+
+    /// 正在读取的页的起点
+    pub fn loading(&self) -> Option<u64> {
+        self.pending.map(|p| p.offset)
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.pending.is_none() && self.offset + self.page_len < self.size
+    }
+
+    pub fn has_previous(&self) -> bool {
+        self.pending.is_none() && !self.history.is_empty()
+    }
+
+    fn current(&self) -> PageRequest {
+        PageRequest {
+            offset: self.offset,
+            first_line: self.first_line,
+            align: false,
+        }
+    }
+
+    /// 请求下一页，返回要读取的偏移
+    pub fn next_page(&mut self) -> Option<u64> {
+        if !self.has_next() {
+            return None;
+        }
+        self.history.push(self.current());
+        let lines = self.text.matches('\n').count();
+        self.request(PageRequest {
+            offset: self.offset + self.page_len,
+            first_line: self.first_line.map(|line| line + lines),
+            align: false,
+        })
+    }
+
+    pub fn previous_page(&mut self) -> Option<u64> {
+        if self.pending.is_some() {
+            return None;
+        }
+        let page = self.history.pop()?;
+        self.request(page)
+    }
+
+    /// 跳到文件开头
+    pub fn first_page(&mut self) -> Option<u64> {
+        if self.pending.is_some() || self.offset == 0 {
+            return None;
+        }
+        self.history.clear();
+        self.request(PageRequest {
+            offset: 0,
+            first_line: Some(1),
+            align: false,
+        })
+    }
+
+    /// 跳到文件末尾（行号未知）
+    pub fn last_page(&mut self) -> Option<u64> {
+        let offset = self.size.saturating_sub(VIEW_CHUNK) & !1;
+        if self.pending.is_some() || offset <= self.offset {
+            return None;
+        }
+        self.history.push(self.current());
+        self.request(PageRequest {
+            offset,
+            first_line: None,
+            align: offset > 0,
+        })
+    }
+
+    fn request(&mut self, page: PageRequest) -> Option<u64> {
+        self.pending = Some(page);
+        self.error = None;
+        Some(page.offset)
+    }
+
+    pub fn apply(&mut self, update: ViewerUpdate) {
+        let Some(page) = self.pending else {
+            return;
+        };
+        match update {
+            ViewerUpdate::Chunk { offset, size, data, .. } if offset == page.offset => {
+                self.pending = None;
+                self.size = size;
+                let encoding = *self.encoding.get_or_insert_with(|| TextEncoding::detect(&data));
+                let at_eof = offset + data.len() as u64 >= size;
+                let start = if page.align {
+                    line_boundary(&data, encoding, false).unwrap_or(0)
+                } else {
+                    0
+                };
+                let end = if at_eof {
+                    data.len()
+                } else {
+                    line_boundary(&data, encoding, true)
+                        .filter(|&end| end > start)
+                        .unwrap_or(data.len())
+                };
+                let (text, _) = encoding.decode(&data[start..end]);
+                self.text = normalize_line_endings(&text);
+                self.offset = offset + start as u64;
+                self.page_len = (end - start) as u64;
+                self.first_line = page.first_line;
+                self.find.current = None;
+            }
+            ViewerUpdate::Failed { error, .. } => {
+                self.pending = None;
+                self.error = Some(error);
+            }
+            ViewerUpdate::Chunk { .. } => {}
+        }
+    }
+}
+
+/// 第一个（`last` 为 false）或最后一个换行符之后的位置
+///
+/// UTF-16 按两字节单元查找，避免把字符的一半当作换行。
+fn line_boundary(data: &[u8], encoding: TextEncoding, last: bool) -> Option<usize> {
+    let newline = |i: usize| match encoding {
+        TextEncoding::Utf16Le => i.is_multiple_of(2) && data.get(i..i + 2) == Some(&[b'\n', 0]),
+        TextEncoding::Utf16Be => i.is_multiple_of(2) && data.get(i..i + 2) == Some(&[0, b'\n']),
+        _ => data[i] == b'\n',
+    };
+    let width = if encoding.is_utf16() { 2 } else { 1 };
+    let found = if last {
+        (0..data.len()).rev().find(|&i| newline(i))
+    } else {
+        (0..data.len()).find(|&i| newline(i))
+    };
+    found.map(|i| i + width)
+}
+
+/// 在后台读取一页
+pub fn spawn_viewer_read(
+    session: Arc<Mutex<SshSession>>,
+    id: u64,
+    remote_path: String,
+    offset: u64,
+    tx: mpsc::UnboundedSender<SftpMessage>,
+) {
+    std::thread::spawn(move || {
+        let sftp = session.lock().unwrap().sftp();
+        let update = match sftp.and_then(|sftp| sftp.read_range(&remote_path, offset, VIEW_CHUNK as usize)) {
+            Ok((data, size)) => ViewerUpdate::Chunk { id, offset, size, data },
+            Err(e) => ViewerUpdate::Failed {
+                id,
+                error: format!("读取失败: {}", e),
+            },
+        };
+        let _ = tx.send(SftpMessage::Viewer(update));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_round_trip_encodings() {
+        let text = "server {\r\n    listen 80; # 端口\r\n}\r\n";
+        for encoding in TextEncoding::ALL {
+            let Ok(bytes) = encoding.encode(text) else {
+                // Latin-1 等无法表示中文
+                assert!(matches!(encoding, TextEncoding::Windows1252 | TextEncoding::EucKr));
+                continue;
+            };
+            let document = Document::decode_as(&bytes, encoding).unwrap();
+            assert_eq!(document.text, "server {\n    listen 80; # 端口\n}\n");
+            assert_eq!((document.line_ending, document.lossy), (LineEnding::CrLf, false));
+            assert_eq!(document.encode().unwrap(), bytes, "{:?}", encoding);
+        }
+
+        assert_eq!(TextEncoding::detect("端口".as_bytes()), TextEncoding::Utf8);
+        let gbk = TextEncoding::Gb18030.encode("日志文件").unwrap();
+        assert_eq!(TextEncoding::detect(&gbk), TextEncoding::Gb18030);
+        assert_eq!(TextEncoding::detect(&[0xef, 0xbb, 0xbf, b'a']), TextEncoding::Utf8Bom);
+        assert_eq!(TextEncoding::detect(&[0xff, 0xfe, b'a', 0]), TextEncoding::Utf16Le);
+        assert_eq!(TextEncoding::detect(&[b'a', 0x81, 0xff]), TextEncoding::Windows1252);
+        // 采样末尾截断的多字节字符不影响判断
+        let mut long = "a".repeat(SNIFF_LEN - 1).into_bytes();
+        long.extend_from_slice("端".as_bytes());
+        assert_eq!(TextEncoding::detect(&long), TextEncoding::Utf8);
+
+        assert!(Document::decode(b"\x7fELF\0\0\x01").is_err());
+        let document = Document::decode_as(&[b'a', 0xff], TextEncoding::Utf8).unwrap();
+        assert!(document.lossy);
+    }
+
+    #[test]
+    fn test_line_endings() {
+        assert_eq!(LineEnding::detect("a\nb\n"), (LineEnding::Lf, false));
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), (LineEnding::CrLf, true));
+        assert_eq!(LineEnding::detect("a\rb\r"), (LineEnding::Cr, false));
+        assert_eq!(LineEnding::detect("no newline"), (LineEnding::Lf, false));
+
+        let mut document = Document::decode(b"a\r\nb\nc").unwrap();
+        assert!(document.mixed_line_endings);
+        assert_eq!(document.line_count(), 3);
+        document.line_ending = LineEnding::Lf;
+        assert_eq!(document.encode().unwrap(), b"a\nb\nc");
+    }
+
+    fn kinds(text: &str, language: Language) -> Vec<(&str, TokenKind)> {
+        highlight(text, language)
+            .into_iter()
+            .map(|t| (&text[t.range], t.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_highlight() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("fn main() { let s = \"a\\\"b\"; } // 结束", Language::Rust),
+            vec![
+                ("fn", Keyword),
+                ("let", Keyword),
+                ("\"a\\\"b\"", String),
+                ("// 结束", Comment)
+            ]
+        );
+        assert_eq!(
+            kinds("/* 多行\n注释 */ x = 0x1f + 2.5", Language::C),
+            vec![("/* 多行\n注释 */", Comment), ("0x1f", Number), ("2.5", Number)]
+        );
+        // `#` 只在空白后开始注释
+        assert_eq!(
+            kinds("echo ${#arr} # count\nfi", Language::Shell),
+            vec![("# count", Comment), ("fi", Keyword)]
+        );
+        assert_eq!(
+            kinds("[server]\nport = 8080 ; 端口\nname: web", Language::Config),
+            vec![
+                ("[server]", Key),
+                ("port", Key),
+                ("8080", Number),
+                ("; 端口", Comment),
+                ("name", Key)
+            ]
+        );
+        assert_eq!(
+            kinds("SELECT id FROM t -- all", Language::Sql),
+            vec![("SELECT", Keyword), ("FROM", Keyword), ("-- all", Comment)]
+        );
+        assert_eq!(
+            kinds("<a href=\"x\"><!-- c --></a>", Language::Markup),
+            vec![("<a", Key), ("\"x\"", String), ("<!-- c -->", Comment), ("</a", Key)]
+        );
+        assert!(highlight("fn x", Language::Plain).is_empty());
+
+        assert_eq!(Language::from_path("/etc/nginx/nginx.conf"), Language::Config);
+        assert_eq!(Language::from_path("/srv/app/Dockerfile"), Language::Shell);
+        assert_eq!(Language::from_path("/srv/docker-compose.YML"), Language::Yaml);
+        assert_eq!(Language::from_path("/var/log/syslog"), Language::Plain);
+    }
+
+    #[test]
+    fn test_find_and_replace() {
+        let finder = Finder::new("Port", FindOptions::default()).unwrap();
+        let mut text = "port=1\nPORT=2\nport=3".to_string();
+        assert_eq!(finder.matches(&text), vec![0..4, 7..11, 14..18]);
+        assert_eq!(finder.next(&text, 5), Some(7..11));
+        assert_eq!(finder.next(&text, 15), Some(0..4));
+        assert_eq!(finder.previous(&text, 7), Some(0..4));
+        assert_eq!(finder.previous(&text, 0), Some(14..18));
+
+        assert_eq!(finder.replace_next(&mut text, 5, "listen"), Some(7..13));
+        assert_eq!(text, "port=1\nlisten=2\nport=3");
+
+        let case = FindOptions {
+            case_sensitive: true,
+            regex: false,
+        };
+        let finder = Finder::new("port", case).unwrap();
+        assert_eq!(finder.replace_all(&mut text, "$0"), 2);
+        assert_eq!(text, "$0=1\nlisten=2\n$0=3");
+
+        let regex = FindOptions {
+            case_sensitive: false,
+            regex: true,
+        };
+        let finder = Finder::new(r"^(\w+)=(\d)$", regex).unwrap();
+        assert_eq!(finder.replace_all(&mut text, "$2:$1"), 1);
+        assert_eq!(text, "$0=1\n2:listen\n$0=3");
+        assert!(Finder::new("(", regex).is_err());
+    }
+
+    #[test]
+    fn test_find_state() {
+        let mut text = "a=1\nb=2\na=3".to_string();
+        let mut find = FindState {
+            query: "a".to_string(),
+            replacement: "c".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(find.find(&text, false), Some(0..1));
+        assert_eq!(find.find(&text, false), Some(8..9));
+        assert_eq!(find.find(&text, true), Some(0..1));
+        assert_eq!(find.message, "2 个匹配");
+
+        assert!(find.replace(&mut text));
+        assert_eq!(find.current, Some(8..9));
+        assert_eq!(text, "c=1\nb=2\na=3");
+        assert_eq!(find.replace_all(&mut text), 1);
+        assert_eq!((text.as_str(), find.message.as_str()), ("c=1\nb=2\nc=3", "已替换 1 处"));
+        assert_eq!(find.find(&text, false), None);
+
+        find.options.regex = true;
+        find.query = "(".to_string();
+        assert_eq!(find.find(&text, false), None);
+        assert!(find.message.starts_with('❌'));
+    }
+
+    fn chunk(viewer: &FileViewer, content: &[u8]) -> ViewerUpdate {
+        let offset = viewer.loading().unwrap();
+        let end = (offset + VIEW_CHUNK).min(content.len() as u64);
+        ViewerUpdate::Chunk {
+            id: viewer.id,
+            offset,
+            size: content.len() as u64,
+            data: content[offset as usize..end as usize].to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_viewer_pages_on_line_boundaries() {
+        // 每行 10 字节，共 60000 行，约 2.3 页
+        let content: Vec<u8> = (1..=60000).flat_map(|n| format!("{:09}\n", n).into_bytes()).collect();
+        let mut viewer = FileViewer::new(1, "web", "/var/log/app.log", content.len() as u64);
+        assert_eq!(viewer.loading(), Some(0));
+        viewer.apply(chunk(&viewer, &content));
+        assert!(viewer.text.starts_with("000000001\n"));
+        assert_eq!(viewer.page_len % 10, 0);
+        assert!(!viewer.has_previous());
+
+        viewer.next_page().unwrap();
+        viewer.apply(chunk(&viewer, &content));
+        let first_line = viewer.first_line.unwrap();
+        assert_eq!(viewer.text.lines().next().unwrap(), format!("{:09}", first_line));
+
+        // 末尾一页从完整的行开始，行号未知
+        viewer.last_page().unwrap();
+        viewer.apply(chunk(&viewer, &content));
+        assert_eq!(viewer.first_line, None);
+        assert_eq!(viewer.offset % 10, 0);
+        assert!(viewer.text.ends_with("000060000\n"));
+        assert!(!viewer.has_next());
+
+        viewer.previous_page().unwrap();
+        viewer.apply(chunk(&viewer, &content));
+        assert_eq!(viewer.first_line, Some(first_line));
+        viewer.previous_page().unwrap();
+        viewer.apply(ViewerUpdate::Failed {
+            id: 1,
+            error: "连接断开".to_string(),
+        });
+        assert_eq!(viewer.error.as_deref(), Some("连接断开"));
+        assert_eq!(viewer.loading(), None);
+    }
+
+    #[test]
+    fn test_line_boundary_utf16() {
+        let data = TextEncoding::Utf16Le.encode("\u{0a41}\u{4e00}\n").unwrap();
+        // 前两个字符的字节之间出现 `0a 00`，但不在两字节单元的边界上
+        assert_eq!(line_boundary(&data, TextEncoding::Utf16Le, false), Some(8));
+        assert_eq!(line_boundary(&data, TextEncoding::Utf16Le, true), Some(data.len()));
+        assert_eq!(line_boundary(b"ab\ncd", TextEncoding::Utf8, true), Some(3));
+        assert_eq!(line_boundary(b"abcd", TextEncoding::Utf8, false), None);
+    }
+}
@@ -0,0 +1,502 @@
+use crate::terminal::{AnsiAction, AnsiParser};
+use crate::types::{ExpectNext, ExpectScript, ExpectSend, SshConfig};
+use regex::Regex;
+use std::time::{Duration, Instant};
+
+// ============================================================================
+// expect 自动化：按脚本等待会话输出并自动应答
+// ============================================================================
+
+/// 匹配窗口保留的最大字节数，超出时丢弃最早的输出
+const WINDOW_LIMIT: usize = 16 * 1024;
+
+/// 单次输入内最多执行的步骤数，防止空模式的分支互相跳转形成死循环
+const MAX_TRANSITIONS: usize = 64;
+
+/// 脚本运行产生的动作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpectEffect {
+    /// 写入会话的字节
+    Send(Vec<u8>),
+    /// 记录到会话中的日志
+    Log(String),
+}
+
+struct CompiledBranch {
+    /// None 表示空模式，立即匹配
+    regex: Option<Regex>,
+    /// 发送内容（密钥已解析），以及日志中显示的描述
+    send: Option<(Vec<u8>, String)>,
+    next: ExpectNext,
+}
+
+struct CompiledStep {
+    branches: Vec<CompiledBranch>,
+    timeout: Option<Duration>,
+    on_timeout: ExpectNext,
+}
+
+/// 一个正在运行的脚本
+pub struct ExpectRunner {
+    name: String,
+    steps: Vec<CompiledStep>,
+    step: usize,
+    deadline: Option<Instant>,
+    /// 本步开始以来去除转义序列后的输出
+    window: String,
+    parser: AnsiParser,
+    utf8_pending: Vec<u8>,
+    finished: bool,
+}
+
+impl ExpectRunner {
+    /// 编译脚本并解析其中引用的密钥
+    pub fn new(
+        script: &ExpectScript,
+        connection: &SshConfig,
+        now: Instant,
+    ) -> Result<Self, String> {
+        let mut steps = Vec::new();
+        for (index, step) in script.steps.iter().enumerate() {
+            let mut branches = Vec::new();
+            for branch in &step.branches {
+                let regex = match branch.pattern.as_str() {
+                    "" => None,
+                    pattern => Some(
+                        Regex::new(pattern)
+                            .map_err(|e| format!("第 {} 步正则表达式错误: {}", index + 1, e))?,
+                    ),
+                };
+                let send = match &branch.send {
+                    None => None,
+                    Some(ExpectSend::Line { text }) => {
+                        Some((format!("{}\r", text).into_bytes(), format!("\"{}\"", text)))
+                    }
+                    Some(ExpectSend::Keys { text }) => {
+                        Some((unescape(text), format!("按键 \"{}\"", text)))
+                    }
+                    Some(ExpectSend::Secret { name }) => {
+                        let value = connection.secret(name).ok_or_else(|| {
+                            format!("连接 {} 没有保存密钥 “{}”", connection.name, name)
+                        })?;
+                        Some((
+                            format!("{}\r", value).into_bytes(),
+                            format!("密钥 “{}”", name),
+                        ))
+                    }
+                };
+                branches.push(CompiledBranch {
+                    regex,
+                    send,
+                    next: branch.next,
+                });
+            }
+            steps.push(CompiledStep {
+                branches,
+                timeout: (step.timeout_secs > 0).then(|| Duration::from_secs(step.timeout_secs)),
+                on_timeout: step.on_timeout,
+            });
+        }
+
+        let mut runner = Self {
+            name: script.name.clone(),
+            steps,
+            step: 0,
+            deadline: None,
+            window: String::new(),
+            parser: AnsiParser::new(),
+            utf8_pending: Vec::new(),
+            finished: false,
+        };
+        runner.enter_step(0, now);
+        Ok(runner)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// 当前步骤的超时时刻
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline.filter(|_| !self.finished)
+    }
+
+    /// 开始运行：处理首步中无需等待的分支
+    pub fn start(&mut self, now: Instant) -> Vec<ExpectEffect> {
+        let mut effects = vec![ExpectEffect::Log(format!("▶ 运行脚本 {}", self.name))];
+        self.advance(now, &mut effects);
+        effects
+    }
+
+    /// 处理会话输出
+    pub fn feed(&mut self, data: &[u8], now: Instant) -> Vec<ExpectEffect> {
+        let mut effects = Vec::new();
+        if self.finished {
+            return effects;
+        }
+
+        self.utf8_pending.extend_from_slice(data);
+        let valid = match std::str::from_utf8(&self.utf8_pending) {
+            Ok(text) => text.len(),
+            // 末尾不完整的字符留到下次，无效字节按替换字符处理
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => self.utf8_pending.len(),
+        };
+        let bytes: Vec<u8> = self.utf8_pending.drain(..valid).collect();
+        for action in self.parser.advance(&String::from_utf8_lossy(&bytes)) {
+            match action {
+                AnsiAction::Print(c) => self.window.push(c),
+                AnsiAction::Control('\n') => self.window.push('\n'),
+                _ => {}
+            }
+        }
+        if self.window.len() > WINDOW_LIMIT {
+            let mut cut = self.window.len() - WINDOW_LIMIT;
+            while !self.window.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.window.drain(..cut);
+        }
+
+        self.advance(now, &mut effects);
+        effects
+    }
+
+    /// 检查当前步骤是否超时
+    pub fn tick(&mut self, now: Instant) -> Vec<ExpectEffect> {
+        let mut effects = Vec::new();
+        if self.deadline().is_some_and(|deadline| now >= deadline) {
+            let step = &self.steps[self.step];
+            effects.push(ExpectEffect::Log(format!(
+                "⏱ 第 {} 步等待 {} 秒超时",
+                self.step + 1,
+                step.timeout.unwrap_or_default().as_secs()
+            )));
+            let next = step.on_timeout;
+            self.follow(next, now, &mut effects);
+            self.advance(now, &mut effects);
+        }
+        effects
+    }
+
+    /// 停止脚本
+    pub fn cancel(&mut self) -> Vec<ExpectEffect> {
+        if self.finished {
+            return Vec::new();
+        }
+        self.finished = true;
+        vec![ExpectEffect::Log(format!("■ 脚本 {} 已停止", self.name))]
+    }
+
+    /// 匹配当前窗口，直到没有分支命中
+    fn advance(&mut self, now: Instant, effects: &mut Vec<ExpectEffect>) {
+        for _ in 0..MAX_TRANSITIONS {
+            if self.finished {
+                return;
+            }
+            // 各分支中最早出现的匹配胜出
+            let hit = self.steps[self.step]
+                .branches
+                .iter()
+                .enumerate()
+                .filter_map(|(index, branch)| match &branch.regex {
+                    None => Some((index, 0..0)),
+                    Some(regex) => regex.find(&self.window).map(|m| (index, m.range())),
+                })
+                .min_by_key(|(index, range)| (range.start, *index));
+            let Some((index, range)) = hit else {
+                return;
+            };
+
+            let step = self.step;
+            let branch = &self.steps[step].branches[index];
+            let next = branch.next;
+            let mut log = match &branch.regex {
+                Some(_) => format!(
+                    "第 {} 步匹配 “{}”",
+                    step + 1,
+                    self.window[range.clone()].trim()
+                ),
+                None => format!("第 {} 步", step + 1),
+            };
+            if let Some((bytes, description)) = &branch.send {
+                log.push_str(&format!("，发送 {}", description));
+                effects.push(ExpectEffect::Send(bytes.clone()));
+            }
+            effects.push(ExpectEffect::Log(log));
+            self.window.drain(..range.end);
+            self.follow(next, now, effects);
+        }
+    }
+
+    fn follow(&mut self, next: ExpectNext, now: Instant, effects: &mut Vec<ExpectEffect>) {
+        match next {
+            ExpectNext::Next if self.step + 1 < self.steps.len() => {
+                self.enter_step(self.step + 1, now)
+            }
+            ExpectNext::Next | ExpectNext::Finish => self.finish(true, effects),
+            ExpectNext::Repeat => self.enter_step(self.step, now),
+            ExpectNext::Goto { step } if (1..=self.steps.len()).contains(&step) => {
+                self.enter_step(step - 1, now)
+            }
+            ExpectNext::Goto { step } => {
+                effects.push(ExpectEffect::Log(format!("第 {} 步不存在", step)));
+                self.finish(false, effects);
+            }
+            ExpectNext::Fail => self.finish(false, effects),
+        }
+    }
+
+    fn enter_step(&mut self, step: usize, now: Instant) {
+        match self.steps.get(step) {
+            Some(s) => {
+                self.step = step;
+                self.deadline = s.timeout.map(|t| now + t);
+            }
+            None => self.finished = true,
+        }
+    }
+
+    fn finish(&mut self, success: bool, effects: &mut Vec<ExpectEffect>) {
+        self.finished = true;
+        effects.push(ExpectEffect::Log(if success {
+            format!("✅ 脚本 {} 完成", self.name)
+        } else {
+            format!("❌ 脚本 {} 失败", self.name)
+        }));
+    }
+}
+
+/// 解码按键文本中的转义：`\r`、`\n`、`\t`、`\e`、`\\` 与 `\xHH`
+fn unescape(text: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('r') => out.push(b'\r'),
+            Some('n') => out.push(b'\n'),
+            Some('t') => out.push(b'\t'),
+            Some('e') => out.push(0x1b),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) => out.push(byte),
+                    Err(_) => out.extend_from_slice(format!("\\x{}", hex).as_bytes()),
+                }
+            }
+            Some(other) => {
+                let mut buf = [0; 4];
+                out.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+            None => out.push(b'\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AuthMethod, ExpectBranch, ExpectStep, StoredSecret};
+
+    fn branch(pattern: &str, send: Option<ExpectSend>, next: ExpectNext) -> ExpectBranch {
+        ExpectBranch {
+            pattern: pattern.to_string(),
+            send,
+            next,
+        }
+    }
+
+    fn step(branches: Vec<ExpectBranch>, timeout_secs: u64, on_timeout: ExpectNext) -> ExpectStep {
+        ExpectStep {
+            branches,
+            timeout_secs,
+            on_timeout,
+        }
+    }
+
+    fn connection() -> SshConfig {
+        let mut conn = SshConfig::new(
+            "sw1".to_string(),
+            "sw1".to_string(),
+            22,
+            "admin".to_string(),
+        );
+        conn.auth = Some(AuthMethod::Password("login".to_string()));
+        conn.secrets.push(StoredSecret {
+            name: "enable".to_string(),
+            value: "en-pw".to_string(),
+            value_encrypted: None,
+        });
+        conn
+    }
+
+    fn sends(effects: &[ExpectEffect]) -> Vec<Vec<u8>> {
+        effects
+            .iter()
+            .filter_map(|e| match e {
+                ExpectEffect::Send(bytes) => Some(bytes.clone()),
+                ExpectEffect::Log(_) => None,
+            })
+            .collect()
+    }
+
+    fn line(text: &str) -> Option<ExpectSend> {
+        Some(ExpectSend::Line {
+            text: text.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_branching_and_secrets() {
+        let script = ExpectScript {
+            name: "enable".to_string(),
+            auto_start: true,
+            steps: vec![
+                step(
+                    vec![
+                        branch(r"\(yes/no\)", line("yes"), ExpectNext::Repeat),
+                        branch(r">\s*$", line("enable"), ExpectNext::Next),
+                    ],
+                    10,
+                    ExpectNext::Fail,
+                ),
+                step(
+                    vec![branch(
+                        "Password:",
+                        Some(ExpectSend::Secret {
+                            name: "enable".to_string(),
+                        }),
+                        ExpectNext::Next,
+                    )],
+                    10,
+                    ExpectNext::Fail,
+                ),
+                step(
+                    vec![branch("#", None, ExpectNext::Finish)],
+                    10,
+                    ExpectNext::Fail,
+                ),
+            ],
+        };
+        let now = Instant::now();
+        let mut runner = ExpectRunner::new(&script, &connection(), now).unwrap();
+        assert!(sends(&runner.start(now)).is_empty());
+
+        // 提示被拆在两个输出块中，并带有颜色序列
+        assert!(sends(&runner.feed(b"Are you sure (yes/", now)).is_empty());
+        assert_eq!(sends(&runner.feed(b"no)? ", now)), vec![b"yes\r".to_vec()]);
+        assert_eq!(
+            sends(&runner.feed(b"\r\n\x1b[1msw1\x1b[0m> ", now)),
+            vec![b"enable\r".to_vec()]
+        );
+        let effects = runner.feed(b"enable\r\nPassword: ", now);
+        assert_eq!(sends(&effects), vec![b"en-pw\r".to_vec()]);
+        // 日志中不显示密钥内容
+        assert!(effects
+            .iter()
+            .all(|e| !matches!(e, ExpectEffect::Log(l) if l.contains("en-pw"))));
+        assert!(!runner.is_finished());
+        runner.feed("\r\nsw1#".as_bytes(), now);
+        assert!(runner.is_finished());
+    }
+
+    #[test]
+    fn test_timeout_and_immediate_send() {
+        let script = ExpectScript {
+            name: "menu".to_string(),
+            auto_start: false,
+            steps: vec![
+                step(
+                    vec![branch(
+                        "",
+                        Some(ExpectSend::Keys {
+                            text: "\\x1b[B\\r".to_string(),
+                        }),
+                        ExpectNext::Next,
+                    )],
+                    0,
+                    ExpectNext::Fail,
+                ),
+                step(
+                    vec![branch("Main menu", line("2"), ExpectNext::Finish)],
+                    5,
+                    ExpectNext::Goto { step: 1 },
+                ),
+            ],
+        };
+        let now = Instant::now();
+        let mut runner = ExpectRunner::new(&script, &connection(), now).unwrap();
+        assert_eq!(sends(&runner.start(now)), vec![b"\x1b[B\r".to_vec()]);
+        assert_eq!(runner.deadline(), Some(now + Duration::from_secs(5)));
+
+        // 超时后回到第 1 步，再次立即发送
+        assert!(runner.tick(now + Duration::from_secs(4)).is_empty());
+        assert_eq!(
+            sends(&runner.tick(now + Duration::from_secs(5))),
+            vec![b"\x1b[B\r".to_vec()]
+        );
+        assert_eq!(
+            sends(&runner.feed(b"== Main menu ==", now)),
+            vec![b"2\r".to_vec()]
+        );
+        assert!(runner.is_finished());
+        assert!(runner.deadline().is_none());
+    }
+
+    #[test]
+    fn test_compile_errors_and_loops() {
+        let conn = connection();
+        let now = Instant::now();
+        let missing = ExpectScript {
+            name: "x".to_string(),
+            auto_start: true,
+            steps: vec![step(
+                vec![branch(
+                    "",
+                    Some(ExpectSend::Secret {
+                        name: "nope".to_string(),
+                    }),
+                    ExpectNext::Next,
+                )],
+                0,
+                ExpectNext::Fail,
+            )],
+        };
+        let error = ExpectRunner::new(&missing, &conn, now).err();
+        assert!(error.is_some_and(|e| e.contains("nope")));
+        let invalid = ExpectScript {
+            steps: vec![step(
+                vec![branch("(", None, ExpectNext::Next)],
+                0,
+                ExpectNext::Fail,
+            )],
+            ..missing.clone()
+        };
+        assert!(ExpectRunner::new(&invalid, &conn, now).is_err());
+
+        // 空模式的分支重复本步不会卡死
+        let looping = ExpectScript {
+            steps: vec![step(
+                vec![branch("", None, ExpectNext::Repeat)],
+                0,
+                ExpectNext::Fail,
+            )],
+            ..missing
+        };
+        let mut runner = ExpectRunner::new(&looping, &conn, now).unwrap();
+        runner.start(now);
+        assert!(!runner.is_finished());
+        assert!(!runner.cancel().is_empty());
+        assert!(runner.is_finished());
+    }
+}
@@ -0,0 +1,254 @@
+use crate::checksum::shell_quote;
+use crate::ssh::SshSession;
+use crate::types::{Result, SftpMessage};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+// ============================================================================
+// 远程文件管理：重命名/移动、复制、权限、属主、符号链接
+// ============================================================================
+
+/// 读取 passwd/group 文件时的大小上限
+const MAX_ID_FILE: usize = 4 * 1024 * 1024;
+
+/// 一次文件管理操作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileOp {
+    /// 重命名，目标在其他目录时即为移动
+    Rename {
+        from: String,
+        to: String,
+    },
+    /// 在服务器端复制（`cp -a`，目录递归复制）
+    Copy {
+        from: String,
+        to: String,
+    },
+    Chmod {
+        path: String,
+        mode: u32,
+    },
+    /// None 表示不修改
+    Chown {
+        path: String,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    },
+    /// 在 `link` 处创建指向 `target` 的符号链接
+    Symlink {
+        target: String,
+        link: String,
+    },
+    Readlink {
+        path: String,
+    },
+}
+
+impl FileOp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileOp::Rename { .. } => "重命名",
+            FileOp::Copy { .. } => "复制",
+            FileOp::Chmod { .. } => "修改权限",
+            FileOp::Chown { .. } => "修改属主",
+            FileOp::Symlink { .. } => "创建链接",
+            FileOp::Readlink { .. } => "读取链接",
+        }
+    }
+
+    /// 执行操作，返回给用户的结果说明
+    ///
+    /// libssh2 不支持 SFTP 的 `copy-data` 扩展，复制通过 exec 通道运行 `cp -a`，
+    /// 数据不经过本机。
+    pub fn run(&self, session: &SshSession) -> Result<String> {
+        if let FileOp::Copy { from, to } = self {
+            session.execute_checked(&format!("cp -a -- {} {}", shell_quote(from), shell_quote(to)))?;
+            return Ok(format!("已复制 {} → {}", from, to));
+        }
+        let sftp = session.sftp()?;
+        Ok(match self {
+            FileOp::Rename { from, to } => {
+                sftp.rename(from, to)?;
+                format!("已移动 {} → {}", from, to)
+            }
+            FileOp::Chmod { path, mode } => {
+                sftp.chmod(path, *mode)?;
+                format!("{} 的权限已改为 {:04o} ({})", path, mode, mode_string(*mode))
+            }
+            FileOp::Chown { path, uid, gid } => {
+                sftp.chown(path, *uid, *gid)?;
+                format!("已修改 {} 的属主", path)
+            }
+            FileOp::Symlink { target, link } => {
+                sftp.symlink(target, link)?;
+                format!("已创建链接 {} → {}", link, target)
+            }
+            FileOp::Readlink { path } => format!("{} → {}", path, sftp.readlink(path)?),
+            FileOp::Copy { .. } => unreachable!(),
+        })
+    }
+}
+
+/// 在后台执行文件操作
+///
+/// 修改了远程文件的操作成功后发送 [`SftpMessage::Changed`]，由界面刷新列表。
+pub fn spawn(session: Arc<Mutex<SshSession>>, op: FileOp, tx: mpsc::UnboundedSender<SftpMessage>) {
+    std::thread::spawn(move || {
+        let result = op.run(&session.lock().unwrap());
+        let message = match result {
+            Ok(text) if matches!(op, FileOp::Readlink { .. }) => SftpMessage::Status(text),
+            Ok(text) => SftpMessage::Changed(text),
+            Err(e) => SftpMessage::Error(format!("{}失败: {}", op.name(), e)),
+        };
+        let _ = tx.send(message);
+    });
+}
+
+/// 在后台解析符号链接的目标
+pub fn spawn_resolve_link(session: Arc<Mutex<SshSession>>, path: String, tx: mpsc::UnboundedSender<SftpMessage>) {
+    std::thread::spawn(move || {
+        let result = session.lock().unwrap().sftp().and_then(|sftp| sftp.resolve_link(&path));
+        let (target, is_dir) = match result {
+            Ok((target, is_dir)) => (Some(target), is_dir),
+            Err(e) => {
+                eprintln!("Failed to read link {}: {}", path, e);
+                (None, false)
+            }
+        };
+        let _ = tx.send(SftpMessage::LinkResolved { path, target, is_dir });
+    });
+}
+
+/// `rwxr-xr-x` 形式的权限（含 setuid/setgid/sticky 位）
+pub fn mode_string(mode: u32) -> String {
+    let special = [(0o4000, 's', 'S'), (0o2000, 's', 'S'), (0o1000, 't', 'T')];
+    let mut text = String::with_capacity(9);
+    for (i, (bit, set, unset)) in special.into_iter().enumerate() {
+        let shift = 6 - i * 3;
+        let rwx = (mode >> shift) & 0o7;
+        text.push(if rwx & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if rwx & 0o2 != 0 { 'w' } else { '-' });
+        text.push(match (rwx & 0o1 != 0, mode & bit != 0) {
+            (true, true) => set,
+            (false, true) => unset,
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+/// 远程主机上的用户名和组名
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IdNames {
+    pub users: BTreeMap<u32, String>,
+    pub groups: BTreeMap<u32, String>,
+}
+
+impl IdNames {
+    /// 解析 `/etc/passwd` 和 `/etc/group` 的内容
+    pub fn parse(passwd: &str, group: &str) -> Self {
+        Self {
+            users: parse_id_file(passwd),
+            groups: parse_id_file(group),
+        }
+    }
+
+    /// 通过 SFTP 读取远程的 `/etc/passwd` 和 `/etc/group`
+    pub fn fetch(session: &SshSession) -> Result<Self> {
+        let sftp = session.sftp()?;
+        let read = |path: &str| -> Result<String> {
+            let (data, _) = sftp.read_range(path, 0, MAX_ID_FILE)?;
+            Ok(String::from_utf8_lossy(&data).into_owned())
+        };
+        Ok(Self::parse(&read("/etc/passwd")?, &read("/etc/group")?))
+    }
+
+    pub fn user(&self, uid: u32) -> Option<&str> {
+        self.users.get(&uid).map(String::as_str)
+    }
+
+    pub fn group(&self, gid: u32) -> Option<&str> {
+        self.groups.get(&gid).map(String::as_str)
+    }
+
+    /// 用户名或数字 uid 转为 uid
+    pub fn resolve_user(&self, name: &str) -> Result<u32> {
+        resolve(&self.users, name).ok_or_else(|| format!("未知用户: {}", name).into())
+    }
+
+    /// 组名或数字 gid 转为 gid
+    pub fn resolve_group(&self, name: &str) -> Result<u32> {
+        resolve(&self.groups, name).ok_or_else(|| format!("未知组: {}", name).into())
+    }
+}
+
+/// 在后台读取连接的用户和组
+pub fn spawn_fetch_ids(session: Arc<Mutex<SshSession>>, connection: String, tx: mpsc::UnboundedSender<SftpMessage>) {
+    std::thread::spawn(move || {
+        let message = match IdNames::fetch(&session.lock().unwrap()) {
+            Ok(names) => SftpMessage::Ids { connection, names },
+            Err(e) => SftpMessage::Error(format!("无法读取用户和组: {}", e)),
+        };
+        let _ = tx.send(message);
+    });
+}
+
+/// `name:x:id:...` 格式的每行取第 1 和第 3 个字段；同一 id 保留第一个名称
+fn parse_id_file(text: &str) -> BTreeMap<u32, String> {
+    let mut ids = BTreeMap::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(':').collect();
+        if let (Some(name), Some(Ok(id))) = (fields.first(), fields.get(2).map(|id| id.parse::<u32>())) {
+            ids.entry(id).or_insert_with(|| name.to_string());
+        }
+    }
+    ids
+}
+
+fn resolve(ids: &BTreeMap<u32, String>, name: &str) -> Option<u32> {
+    let name = name.trim();
+    ids.iter()
+        .find(|(_, n)| n.as_str() == name)
+        .map(|(id, _)| *id)
+        .or_else(|| name.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_string() {
+        assert_eq!(mode_string(0o755), "rwxr-xr-x");
+        assert_eq!(mode_string(0o100644), "rw-r--r--");
+        assert_eq!(mode_string(0o4755), "rwsr-xr-x");
+        assert_eq!(mode_string(0o2640), "rw-r-S---");
+        assert_eq!(mode_string(0o1777), "rwxrwxrwt");
+        assert_eq!(mode_string(0), "---------");
+    }
+
+    #[test]
+    fn test_id_names() {
+        let passwd = "# comment\nroot:x:0:0:root:/root:/bin/bash\nwww-data:x:33:33::/var/www:/usr/sbin/nologin\n\
+                      toor:x:0:0::/root:/bin/sh\nbroken line\n";
+        let group = "root:x:0:\nwww-data:x:33:\nadm:x:4:syslog,alice\n";
+        let names = IdNames::parse(passwd, group);
+
+        assert_eq!(names.user(0), Some("root"));
+        assert_eq!(names.user(33), Some("www-data"));
+        assert_eq!(names.user(1000), None);
+        assert_eq!(names.group(4), Some("adm"));
+        assert_eq!(names.users.len(), 2);
+
+        assert_eq!(names.resolve_user("www-data").unwrap(), 33);
+        assert_eq!(names.resolve_user(" 1000 ").unwrap(), 1000);
+        assert!(names.resolve_user("nobody").is_err());
+        assert_eq!(names.resolve_group("adm").unwrap(), 4);
+    }
+}
@@ -7,6 +7,9 @@ pub struct HistoryEntry {
     pub command: String,
     pub timestamp: String,
     pub connection: String,
+    /// 命令的退出码（由 shell 集成的 OSC 133 标记上报）
+    #[serde(default)]
+    pub exit_code: Option<i32>,
 }
 
 /// 命令历史管理器
@@ -42,6 +45,7 @@ impl CommandHistory {
             command,
             timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
             connection,
+            exit_code: None,
         };
 
         self.commands.push(entry);
@@ -52,6 +56,28 @@ impl CommandHistory {
         }
     }
 
+    /// 记录命令的退出码
+    ///
+    /// 优先补全同一连接最近一条尚无退出码的相同命令；直接在终端中输入、
+    /// 没有经过命令输入框的命令则作为新记录加入。
+    pub fn record_exit(&mut self, command: &str, connection: &str, exit_code: Option<i32>) {
+        let pending = self
+            .commands
+            .iter_mut()
+            .rev()
+            .find(|e| e.connection == connection && e.command.trim() == command.trim())
+            .filter(|e| e.exit_code.is_none());
+        match pending {
+            Some(entry) => entry.exit_code = exit_code,
+            None => {
+                self.add(command.to_string(), connection.to_string());
+                if let Some(entry) = self.commands.last_mut().filter(|e| e.command == command) {
+                    entry.exit_code = exit_code;
+                }
+            }
+        }
+    }
+
     /// 获取所有历史记录（最新的在前）
     pub fn get_all(&self) -> Vec<&HistoryEntry> {
         self.commands.iter().rev().collect()
@@ -78,7 +104,7 @@ impl CommandHistory {
             .collect();
 
         // 按匹配分数降序排序
-        results.sort_by(|a, b| b.1.cmp(&a.1));
+        results.sort_by_key(|r| std::cmp::Reverse(r.1));
         results.into_iter().map(|(entry, _)| entry).collect()
     }
 
@@ -216,4 +242,20 @@ mod tests {
         assert_eq!(stats.unique_commands, 2);
         assert_eq!(stats.unique_connections, 2);
     }
+
+    #[test]
+    fn test_record_exit_code() {
+        let mut history = CommandHistory::new();
+        history.add("make".to_string(), "s1".to_string());
+        history.record_exit("make", "s1", Some(2));
+        assert_eq!(history.commands.len(), 1);
+        assert_eq!(history.commands[0].exit_code, Some(2));
+
+        // 直接在终端中输入的命令作为新记录加入
+        history.record_exit("make", "s1", Some(0));
+        assert_eq!(history.commands.len(), 2);
+        assert_eq!(history.commands[1].exit_code, Some(0));
+        history.record_exit("  ", "s1", Some(0));
+        assert_eq!(history.commands.len(), 2);
+    }
 }
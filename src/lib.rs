@@ -1,15 +1,31 @@
+pub mod actions;
 pub mod ai;
 pub mod app;
+pub mod checksum;
+pub mod color_scheme;
 pub mod config;
 pub mod crypto;
+pub mod editor;
+pub mod expect;
+pub mod export;
+pub mod file_ops;
 pub mod history;
 pub mod monitor;
+pub mod notify;
+pub mod pty;
+pub mod remote_edit;
+pub mod session;
 pub mod sftp;
 pub mod ssh;
 pub mod state;
+pub mod telnet;
+pub mod terminal;
+pub mod triggers;
 pub mod theme;
+pub mod transfer;
 pub mod types;
 pub mod ui;
+pub mod zmodem;
 
 pub use app::App;
 pub use types::*;
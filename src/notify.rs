@@ -0,0 +1,78 @@
+use std::process::{Command, Stdio};
+
+// ============================================================================
+// 桌面通知与提示音（调用系统自带工具，不可用时静默忽略）
+// ============================================================================
+
+/// 在后台运行命令并回收进程
+fn spawn_detached(mut command: Command) {
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    std::thread::spawn(move || {
+        if let Err(e) = command.status() {
+            eprintln!("Failed to run {:?}: {}", command.get_program(), e);
+        }
+    });
+}
+
+/// PowerShell 单引号字符串转义
+#[cfg(target_os = "windows")]
+fn quote(text: &str, quote: char) -> String {
+    text.replace(quote, &format!("{}{}", quote, quote))
+}
+
+/// 发送桌面通知
+pub fn desktop_notification(title: &str, body: &str) {
+    #[cfg(target_os = "macos")]
+    {
+        let script = format!(
+            "display notification \"{}\" with title \"{}\"",
+            body.replace('\\', "\\\\").replace('"', "\\\""),
+            title.replace('\\', "\\\\").replace('"', "\\\"")
+        );
+        let mut command = Command::new("osascript");
+        command.args(["-e", &script]);
+        spawn_detached(command);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $n = New-Object System.Windows.Forms.NotifyIcon; \
+             $n.Icon = [System.Drawing.SystemIcons]::Information; $n.Visible = $true; \
+             $n.ShowBalloonTip(5000, '{}', '{}', 'Info'); Start-Sleep -Seconds 6; $n.Dispose()",
+            quote(title, '\''),
+            quote(body, '\'')
+        );
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-WindowStyle", "Hidden", "-Command", &script]);
+        spawn_detached(command);
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name=iShell", title, body]);
+        spawn_detached(command);
+    }
+}
+
+/// 播放提示音
+pub fn play_alert_sound() {
+    #[cfg(target_os = "macos")]
+    {
+        let mut command = Command::new("afplay");
+        command.arg("/System/Library/Sounds/Glass.aiff");
+        spawn_detached(command);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-Command", "[System.Media.SystemSounds]::Exclamation.Play()"]);
+        spawn_detached(command);
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        let mut command = Command::new("canberra-gtk-play");
+        command.args(["-i", "bell"]);
+        spawn_detached(command);
+    }
+}
@@ -0,0 +1,258 @@
+use crate::session::SessionBackend;
+use crate::types::Result;
+
+// ============================================================================
+// 本地伪终端 (v0.4.0)
+// ============================================================================
+
+/// 用户的默认 shell（`$SHELL`，未设置时使用 `/bin/sh`）
+pub fn default_shell() -> String {
+    std::env::var("SHELL")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "/bin/sh".to_string())
+}
+
+/// 运行在本地伪终端中的进程
+#[cfg(unix)]
+pub struct LocalPty {
+    master: std::fs::File,
+    child: std::process::Child,
+}
+
+#[cfg(unix)]
+impl LocalPty {
+    /// 在伪终端中启动用户的默认 shell
+    pub fn spawn_shell(cols: u16, rows: u16) -> Result<Self> {
+        Self::spawn(&default_shell(), &[], cols, rows)
+    }
+
+    /// 在伪终端中启动指定程序
+    pub fn spawn(program: &str, args: &[&str], cols: u16, rows: u16) -> Result<Self> {
+        use std::os::fd::FromRawFd;
+        use std::os::unix::process::CommandExt;
+        use std::process::{Command, Stdio};
+
+        let mut master_fd = 0;
+        let mut slave_fd = 0;
+        let mut winsize = Self::winsize(cols, rows);
+        // SAFETY: 输出参数均指向有效的局部变量
+        let ret = unsafe {
+            libc::openpty(
+                &mut master_fd,
+                &mut slave_fd,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::addr_of_mut!(winsize),
+            )
+        };
+        if ret != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        // SAFETY: openpty 成功后两个描述符都归我们所有
+        let master = unsafe { std::fs::File::from_raw_fd(master_fd) };
+        let slave = unsafe { std::fs::File::from_raw_fd(slave_fd) };
+
+        // 两端都不应被之后启动的其他子进程继承（从端通过 dup2 成为 shell 的标准输入输出），
+        // 主端读取使用非阻塞模式
+        // SAFETY: master_fd 与 slave_fd 是有效的描述符
+        unsafe {
+            libc::fcntl(master_fd, libc::F_SETFD, libc::FD_CLOEXEC);
+            libc::fcntl(slave_fd, libc::F_SETFD, libc::FD_CLOEXEC);
+            let flags = libc::fcntl(master_fd, libc::F_GETFL);
+            libc::fcntl(master_fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+        }
+
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .env("TERM", "xterm-256color")
+            .env("COLORTERM", "truecolor")
+            .stdin(Stdio::from(slave.try_clone()?))
+            .stdout(Stdio::from(slave.try_clone()?))
+            .stderr(Stdio::from(slave));
+        if let Some(home) = dirs::home_dir() {
+            command.current_dir(home);
+        }
+
+        // 子进程成为新会话的首进程，并以伪终端作为控制终端
+        // SAFETY: pre_exec 中只调用异步信号安全的函数
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                if libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        let child = command.spawn()?;
+        // 关闭父进程中的从端，shell 退出后主端才能读到挂断（EIO）
+        drop(command);
+        Ok(Self { master, child })
+    }
+
+    fn winsize(cols: u16, rows: u16) -> libc::winsize {
+        libc::winsize {
+            ws_row: rows,
+            ws_col: cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        }
+    }
+}
+
+#[cfg(unix)]
+impl SessionBackend for LocalPty {
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        use std::io::Write;
+
+        let mut written = 0;
+        while written < data.len() {
+            match self.master.write(&data[written..]) {
+                Ok(n) => written += n,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        use std::io::Read;
+
+        match self.master.read(buf) {
+            Ok(n) => Ok(n),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(0),
+            // 从端全部关闭（子进程退出）时 Linux 返回 EIO
+            Err(e) if e.raw_os_error() == Some(libc::EIO) => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn resize(&mut self, cols: u16, rows: u16) -> Result<()> {
+        use std::os::fd::AsRawFd;
+
+        let winsize = Self::winsize(cols, rows);
+        // SAFETY: 描述符有效，winsize 指向有效的结构体
+        let ret = unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ as _, &winsize) };
+        if ret != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    fn is_active(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    fn close(&mut self) -> Result<()> {
+        if self.is_active() {
+            self.child.kill()?;
+        }
+        self.child.wait()?;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+impl Drop for LocalPty {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+/// 非 Unix 平台暂不支持本地伪终端
+#[cfg(not(unix))]
+pub struct LocalPty;
+
+#[cfg(not(unix))]
+impl LocalPty {
+    pub fn spawn_shell(_cols: u16, _rows: u16) -> Result<Self> {
+        Err("Local shell is only supported on Unix platforms".into())
+    }
+}
+
+#[cfg(not(unix))]
+impl SessionBackend for LocalPty {
+    fn write(&mut self, _data: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+        Ok(0)
+    }
+
+    fn resize(&mut self, _cols: u16, _rows: u16) -> Result<()> {
+        Ok(())
+    }
+
+    fn is_active(&mut self) -> bool {
+        false
+    }
+
+    fn close(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn read_until(pty: &mut LocalPty, needle: &str) -> String {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut output = String::new();
+        let mut buf = [0u8; 1024];
+        while Instant::now() < deadline && !output.contains(needle) {
+            let n = pty.read(&mut buf).unwrap();
+            if n == 0 {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            output.push_str(&String::from_utf8_lossy(&buf[..n]));
+        }
+        output
+    }
+
+    #[test]
+    fn test_pty_runs_interactive_program() {
+        let mut pty = LocalPty::spawn("/bin/sh", &[], 80, 24).unwrap();
+        assert!(pty.is_active());
+
+        // 通过 stty 确认程序运行在尺寸正确的终端中
+        pty.write(b"stty size; echo done-$((1+1))\n").unwrap();
+        let output = read_until(&mut pty, "done-2");
+        assert!(output.contains("24 80"), "output: {:?}", output);
+
+        pty.resize(100, 30).unwrap();
+        pty.write(b"stty size\n").unwrap();
+        assert!(read_until(&mut pty, "30 100").contains("30 100"));
+
+        pty.write(b"exit\n").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while pty.is_active() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!pty.is_active());
+
+        // 父进程不持有从端，shell 退出后主端报告挂断
+        let mut buf = [0u8; 1024];
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let hangup = loop {
+            match std::io::Read::read(&mut pty.master, &mut buf) {
+                Err(e) if e.raw_os_error() == Some(libc::EIO) => break true,
+                _ if Instant::now() > deadline => break false,
+                Ok(n) if n > 0 => {}
+                _ => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        assert!(hangup);
+    }
+}
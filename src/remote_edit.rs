@@ -0,0 +1,588 @@
+use crate::editor::{Document, FindState, Language, TextEncoding};
+use crate::ssh::SshSession;
+use crate::types::{FileEntry, Result, SftpMessage};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
+
+// ============================================================================
+// 远程文件编辑：下载到本地工作区，监视保存并原子地上传回去
+// ============================================================================
+
+/// 检查本地文件是否被保存的间隔
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 远程文件的版本（修改时间与大小），用于发现他人的修改
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoteVersion {
+    pub modified: Option<SystemTime>,
+    pub size: u64,
+}
+
+impl RemoteVersion {
+    pub fn of(entry: &FileEntry) -> Self {
+        Self {
+            modified: entry.modified,
+            size: entry.size,
+        }
+    }
+}
+
+/// 编辑会话的状态
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditState {
+    Downloading,
+    /// 等待本地保存
+    Watching,
+    Uploading,
+    /// 远程文件在打开后被修改（或删除），本次保存没有上传
+    Conflict {
+        remote: Option<RemoteVersion>,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+/// 一个正在编辑的远程文件
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditSession {
+    pub id: u64,
+    /// 连接名称
+    pub connection: String,
+    pub remote_path: String,
+    /// 工作区中的本地副本
+    pub local_path: PathBuf,
+    /// 在内置编辑器而不是外部编辑器中打开
+    pub builtin: bool,
+    pub state: EditState,
+    /// 打开或上次上传后的远程版本，尚未下载完成时为 `None`
+    pub remote_version: Option<RemoteVersion>,
+    /// 上次下载或上传时本地副本的修改时间
+    synced_local: Option<SystemTime>,
+    /// 已上传的次数
+    pub uploads: u32,
+}
+
+impl EditSession {
+    pub fn name(&self) -> &str {
+        self.remote_path.rsplit('/').next().unwrap_or(&self.remote_path)
+    }
+}
+
+/// 后台线程发回的结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditUpdate {
+    Downloaded {
+        id: u64,
+        remote: RemoteVersion,
+        local: Option<SystemTime>,
+    },
+    Uploaded {
+        id: u64,
+        remote: RemoteVersion,
+        local: Option<SystemTime>,
+    },
+    Conflict {
+        id: u64,
+        remote: Option<RemoteVersion>,
+    },
+    Failed {
+        id: u64,
+        error: String,
+    },
+}
+
+/// 远程编辑会话管理器
+///
+/// 与 [`crate::transfer::TransferManager`] 一样只维护状态，
+/// 下载和上传由界面通过 [`spawn_download`]/[`spawn_upload`] 启动。
+#[derive(Debug, Default)]
+pub struct RemoteEditManager {
+    sessions: Vec<EditSession>,
+    next_id: u64,
+    last_poll: Option<Instant>,
+}
+
+impl RemoteEditManager {
+    pub fn sessions(&self) -> &[EditSession] {
+        &self.sessions
+    }
+
+    pub fn get(&self, id: u64) -> Option<&EditSession> {
+        self.sessions.iter().find(|s| s.id == id)
+    }
+
+    fn get_mut(&mut self, id: u64) -> Option<&mut EditSession> {
+        self.sessions.iter_mut().find(|s| s.id == id)
+    }
+
+    /// 已在编辑的同一文件
+    pub fn find(&self, connection: &str, remote_path: &str) -> Option<&EditSession> {
+        self.sessions
+            .iter()
+            .find(|s| s.connection == connection && s.remote_path == remote_path)
+    }
+
+    /// 新建编辑会话并创建工作区目录，返回交给下载线程的会话
+    pub fn open(&mut self, connection: &str, remote_path: &str, builtin: bool) -> Result<EditSession> {
+        let id = self.next_id.max(1);
+        self.next_id = id + 1;
+        let workspace = workspace_root()?.join(format!("{:08x}", rand::random::<u32>()));
+        private_dir_builder().create(&workspace)?;
+        let name = remote_path
+            .rsplit('/')
+            .next()
+            .filter(|n| !n.is_empty())
+            .unwrap_or("file");
+        let session = EditSession {
+            id,
+            connection: connection.to_string(),
+            remote_path: remote_path.to_string(),
+            local_path: workspace.join(name),
+            builtin,
+            state: EditState::Downloading,
+            remote_version: None,
+            synced_local: None,
+            uploads: 0,
+        };
+        self.sessions.push(session.clone());
+        Ok(session)
+    }
+
+    /// 处理后台线程的结果，返回更新后的会话
+    pub fn apply(&mut self, update: EditUpdate) -> Option<&EditSession> {
+        let id = match &update {
+            EditUpdate::Downloaded { id, .. }
+            | EditUpdate::Uploaded { id, .. }
+            | EditUpdate::Conflict { id, .. }
+            | EditUpdate::Failed { id, .. } => *id,
+        };
+        let session = self.get_mut(id)?;
+        match update {
+            EditUpdate::Downloaded { remote, local, .. } => {
+                session.state = EditState::Watching;
+                session.remote_version = Some(remote);
+                session.synced_local = local;
+            }
+            EditUpdate::Uploaded { remote, local, .. } => {
+                session.state = EditState::Watching;
+                session.remote_version = Some(remote);
+                session.synced_local = local;
+                session.uploads += 1;
+            }
+            EditUpdate::Conflict { remote, .. } => session.state = EditState::Conflict { remote },
+            EditUpdate::Failed { error, .. } => session.state = EditState::Failed { error },
+        }
+        Some(session)
+    }
+
+    /// 找出本地副本已被保存的会话并标记为上传中（每 [`POLL_INTERVAL`] 检查一次）
+    pub fn poll(&mut self, now: Instant) -> Vec<EditSession> {
+        if self
+            .last_poll
+            .is_some_and(|last| now.duration_since(last) < POLL_INTERVAL)
+        {
+            return Vec::new();
+        }
+        self.last_poll = Some(now);
+        let mut changed = Vec::new();
+        for session in &mut self.sessions {
+            if session.state == EditState::Watching && local_modified(&session.local_path) != session.synced_local {
+                session.state = EditState::Uploading;
+                changed.push(session.clone());
+            }
+        }
+        changed
+    }
+
+    /// 发生冲突后仍用本地内容覆盖远程文件
+    pub fn overwrite(&mut self, id: u64) -> Option<EditSession> {
+        let session = self.get_mut(id)?;
+        if !matches!(session.state, EditState::Conflict { .. }) {
+            return None;
+        }
+        session.state = EditState::Uploading;
+        Some(session.clone())
+    }
+
+    /// 放弃本地修改，重新下载远程文件
+    pub fn reload(&mut self, id: u64) -> Option<EditSession> {
+        let session = self.get_mut(id)?;
+        if matches!(session.state, EditState::Downloading | EditState::Uploading) {
+            return None;
+        }
+        session.state = EditState::Downloading;
+        Some(session.clone())
+    }
+
+    /// 重试失败的下载或上传
+    pub fn retry(&mut self, id: u64) -> Option<EditSession> {
+        let session = self.get_mut(id)?;
+        if !matches!(session.state, EditState::Failed { .. }) {
+            return None;
+        }
+        session.state = if session.remote_version.is_some() {
+            EditState::Uploading
+        } else {
+            EditState::Downloading
+        };
+        Some(session.clone())
+    }
+
+    /// 结束编辑并删除工作区
+    pub fn close(&mut self, id: u64) {
+        if let Some(index) = self.sessions.iter().position(|s| s.id == id) {
+            let session = self.sessions.remove(index);
+            remove_workspace(&session.local_path);
+        }
+    }
+
+    /// 退出时删除所有工作区
+    pub fn close_all(&mut self) {
+        for session in self.sessions.drain(..) {
+            remove_workspace(&session.local_path);
+        }
+    }
+}
+
+/// 内置编辑器中打开的内容
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditorBuffer {
+    pub edit_id: u64,
+    pub document: Document,
+    pub language: Language,
+    pub find: FindState,
+    /// 有未保存的修改
+    pub modified: bool,
+}
+
+impl EditorBuffer {
+    /// 读取工作区中的副本，自动识别编码和换行符
+    pub fn load(edit: &EditSession) -> Result<Self> {
+        let document = Document::decode(&std::fs::read(&edit.local_path)?)?;
+        Ok(Self {
+            edit_id: edit.id,
+            document,
+            language: Language::from_path(&edit.remote_path),
+            find: FindState::default(),
+            modified: false,
+        })
+    }
+
+    /// 解码时有无法识别的字节，保存会损坏文件
+    pub fn read_only(&self) -> bool {
+        self.document.lossy
+    }
+
+    /// 按指定编码重新读取副本（丢弃未保存的修改）
+    pub fn reload_as(&mut self, edit: &EditSession, encoding: TextEncoding) -> Result<()> {
+        self.document = Document::decode_as(&std::fs::read(&edit.local_path)?, encoding)?;
+        self.find.current = None;
+        self.modified = false;
+        Ok(())
+    }
+
+    /// 按原编码和换行符写回工作区，随后由 [`RemoteEditManager::poll`] 发现并上传
+    pub fn save(&mut self, edit: &EditSession) -> Result<()> {
+        if self.read_only() {
+            return Err(format!("{} 无法按 {} 完整解码，请选择正确的编码", edit.name(), self.document.encoding.label()).into());
+        }
+        std::fs::write(&edit.local_path, self.document.encode()?)?;
+        self.modified = false;
+        Ok(())
+    }
+}
+
+/// 所有编辑工作区的上级目录 `$TMPDIR/ishell-edit`
+///
+/// 编辑的远程文件常含有密码等敏感内容，目录只允许当前用户访问；
+/// 已存在时同样收紧权限，不是目录（如指向别处的符号链接）或不属于当前用户时报错。
+fn workspace_root() -> Result<PathBuf> {
+    let root = std::env::temp_dir().join("ishell-edit");
+    match private_dir_builder().create(&root) {
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        result => result?,
+    }
+    let metadata = std::fs::symlink_metadata(&root)?;
+    if !metadata.is_dir() {
+        return Err(format!("{} 不是目录", root.display()).into());
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        // SAFETY: geteuid 没有前置条件
+        if metadata.uid() != unsafe { libc::geteuid() } {
+            return Err(format!("{} 不属于当前用户", root.display()).into());
+        }
+        std::fs::set_permissions(&root, std::fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(root)
+}
+
+/// 以 0700 权限创建目录
+fn private_dir_builder() -> std::fs::DirBuilder {
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder
+}
+
+/// 以 0600 权限创建（或清空）本地副本，之后的下载沿用该权限
+fn create_private_file(path: &Path) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?;
+    Ok(())
+}
+
+fn remove_workspace(local_path: &Path) {
+    if let Some(workspace) = local_path.parent() {
+        if let Err(e) = std::fs::remove_dir_all(workspace) {
+            eprintln!("Failed to remove edit workspace {}: {}", workspace.display(), e);
+        }
+    }
+}
+
+fn local_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).ok()?.modified().ok()
+}
+
+/// 在后台下载远程文件到工作区
+pub fn spawn_download(session: Arc<Mutex<SshSession>>, edit: EditSession, tx: mpsc::UnboundedSender<SftpMessage>) {
+    std::thread::spawn(move || {
+        let id = edit.id;
+        let sftp = session.lock().unwrap().sftp();
+        let result = sftp.and_then(|sftp| {
+            let remote = RemoteVersion::of(&sftp.stat(&edit.remote_path)?);
+            create_private_file(&edit.local_path)?;
+            sftp.download_file(&edit.remote_path, &edit.local_path, |_| {})?;
+            Ok(remote)
+        });
+        let update = match result {
+            Ok(remote) => EditUpdate::Downloaded {
+                id,
+                remote,
+                local: local_modified(&edit.local_path),
+            },
+            Err(e) => EditUpdate::Failed {
+                id,
+                error: format!("下载失败: {}", e),
+            },
+        };
+        let _ = tx.send(SftpMessage::Edit(update));
+    });
+}
+
+/// 在后台把本地副本上传回远程
+///
+/// 远程文件与打开时的版本不同（`force` 为 false 时）则不上传，报告冲突。
+pub fn spawn_upload(
+    session: Arc<Mutex<SshSession>>,
+    edit: EditSession,
+    force: bool,
+    tx: mpsc::UnboundedSender<SftpMessage>,
+) {
+    std::thread::spawn(move || {
+        let id = edit.id;
+        // 先记录修改时间，上传期间再次保存会在下次检查时重新上传
+        let local = local_modified(&edit.local_path);
+        let sftp = session.lock().unwrap().sftp();
+        let result = sftp.and_then(|sftp| {
+            let current = sftp.stat(&edit.remote_path).ok().map(|entry| RemoteVersion::of(&entry));
+            if !force && current != edit.remote_version {
+                return Ok(Err(current));
+            }
+            sftp.upload_atomic(&session.lock().unwrap(), &edit.local_path, &edit.remote_path)?;
+            Ok(Ok(RemoteVersion::of(&sftp.stat(&edit.remote_path)?)))
+        });
+        let update = match result {
+            Ok(Ok(remote)) => EditUpdate::Uploaded { id, remote, local },
+            Ok(Err(remote)) => EditUpdate::Conflict { id, remote },
+            Err(e) => EditUpdate::Failed {
+                id,
+                error: format!("上传失败: {}", e),
+            },
+        };
+        let _ = tx.send(SftpMessage::Edit(update));
+    });
+}
+
+/// 构造打开外部编辑器的命令
+///
+/// `setting` 为编辑器命令（如 `code --wait`，按空白拆分参数），
+/// 为空时使用系统默认的打开方式。
+pub fn editor_command(setting: &str, path: &Path) -> Command {
+    let mut parts = setting.split_whitespace();
+    let mut command = match parts.next() {
+        Some(program) => {
+            let mut command = Command::new(program);
+            command.args(parts);
+            command
+        }
+        None if cfg!(target_os = "macos") => Command::new("open"),
+        None if cfg!(target_os = "windows") => {
+            let mut command = Command::new("cmd");
+            command.args(["/C", "start", ""]);
+            command
+        }
+        None => Command::new("xdg-open"),
+    };
+    command.arg(path);
+    command
+}
+
+/// 启动外部编辑器（不等待其退出）
+pub fn launch_editor(setting: &str, path: &Path) -> Result<()> {
+    let mut child = editor_command(setting, path).spawn()?;
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(secs: u64, size: u64) -> RemoteVersion {
+        RemoteVersion {
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+            size,
+        }
+    }
+
+    #[test]
+    fn test_poll_detects_local_saves() {
+        let mut manager = RemoteEditManager::default();
+        let edit = manager.open("web", "/etc/nginx/nginx.conf", false).unwrap();
+        assert_eq!(edit.local_path.file_name().unwrap(), "nginx.conf");
+        assert_eq!(edit.name(), "nginx.conf");
+        create_private_file(&edit.local_path).unwrap();
+        std::fs::write(&edit.local_path, "worker_processes 1;\n").unwrap();
+        #[cfg(unix)]
+        {
+            // 工作区只有当前用户可以访问
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            let workspace = edit.local_path.parent().unwrap();
+            assert_eq!(mode(workspace), 0o700);
+            assert_eq!(mode(workspace.parent().unwrap()), 0o700);
+            assert_eq!(mode(&edit.local_path), 0o600);
+        }
+        manager.apply(EditUpdate::Downloaded {
+            id: edit.id,
+            remote: version(100, 20),
+            local: local_modified(&edit.local_path),
+        });
+        assert_eq!(
+            manager.find("web", "/etc/nginx/nginx.conf").unwrap().state,
+            EditState::Watching
+        );
+
+        let start = Instant::now();
+        assert!(manager.poll(start).is_empty());
+
+        // 修改时间变化即视为保存
+        let file = std::fs::File::options().write(true).open(&edit.local_path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+        assert!(manager.poll(start + Duration::from_millis(100)).is_empty());
+        let changed = manager.poll(start + POLL_INTERVAL);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(manager.get(edit.id).unwrap().state, EditState::Uploading);
+        // 上传中不会重复触发
+        assert!(manager.poll(start + POLL_INTERVAL * 2).is_empty());
+
+        manager.apply(EditUpdate::Uploaded {
+            id: edit.id,
+            remote: version(200, 21),
+            local: local_modified(&edit.local_path),
+        });
+        let session = manager.get(edit.id).unwrap();
+        assert_eq!((session.uploads, session.remote_version), (1, Some(version(200, 21))));
+        assert!(manager.poll(start + POLL_INTERVAL * 3).is_empty());
+
+        let workspace = edit.local_path.parent().unwrap().to_path_buf();
+        manager.close(edit.id);
+        assert!(!workspace.exists());
+        assert!(manager.sessions().is_empty());
+    }
+
+    #[test]
+    fn test_conflict_overwrite_and_reload() {
+        let mut manager = RemoteEditManager::default();
+        let edit = manager.open("web", "/srv/app.env", true).unwrap();
+        assert!(manager.overwrite(edit.id).is_none());
+        manager.apply(EditUpdate::Failed {
+            id: edit.id,
+            error: "下载失败".to_string(),
+        });
+        // 还没有下载成功，重试时重新下载
+        assert_eq!(manager.retry(edit.id).unwrap().state, EditState::Downloading);
+
+        manager.apply(EditUpdate::Downloaded {
+            id: edit.id,
+            remote: version(100, 10),
+            local: None,
+        });
+        manager.apply(EditUpdate::Conflict {
+            id: edit.id,
+            remote: Some(version(150, 12)),
+        });
+        assert_eq!(manager.overwrite(edit.id).unwrap().state, EditState::Uploading);
+        assert!(manager.reload(edit.id).is_none());
+
+        manager.apply(EditUpdate::Conflict {
+            id: edit.id,
+            remote: None,
+        });
+        assert_eq!(manager.reload(edit.id).unwrap().state, EditState::Downloading);
+        manager.close_all();
+        assert!(!edit.local_path.parent().unwrap().exists());
+    }
+
+    #[test]
+    fn test_editor_buffer_round_trip() {
+        let mut manager = RemoteEditManager::default();
+        let edit = manager.open("web", "/srv/motd.conf", true).unwrap();
+        let gbk = TextEncoding::Gb18030.encode("你好\r\n").unwrap();
+        std::fs::write(&edit.local_path, &gbk).unwrap();
+        let mut buffer = EditorBuffer::load(&edit).unwrap();
+        assert_eq!((buffer.document.text.as_str(), buffer.modified), ("你好\n", false));
+        assert_eq!(buffer.document.encoding, TextEncoding::Gb18030);
+        assert_eq!(buffer.language, Language::from_path("motd.conf"));
+        buffer.document.text.push_str("world\n");
+        buffer.modified = true;
+        buffer.save(&edit).unwrap();
+        assert!(!buffer.modified);
+        // 保存时保留原编码和换行符
+        let saved = std::fs::read(&edit.local_path).unwrap();
+        assert_eq!(saved, TextEncoding::Gb18030.encode("你好\r\nworld\r\n").unwrap());
+
+        // 用错误的编码打开时只读
+        buffer.reload_as(&edit, TextEncoding::Utf8).unwrap();
+        assert!(buffer.read_only());
+        assert!(buffer.save(&edit).is_err());
+        assert_eq!(std::fs::read(&edit.local_path).unwrap(), saved);
+
+        std::fs::write(&edit.local_path, b"\x7fELF\0\0\x01").unwrap();
+        assert!(EditorBuffer::load(&edit).is_err());
+        manager.close_all();
+    }
+
+    #[test]
+    fn test_editor_command() {
+        let path = Path::new("/tmp/ishell-edit/1/app.conf");
+        let command = editor_command("code --wait", path);
+        assert_eq!(command.get_program(), "code");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, ["--wait", "/tmp/ishell-edit/1/app.conf"]);
+
+        let command = editor_command("  ", path);
+        assert_eq!(command.get_args().last().unwrap(), "/tmp/ishell-edit/1/app.conf");
+    }
+}
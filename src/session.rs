@@ -0,0 +1,574 @@
+use crate::expect::{ExpectEffect, ExpectRunner};
+use crate::terminal::TerminalBuffer;
+use crate::triggers::TriggerScan;
+use crate::types::{ConnectionStatus, Result};
+use crate::zmodem::{self, Direction, ZmodemEvent, ZmodemSession};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+// ============================================================================
+// 交互式会话后端与终端标签 (v0.4.0)
+// ============================================================================
+
+/// 交互式会话后端
+///
+/// SSH shell、本地 PTY 等都实现该 trait，共用同一套终端仿真与标签模型。
+pub trait SessionBackend: Send {
+    /// 写入用户输入
+    fn write(&mut self, data: &[u8]) -> Result<()>;
+
+    /// 非阻塞读取输出，没有数据时返回 0
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// 通知远端终端尺寸变化
+    fn resize(&mut self, cols: u16, rows: u16) -> Result<()>;
+
+    /// 会话是否仍然存活
+    fn is_active(&mut self) -> bool;
+
+    /// 关闭会话
+    fn close(&mut self) -> Result<()>;
+}
+
+/// 在 UI 线程与读取线程之间共享的后端（连接建立前为 None）
+pub type SharedBackend = Arc<Mutex<Option<Box<dyn SessionBackend>>>>;
+
+/// 会话线程发往 UI 的消息
+#[derive(Debug, Clone)]
+pub enum SessionMessage {
+    Opened(u64),
+    Output { tab: u64, data: Vec<u8> },
+    Closed(u64),
+    Error { tab: u64, error: String },
+    /// 后台文件对话框选择的 ZMODEM 上传文件（取消时为空）
+    ZmodemFiles { tab: u64, files: Vec<PathBuf> },
+}
+
+/// 终端标签类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabKind {
+    /// 连接列表中某个连接（SSH / Telnet / 原始 TCP）的交互式会话
+    Remote { connection: usize },
+    /// 本地 shell
+    Local,
+}
+
+/// 每个标签保留的 expect 脚本日志行数
+pub const MAX_EXPECT_LOG: usize = 500;
+
+/// 终端标签
+pub struct TerminalTab {
+    pub id: u64,
+    pub title: String,
+    pub kind: TabKind,
+    pub terminal: TerminalBuffer,
+    pub backend: SharedBackend,
+    pub status: ConnectionStatus,
+    /// 进行中的 ZMODEM 传输，期间会话输出不进入终端
+    pub zmodem: Option<ZmodemSession>,
+    /// 已选择、等待远端 `rz` 启动后上传的文件
+    pub zmodem_upload: Option<Vec<PathBuf>>,
+    /// 正在选择 ZMODEM 上传的文件（对话框在后台打开）
+    pub zmodem_picking: bool,
+    /// 上一块输出的末尾，ZMODEM 起始序列可能跨两次读取
+    zmodem_tail: Vec<u8>,
+    /// 输出触发器的扫描进度
+    pub triggers: TriggerScan,
+    /// 正在运行的 expect 脚本
+    pub expect: Vec<ExpectRunner>,
+    /// expect 脚本日志（显示在终端下方，不写入终端内容）
+    pub expect_log: VecDeque<String>,
+}
+
+impl TerminalTab {
+    pub fn new(id: u64, title: String, kind: TabKind, max_lines: usize) -> Self {
+        Self {
+            id,
+            title,
+            kind,
+            terminal: TerminalBuffer::new(max_lines),
+            backend: Arc::new(Mutex::new(None)),
+            status: ConnectionStatus::Connecting,
+            zmodem: None,
+            zmodem_upload: None,
+            zmodem_picking: false,
+            zmodem_tail: Vec::new(),
+            triggers: TriggerScan::default(),
+            expect: Vec::new(),
+            expect_log: VecDeque::new(),
+        }
+    }
+
+    /// 标签显示的标题（优先使用 shell 通过 OSC 设置的标题）
+    pub fn display_title(&self) -> &str {
+        match self.terminal.title() {
+            Some(title) if !title.is_empty() => title,
+            _ => &self.title,
+        }
+    }
+
+    /// 会话是否可以接收输入
+    pub fn is_live(&self) -> bool {
+        self.status == ConnectionStatus::Connected
+    }
+
+    /// 向会话写入数据
+    pub fn write(&self, data: &[u8]) -> Result<()> {
+        match self.backend.lock().unwrap().as_mut() {
+            Some(backend) => backend.write(data),
+            None => Err("Session not ready".into()),
+        }
+    }
+
+    /// 调整终端尺寸，并同步给后端
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        if self.terminal.size() == (cols.max(1), rows.max(1)) {
+            return;
+        }
+        self.terminal.resize(cols, rows);
+        if let Some(backend) = self.backend.lock().unwrap().as_mut() {
+            let _ = backend.resize(cols as u16, rows as u16);
+        }
+    }
+
+    /// 将终端仿真器产生的应答（如光标位置报告）回写给会话
+    pub fn flush_responses(&mut self) {
+        let responses = self.terminal.take_responses();
+        if !responses.is_empty() {
+            let _ = self.write(&responses);
+        }
+    }
+
+    /// 处理会话输出：识别 ZMODEM 起始序列，传输期间数据交给传输而不是终端
+    ///
+    /// 远端 `sz` 发送的文件保存到 `download_dir`。
+    ///
+    /// 起始序列被拆到两次读取时，上一块末尾的几个字节已经显示在终端中，
+    /// 识别后与本块一起交给传输。
+    pub fn feed_output(&mut self, data: &[u8], download_dir: &Path) -> Vec<ZmodemEvent> {
+        let mut window;
+        let mut data = data;
+        if self.zmodem.is_none() {
+            window = std::mem::take(&mut self.zmodem_tail);
+            let carried = window.len();
+            window.extend_from_slice(data);
+            let Some((offset, direction)) = zmodem::detect(&window) else {
+                self.feed_terminal(data);
+                let keep = window.len().min(zmodem::DETECT_LEN - 1);
+                self.zmodem_tail = window.split_off(window.len() - keep);
+                return Vec::new();
+            };
+            self.feed_terminal(&data[..offset.saturating_sub(carried)]);
+            data = &window[offset..];
+            self.zmodem = Some(match (direction, self.zmodem_upload.take()) {
+                (Direction::Download, _) => ZmodemSession::receive(download_dir),
+                (Direction::Upload, Some(files)) => ZmodemSession::send(files),
+                (Direction::Upload, None) => ZmodemSession::awaiting_files(),
+            });
+        }
+        if let Some(transfer) = self.zmodem.as_mut() {
+            transfer.feed(data);
+        }
+        self.pump_zmodem(0)
+    }
+
+    /// 推进 ZMODEM 传输：发送最多 `budget` 字节的文件数据并写出协议应答，
+    /// 传输结束后把剩余输出交还给终端
+    pub fn pump_zmodem(&mut self, budget: usize) -> Vec<ZmodemEvent> {
+        let Some(transfer) = self.zmodem.as_mut() else {
+            return Vec::new();
+        };
+        if budget > 0 {
+            transfer.poll(budget);
+        }
+        let output = transfer.take_output();
+        if !output.is_empty() {
+            if let Err(e) = self.write(&output) {
+                let transfer = self.zmodem.as_mut().unwrap();
+                transfer.cancel();
+                transfer.take_output();
+                eprintln!("ZMODEM write failed: {}", e);
+            }
+        }
+
+        let transfer = self.zmodem.as_mut().unwrap();
+        let events = transfer.take_events();
+        if transfer.is_finished() {
+            let rest = transfer.take_leftover();
+            self.zmodem = None;
+            self.feed_terminal(&rest);
+            self.flush_responses();
+        }
+        events
+    }
+
+    /// 关闭会话
+    pub fn close(&mut self) {
+        if let Some(mut backend) = self.backend.lock().unwrap().take() {
+            let _ = backend.close();
+        }
+        self.zmodem = None;
+        self.zmodem_upload = None;
+        self.expect.clear();
+        self.status = ConnectionStatus::Disconnected;
+    }
+
+    /// 输出写入终端，并交给运行中的 expect 脚本
+    fn feed_terminal(&mut self, data: &[u8]) {
+        self.terminal.feed_bytes(data);
+        if self.expect.is_empty() || data.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        let effects: Vec<ExpectEffect> = self.expect.iter_mut().flat_map(|r| r.feed(data, now)).collect();
+        self.apply_expect(effects);
+    }
+
+    /// 开始运行 expect 脚本
+    pub fn start_expect(&mut self, mut runner: ExpectRunner) {
+        let effects = runner.start(Instant::now());
+        self.expect.push(runner);
+        self.apply_expect(effects);
+    }
+
+    /// 检查 expect 脚本是否超时，返回下一个超时时刻
+    pub fn tick_expect(&mut self, now: Instant) -> Option<Instant> {
+        let effects: Vec<ExpectEffect> = self.expect.iter_mut().flat_map(|r| r.tick(now)).collect();
+        self.apply_expect(effects);
+        self.expect.iter().filter_map(ExpectRunner::deadline).min()
+    }
+
+    /// 停止所有 expect 脚本
+    pub fn stop_expect(&mut self) {
+        let effects: Vec<ExpectEffect> = self.expect.iter_mut().flat_map(|r| r.cancel()).collect();
+        self.apply_expect(effects);
+    }
+
+    /// 执行脚本动作：应答写入会话，日志记录到标签的脚本日志
+    fn apply_expect(&mut self, effects: Vec<ExpectEffect>) {
+        for effect in effects {
+            match effect {
+                ExpectEffect::Send(bytes) => {
+                    if let Err(e) = self.write(&bytes) {
+                        self.log_expect(&format!("❌ 发送失败: {}", e));
+                    }
+                }
+                ExpectEffect::Log(line) => self.log_expect(&line),
+            }
+        }
+        self.expect.retain(|runner| !runner.is_finished());
+    }
+
+    /// 记录一行脚本日志，超过 [`MAX_EXPECT_LOG`] 行时丢弃最早的
+    pub fn log_expect(&mut self, line: &str) {
+        if self.expect_log.len() >= MAX_EXPECT_LOG {
+            self.expect_log.pop_front();
+        }
+        self.expect_log
+            .push_back(format!("{} {}", chrono::Local::now().format("%H:%M:%S"), line));
+    }
+}
+
+/// shell 集成脚本：在提示符与命令前后输出 OSC 133 标记（支持 bash 与 zsh）
+const SHELL_INTEGRATION: &str = r#"if [ -n "$ZSH_VERSION" ]; then __ishell_precmd() { local s=$?; [ -n "$__ishell_ran" ] && printf '\e]133;D;%s\a' "$s"; __ishell_ran=; printf '\e]133;A\a'; }; __ishell_preexec() { __ishell_ran=1; printf '\e]133;C\a'; }; precmd_functions=(__ishell_precmd $precmd_functions); preexec_functions+=(__ishell_preexec); PS1="$PS1%{$(printf '\e]133;B\a')%}"; elif [ -n "$BASH_VERSION" ]; then __ishell_pc() { [ -n "$__ishell_ran" ] && printf '\e]133;D;%s\a' "$__ishell_s"; __ishell_ran=; printf '\e]133;A\a'; __ishell_ready=1; }; trap '[ -n "$__ishell_ready" ] && { __ishell_ready=; __ishell_ran=1; printf "\e]133;C\a"; }' DEBUG; PROMPT_COMMAND="__ishell_s=\$?;${PROMPT_COMMAND:+$PROMPT_COMMAND;}__ishell_pc"; PS1="$PS1\[\e]133;B\a\]"; fi; clear"#;
+
+/// 注入 shell 集成脚本的输入（以空格开头，不记入 shell 历史）
+pub fn shell_integration_input() -> Vec<u8> {
+    format!(" {}\r", SHELL_INTEGRATION).into_bytes()
+}
+
+/// 持续读取后端输出并转发给 UI，直到会话结束或标签被关闭
+///
+/// 在调用线程中阻塞运行，`repaint` 用于在收到输出时立即唤醒界面。
+pub fn run_session_reader(
+    tab: u64,
+    backend: SharedBackend,
+    tx: mpsc::UnboundedSender<SessionMessage>,
+    repaint: Option<egui::Context>,
+) {
+    let mut buf = vec![0u8; 16 * 1024];
+    loop {
+        let (result, active) = {
+            let mut guard = backend.lock().unwrap();
+            match guard.as_mut() {
+                Some(b) => (b.read(&mut buf), b.is_active()),
+                // 标签已关闭
+                None => return,
+            }
+        };
+
+        let message = match result {
+            Ok(0) if active => {
+                std::thread::sleep(Duration::from_millis(10));
+                continue;
+            }
+            Ok(0) => SessionMessage::Closed(tab),
+            Ok(n) => SessionMessage::Output {
+                tab,
+                data: buf[..n].to_vec(),
+            },
+            Err(e) => SessionMessage::Error {
+                tab,
+                error: e.to_string(),
+            },
+        };
+
+        let finished = !matches!(message, SessionMessage::Output { .. });
+        if tx.send(message).is_err() {
+            return;
+        }
+        if let Some(ctx) = &repaint {
+            ctx.request_repaint();
+        }
+        if finished {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// 内存中的回环后端：写入的数据原样作为输出返回
+    struct LoopbackBackend {
+        pending: VecDeque<u8>,
+        size: (u16, u16),
+        active: bool,
+    }
+
+    impl SessionBackend for LoopbackBackend {
+        fn write(&mut self, data: &[u8]) -> Result<()> {
+            self.pending.extend(data);
+            Ok(())
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let n = buf.len().min(self.pending.len());
+            for (slot, byte) in buf.iter_mut().zip(self.pending.drain(..n)) {
+                *slot = byte;
+            }
+            Ok(n)
+        }
+
+        fn resize(&mut self, cols: u16, rows: u16) -> Result<()> {
+            self.size = (cols, rows);
+            Ok(())
+        }
+
+        fn is_active(&mut self) -> bool {
+            self.active || !self.pending.is_empty()
+        }
+
+        fn close(&mut self) -> Result<()> {
+            self.active = false;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_reader_forwards_output_until_closed() {
+        let mut tab = TerminalTab::new(7, "loop".to_string(), TabKind::Local, 100);
+        *tab.backend.lock().unwrap() = Some(Box::new(LoopbackBackend {
+            pending: VecDeque::new(),
+            size: (80, 24),
+            active: false,
+        }));
+        tab.write(b"hello\r\n").unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        run_session_reader(tab.id, tab.backend.clone(), tx, None);
+
+        match rx.try_recv().unwrap() {
+            SessionMessage::Output { tab: 7, data } => tab.terminal.feed_bytes(&data),
+            other => panic!("unexpected message: {:?}", other),
+        }
+        assert!(matches!(rx.try_recv().unwrap(), SessionMessage::Closed(7)));
+        assert_eq!(tab.terminal.plain_text(), "hello");
+    }
+
+    #[test]
+    fn test_resize_and_responses_reach_backend() {
+        let mut tab = TerminalTab::new(1, "loop".to_string(), TabKind::Local, 100);
+        *tab.backend.lock().unwrap() = Some(Box::new(LoopbackBackend {
+            pending: VecDeque::new(),
+            size: (80, 24),
+            active: true,
+        }));
+
+        tab.resize(100, 30);
+        assert_eq!(tab.terminal.size(), (100, 30));
+
+        // 光标位置查询的应答写回后端
+        tab.terminal.feed("\x1b[6n");
+        tab.flush_responses();
+        let mut buf = [0u8; 16];
+        let n = tab
+            .backend
+            .lock()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .read(&mut buf)
+            .unwrap();
+        assert_eq!(&buf[..n], b"\x1b[1;1R");
+
+        tab.close();
+        assert!(tab.backend.lock().unwrap().is_none());
+        assert!(tab.write(b"x").is_err());
+    }
+
+    /// 模拟远端 `rz`：写入的数据交给接收端，读取接收端的应答
+    struct RemoteRz(Arc<Mutex<ZmodemSession>>);
+
+    impl SessionBackend for RemoteRz {
+        fn write(&mut self, data: &[u8]) -> Result<()> {
+            self.0.lock().unwrap().feed(data);
+            Ok(())
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let output = self.0.lock().unwrap().take_output();
+            buf[..output.len()].copy_from_slice(&output);
+            Ok(output.len())
+        }
+
+        fn resize(&mut self, _cols: u16, _rows: u16) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_active(&mut self) -> bool {
+            true
+        }
+
+        fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_zmodem_upload_through_tab() {
+        let dir = std::env::temp_dir().join(format!(
+            "ishell_zmodem_tab_{}",
+            rand::Rng::gen::<u32>(&mut rand::thread_rng())
+        ));
+        let remote_dir = dir.join("remote");
+        std::fs::create_dir_all(&remote_dir).unwrap();
+        let content = vec![0x18u8; 5000];
+        std::fs::write(dir.join("upload.bin"), &content).unwrap();
+
+        let remote = Arc::new(Mutex::new(ZmodemSession::receive(&remote_dir)));
+        let mut tab = TerminalTab::new(3, "rz".to_string(), TabKind::Local, 100);
+        *tab.backend.lock().unwrap() = Some(Box::new(RemoteRz(remote.clone())));
+        tab.zmodem_upload = Some(vec![dir.join("upload.bin")]);
+
+        // rz 启动时输出的 ZRINIT 之前的文本仍然显示在终端中
+        let mut rinit = crate::zmodem::Header::new(crate::zmodem::FrameType::RInit);
+        rinit.data[3] = 0x23;
+        let mut output = b"$ rz\r\n".to_vec();
+        output.extend(rinit.encode_hex());
+        tab.feed_output(&output, &dir);
+        assert!(tab.zmodem.is_some());
+
+        let mut buf = vec![0u8; 64 * 1024];
+        for _ in 0..100 {
+            tab.pump_zmodem(1024);
+            let n = tab.backend.lock().unwrap().as_mut().unwrap().read(&mut buf).unwrap();
+            tab.feed_output(&buf[..n], &dir);
+            if tab.zmodem.is_none() {
+                break;
+            }
+        }
+        assert!(tab.zmodem.is_none());
+        assert_eq!(std::fs::read(remote_dir.join("upload.bin")).unwrap(), content);
+
+        // 传输结束后输出回到终端
+        tab.feed_output(b"done", &dir);
+        assert_eq!(tab.terminal.plain_text(), "$ rz\ndone");
+    }
+
+    #[test]
+    fn test_zmodem_header_split_across_reads() {
+        let dir = std::env::temp_dir();
+        let mut tab = TerminalTab::new(4, "sz".to_string(), TabKind::Local, 100);
+        *tab.backend.lock().unwrap() = Some(Box::new(LoopbackBackend {
+            pending: VecDeque::new(),
+            size: (80, 24),
+            active: true,
+        }));
+
+        // 远端 sz 的 ZRQINIT 头被拆成两次读取
+        let mut output = b"$ sz a.txt\r\n".to_vec();
+        output.extend(crate::zmodem::Header::new(crate::zmodem::FrameType::RqInit).encode_hex());
+        let (first, second) = output.split_at(b"$ sz a.txt\r\n**\x18".len());
+        tab.feed_output(first, &dir);
+        assert!(tab.zmodem.is_none());
+        tab.feed_output(second, &dir);
+        let transfer = tab.zmodem.as_ref().expect("transfer should start");
+        assert_eq!(transfer.direction(), Direction::Download);
+
+        // 普通输出不会留下残余
+        let mut tab = TerminalTab::new(5, "sh".to_string(), TabKind::Local, 100);
+        tab.feed_output(b"ab", &dir);
+        tab.feed_output(b"c*", &dir);
+        tab.feed_output(b"*d", &dir);
+        assert!(tab.zmodem.is_none());
+        assert_eq!(tab.terminal.plain_text(), "abc**d");
+    }
+
+    #[test]
+    fn test_expect_script_answers_prompts() {
+        use crate::types::{ExpectBranch, ExpectNext, ExpectScript, ExpectSend, ExpectStep, SshConfig, StoredSecret};
+
+        let mut conn = SshConfig::new("db".to_string(), "db".to_string(), 22, "ops".to_string());
+        conn.secrets.push(StoredSecret {
+            name: "sudo".to_string(),
+            value: "pw".to_string(),
+            value_encrypted: None,
+        });
+        let script = ExpectScript {
+            name: "sudo".to_string(),
+            auto_start: true,
+            steps: vec![ExpectStep {
+                branches: vec![ExpectBranch {
+                    pattern: r"\[sudo\] password".to_string(),
+                    send: Some(ExpectSend::Secret { name: "sudo".to_string() }),
+                    next: ExpectNext::Finish,
+                }],
+                timeout_secs: 0,
+                on_timeout: ExpectNext::Fail,
+            }],
+        };
+
+        let mut tab = TerminalTab::new(3, "db".to_string(), TabKind::Remote { connection: 0 }, 100);
+        *tab.backend.lock().unwrap() = Some(Box::new(LoopbackBackend {
+            pending: VecDeque::new(),
+            size: (80, 24),
+            active: true,
+        }));
+        tab.start_expect(ExpectRunner::new(&script, &conn, Instant::now()).unwrap());
+        assert_eq!(tab.expect.len(), 1);
+
+        tab.feed_output(b"[sudo] password for ops: ", Path::new("."));
+        let mut buf = [0u8; 16];
+        let n = tab.backend.lock().unwrap().as_mut().unwrap().read(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"pw\r");
+        assert!(tab.expect.is_empty());
+
+        // 日志记录在标签的脚本日志中，不写入终端，且不显示密钥内容
+        let log: Vec<&String> = tab.expect_log.iter().collect();
+        assert!(log.iter().any(|line| line.ends_with("▶ 运行脚本 sudo")));
+        assert!(log.iter().any(|line| line.contains("发送 密钥 “sudo”")));
+        assert!(!tab.terminal.plain_text().contains("运行脚本"));
+
+        for i in 0..MAX_EXPECT_LOG {
+            tab.log_expect(&i.to_string());
+        }
+        assert_eq!(tab.expect_log.len(), MAX_EXPECT_LOG);
+        assert!(tab.expect_log.front().unwrap().ends_with(" 0"));
+    }
+}
@@ -1,67 +1,238 @@
-use crate::types::{FileEntry, Result};
-use ssh2::Sftp as Ssh2Sftp;
-use std::io::{Read, Write};
-use std::path::Path;
+use crate::checksum::shell_quote;
+use crate::ssh::SshSession;
+use crate::types::{ConflictPolicy, FileEntry, FileKind, FileSort, Result, SymlinkPolicy, TransferOptions};
+use sha2::{Digest, Sha256};
+use ssh2::{FileStat, FileType, OpenFlags, OpenType, Sftp as Ssh2Sftp};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// 目录树中的一项（路径相对于传输的根目录，以 `/` 分隔）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeEntry {
+    Dir { relative: String },
+    File { relative: String, size: u64, mtime: Option<u64> },
+    /// 按 `SymlinkPolicy::Preserve` 重建的链接
+    Symlink { relative: String, target: String },
+}
+
+/// 递归传输的进度（按总字节数汇总）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeProgress {
+    /// 正在传输的文件（相对路径）
+    pub current: String,
+    pub files_total: usize,
+    pub files_done: usize,
+    pub files_skipped: usize,
+    pub bytes_total: u64,
+    /// 已完成的字节数，跳过的文件也计入
+    pub bytes_done: u64,
+}
+
+impl TreeProgress {
+    fn new(entries: &[TreeEntry]) -> Self {
+        let mut progress = Self::default();
+        for entry in entries {
+            if let TreeEntry::File { size, .. } = entry {
+                progress.files_total += 1;
+                progress.bytes_total += size;
+            }
+        }
+        progress
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.bytes_total > 0 {
+            (self.bytes_done as f32 / self.bytes_total as f32).min(1.0)
+        } else if self.files_total > 0 {
+            (self.files_done + self.files_skipped) as f32 / self.files_total as f32
+        } else {
+            1.0
+        }
+    }
+}
+
+/// 目标已存在时对单个文件的处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConflictAction {
+    Write,
+    Skip,
+    Rename,
+}
+
+/// 根据策略决定如何处理目标文件
+///
+/// `existing` 为目标的修改时间，目标不存在时为 `None`；
+/// 任一修改时间未知时“仅更新较新的”按覆盖处理。
+fn conflict_action(policy: ConflictPolicy, source_mtime: Option<u64>, existing: Option<Option<u64>>) -> ConflictAction {
+    let Some(existing_mtime) = existing else {
+        return ConflictAction::Write;
+    };
+    match policy {
+        ConflictPolicy::Overwrite => ConflictAction::Write,
+        ConflictPolicy::Skip => ConflictAction::Skip,
+        ConflictPolicy::Rename => ConflictAction::Rename,
+        ConflictPolicy::NewerOnly => match (source_mtime, existing_mtime) {
+            (Some(source), Some(existing)) if source <= existing => ConflictAction::Skip,
+            _ => ConflictAction::Write,
+        },
+    }
+}
+
+/// 找到第一个不存在的 `path.N`（与 ZMODEM 接收时的命名一致）
+fn unique_name(path: &str, exists: impl Fn(&str) -> bool) -> String {
+    (1..)
+        .map(|n| format!("{}.{}", path, n))
+        .find(|candidate| !exists(candidate))
+        .unwrap()
+}
+
+fn join_relative(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+fn join_remote(root: &str, relative: &str) -> String {
+    if relative.is_empty() {
+        root.to_string()
+    } else {
+        format!("{}/{}", root.trim_end_matches('/'), relative)
+    }
+}
+
+/// 同一目录下的隐藏临时文件名，如 `/etc/.nginx.conf.ishell-1a2b3c4d`
+fn temp_sibling(path: &str) -> String {
+    let (dir, name) = match path.rsplit_once('/') {
+        Some((dir, name)) => (format!("{}/", dir), name),
+        None => (String::new(), path),
+    };
+    format!("{}.{}.ishell-{:08x}", dir, name, rand::random::<u32>())
+}
+
+fn local_mtime(metadata: &std::fs::Metadata) -> Option<u64> {
+    metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+/// 遍历本地目录树，目录先于其内容出现
+///
+/// 跟随链接时通过规范路径检测循环，指向自身祖先的目录链接会被忽略；
+/// 套接字、设备等特殊文件不传输。
+pub fn walk_local_tree(root: &Path, symlinks: SymlinkPolicy) -> Result<Vec<TreeEntry>> {
+    let mut entries = Vec::new();
+    let mut ancestors = vec![std::fs::canonicalize(root)?];
+    walk_local_dir(root, "", symlinks, &mut ancestors, &mut entries)?;
+    Ok(entries)
+}
+
+fn walk_local_dir(
+    dir: &Path,
+    prefix: &str,
+    symlinks: SymlinkPolicy,
+    ancestors: &mut Vec<PathBuf>,
+    entries: &mut Vec<TreeEntry>,
+) -> Result<()> {
+    let mut children = std::fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+    children.sort_by_key(|child| child.file_name());
+    for child in children {
+        let relative = join_relative(prefix, &child.file_name().to_string_lossy());
+        let path = child.path();
+        let mut metadata = std::fs::symlink_metadata(&path)?;
+        if metadata.file_type().is_symlink() {
+            match symlinks {
+                SymlinkPolicy::Skip => continue,
+                SymlinkPolicy::Preserve => {
+                    let target = std::fs::read_link(&path)?.to_string_lossy().to_string();
+                    entries.push(TreeEntry::Symlink { relative, target });
+                    continue;
+                }
+                SymlinkPolicy::Follow => match std::fs::metadata(&path) {
+                    Ok(target) => metadata = target,
+                    // 悬空链接
+                    Err(_) => continue,
+                },
+            }
+        }
+        if metadata.is_dir() {
+            let canonical = std::fs::canonicalize(&path)?;
+            if ancestors.contains(&canonical) {
+                continue;
+            }
+            entries.push(TreeEntry::Dir { relative: relative.clone() });
+            ancestors.push(canonical);
+            walk_local_dir(&path, &relative, symlinks, ancestors, entries)?;
+            ancestors.pop();
+        } else if metadata.is_file() {
+            entries.push(TreeEntry::File {
+                relative,
+                size: metadata.len(),
+                mtime: local_mtime(&metadata),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 默认的传输块大小
+pub const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// 传输块大小的允许范围
+pub const CHUNK_SIZE_RANGE: std::ops::RangeInclusive<usize> = 8 * 1024..=16 * 1024 * 1024;
 
 /// SFTP 客户端封装
 pub struct SftpClient {
     sftp: Ssh2Sftp,
+    chunk_size: usize,
 }
 
 impl SftpClient {
     /// 创建新的 SFTP 客户端
     pub fn new(sftp: Ssh2Sftp) -> Self {
-        Self { sftp }
+        Self {
+            sftp,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    /// 设置每次读写的块大小
+    ///
+    /// libssh2 会把一次大块读写拆成多个约 32 KB 的 SFTP 请求同时发出
+    /// （读取时预读，写入时不等待逐个确认），块越大在途请求越多，
+    /// 高延迟链路上的吞吐量大致为 `块大小 / 往返时间`。
+    /// 同一会话上的多个句柄会被 libssh2 的会话锁串行化，
+    /// 因此这里用单句柄的流水线而不是多句柄分段并行。
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.clamp(*CHUNK_SIZE_RANGE.start(), *CHUNK_SIZE_RANGE.end());
+        self
     }
 
     /// 列出目录内容
+    ///
+    /// OpenSSH 的 readdir 属性来自 lstat：符号链接保留自身的类型、大小和时间，
+    /// 另外读取链接目标，并按目标是否为目录决定能否进入（失效的链接当作文件）。
     pub fn list_dir(&self, path: &str) -> Result<Vec<FileEntry>> {
         let path = if path.is_empty() { "." } else { path };
-        
+
         let entries = self.sftp.readdir(std::path::Path::new(path))?;
-        
-        let mut file_entries = Vec::new();
-        for (path, stat) in entries {
-            let name = path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("")
-                .to_string();
-            
-            let path_str = path.to_string_lossy().to_string();
-            let is_dir = stat.is_dir();
-            let size = stat.size.unwrap_or(0);
-            
-            // 转换时间戳
-            let modified = stat.mtime.and_then(|mtime| {
-                std::time::UNIX_EPOCH.checked_add(std::time::Duration::from_secs(mtime))
-            });
-            
-            // 转换权限
-            let permissions = stat.perm.map(|p| format!("{:o}", p));
-            
-            file_entries.push(FileEntry {
-                name,
-                path: path_str,
-                is_dir,
-                size,
-                modified,
-                permissions,
-            });
-        }
-        
+
+        // 符号链接的目标不在这里解析（每个链接两次往返），由 [`Self::resolve_link`] 按需读取
+        let mut file_entries: Vec<FileEntry> = entries.iter().map(|(path, stat)| file_entry(path, stat)).collect();
+
         // 排序：目录在前，然后按名称
-        file_entries.sort_by(|a, b| {
-            match (a.is_dir, b.is_dir) {
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => a.name.cmp(&b.name),
-            }
-        });
-        
+        FileSort::default().sort(&mut file_entries);
+
         Ok(file_entries)
     }
 
+    /// 读取符号链接的目标，并判断它是否指向目录（失效的链接视为文件）
+    pub fn resolve_link(&self, path: &str) -> Result<(String, bool)> {
+        let target = self.readlink(path)?;
+        let is_dir = self.sftp.stat(Path::new(path)).is_ok_and(|stat| stat.is_dir());
+        Ok((target, is_dir))
+    }
+
     /// 上传文件（带进度回调）
     pub fn upload_file<F>(
         &self,
@@ -72,37 +243,11 @@ impl SftpClient {
     where
         F: FnMut(f32),
     {
-        // 打开本地文件
-        let mut local_file = std::fs::File::open(local)?;
-        let file_size = local_file.metadata()?.len();
-        
-        // 创建远程文件
-        let mut remote_file = self.sftp.create(std::path::Path::new(remote))?;
-        
-        // 分块传输
-        let mut buffer = vec![0u8; 8192]; // 8KB 缓冲区
-        let mut total_sent = 0u64;
-        
-        loop {
-            let bytes_read = local_file.read(&mut buffer)?;
-            if bytes_read == 0 {
-                break;
-            }
-            
-            remote_file.write_all(&buffer[..bytes_read])?;
-            total_sent += bytes_read as u64;
-            
-            // 报告进度
-            let progress = if file_size > 0 {
-                (total_sent as f32 / file_size as f32).min(1.0)
-            } else {
-                1.0
-            };
-            progress_callback(progress);
-        }
-        
-        remote_file.flush()?;
-        Ok(())
+        let file_size = std::fs::metadata(local)?.len();
+        self.upload_bytes(local, remote, |sent| {
+            progress_callback(fraction(sent, file_size));
+            true
+        })
     }
 
     /// 下载文件（带进度回调）
@@ -115,39 +260,435 @@ impl SftpClient {
     where
         F: FnMut(f32),
     {
-        // 打开远程文件
-        let mut remote_file = self.sftp.open(std::path::Path::new(remote))?;
-        let file_size = remote_file.stat()?.size.unwrap_or(0);
-        
-        // 创建本地文件
+        let file_size = self.sftp.stat(Path::new(remote))?.size.unwrap_or(0);
+        self.download_bytes(remote, local, |received| {
+            progress_callback(fraction(received, file_size));
+            true
+        })
+    }
+
+    /// 上传文件，每块之后以已发送的字节数回调（返回 false 时中止）
+    fn upload_bytes(&self, local: &Path, remote: &str, on_chunk: impl FnMut(u64) -> bool) -> Result<()> {
+        let mut local_file = std::fs::File::open(local)?;
+        let mut remote_file = self.sftp.create(Path::new(remote))?;
+        copy_chunks(&mut local_file, &mut remote_file, self.chunk_size, on_chunk)
+    }
+
+    /// 下载文件，每块之后以已接收的字节数回调（返回 false 时中止）
+    fn download_bytes(&self, remote: &str, local: &Path, on_chunk: impl FnMut(u64) -> bool) -> Result<()> {
+        let mut remote_file = self.sftp.open(Path::new(remote))?;
         let mut local_file = std::fs::File::create(local)?;
-        
-        // 分块传输
-        let mut buffer = vec![0u8; 8192]; // 8KB 缓冲区
-        let mut total_received = 0u64;
-        
-        loop {
-            let bytes_read = remote_file.read(&mut buffer)?;
-            if bytes_read == 0 {
-                break;
+        copy_chunks(&mut remote_file, &mut local_file, self.chunk_size, on_chunk)
+    }
+
+    /// 原子地替换远程文件：先写入同目录下的临时文件，再重命名覆盖
+    ///
+    /// 远程路径是符号链接时替换其指向的文件，链接本身保持不变。临时文件沿用原文件的
+    /// 权限与属主，读者不会看到写了一半的内容。
+    ///
+    /// SFTP v3 服务器（如 OpenSSH）的 rename 不会覆盖已有文件，libssh2 也不支持
+    /// `posix-rename@openssh.com` 扩展，因此最后一步通过 exec 通道执行 `mv -f`（即 rename(2)）。
+    /// 无法保留属主时（如文件属于其他用户）返回错误，不替换原文件。
+    pub fn upload_atomic(&self, session: &SshSession, local: &Path, remote: &str) -> Result<()> {
+        let target = match self.sftp.lstat(Path::new(remote)) {
+            Ok(stat) if stat.file_type().is_symlink() => {
+                self.sftp.realpath(Path::new(remote))?.to_string_lossy().into_owned()
             }
-            
-            local_file.write_all(&buffer[..bytes_read])?;
-            total_received += bytes_read as u64;
-            
-            // 报告进度
-            let progress = if file_size > 0 {
-                (total_received as f32 / file_size as f32).min(1.0)
-            } else {
-                1.0
+            _ => remote.to_string(),
+        };
+        let original = self.sftp.stat(Path::new(&target)).ok();
+        let temp = temp_sibling(&target);
+        let result = self.replace_via(session, local, &temp, &target, original.as_ref());
+        if result.is_err() {
+            let _ = self.sftp.unlink(Path::new(&temp));
+        }
+        result
+    }
+
+    /// 把 `local` 写入临时文件 `temp`，复制 `original` 的属性后重命名为 `target`
+    fn replace_via(
+        &self,
+        session: &SshSession,
+        local: &Path,
+        temp: &str,
+        target: &str,
+        original: Option<&FileStat>,
+    ) -> Result<()> {
+        // 写入内容之前就以最终权限创建临时文件，原文件未知时只允许属主访问
+        let mode = original.and_then(|o| o.perm).map_or(0o600, |perm| perm & 0o7777);
+        let flags = OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE;
+        let mut local_file = std::fs::File::open(local)?;
+        let mut remote_file = self.sftp.open_mode(Path::new(temp), flags, mode as i32, OpenType::File)?;
+        copy_chunks(&mut local_file, &mut remote_file, self.chunk_size, |_| true)?;
+        drop(remote_file);
+        if let Some(original) = original {
+            if !self.copy_attributes(original, temp)? {
+                return Err(format!("无法保留 {} 的属主，远程文件未被替换", target).into());
+            }
+        }
+        session.execute_checked(&format!("mv -f -- {} {}", shell_quote(temp), shell_quote(target)))?;
+        Ok(())
+    }
+
+    /// 把原文件的权限、属主和属组复制到 `path`，无权修改属主时返回 false
+    fn copy_attributes(&self, original: &FileStat, path: &str) -> Result<bool> {
+        let attributes = |perm, uid, gid| FileStat {
+            size: None,
+            uid,
+            gid,
+            perm,
+            atime: None,
+            mtime: None,
+        };
+        if let Some(perm) = original.perm {
+            self.sftp
+                .setstat(Path::new(path), attributes(Some(perm & 0o7777), None, None))?;
+        }
+        let current = self.sftp.stat(Path::new(path))?;
+        let unknown = original.uid.is_none() || original.gid.is_none();
+        if unknown || (original.uid, original.gid) == (current.uid, current.gid) {
+            return Ok(true);
+        }
+        let owned = self
+            .sftp
+            .setstat(Path::new(path), attributes(None, original.uid, original.gid))
+            .is_ok();
+        // chown 会清除 setuid/setgid 位，重新设置权限
+        if owned {
+            if let Some(perm) = original.perm {
+                self.sftp
+                    .setstat(Path::new(path), attributes(Some(perm & 0o7777), None, None))?;
+            }
+        }
+        Ok(owned)
+    }
+
+    /// 从 `offset` 起读取最多 `len` 字节，同时返回文件的当前大小
+    pub fn read_range(&self, path: &str, offset: u64, len: usize) -> Result<(Vec<u8>, u64)> {
+        let mut file = self.sftp.open(Path::new(path))?;
+        let size = file.stat()?.size.unwrap_or(0);
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buffer = vec![0; len.min(size.saturating_sub(offset) as usize)];
+        let read = fill_chunk(&mut file, &mut buffer)?;
+        buffer.truncate(read);
+        Ok((buffer, size))
+    }
+
+    /// 断点续传上传：`resume` 为真且远程已有部分文件时从其末尾继续
+    ///
+    /// 进度回调参数为（已完成字节数，总字节数），返回 false 时以 [`TransferAborted`] 中止。
+    /// 返回续传的起始偏移，0 表示从头上传。
+    pub fn resume_upload<F>(
+        &self,
+        local: &Path,
+        remote: &str,
+        resume: bool,
+        verify_tail: bool,
+        mut progress_callback: F,
+    ) -> Result<u64>
+    where
+        F: FnMut(u64, u64) -> bool,
+    {
+        let mut local_file = std::fs::File::open(local)?;
+        let total = local_file.metadata()?.len();
+        let partial = match self.sftp.stat(Path::new(remote)) {
+            Ok(stat) if resume && stat.is_file() => stat.size.unwrap_or(0),
+            _ => 0,
+        };
+        let offset = if partial > 0 {
+            let mut remote_file = self.sftp.open(Path::new(remote))?;
+            resume_offset(&mut local_file, total, &mut remote_file, partial, verify_tail)?
+        } else {
+            0
+        };
+
+        let mut remote_file = if offset > 0 {
+            let mut file = self.sftp.open_mode(Path::new(remote), OpenFlags::WRITE, 0o644, OpenType::File)?;
+            file.seek(SeekFrom::Start(offset))?;
+            file
+        } else {
+            self.sftp.create(Path::new(remote))?
+        };
+        local_file.seek(SeekFrom::Start(offset))?;
+        if !progress_callback(offset, total) {
+            return Err(TransferAborted.into());
+        }
+        copy_chunks(&mut local_file, &mut remote_file, self.chunk_size, |sent| {
+            progress_callback(offset + sent, total)
+        })?;
+        Ok(offset)
+    }
+
+    /// 断点续传下载：`resume` 为真且本地已有部分文件时从其末尾继续
+    ///
+    /// 回调与返回值同 [`SftpClient::resume_upload`]。
+    pub fn resume_download<F>(
+        &self,
+        remote: &str,
+        local: &Path,
+        resume: bool,
+        verify_tail: bool,
+        mut progress_callback: F,
+    ) -> Result<u64>
+    where
+        F: FnMut(u64, u64) -> bool,
+    {
+        let mut remote_file = self.sftp.open(Path::new(remote))?;
+        let total = remote_file.stat()?.size.unwrap_or(0);
+        let partial = match std::fs::metadata(local) {
+            Ok(metadata) if resume && metadata.is_file() => metadata.len(),
+            _ => 0,
+        };
+        let offset = if partial > 0 {
+            let mut local_file = std::fs::File::open(local)?;
+            resume_offset(&mut remote_file, total, &mut local_file, partial, verify_tail)?
+        } else {
+            0
+        };
+
+        let mut local_file = if offset > 0 {
+            let mut file = std::fs::File::options().write(true).open(local)?;
+            file.seek(SeekFrom::Start(offset))?;
+            file
+        } else {
+            std::fs::File::create(local)?
+        };
+        remote_file.seek(SeekFrom::Start(offset))?;
+        if !progress_callback(offset, total) {
+            return Err(TransferAborted.into());
+        }
+        copy_chunks(&mut remote_file, &mut local_file, self.chunk_size, |received| {
+            progress_callback(offset + received, total)
+        })?;
+        Ok(offset)
+    }
+
+    /// 递归上传本地目录到 `remote_root`（不存在时创建）
+    ///
+    /// 进度回调返回 false 时以 [`TransferAborted`] 中止。
+    pub fn upload_dir<F>(
+        &self,
+        local_root: &Path,
+        remote_root: &str,
+        options: TransferOptions,
+        mut progress_callback: F,
+    ) -> Result<TreeProgress>
+    where
+        F: FnMut(&TreeProgress) -> bool,
+    {
+        let entries = walk_local_tree(local_root, options.symlinks)?;
+        let mut progress = TreeProgress::new(&entries);
+        self.ensure_remote_dir(remote_root)?;
+        if !progress_callback(&progress) {
+            return Err(TransferAborted.into());
+        }
+
+        for entry in entries {
+            match entry {
+                TreeEntry::Dir { relative } => self.ensure_remote_dir(&join_remote(remote_root, &relative))?,
+                TreeEntry::Symlink { relative, target } => {
+                    let link = join_remote(remote_root, &relative);
+                    if self.sftp.lstat(Path::new(&link)).is_ok() {
+                        // 已存在的链接只在覆盖策略下替换
+                        if options.conflict != ConflictPolicy::Overwrite {
+                            continue;
+                        }
+                        self.sftp.unlink(Path::new(&link))?;
+                    }
+                    // OpenSSH 的 SYMLINK 参数顺序与规范相反，ssh2 的 (path, target) 据此传入
+                    self.sftp.symlink(Path::new(&target), Path::new(&link))?;
+                }
+                TreeEntry::File { relative, size, mtime } => {
+                    progress.current = relative.clone();
+                    let remote = join_remote(remote_root, &relative);
+                    let existing = self.sftp.stat(Path::new(&remote)).ok().map(|stat| stat.mtime);
+                    let remote = match conflict_action(options.conflict, mtime, existing) {
+                        ConflictAction::Write => remote,
+                        ConflictAction::Rename => unique_name(&remote, |p| self.sftp.lstat(Path::new(p)).is_ok()),
+                        ConflictAction::Skip => {
+                            progress.files_skipped += 1;
+                            progress.bytes_done += size;
+                            if !progress_callback(&progress) {
+                                return Err(TransferAborted.into());
+                            }
+                            continue;
+                        }
+                    };
+                    let base = progress.bytes_done;
+                    self.upload_bytes(&local_root.join(&relative), &remote, |sent| {
+                        progress.bytes_done = base + sent.min(size);
+                        progress_callback(&progress)
+                    })?;
+                    progress.bytes_done = base + size;
+                    progress.files_done += 1;
+                    if let Some(mtime) = mtime {
+                        let times = FileStat {
+                            size: None,
+                            uid: None,
+                            gid: None,
+                            perm: None,
+                            atime: Some(mtime),
+                            mtime: Some(mtime),
+                        };
+                        // 部分服务器不允许修改时间，不影响传输结果
+                        let _ = self.sftp.setstat(Path::new(&remote), times);
+                    }
+                    if !progress_callback(&progress) {
+                        return Err(TransferAborted.into());
+                    }
+                }
+            }
+        }
+        progress.current.clear();
+        Ok(progress)
+    }
+
+    /// 递归下载远程目录到 `local_root`（不存在时创建）
+    ///
+    /// 进度回调返回 false 时以 [`TransferAborted`] 中止。
+    pub fn download_dir<F>(
+        &self,
+        remote_root: &str,
+        local_root: &Path,
+        options: TransferOptions,
+        mut progress_callback: F,
+    ) -> Result<TreeProgress>
+    where
+        F: FnMut(&TreeProgress) -> bool,
+    {
+        let entries = self.walk_remote_tree(remote_root, options.symlinks)?;
+        let mut progress = TreeProgress::new(&entries);
+        std::fs::create_dir_all(local_root)?;
+        if !progress_callback(&progress) {
+            return Err(TransferAborted.into());
+        }
+
+        for entry in entries {
+            match entry {
+                TreeEntry::Dir { relative } => std::fs::create_dir_all(local_root.join(&relative))?,
+                TreeEntry::Symlink { relative, target } => {
+                    let link = local_root.join(&relative);
+                    if std::fs::symlink_metadata(&link).is_ok() {
+                        if options.conflict != ConflictPolicy::Overwrite {
+                            continue;
+                        }
+                        std::fs::remove_file(&link)?;
+                    }
+                    create_local_symlink(&target, &link)?;
+                }
+                TreeEntry::File { relative, size, mtime } => {
+                    progress.current = relative.clone();
+                    let local = local_root.join(&relative);
+                    let existing = std::fs::metadata(&local).ok().map(|m| local_mtime(&m));
+                    let local = match conflict_action(options.conflict, mtime, existing) {
+                        ConflictAction::Write => local,
+                        ConflictAction::Rename => {
+                            let renamed = unique_name(&local.to_string_lossy(), |p| {
+                                std::fs::symlink_metadata(p).is_ok()
+                            });
+                            PathBuf::from(renamed)
+                        }
+                        ConflictAction::Skip => {
+                            progress.files_skipped += 1;
+                            progress.bytes_done += size;
+                            if !progress_callback(&progress) {
+                                return Err(TransferAborted.into());
+                            }
+                            continue;
+                        }
+                    };
+                    let base = progress.bytes_done;
+                    self.download_bytes(&join_remote(remote_root, &relative), &local, |received| {
+                        progress.bytes_done = base + received.min(size);
+                        progress_callback(&progress)
+                    })?;
+                    progress.bytes_done = base + size;
+                    progress.files_done += 1;
+                    if let Some(mtime) = mtime {
+                        let modified = UNIX_EPOCH + Duration::from_secs(mtime);
+                        let _ = std::fs::File::options()
+                            .write(true)
+                            .open(&local)
+                            .and_then(|file| file.set_modified(modified));
+                    }
+                    if !progress_callback(&progress) {
+                        return Err(TransferAborted.into());
+                    }
+                }
+            }
+        }
+        progress.current.clear();
+        Ok(progress)
+    }
+
+    /// 遍历远程目录树，目录先于其内容出现
+    pub fn walk_remote_tree(&self, root: &str, symlinks: SymlinkPolicy) -> Result<Vec<TreeEntry>> {
+        let mut entries = Vec::new();
+        let mut ancestors = vec![self.realpath(root)?];
+        self.walk_remote_dir(root, "", symlinks, &mut ancestors, &mut entries)?;
+        Ok(entries)
+    }
+
+    fn walk_remote_dir(
+        &self,
+        dir: &str,
+        prefix: &str,
+        symlinks: SymlinkPolicy,
+        ancestors: &mut Vec<String>,
+        entries: &mut Vec<TreeEntry>,
+    ) -> Result<()> {
+        let mut children = self.sftp.readdir(Path::new(dir))?;
+        children.sort_by(|a, b| a.0.cmp(&b.0));
+        for (path, mut stat) in children {
+            let Some(name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+                continue;
             };
-            progress_callback(progress);
+            let relative = join_relative(prefix, &name);
+            let remote = join_remote(dir, &name);
+            // OpenSSH 的 READDIR 返回 lstat 结果，链接需要单独处理
+            if stat.file_type() == FileType::Symlink {
+                match symlinks {
+                    SymlinkPolicy::Skip => continue,
+                    SymlinkPolicy::Preserve => {
+                        let target = self.sftp.readlink(Path::new(&remote))?.to_string_lossy().to_string();
+                        entries.push(TreeEntry::Symlink { relative, target });
+                        continue;
+                    }
+                    SymlinkPolicy::Follow => match self.sftp.stat(Path::new(&remote)) {
+                        Ok(target) => stat = target,
+                        Err(_) => continue,
+                    },
+                }
+            }
+            if stat.is_dir() {
+                let canonical = self.realpath(&remote)?;
+                if ancestors.contains(&canonical) {
+                    continue;
+                }
+                entries.push(TreeEntry::Dir { relative: relative.clone() });
+                ancestors.push(canonical);
+                self.walk_remote_dir(&remote, &relative, symlinks, ancestors, entries)?;
+                ancestors.pop();
+            } else if stat.is_file() {
+                entries.push(TreeEntry::File {
+                    relative,
+                    size: stat.size.unwrap_or(0),
+                    mtime: stat.mtime,
+                });
+            }
         }
-        
-        local_file.flush()?;
         Ok(())
     }
 
+    /// 目录不存在时创建（已存在的同名文件视为错误）
+    fn ensure_remote_dir(&self, path: &str) -> Result<()> {
+        match self.sftp.stat(Path::new(path)) {
+            Ok(stat) if stat.is_dir() => Ok(()),
+            Ok(_) => Err(format!("{} 已存在且不是目录", path).into()),
+            Err(_) => self.create_dir(path),
+        }
+    }
+
     /// 删除文件或目录
     pub fn delete(&self, path: &str) -> Result<()> {
         let path_obj = std::path::Path::new(path);
@@ -199,40 +740,251 @@ impl SftpClient {
         Ok(())
     }
 
-    /// 获取文件/目录状态
+    /// 获取文件/目录状态（跟随符号链接）
     pub fn stat(&self, path: &str) -> Result<FileEntry> {
         let stat = self.sftp.stat(std::path::Path::new(path))?;
-        
-        let name = std::path::Path::new(path)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("")
-            .to_string();
-        
-        let is_dir = stat.is_dir();
-        let size = stat.size.unwrap_or(0);
-        let modified = stat.mtime.and_then(|mtime| {
-            std::time::UNIX_EPOCH.checked_add(std::time::Duration::from_secs(mtime))
-        });
-        let permissions = stat.perm.map(|p| format!("{:o}", p));
-        
-        Ok(FileEntry {
-            name,
-            path: path.to_string(),
-            is_dir,
-            size,
-            modified,
-            permissions,
-        })
+        Ok(file_entry(Path::new(path), &stat))
+    }
+
+    /// 重命名或移动（目标已存在时由服务器决定是否失败）
+    pub fn rename(&self, from: &str, to: &str) -> Result<()> {
+        self.sftp.rename(Path::new(from), Path::new(to), None)?;
+        Ok(())
+    }
+
+    /// 修改权限位（如 `0o644`）
+    pub fn chmod(&self, path: &str, mode: u32) -> Result<()> {
+        self.sftp.setstat(
+            Path::new(path),
+            FileStat {
+                size: None,
+                uid: None,
+                gid: None,
+                perm: Some(mode & 0o7777),
+                atime: None,
+                mtime: None,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// 修改属主和/或属组（None 表示不变）
+    pub fn chown(&self, path: &str, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        if uid.is_none() && gid.is_none() {
+            return Ok(());
+        }
+        // SFTP v3 的 uid 和 gid 必须同时设置，缺少的一方沿用当前值
+        let (uid, gid) = match (uid, gid) {
+            (Some(uid), Some(gid)) => (uid, gid),
+            _ => {
+                let stat = self.sftp.stat(Path::new(path))?;
+                match (uid.or(stat.uid), gid.or(stat.gid)) {
+                    (Some(uid), Some(gid)) => (uid, gid),
+                    _ => return Err(format!("无法获取 {} 的属主", path).into()),
+                }
+            }
+        };
+        self.sftp.setstat(
+            Path::new(path),
+            FileStat {
+                size: None,
+                uid: Some(uid),
+                gid: Some(gid),
+                perm: None,
+                atime: None,
+                mtime: None,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// 在 `link` 处创建指向 `target` 的符号链接
+    pub fn symlink(&self, target: &str, link: &str) -> Result<()> {
+        // OpenSSH 的 SSH_FXP_SYMLINK 参数顺序与草案相反，ssh2 按 OpenSSH 的顺序发送
+        self.sftp.symlink(Path::new(target), Path::new(link))?;
+        Ok(())
+    }
+
+    /// 读取符号链接的目标（不解析为绝对路径）
+    pub fn readlink(&self, path: &str) -> Result<String> {
+        Ok(self.sftp.readlink(Path::new(path))?.to_string_lossy().to_string())
+    }
+
+    /// 解析为服务器上的规范绝对路径（相对路径基于登录目录）
+    pub fn realpath(&self, path: &str) -> Result<String> {
+        let path = if path.is_empty() { "." } else { path };
+        Ok(self.sftp.realpath(Path::new(path))?.to_string_lossy().to_string())
+    }
+}
+
+/// 由 SFTP 属性构造条目（属主名由调用方解析）
+fn file_entry(path: &Path, stat: &FileStat) -> FileEntry {
+    let time = |secs: Option<u64>| secs.and_then(|secs| UNIX_EPOCH.checked_add(Duration::from_secs(secs)));
+    let kind = match stat.file_type() {
+        FileType::RegularFile => FileKind::File,
+        FileType::Directory => FileKind::Directory,
+        FileType::Symlink => FileKind::Symlink,
+        FileType::Socket => FileKind::Socket,
+        FileType::NamedPipe => FileKind::Fifo,
+        FileType::CharDevice => FileKind::CharDevice,
+        FileType::BlockDevice => FileKind::BlockDevice,
+        FileType::Other(_) => FileKind::Other,
+    };
+    FileEntry {
+        name: path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string(),
+        path: path.to_string_lossy().to_string(),
+        is_dir: stat.is_dir(),
+        size: stat.size.unwrap_or(0),
+        modified: time(stat.mtime),
+        accessed: time(stat.atime),
+        permissions: stat.perm.map(|p| format!("{:o}", p)),
+        kind,
+        link_target: None,
+        uid: stat.uid,
+        gid: stat.gid,
+        owner: None,
+        group: None,
+    }
+}
+
+fn fraction(done: u64, total: u64) -> f32 {
+    if total > 0 {
+        (done as f32 / total as f32).min(1.0)
+    } else {
+        1.0
+    }
+}
+
+/// 续传前校验的重叠尾部长度
+const RESUME_TAIL: u64 = 64 * 1024;
+
+/// 计算续传起点
+///
+/// 已有部分不超过源文件长度、且（需要校验时）两端末尾 `RESUME_TAIL` 字节的
+/// SHA-256 一致时返回其长度，否则返回 0 表示从头传输。
+fn resume_offset<S: Read + Seek, D: Read + Seek>(
+    source: &mut S,
+    source_len: u64,
+    partial: &mut D,
+    partial_len: u64,
+    verify_tail: bool,
+) -> Result<u64> {
+    if partial_len == 0 || partial_len > source_len {
+        return Ok(0);
     }
+    if verify_tail {
+        let start = partial_len.saturating_sub(RESUME_TAIL);
+        if range_digest(source, start, partial_len)? != range_digest(partial, start, partial_len)? {
+            return Ok(0);
+        }
+    }
+    Ok(partial_len)
+}
+
+fn range_digest<R: Read + Seek>(reader: &mut R, start: u64, end: u64) -> Result<[u8; 32]> {
+    reader.seek(SeekFrom::Start(start))?;
+    let mut hasher = Sha256::new();
+    let copied = std::io::copy(&mut reader.by_ref().take(end - start), &mut hasher)?;
+    if copied != end - start {
+        return Err("读取续传校验数据时文件被截断".into());
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// 判断传输错误是否由连接中断引起（可在重连后续传）
+///
+/// 权限不足、文件不存在等 SFTP 状态错误和本地文件错误返回 false。
+pub fn is_connection_error(error: &(dyn std::error::Error + 'static)) -> bool {
+    if let Some(error) = error.downcast_ref::<ssh2::Error>() {
+        return matches!(error.code(), ssh2::ErrorCode::Session(_));
+    }
+    if let Some(error) = error.downcast_ref::<std::io::Error>() {
+        if let Some(inner) = error.get_ref() {
+            if inner.downcast_ref::<ssh2::Error>().is_some() {
+                return is_connection_error(inner);
+            }
+        }
+        return matches!(
+            error.kind(),
+            std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::BrokenPipe
+                | std::io::ErrorKind::TimedOut
+                | std::io::ErrorKind::UnexpectedEof
+        );
+    }
+    error.to_string() == "Not connected"
+}
+
+/// 进度回调要求停止时传输返回的错误（用于暂停和取消）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferAborted;
+
+impl std::fmt::Display for TransferAborted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "传输已中止")
+    }
+}
+
+impl std::error::Error for TransferAborted {}
+
+/// 分块复制，每块之后以累计字节数回调，回调返回 false 时中止
+///
+/// 每块尽量读满再整块写出，使远程端的读写都能形成多个在途请求。
+fn copy_chunks<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    chunk_size: usize,
+    mut on_chunk: impl FnMut(u64) -> bool,
+) -> Result<()> {
+    let mut buffer = vec![0u8; chunk_size];
+    let mut total = 0u64;
+    loop {
+        let bytes_read = fill_chunk(reader, &mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..bytes_read])?;
+        total += bytes_read as u64;
+        if !on_chunk(total) {
+            writer.flush()?;
+            return Err(TransferAborted.into());
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// 读到缓冲区满或文件结束，返回读到的字节数
+fn fill_chunk<R: Read>(reader: &mut R, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(unix)]
+fn create_local_symlink(target: &str, link: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn create_local_symlink(target: &str, link: &Path) -> Result<()> {
+    Err(format!("当前系统不支持创建符号链接: {} -> {}", link.display(), target).into())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     // 注意：这些测试需要真实的 SSH 连接，通常在集成测试中运行
     // 这里提供测试框架结构
+    use super::*;
 
     #[test]
     fn test_sftp_client_creation() {
@@ -249,4 +1001,159 @@ mod tests {
     fn test_file_operations() {
         // 测试文件上传/下载/删除
     }
+
+    #[test]
+    fn test_conflict_action() {
+        use ConflictAction::*;
+        // 目标不存在时总是写入
+        for policy in ConflictPolicy::ALL {
+            assert_eq!(conflict_action(policy, Some(5), None), Write);
+        }
+        assert_eq!(conflict_action(ConflictPolicy::Overwrite, Some(5), Some(Some(9))), Write);
+        assert_eq!(conflict_action(ConflictPolicy::Skip, Some(5), Some(None)), Skip);
+        assert_eq!(conflict_action(ConflictPolicy::Rename, Some(5), Some(Some(1))), Rename);
+        assert_eq!(conflict_action(ConflictPolicy::NewerOnly, Some(5), Some(Some(9))), Skip);
+        assert_eq!(conflict_action(ConflictPolicy::NewerOnly, Some(5), Some(Some(5))), Skip);
+        assert_eq!(conflict_action(ConflictPolicy::NewerOnly, Some(9), Some(Some(5))), Write);
+        assert_eq!(conflict_action(ConflictPolicy::NewerOnly, None, Some(Some(5))), Write);
+
+        let taken = ["/a/x.txt", "/a/x.txt.1"];
+        assert_eq!(unique_name("/a/x.txt", |p| taken.contains(&p)), "/a/x.txt.2");
+        assert_eq!(join_remote("/srv/", "a/b"), "/srv/a/b");
+        assert_eq!(join_remote("/srv", ""), "/srv");
+
+        let temp = temp_sibling("/etc/nginx/nginx.conf");
+        assert!(temp.starts_with("/etc/nginx/.nginx.conf.ishell-"), "{}", temp);
+        assert_ne!(temp, temp_sibling("/etc/nginx/nginx.conf"));
+        assert!(temp_sibling("notes.txt").starts_with(".notes.txt.ishell-"));
+    }
+
+    #[test]
+    fn test_walk_local_tree() {
+        let root = std::env::temp_dir().join(format!("sftp_tree_{}", rand::random::<u32>()));
+        std::fs::create_dir_all(root.join("sub/deep")).unwrap();
+        std::fs::write(root.join("a.txt"), b"hello").unwrap();
+        std::fs::write(root.join("sub/deep/b.bin"), vec![0u8; 300]).unwrap();
+
+        let entries = walk_local_tree(&root, SymlinkPolicy::Follow).unwrap();
+        let mut progress = TreeProgress::new(&entries);
+        assert_eq!(progress.files_total, 2);
+        assert_eq!(progress.bytes_total, 305);
+        assert_eq!(progress.fraction(), 0.0);
+        progress.bytes_done = 305;
+        assert_eq!(progress.fraction(), 1.0);
+        let names: Vec<_> = entries
+            .iter()
+            .map(|e| match e {
+                TreeEntry::Dir { relative } | TreeEntry::File { relative, .. } | TreeEntry::Symlink { relative, .. } => {
+                    relative.as_str()
+                }
+            })
+            .collect();
+        assert_eq!(names, ["a.txt", "sub", "sub/deep", "sub/deep/b.bin"]);
+
+        #[cfg(unix)]
+        {
+            // 指向祖先目录的链接不会无限递归
+            std::os::unix::fs::symlink(&root, root.join("sub/loop")).unwrap();
+            std::os::unix::fs::symlink("a.txt", root.join("link.txt")).unwrap();
+            let followed = walk_local_tree(&root, SymlinkPolicy::Follow).unwrap();
+            assert_eq!(TreeProgress::new(&followed).files_total, 3);
+            let skipped = walk_local_tree(&root, SymlinkPolicy::Skip).unwrap();
+            assert_eq!(skipped, entries);
+            let preserved = walk_local_tree(&root, SymlinkPolicy::Preserve).unwrap();
+            assert!(preserved.contains(&TreeEntry::Symlink {
+                relative: "link.txt".to_string(),
+                target: "a.txt".to_string()
+            }));
+            assert!(preserved.iter().any(|e| matches!(e, TreeEntry::Symlink { relative, .. } if relative == "sub/loop")));
+        }
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_resume_offset() {
+        use std::io::Cursor;
+        let source: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let mut src = Cursor::new(source.clone());
+
+        // 完好的部分数据从末尾继续
+        let mut partial = Cursor::new(source[..150_000].to_vec());
+        assert_eq!(resume_offset(&mut src, 200_000, &mut partial, 150_000, true).unwrap(), 150_000);
+
+        // 尾部损坏时从头开始，不校验时照常续传
+        let mut corrupt = source[..150_000].to_vec();
+        corrupt[149_000] ^= 0xff;
+        let mut partial = Cursor::new(corrupt);
+        assert_eq!(resume_offset(&mut src, 200_000, &mut partial, 150_000, true).unwrap(), 0);
+        assert_eq!(resume_offset(&mut src, 200_000, &mut partial, 150_000, false).unwrap(), 150_000);
+
+        // 目标比源文件长说明不是同一文件
+        let mut longer = Cursor::new(vec![0u8; 10]);
+        assert_eq!(resume_offset(&mut Cursor::new(vec![0u8; 5]), 5, &mut longer, 10, true).unwrap(), 0);
+
+        // 从续传点继续后内容与源文件一致
+        let mut dest = Cursor::new(source[..150_000].to_vec());
+        dest.seek(SeekFrom::Start(150_000)).unwrap();
+        src.seek(SeekFrom::Start(150_000)).unwrap();
+        let mut chunks = Vec::new();
+        copy_chunks(&mut src, &mut dest, 16 * 1024, |n| {
+            chunks.push(n);
+            true
+        })
+        .unwrap();
+        assert_eq!(chunks, [16_384, 32_768, 49_152, 50_000]);
+        assert_eq!(dest.into_inner(), source);
+    }
+
+    #[test]
+    fn test_is_connection_error() {
+        let session = ssh2::Error::new(ssh2::ErrorCode::Session(-43), "socket recv");
+        assert!(is_connection_error(&session));
+        let denied = ssh2::Error::new(ssh2::ErrorCode::SFTP(3), "permission denied");
+        assert!(!is_connection_error(&denied));
+        let wrapped = std::io::Error::other(ssh2::Error::new(ssh2::ErrorCode::Session(-7), "send"));
+        assert!(is_connection_error(&wrapped));
+        assert!(is_connection_error(&std::io::Error::from(std::io::ErrorKind::BrokenPipe)));
+        assert!(!is_connection_error(&std::io::Error::from(std::io::ErrorKind::NotFound)));
+    }
+
+    /// 每次只返回少量数据的读取端（模拟 SFTP 逐个返回的读取应答）
+    struct Trickle<R>(R);
+
+    impl<R: Read> Read for Trickle<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(1000);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn test_copy_chunks_fills_large_chunks_and_aborts() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let mut writes = Vec::new();
+        struct Recorder<'a>(&'a mut Vec<usize>, Vec<u8>);
+        impl Write for Recorder<'_> {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.push(buf.len());
+                self.1.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        // 小块的读取应答被合并成整块写出
+        let mut recorder = Recorder(&mut writes, Vec::new());
+        copy_chunks(&mut Trickle(&data[..]), &mut recorder, 4096, |_| true).unwrap();
+        assert_eq!(recorder.1, data);
+        assert_eq!(writes, [4096, 4096, 1808]);
+
+        // 回调返回 false 时在当前块之后停止
+        let mut out = Vec::new();
+        let err = copy_chunks(&mut &data[..], &mut out, 4096, |n| n < 8192).unwrap_err();
+        assert!(err.downcast_ref::<TransferAborted>().is_some());
+        assert_eq!(out.len(), 8192);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::session::SessionBackend;
 use crate::types::{AuthMethod, ConnectionStatus, Result};
 use crate::sftp::SftpClient;
 use ssh2::Session;
@@ -86,6 +87,28 @@ impl SshSession {
 
     /// 执行单个命令
     pub fn execute_command(&self, command: &str) -> Result<String> {
+        let (mut output, stderr, exit_status) = self.exec(command)?;
+
+        if exit_status != 0 && !stderr.is_empty() {
+            output.push_str("\n[stderr]:\n");
+            output.push_str(&stderr);
+        }
+
+        Ok(output)
+    }
+
+    /// 执行单个命令，退出码非 0 时以 stderr 作为错误返回
+    pub fn execute_checked(&self, command: &str) -> Result<String> {
+        let (output, stderr, exit_status) = self.exec(command)?;
+        if exit_status != 0 {
+            let message = if stderr.trim().is_empty() { output.trim() } else { stderr.trim() };
+            return Err(format!("命令退出码 {}: {}", exit_status, message).into());
+        }
+        Ok(output)
+    }
+
+    /// 返回 (stdout, stderr, 退出码)
+    fn exec(&self, command: &str) -> Result<(String, String, i32)> {
         let session = self.session.lock().unwrap();
         let sess = session.as_ref().ok_or("Not connected")?;
 
@@ -101,24 +124,26 @@ impl SshSession {
         channel.wait_close()?;
         let exit_status = channel.exit_status()?;
 
-        if exit_status != 0 && !stderr.is_empty() {
-            output.push_str("\n[stderr]:\n");
-            output.push_str(&stderr);
-        }
-
-        Ok(output)
+        Ok((output, stderr, exit_status))
     }
 
     /// 启动交互式 shell
-    pub fn start_shell(&self) -> Result<SshShell> {
+    ///
+    /// Shell 需要非阻塞读取，会把底层会话切换为非阻塞模式，
+    /// 因此应在专用的 SshSession 上调用，而不是与 exec/SFTP 共用。
+    pub fn start_shell(&self, cols: u32, rows: u32) -> Result<SshShell> {
         let session = self.session.lock().unwrap();
         let sess = session.as_ref().ok_or("Not connected")?;
 
         let mut channel = sess.channel_session()?;
-        channel.request_pty("xterm", None, None)?;
+        channel.request_pty("xterm-256color", None, Some((cols, rows, 0, 0)))?;
         channel.shell()?;
+        sess.set_blocking(false);
 
-        Ok(SshShell { channel })
+        Ok(SshShell {
+            channel,
+            session: sess.clone(),
+        })
     }
 
     /// 获取连接状态
@@ -154,54 +179,83 @@ impl SshSession {
 /// 交互式 SSH Shell
 pub struct SshShell {
     channel: ssh2::Channel,
+    session: Session,
 }
 
 impl SshShell {
     /// 发送命令
     pub fn send_command(&mut self, command: &str) -> Result<()> {
-        self.channel.write_all(command.as_bytes())?;
-        self.channel.write_all(b"\n")?;
-        self.channel.flush()?;
-        Ok(())
+        self.write(command.as_bytes())?;
+        self.write(b"\n")
     }
 
-    /// 读取输出（非阻塞）
+    /// 读取当前可用的全部输出（非阻塞）
     pub fn read_output(&mut self) -> Result<String> {
         let mut buffer = vec![0; 8192];
-        // ssh2 的 Channel 默认就是非阻塞的，直接读取即可
-
-        let mut output = String::new();
+        let mut output = Vec::new();
         loop {
-            match self.channel.read(&mut buffer) {
-                Ok(0) => break, // EOF
-                Ok(n) => {
-                    output.push_str(&String::from_utf8_lossy(&buffer[..n]));
+            match SessionBackend::read(self, &mut buffer)? {
+                0 => break,
+                n => output.extend_from_slice(&buffer[..n]),
+            }
+        }
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+}
+
+/// 非阻塞模式下重试返回 EAGAIN 的操作
+fn retry_would_block<T>(mut op: impl FnMut() -> std::result::Result<T, ssh2::Error>) -> Result<T> {
+    loop {
+        match op() {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                let err = std::io::Error::from(e);
+                if err.kind() != std::io::ErrorKind::WouldBlock {
+                    return Err(err.into());
                 }
-                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    break; // 没有更多数据
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+}
+
+impl SessionBackend for SshShell {
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        let mut written = 0;
+        while written < data.len() {
+            match self.channel.write(&data[written..]) {
+                Ok(n) => written += n,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(Duration::from_millis(1));
                 }
                 Err(e) => return Err(e.into()),
             }
+        }
+        Ok(())
+    }
 
-            // 防止无限循环，如果没有数据就退出
-            if output.is_empty() {
-                break;
-            }
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match self.channel.read(buf) {
+            Ok(n) => Ok(n),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(0),
+            Err(e) => Err(e.into()),
         }
+    }
 
-        Ok(output)
+    fn resize(&mut self, cols: u16, rows: u16) -> Result<()> {
+        let channel = &mut self.channel;
+        retry_would_block(|| channel.request_pty_size(cols as u32, rows as u32, None, None))
     }
 
-    /// 检查 shell 是否仍然活跃
-    pub fn is_active(&self) -> bool {
+    fn is_active(&mut self) -> bool {
         !self.channel.eof()
     }
 
-    /// 关闭 shell
-    pub fn close(mut self) -> Result<()> {
-        self.channel.close()?;
-        self.channel.wait_close()?;
-        Ok(())
+    fn close(&mut self) -> Result<()> {
+        let channel = &mut self.channel;
+        retry_would_block(|| channel.close())?;
+        let session = &self.session;
+        retry_would_block(|| session.disconnect(None, "Shell closed", None))
     }
 }
 
@@ -2,8 +2,11 @@ use crate::ai::AiManager;
 use crate::config::ConfigManager;
 use crate::history::CommandHistory;
 use crate::monitor::SystemMonitor;
+use crate::session::{SessionMessage, TerminalTab};
 use crate::ssh::SshSession;
+use crate::terminal::{ClipboardRequest, TerminalBuffer};
 use crate::types::{AiProviderType, AppConfig, ConnectionStatus, FileEntry, SettingsPage, SftpMessage, SshConfig};
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex as TokioMutex};
 
@@ -21,7 +24,7 @@ pub enum SshMessage {
 
 #[derive(Debug, Clone)]
 pub enum SshCommand {
-    Connect { config: SshConfig },
+    Connect { config: Box<SshConfig> },
     Disconnect,
     ExecuteCommand(String),
 }
@@ -32,6 +35,67 @@ pub enum AiChannelMessage {
     Error(String),
 }
 
+/// 等待用户授权的 OSC 52 剪贴板请求
+#[derive(Debug, Clone)]
+pub struct ClipboardPrompt {
+    pub tab: u64,
+    pub host: String,
+    pub request: ClipboardRequest,
+}
+
+/// 粘贴目标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasteTarget {
+    Tab(u64),
+    CommandInput,
+}
+
+/// 等待用户确认的粘贴
+#[derive(Debug, Clone)]
+pub struct PendingPaste {
+    pub target: PasteTarget,
+    pub text: String,
+}
+
+/// 导出对话框：导出哪个终端（None 为日志控制台）的整个会话或某个命令块
+#[derive(Debug, Clone)]
+pub struct ExportDialog {
+    pub tab: Option<u64>,
+    pub block: Option<usize>,
+    pub format: crate::export::ExportFormat,
+    pub redact: bool,
+    pub status: String,
+}
+
+/// 符号链接目标的解析状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkRequest {
+    /// 正在解析；`open` 表示解析后打开（点击了尚未解析的链接）
+    Pending { open: bool },
+    /// 已解析或解析失败
+    Done,
+}
+
+/// 文件管理对话框（文件浏览器右键菜单打开）
+#[derive(Debug, Clone)]
+pub struct FileOpDialog {
+    pub entry: FileEntry,
+    pub kind: FileOpDialogKind,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileOpDialogKind {
+    /// 新的完整路径，在其他目录时即为移动
+    Rename { to: String },
+    Copy { to: String },
+    Chmod { mode: u32 },
+    /// 用户名/组名或数字 id，留空不修改
+    Chown { owner: String, group: String },
+    /// 要创建的链接路径，指向对话框的文件
+    Symlink { link: String },
+}
+
 // ============================================================================
 // 主应用状态结构
 // ============================================================================
@@ -54,15 +118,37 @@ pub struct AppState {
     pub new_conn_password: String,
     pub new_conn_use_key: bool,
     pub new_conn_key_path: String,
+    pub new_conn_color_scheme: Option<String>,
+    pub new_conn_protocol: crate::types::Protocol,
 
     // SSH 状态
     pub ssh_sessions: Vec<Option<Arc<std::sync::Mutex<SshSession>>>>,
     pub connection_status: Vec<ConnectionStatus>,
 
-    // 终端
-    pub terminal_output: String,
+    // 终端（日志控制台）
+    pub terminal: TerminalBuffer,
     pub command_input: String,
 
+    // 终端标签：SSH shell 与本地 shell (v0.4.0)
+    pub tabs: Vec<TerminalTab>,
+    /// 当前标签，None 表示日志控制台
+    pub active_tab: Option<usize>,
+    pub next_tab_id: u64,
+    pub session_msg_tx: mpsc::UnboundedSender<SessionMessage>, // 会话线程->UI
+    pub session_msg_rx: Arc<std::sync::Mutex<mpsc::UnboundedReceiver<SessionMessage>>>,
+    /// 供后台线程在收到输出时唤醒界面
+    pub repaint_ctx: Option<egui::Context>,
+
+    // 剪贴板 (v0.4.0)
+    pub clipboard_prompts: VecDeque<ClipboardPrompt>,
+    pub pending_paste: Option<PendingPaste>,
+
+    // 终端内容导出对话框
+    pub export_dialog: Option<ExportDialog>,
+
+    // 输出触发器（由 config.triggers 编译）
+    pub triggers: crate::triggers::TriggerSet,
+
     // SSH 异步通信
     pub ssh_msg_tx: mpsc::UnboundedSender<SshMessage>, // 后台->UI
     pub ssh_msg_rx: Arc<std::sync::Mutex<mpsc::UnboundedReceiver<SshMessage>>>,
@@ -83,11 +169,29 @@ pub struct AppState {
     pub remote_current_path: String,
     pub local_current_path: std::path::PathBuf,
     pub remote_files: Vec<FileEntry>,
+    /// 远程文件列表的排序列和方向
+    pub remote_sort: crate::types::FileSort,
     pub local_files: Vec<FileEntry>,
     pub selected_remote_files: Vec<String>,
     pub selected_local_file: Option<std::path::PathBuf>,
-    pub sftp_progress: f32,
+    /// ZMODEM 传输进度（SFTP 传输的进度记录在传输队列的各项中）
+    pub zmodem_progress: f32,
     pub sftp_status: String,
+    /// 传输队列
+    pub transfers: crate::transfer::TransferManager,
+    pub show_transfers: bool,
+    /// 正在编辑的远程文件
+    pub remote_edits: crate::remote_edit::RemoteEditManager,
+    /// 内置编辑器中打开的远程文件
+    pub editor_buffers: Vec<crate::remote_edit::EditorBuffer>,
+    /// 只读分页查看的远程大文件
+    pub file_viewers: Vec<crate::editor::FileViewer>,
+    /// 文件管理对话框
+    pub file_op_dialog: Option<FileOpDialog>,
+    /// 各连接的远程用户名和组名
+    pub remote_ids: HashMap<String, crate::file_ops::IdNames>,
+    /// 当前目录中已请求解析的符号链接
+    pub remote_link_requests: HashMap<String, LinkRequest>,
 
     // SFTP 异步通信 (v0.3.0)
     pub sftp_msg_tx: mpsc::UnboundedSender<SftpMessage>,
@@ -103,9 +207,26 @@ pub struct AppState {
     pub show_history_search: bool,
     pub history_search_query: String,
 
+    // 命令面板与快捷键 (v0.4.0)
+    pub show_command_palette: bool,
+    pub command_palette_query: String,
+    pub command_palette_selected: usize,
+    /// 终端命令输入框是否拥有焦点（上一帧）
+    pub terminal_focused: bool,
+    /// 正在录制快捷键的动作
+    pub keybinding_capture: Option<crate::actions::Action>,
+    pub new_snippet_name: String,
+    pub new_snippet_command: String,
+
     // 设置界面 (v0.3.0)
     pub show_settings: bool,
     pub settings_page: SettingsPage,
+    pub color_scheme_status: String,
+    /// 正在编辑的主题草稿（编辑期间实时预览）
+    pub theme_editor: Option<crate::theme::CustomTheme>,
+    pub theme_editor_status: String,
+    /// 已加载的 CJK 后备字体
+    pub cjk_font: Option<std::path::PathBuf>,
 
     // Tokio 运行时
     pub runtime: Arc<tokio::runtime::Runtime>,
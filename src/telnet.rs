@@ -0,0 +1,520 @@
+use crate::session::SessionBackend;
+use crate::types::Result;
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+// ============================================================================
+// Telnet / 原始 TCP 传输 (v0.4.0)
+// ============================================================================
+
+/// Telnet 命令字节（RFC 854）
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+/// Telnet 选项
+const OPT_ECHO: u8 = 1;
+const OPT_SGA: u8 = 3;
+const OPT_TTYPE: u8 = 24;
+const OPT_NAWS: u8 = 31;
+
+/// TTYPE 子协商
+const TTYPE_IS: u8 = 0;
+const TTYPE_SEND: u8 = 1;
+
+/// 上报给服务器的终端类型
+const TERMINAL_TYPE: &str = "XTERM-256COLOR";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DecodeState {
+    Data,
+    /// 收到 CR，后面的 NUL 需要丢弃
+    CarriageReturn,
+    Iac,
+    /// 收到 IAC WILL/WONT/DO/DONT，等待选项字节
+    Negotiate(u8),
+    Subnegotiation,
+    SubnegotiationIac,
+}
+
+/// Telnet 协议编解码器
+///
+/// 负责剥离与应答选项协商，本身不做 I/O，便于测试。
+/// 客户端支持 NAWS、TTYPE（本端）以及 ECHO、SGA（远端）。
+#[derive(Debug, Clone)]
+pub struct TelnetCodec {
+    state: DecodeState,
+    /// 本端已启用的选项（我方 WILL）
+    local: HashSet<u8>,
+    /// 远端已启用的选项（对方 WILL）
+    remote: HashSet<u8>,
+    /// 我方主动发出、尚未得到回复的 WILL / DO
+    pending_will: HashSet<u8>,
+    pending_do: HashSet<u8>,
+    subnegotiation: Vec<u8>,
+    size: (u16, u16),
+}
+
+impl TelnetCodec {
+    pub fn new(cols: u16, rows: u16) -> Self {
+        Self {
+            state: DecodeState::Data,
+            local: HashSet::new(),
+            remote: HashSet::new(),
+            pending_will: HashSet::new(),
+            pending_do: HashSet::new(),
+            subnegotiation: Vec::new(),
+            size: (cols, rows),
+        }
+    }
+
+    /// 连接建立后主动发出的协商
+    pub fn initial_negotiation(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for opt in [OPT_NAWS, OPT_TTYPE] {
+            self.pending_will.insert(opt);
+            out.extend_from_slice(&[IAC, WILL, opt]);
+        }
+        self.pending_do.insert(OPT_SGA);
+        out.extend_from_slice(&[IAC, DO, OPT_SGA]);
+        out
+    }
+
+    /// 服务器是否负责回显
+    pub fn remote_echo(&self) -> bool {
+        self.remote.contains(&OPT_ECHO)
+    }
+
+    /// 解码服务器数据，返回（终端数据，需要回复给服务器的字节）
+    pub fn decode(&mut self, input: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let mut data = Vec::with_capacity(input.len());
+        let mut reply = Vec::new();
+
+        for &byte in input {
+            self.state = match self.state {
+                DecodeState::Data | DecodeState::CarriageReturn => match byte {
+                    IAC => DecodeState::Iac,
+                    0 if self.state == DecodeState::CarriageReturn => DecodeState::Data,
+                    b'\r' => {
+                        data.push(byte);
+                        DecodeState::CarriageReturn
+                    }
+                    _ => {
+                        data.push(byte);
+                        DecodeState::Data
+                    }
+                },
+                DecodeState::Iac => match byte {
+                    IAC => {
+                        data.push(IAC);
+                        DecodeState::Data
+                    }
+                    WILL | WONT | DO | DONT => DecodeState::Negotiate(byte),
+                    SB => {
+                        self.subnegotiation.clear();
+                        DecodeState::Subnegotiation
+                    }
+                    // NOP、GA 等其他命令直接忽略
+                    _ => DecodeState::Data,
+                },
+                DecodeState::Negotiate(command) => {
+                    self.negotiate(command, byte, &mut reply);
+                    DecodeState::Data
+                }
+                DecodeState::Subnegotiation => match byte {
+                    IAC => DecodeState::SubnegotiationIac,
+                    _ => {
+                        self.subnegotiation.push(byte);
+                        DecodeState::Subnegotiation
+                    }
+                },
+                DecodeState::SubnegotiationIac => match byte {
+                    SE => {
+                        self.subnegotiate(&mut reply);
+                        DecodeState::Data
+                    }
+                    _ => {
+                        // IAC IAC 表示数据中的 255
+                        self.subnegotiation.push(byte);
+                        DecodeState::Subnegotiation
+                    }
+                },
+            };
+        }
+
+        (data, reply)
+    }
+
+    /// 编码用户输入：转义 IAC，单独的 CR 按 RFC 854 发送为 CR NUL
+    pub fn encode(&self, input: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(input.len());
+        for (i, &byte) in input.iter().enumerate() {
+            match byte {
+                IAC => out.extend_from_slice(&[IAC, IAC]),
+                b'\r' if input.get(i + 1) != Some(&b'\n') => out.extend_from_slice(b"\r\0"),
+                _ => out.push(byte),
+            }
+        }
+        out
+    }
+
+    /// 更新窗口尺寸，NAWS 已启用时返回需要发送的子协商
+    pub fn resize(&mut self, cols: u16, rows: u16) -> Option<Vec<u8>> {
+        self.size = (cols, rows);
+        self.local.contains(&OPT_NAWS).then(|| self.naws())
+    }
+
+    fn naws(&self) -> Vec<u8> {
+        let mut out = vec![IAC, SB, OPT_NAWS];
+        for byte in [self.size.0.to_be_bytes(), self.size.1.to_be_bytes()].concat() {
+            out.push(byte);
+            if byte == IAC {
+                out.push(IAC);
+            }
+        }
+        out.extend_from_slice(&[IAC, SE]);
+        out
+    }
+
+    fn negotiate(&mut self, command: u8, opt: u8, reply: &mut Vec<u8>) {
+        match command {
+            DO => {
+                if matches!(opt, OPT_NAWS | OPT_TTYPE) {
+                    let offered = self.pending_will.remove(&opt);
+                    if self.local.insert(opt) && !offered {
+                        reply.extend_from_slice(&[IAC, WILL, opt]);
+                    }
+                    if opt == OPT_NAWS {
+                        reply.extend(self.naws());
+                    }
+                } else {
+                    reply.extend_from_slice(&[IAC, WONT, opt]);
+                }
+            }
+            DONT => {
+                // 只在状态改变时应答，避免协商死循环
+                self.pending_will.remove(&opt);
+                if self.local.remove(&opt) {
+                    reply.extend_from_slice(&[IAC, WONT, opt]);
+                }
+            }
+            WILL => {
+                if matches!(opt, OPT_ECHO | OPT_SGA) {
+                    let requested = self.pending_do.remove(&opt);
+                    if self.remote.insert(opt) && !requested {
+                        reply.extend_from_slice(&[IAC, DO, opt]);
+                    }
+                } else {
+                    reply.extend_from_slice(&[IAC, DONT, opt]);
+                }
+            }
+            WONT => {
+                self.pending_do.remove(&opt);
+                if self.remote.remove(&opt) {
+                    reply.extend_from_slice(&[IAC, DONT, opt]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn subnegotiate(&mut self, reply: &mut Vec<u8>) {
+        if self.subnegotiation.as_slice() == [OPT_TTYPE, TTYPE_SEND] && self.local.contains(&OPT_TTYPE)
+        {
+            reply.extend_from_slice(&[IAC, SB, OPT_TTYPE, TTYPE_IS]);
+            reply.extend_from_slice(TERMINAL_TYPE.as_bytes());
+            reply.extend_from_slice(&[IAC, SE]);
+        }
+    }
+}
+
+/// Telnet 或原始 TCP 会话
+pub struct TelnetSession {
+    stream: TcpStream,
+    /// None 表示原始 TCP 模式（不处理任何协议字节）
+    codec: Option<TelnetCodec>,
+    /// 服务器不回显时由本地回显的输入
+    local_echo: Vec<u8>,
+    closed: bool,
+}
+
+impl TelnetSession {
+    /// 连接到 Telnet 服务器，`raw` 为 true 时使用原始 TCP 模式
+    pub fn connect(host: &str, port: u16, raw: bool, cols: u16, rows: u16) -> Result<Self> {
+        let addr = (host, port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or("Invalid address")?;
+        let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(10))?;
+        stream.set_nodelay(true)?;
+
+        let mut session = Self {
+            stream,
+            codec: (!raw).then(|| TelnetCodec::new(cols, rows)),
+            local_echo: Vec::new(),
+            closed: false,
+        };
+        if let Some(codec) = &mut session.codec {
+            let negotiation = codec.initial_negotiation();
+            session.stream.write_all(&negotiation)?;
+        }
+        session.stream.set_nonblocking(true)?;
+        Ok(session)
+    }
+
+    /// 在非阻塞套接字上写入全部数据
+    fn send(&mut self, mut data: &[u8]) -> Result<()> {
+        while !data.is_empty() {
+            match self.stream.write(data) {
+                Ok(0) => return Err("Connection closed".into()),
+                Ok(n) => data = &data[n..],
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SessionBackend for TelnetSession {
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        let encoded = match &self.codec {
+            Some(codec) => {
+                if !codec.remote_echo() {
+                    for &byte in data {
+                        match byte {
+                            b'\r' => self.local_echo.extend_from_slice(b"\r\n"),
+                            0x7f => self.local_echo.extend_from_slice(b"\x08 \x08"),
+                            _ => self.local_echo.push(byte),
+                        }
+                    }
+                }
+                codec.encode(data)
+            }
+            None => data.to_vec(),
+        };
+        self.send(&encoded)
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if !self.local_echo.is_empty() {
+            let n = buf.len().min(self.local_echo.len());
+            buf[..n].copy_from_slice(&self.local_echo[..n]);
+            self.local_echo.drain(..n);
+            return Ok(n);
+        }
+
+        let n = match self.stream.read(buf) {
+            Ok(0) => {
+                self.closed = true;
+                return Ok(0);
+            }
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let Some(codec) = &mut self.codec else {
+            return Ok(n);
+        };
+        let (data, reply) = codec.decode(&buf[..n]);
+        if !reply.is_empty() {
+            self.send(&reply)?;
+        }
+        // 解码后的数据不会比原始数据长
+        buf[..data.len()].copy_from_slice(&data);
+        Ok(data.len())
+    }
+
+    fn resize(&mut self, cols: u16, rows: u16) -> Result<()> {
+        let naws = self.codec.as_mut().and_then(|c| c.resize(cols, rows));
+        match naws {
+            Some(naws) => self.send(&naws),
+            None => Ok(()),
+        }
+    }
+
+    fn is_active(&mut self) -> bool {
+        !self.closed
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.closed = true;
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    #[test]
+    fn test_codec_strips_commands_and_unescapes() {
+        let mut codec = TelnetCodec::new(80, 24);
+        let (data, reply) = codec.decode(&[b'a', IAC, IAC, b'b', IAC, 241, b'c', b'\r', 0, b'\n']);
+        assert_eq!(data, vec![b'a', IAC, b'b', b'c', b'\r', b'\n']);
+        assert!(reply.is_empty());
+    }
+
+    #[test]
+    fn test_codec_negotiation() {
+        let mut codec = TelnetCodec::new(80, 24);
+        let initial = codec.initial_negotiation();
+        assert_eq!(initial, vec![IAC, WILL, OPT_NAWS, IAC, WILL, OPT_TTYPE, IAC, DO, OPT_SGA]);
+
+        // 服务器接受我方提议：NAWS 立即发送窗口尺寸，不重复 WILL
+        let (_, reply) = codec.decode(&[IAC, DO, OPT_NAWS, IAC, WILL, OPT_SGA]);
+        assert_eq!(reply, vec![IAC, SB, OPT_NAWS, 0, 80, 0, 24, IAC, SE]);
+
+        // 服务器主动回显
+        let (_, reply) = codec.decode(&[IAC, WILL, OPT_ECHO]);
+        assert_eq!(reply, vec![IAC, DO, OPT_ECHO]);
+        assert!(codec.remote_echo());
+
+        // 不支持的选项被拒绝
+        let (_, reply) = codec.decode(&[IAC, DO, 39, IAC, WILL, 42]);
+        assert_eq!(reply, vec![IAC, WONT, 39, IAC, DONT, 42]);
+
+        // 终端类型查询
+        let (_, reply) = codec.decode(&[IAC, DO, OPT_TTYPE, IAC, SB, OPT_TTYPE, TTYPE_SEND, IAC, SE]);
+        let mut expected = vec![IAC, SB, OPT_TTYPE, TTYPE_IS];
+        expected.extend_from_slice(TERMINAL_TYPE.as_bytes());
+        expected.extend_from_slice(&[IAC, SE]);
+        assert_eq!(reply, expected);
+
+        // 尺寸中的 255 需要转义
+        assert_eq!(
+            codec.resize(255, 50),
+            Some(vec![IAC, SB, OPT_NAWS, 0, IAC, IAC, 0, 50, IAC, SE])
+        );
+    }
+
+    #[test]
+    fn test_codec_encode() {
+        let codec = TelnetCodec::new(80, 24);
+        assert_eq!(codec.encode(b"ls\r"), b"ls\r\0".to_vec());
+        assert_eq!(codec.encode(b"a\r\nb"), b"a\r\nb".to_vec());
+        assert_eq!(codec.encode(&[IAC]), vec![IAC, IAC]);
+    }
+
+    /// 进程内 Telnet 回显服务器：请求 NAWS/TTYPE、声明 ECHO，并回显数据字节
+    fn spawn_echo_server() -> (u16, mpsc::Receiver<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .write_all(&[
+                    IAC, DO, OPT_NAWS, IAC, DO, OPT_TTYPE, IAC, WILL, OPT_ECHO, IAC, WILL, OPT_SGA,
+                    IAC, SB, OPT_TTYPE, TTYPE_SEND, IAC, SE,
+                ])
+                .unwrap();
+
+            let mut buf = [0u8; 1024];
+            let mut in_command = 0;
+            loop {
+                let n = match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => n,
+                };
+                let _ = tx.send(buf[..n].to_vec());
+
+                // 粗略跳过协商字节，只回显普通数据
+                let mut echo = Vec::new();
+                for &byte in &buf[..n] {
+                    if in_command > 0 {
+                        in_command -= 1;
+                        if byte == SB {
+                            in_command = usize::MAX;
+                        } else if byte == SE {
+                            in_command = 0;
+                        }
+                    } else if byte == IAC {
+                        in_command = 2;
+                    } else if byte != 0 {
+                        echo.push(byte);
+                    }
+                }
+                let _ = stream.write_all(&echo);
+            }
+        });
+
+        (port, rx)
+    }
+
+    fn read_until(session: &mut TelnetSession, needle: &[u8]) -> Vec<u8> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut output = Vec::new();
+        let mut buf = [0u8; 1024];
+        while Instant::now() < deadline && !output.windows(needle.len()).any(|w| w == needle) {
+            let n = session.read(&mut buf).unwrap();
+            if n == 0 {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            output.extend_from_slice(&buf[..n]);
+        }
+        output
+    }
+
+    #[test]
+    fn test_telnet_session_against_echo_server() {
+        let (port, received) = spawn_echo_server();
+        let mut session = TelnetSession::connect("127.0.0.1", port, false, 100, 40).unwrap();
+
+        // 等待协商完成（服务器声明 ECHO）
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut buf = [0u8; 256];
+        while !session.codec.as_ref().unwrap().remote_echo() && Instant::now() < deadline {
+            session.read(&mut buf).unwrap();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(session.codec.as_ref().unwrap().remote_echo());
+
+        session.write(b"show version\r").unwrap();
+        let output = read_until(&mut session, b"show version");
+        assert!(output.ends_with(b"show version\r"), "output: {:?}", output);
+
+        // 服务器收到了窗口尺寸与终端类型
+        let all: Vec<u8> = received.try_iter().flatten().collect();
+        let naws = [IAC, SB, OPT_NAWS, 0, 100, 0, 40, IAC, SE];
+        assert!(all.windows(naws.len()).any(|w| w == naws));
+        assert!(all.windows(TERMINAL_TYPE.len()).any(|w| w == TERMINAL_TYPE.as_bytes()));
+
+        session.close().unwrap();
+        assert!(!session.is_active());
+    }
+
+    #[test]
+    fn test_raw_tcp_mode_passes_bytes_through() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(&[b'>', IAC, b'<']).unwrap();
+            let mut buf = [0u8; 16];
+            let n = stream.read(&mut buf).unwrap();
+            stream.write_all(&buf[..n]).unwrap();
+        });
+
+        let mut session = TelnetSession::connect("127.0.0.1", port, true, 80, 24).unwrap();
+        assert_eq!(read_until(&mut session, b"<"), vec![b'>', IAC, b'<']);
+
+        // 原始模式下不转换 CR，也不做本地回显
+        session.write(b"ping\r").unwrap();
+        assert_eq!(read_until(&mut session, b"\r"), b"ping\r".to_vec());
+    }
+}
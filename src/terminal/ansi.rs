@@ -0,0 +1,482 @@
+// ============================================================================
+// ANSI 转义序列解析器
+// ============================================================================
+//
+// 将远程输出拆分为可打印字符、控制字符、CSI / OSC / ESC 序列。
+// 解析器保存中间状态，因此一个序列被拆分到多个数据块时也能正确处理。
+
+/// OSC 字符串的最大长度，超出部分被丢弃
+const MAX_OSC_LEN: usize = 1024 * 1024;
+
+/// CSI 参数的最大长度
+const MAX_CSI_LEN: usize = 256;
+
+/// 终端颜色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TermColor {
+    /// 默认前景/背景色
+    #[default]
+    Default,
+    /// 256 色调色板索引（0-15 为基本色）
+    Indexed(u8),
+    /// 24 位真彩色
+    Rgb(u8, u8, u8),
+}
+
+/// 单元格文本样式（由 SGR 序列控制）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CellStyle {
+    pub fg: TermColor,
+    pub bg: TermColor,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+    pub hidden: bool,
+    pub strikethrough: bool,
+}
+
+impl CellStyle {
+    /// 应用 SGR (Select Graphic Rendition) 参数
+    ///
+    /// 同时支持 `38;5;n` / `38;2;r;g;b` 分号形式和 `38:2::r:g:b` 冒号形式。
+    pub fn apply_sgr(&mut self, params: &[Vec<u16>]) {
+        if params.is_empty() {
+            *self = CellStyle::default();
+            return;
+        }
+
+        let mut i = 0;
+        while i < params.len() {
+            let group = &params[i];
+            let code = group.first().copied().unwrap_or(0);
+
+            // 冒号形式的子参数
+            if group.len() > 1 {
+                match code {
+                    38 => self.fg = parse_extended_color(&group[1..], true).unwrap_or(self.fg),
+                    48 => self.bg = parse_extended_color(&group[1..], true).unwrap_or(self.bg),
+                    4 => self.underline = group[1] != 0,
+                    _ => {}
+                }
+                i += 1;
+                continue;
+            }
+
+            match code {
+                0 => *self = CellStyle::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 | 21 => self.underline = true,
+                7 => self.inverse = true,
+                8 => self.hidden = true,
+                9 => self.strikethrough = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.inverse = false,
+                28 => self.hidden = false,
+                29 => self.strikethrough = false,
+                30..=37 => self.fg = TermColor::Indexed((code - 30) as u8),
+                39 => self.fg = TermColor::Default,
+                40..=47 => self.bg = TermColor::Indexed((code - 40) as u8),
+                49 => self.bg = TermColor::Default,
+                90..=97 => self.fg = TermColor::Indexed((code - 90 + 8) as u8),
+                100..=107 => self.bg = TermColor::Indexed((code - 100 + 8) as u8),
+                38 | 48 | 58 => {
+                    // 分号形式：后续参数属于同一个颜色
+                    let rest: Vec<u16> = params[i + 1..]
+                        .iter()
+                        .map(|g| g.first().copied().unwrap_or(0))
+                        .collect();
+                    let consumed = match rest.first() {
+                        Some(5) => 2,
+                        Some(2) => 4,
+                        _ => rest.len(),
+                    };
+                    if let Some(color) = parse_extended_color(&rest, false) {
+                        match code {
+                            38 => self.fg = color,
+                            48 => self.bg = color,
+                            _ => {} // 下划线颜色暂不支持
+                        }
+                    }
+                    i += consumed.min(rest.len());
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+/// 解析扩展颜色参数（`5;n` 或 `2;r;g;b`）
+///
+/// 冒号形式下 `2` 后面可能带有颜色空间 ID（`2::r:g:b`）。
+fn parse_extended_color(params: &[u16], colon_form: bool) -> Option<TermColor> {
+    match params.first()? {
+        5 => params.get(1).map(|n| TermColor::Indexed((*n).min(255) as u8)),
+        2 => {
+            let rgb = if colon_form && params.len() >= 5 {
+                &params[2..5]
+            } else {
+                params.get(1..4)?
+            };
+            Some(TermColor::Rgb(
+                rgb[0].min(255) as u8,
+                rgb[1].min(255) as u8,
+                rgb[2].min(255) as u8,
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// CSI 序列（`ESC [ ... final`）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsiSequence {
+    /// 私有前缀，如 `?`、`>`、`<`
+    pub private: Option<char>,
+    /// 参数；每个参数可包含冒号分隔的子参数
+    pub params: Vec<Vec<u16>>,
+    pub intermediates: String,
+    pub action: char,
+}
+
+impl CsiSequence {
+    /// 获取第 `index` 个参数，缺省或为 0 时返回 `default`
+    pub fn param(&self, index: usize, default: u16) -> u16 {
+        match self.params.get(index).and_then(|g| g.first()) {
+            Some(0) | None => default,
+            Some(v) => *v,
+        }
+    }
+}
+
+/// 解析器输出的动作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnsiAction {
+    /// 可打印字符
+    Print(char),
+    /// C0 控制字符（`\n`、`\r`、`\x08`、`\t`、`\x07` 等）
+    Control(char),
+    /// CSI 序列
+    Csi(CsiSequence),
+    /// OSC 字符串（不含 `ESC ]` 与终止符）
+    Osc(String),
+    /// 其他 ESC 序列
+    Esc { intermediates: String, action: char },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParserState {
+    Ground,
+    Escape,
+    EscapeIntermediate,
+    Csi,
+    Osc,
+    OscEscape,
+    IgnoreString,
+    IgnoreStringEscape,
+}
+
+/// 增量式 ANSI 解析器
+#[derive(Debug, Clone)]
+pub struct AnsiParser {
+    state: ParserState,
+    params: String,
+    private: Option<char>,
+    intermediates: String,
+    osc: String,
+}
+
+impl AnsiParser {
+    pub fn new() -> Self {
+        Self {
+            state: ParserState::Ground,
+            params: String::new(),
+            private: None,
+            intermediates: String::new(),
+            osc: String::new(),
+        }
+    }
+
+    /// 解析一段输入，返回识别出的动作
+    pub fn advance(&mut self, input: &str) -> Vec<AnsiAction> {
+        let mut actions = Vec::new();
+        for ch in input.chars() {
+            self.advance_char(ch, &mut actions);
+        }
+        actions
+    }
+
+    fn advance_char(&mut self, ch: char, actions: &mut Vec<AnsiAction>) {
+        match self.state {
+            ParserState::Ground => match ch {
+                '\x1b' => self.enter_escape(),
+                '\x7f' => {}
+                c if (c as u32) < 0x20 => actions.push(AnsiAction::Control(c)),
+                c => actions.push(AnsiAction::Print(c)),
+            },
+            ParserState::Escape => match ch {
+                '[' => {
+                    self.params.clear();
+                    self.private = None;
+                    self.intermediates.clear();
+                    self.state = ParserState::Csi;
+                }
+                ']' => {
+                    self.osc.clear();
+                    self.state = ParserState::Osc;
+                }
+                'P' | 'X' | '^' | '_' => self.state = ParserState::IgnoreString,
+                '\x1b' => {}
+                c if (0x20..=0x2f).contains(&(c as u32)) => {
+                    self.intermediates.push(c);
+                    self.state = ParserState::EscapeIntermediate;
+                }
+                c if (c as u32) < 0x20 => actions.push(AnsiAction::Control(c)),
+                c => {
+                    actions.push(AnsiAction::Esc {
+                        intermediates: String::new(),
+                        action: c,
+                    });
+                    self.state = ParserState::Ground;
+                }
+            },
+            ParserState::EscapeIntermediate => match ch {
+                c if (0x20..=0x2f).contains(&(c as u32)) => self.intermediates.push(c),
+                '\x1b' => self.enter_escape(),
+                c => {
+                    actions.push(AnsiAction::Esc {
+                        intermediates: std::mem::take(&mut self.intermediates),
+                        action: c,
+                    });
+                    self.state = ParserState::Ground;
+                }
+            },
+            ParserState::Csi => match ch {
+                '0'..='9' | ';' | ':' => {
+                    if self.params.len() < MAX_CSI_LEN {
+                        self.params.push(ch);
+                    }
+                }
+                '<' | '=' | '>' | '?' if self.params.is_empty() && self.private.is_none() => {
+                    self.private = Some(ch);
+                }
+                c if (0x20..=0x2f).contains(&(c as u32)) => self.intermediates.push(c),
+                c if (0x40..=0x7e).contains(&(c as u32)) => {
+                    actions.push(AnsiAction::Csi(CsiSequence {
+                        private: self.private.take(),
+                        params: parse_params(&self.params),
+                        intermediates: std::mem::take(&mut self.intermediates),
+                        action: c,
+                    }));
+                    self.state = ParserState::Ground;
+                }
+                '\x1b' => self.enter_escape(),
+                c if (c as u32) < 0x20 => actions.push(AnsiAction::Control(c)),
+                _ => {
+                    // 非法字符，丢弃整个序列
+                    self.state = ParserState::Ground;
+                }
+            },
+            ParserState::Osc => match ch {
+                '\x07' => {
+                    actions.push(AnsiAction::Osc(std::mem::take(&mut self.osc)));
+                    self.state = ParserState::Ground;
+                }
+                '\x1b' => self.state = ParserState::OscEscape,
+                c => {
+                    if self.osc.len() < MAX_OSC_LEN {
+                        self.osc.push(c);
+                    }
+                }
+            },
+            ParserState::OscEscape => {
+                actions.push(AnsiAction::Osc(std::mem::take(&mut self.osc)));
+                self.state = ParserState::Ground;
+                if ch != '\\' {
+                    // ESC 开始了新的序列
+                    self.enter_escape();
+                    self.advance_char(ch, actions);
+                }
+            }
+            ParserState::IgnoreString => match ch {
+                '\x1b' => self.state = ParserState::IgnoreStringEscape,
+                '\x07' => self.state = ParserState::Ground,
+                _ => {}
+            },
+            ParserState::IgnoreStringEscape => {
+                self.state = if ch == '\\' {
+                    ParserState::Ground
+                } else {
+                    ParserState::IgnoreString
+                };
+            }
+        }
+    }
+
+    fn enter_escape(&mut self) {
+        self.intermediates.clear();
+        self.state = ParserState::Escape;
+    }
+}
+
+impl Default for AnsiParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 解析 CSI 参数字符串（`1;38:2::255:0:0`）
+fn parse_params(raw: &str) -> Vec<Vec<u16>> {
+    if raw.is_empty() {
+        return Vec::new();
+    }
+    raw.split(';')
+        .map(|group| {
+            group
+                .split(':')
+                .map(|p| p.parse::<u32>().unwrap_or(0).min(u16::MAX as u32) as u16)
+                .collect()
+        })
+        .collect()
+}
+
+/// 去除字符串中的所有转义序列，只保留文本与换行
+pub fn strip_ansi(input: &str) -> String {
+    let mut parser = AnsiParser::new();
+    let mut output = String::with_capacity(input.len());
+    for action in parser.advance(input) {
+        match action {
+            AnsiAction::Print(c) => output.push(c),
+            AnsiAction::Control(c @ ('\n' | '\t')) => output.push(c),
+            _ => {}
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sgr(input: &str) -> CellStyle {
+        let mut parser = AnsiParser::new();
+        let mut style = CellStyle::default();
+        for action in parser.advance(input) {
+            if let AnsiAction::Csi(csi) = action {
+                if csi.action == 'm' {
+                    style.apply_sgr(&csi.params);
+                }
+            }
+        }
+        style
+    }
+
+    #[test]
+    fn test_print_and_control() {
+        let mut parser = AnsiParser::new();
+        let actions = parser.advance("a\r\n");
+        assert_eq!(
+            actions,
+            vec![
+                AnsiAction::Print('a'),
+                AnsiAction::Control('\r'),
+                AnsiAction::Control('\n')
+            ]
+        );
+    }
+
+    #[test]
+    fn test_basic_attributes() {
+        let style = sgr("\x1b[1;3;4;7m");
+        assert!(style.bold && style.italic && style.underline && style.inverse);
+
+        let style = sgr("\x1b[1;3m\x1b[22;23m");
+        assert!(!style.bold && !style.italic);
+
+        assert_eq!(sgr("\x1b[1;31m\x1b[0m"), CellStyle::default());
+        assert_eq!(sgr("\x1b[1;31m\x1b[m"), CellStyle::default());
+    }
+
+    #[test]
+    fn test_16_colors() {
+        let style = sgr("\x1b[31;42m");
+        assert_eq!(style.fg, TermColor::Indexed(1));
+        assert_eq!(style.bg, TermColor::Indexed(2));
+
+        let style = sgr("\x1b[94;103m");
+        assert_eq!(style.fg, TermColor::Indexed(12));
+        assert_eq!(style.bg, TermColor::Indexed(11));
+
+        let style = sgr("\x1b[31m\x1b[39m");
+        assert_eq!(style.fg, TermColor::Default);
+    }
+
+    #[test]
+    fn test_256_and_truecolor() {
+        let style = sgr("\x1b[38;5;208;48;2;10;20;30;1m");
+        assert_eq!(style.fg, TermColor::Indexed(208));
+        assert_eq!(style.bg, TermColor::Rgb(10, 20, 30));
+        assert!(style.bold);
+
+        let style = sgr("\x1b[38:2::255:128:0m");
+        assert_eq!(style.fg, TermColor::Rgb(255, 128, 0));
+
+        let style = sgr("\x1b[48:5:17m");
+        assert_eq!(style.bg, TermColor::Indexed(17));
+    }
+
+    #[test]
+    fn test_split_sequence() {
+        let mut parser = AnsiParser::new();
+        assert!(parser.advance("\x1b[3").is_empty());
+        let actions = parser.advance("1mX");
+        assert_eq!(actions.len(), 2);
+        assert!(matches!(&actions[0], AnsiAction::Csi(csi) if csi.action == 'm' && csi.params == vec![vec![31]]));
+        assert_eq!(actions[1], AnsiAction::Print('X'));
+    }
+
+    #[test]
+    fn test_osc_terminators() {
+        let mut parser = AnsiParser::new();
+        let actions = parser.advance("\x1b]0;title\x07\x1b]2;other\x1b\\x");
+        assert_eq!(
+            actions,
+            vec![
+                AnsiAction::Osc("0;title".to_string()),
+                AnsiAction::Osc("2;other".to_string()),
+                AnsiAction::Print('x'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_private_csi() {
+        let mut parser = AnsiParser::new();
+        let actions = parser.advance("\x1b[?25l");
+        match &actions[0] {
+            AnsiAction::Csi(csi) => {
+                assert_eq!(csi.private, Some('?'));
+                assert_eq!(csi.param(0, 0), 25);
+                assert_eq!(csi.action, 'l');
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(
+            strip_ansi("\x1b[01;34mdir\x1b[0m  file\r\n"),
+            "dir  file\n"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use super::buffer::Point;
+use std::time::{Duration, Instant};
+
+// ============================================================================
+// 命令块（OSC 133 语义提示符标记）
+// ============================================================================
+
+/// 由 shell 集成的 OSC 133 标记划分出的一条命令
+///
+/// `A` 标记提示符开始，`B` 标记命令输入开始，`C` 标记命令开始执行
+/// （输出开始），`D;<退出码>` 标记命令结束。行号与 [`Point`] 一样包含回滚历史。
+#[derive(Debug, Clone)]
+pub struct CommandBlock {
+    /// 提示符所在行
+    pub prompt_line: usize,
+    /// 命令输入的起点（提示符结束处）
+    pub input_start: Option<Point>,
+    /// 输出的行范围 `[start, end)`，命令结束前 end 为 None
+    pub output_start: Option<usize>,
+    pub output_end: Option<usize>,
+    /// 用户输入的命令
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub started_at: Option<Instant>,
+    pub duration: Option<Duration>,
+    /// 输出是否折叠
+    pub folded: bool,
+}
+
+impl CommandBlock {
+    pub(crate) fn new(prompt_line: usize) -> Self {
+        Self {
+            prompt_line,
+            input_start: None,
+            output_start: None,
+            output_end: None,
+            command: String::new(),
+            exit_code: None,
+            started_at: None,
+            duration: None,
+            folded: false,
+        }
+    }
+
+    /// 命令已开始执行但尚未结束
+    pub fn is_running(&self) -> bool {
+        self.output_start.is_some() && self.output_end.is_none()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.output_end.is_some()
+    }
+
+    /// 命令所在行（没有 B 标记时取提示符行）
+    pub fn command_line(&self) -> usize {
+        self.input_start.map_or(self.prompt_line, |p| p.line)
+    }
+
+    /// 块占据的最后一行（不含）
+    pub fn end_line(&self, line_count: usize) -> usize {
+        self.output_end.unwrap_or(line_count)
+    }
+
+    /// 已结束且有输出行的块可以折叠
+    pub fn output_range(&self) -> Option<std::ops::Range<usize>> {
+        let range = self.output_start?..self.output_end?;
+        (!range.is_empty()).then_some(range)
+    }
+
+    /// 状态摘要，如 `✔ 1.2s`、`✘ 127 · 15ms`
+    pub fn status_text(&self) -> String {
+        let duration = self.duration.map(format_duration);
+        match (self.exit_code, duration) {
+            (Some(0), Some(d)) => format!("✔ {}", d),
+            (Some(code), Some(d)) => format!("✘ {} · {}", code, d),
+            (None, Some(d)) => format!("● {}", d),
+            (_, None) if self.is_running() => "⏳".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// 行号整体前移 `n` 行（回滚历史被裁剪），块的提示符被移出时返回 false
+    pub(crate) fn shift_up(&mut self, n: usize) -> bool {
+        let Some(prompt_line) = self.prompt_line.checked_sub(n) else {
+            return false;
+        };
+        self.prompt_line = prompt_line;
+        if let Some(point) = &mut self.input_start {
+            point.line -= n;
+        }
+        for line in [&mut self.output_start, &mut self.output_end].into_iter().flatten() {
+            *line -= n;
+        }
+        true
+    }
+}
+
+/// 紧凑的耗时显示
+pub fn format_duration(duration: Duration) -> String {
+    let ms = duration.as_millis();
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else if ms < 60_000 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        let secs = duration.as_secs();
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    }
+}
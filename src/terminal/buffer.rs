@@ -0,0 +1,1844 @@
+use super::ansi::{AnsiAction, AnsiParser, CellStyle, CsiSequence, TermColor};
+use super::blocks::CommandBlock;
+use super::mouse::MouseTracking;
+use base64::{engine::general_purpose, Engine as _};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use std::collections::VecDeque;
+
+/// 制表位宽度
+const TAB_WIDTH: usize = 8;
+
+/// 默认终端尺寸
+pub const DEFAULT_COLS: usize = 80;
+pub const DEFAULT_ROWS: usize = 24;
+
+/// 终端单元格
+///
+/// 每个单元格保存一个字符簇：首字符加上附着其后的组合字符。宽字符
+/// （CJK、emoji）占两列，右半部分由宽度为 0 的占位单元格填充。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    /// 组合附加符号、变体选择符、零宽连接序列等（大多数单元格为空）
+    pub combining: Option<Box<str>>,
+    /// 显示宽度：1 或 2，宽字符的占位单元格为 0
+    pub width: u8,
+    pub style: CellStyle,
+    /// OSC 8 超链接编号，0 表示无链接（见 [`TerminalBuffer::hyperlink`]）
+    pub link: u32,
+}
+
+impl Cell {
+    fn blank(style: CellStyle) -> Self {
+        Self {
+            ch: ' ',
+            combining: None,
+            width: 1,
+            style,
+            link: 0,
+        }
+    }
+
+    /// 宽字符右半部分的占位单元格
+    fn spacer(style: CellStyle) -> Self {
+        Self {
+            width: 0,
+            ..Self::blank(style)
+        }
+    }
+
+    pub fn is_spacer(&self) -> bool {
+        self.width == 0
+    }
+
+    /// 单元格的完整字符簇
+    pub fn push_to(&self, text: &mut String) {
+        text.push(self.ch);
+        if let Some(combining) = &self.combining {
+            text.push_str(combining);
+        }
+    }
+}
+
+/// 字符的显示宽度（控制字符与组合字符为 0，东亚宽字符为 2）
+pub fn char_width(c: char) -> usize {
+    UnicodeWidthChar::width(c).unwrap_or(0)
+}
+
+/// 字符串在终端中的显示宽度
+pub fn str_width(s: &str) -> usize {
+    s.graphemes(true).map(cluster_width).sum()
+}
+
+/// 字符簇的显示宽度（1 或 2，纯零宽字符簇为 0）
+fn cluster_width(cluster: &str) -> usize {
+    UnicodeWidthStr::width(cluster).min(2)
+}
+
+/// 终端中的一行
+#[derive(Debug, Clone, Default)]
+pub struct TerminalLine {
+    pub cells: Vec<Cell>,
+    /// 该行因自动换行而延续到下一行
+    pub wrapped: bool,
+}
+
+impl TerminalLine {
+    /// 行的纯文本内容
+    pub fn text(&self) -> String {
+        self.text_in(0..self.cells.len())
+    }
+
+    /// 指定列范围内的文本（跳过宽字符占位单元格）
+    pub fn text_in(&self, range: std::ops::Range<usize>) -> String {
+        let end = range.end.min(self.cells.len());
+        // 从宽字符的右半部分开始时包含整个字符
+        let mut start = range.start.min(end);
+        if start > 0 && start < end && self.cells[start].is_spacer() {
+            start -= 1;
+        }
+        let mut text = String::new();
+        for cell in &self.cells[start..end] {
+            if !cell.is_spacer() {
+                cell.push_to(&mut text);
+            }
+        }
+        text
+    }
+
+    /// `text()` 中字节范围对应的列范围（用于把正则匹配映射回单元格）
+    pub fn column_range(&self, bytes: std::ops::Range<usize>) -> std::ops::Range<usize> {
+        let (mut offset, mut start, mut end) = (0, None, 0);
+        let mut text = String::new();
+        for (col, cell) in self.cells.iter().enumerate() {
+            if cell.is_spacer() {
+                continue;
+            }
+            text.clear();
+            cell.push_to(&mut text);
+            let next = offset + text.len();
+            if next > bytes.start && offset < bytes.end {
+                start.get_or_insert(col);
+                end = col + cell.width.max(1) as usize;
+            }
+            offset = next;
+        }
+        start.map_or(0..0, |start| start..end)
+    }
+
+    /// 将连续相同样式的单元格合并为片段
+    pub fn spans(&self) -> Vec<(String, CellStyle)> {
+        let mut spans: Vec<(String, CellStyle)> = Vec::new();
+        for cell in self.cells.iter().filter(|c| !c.is_spacer()) {
+            match spans.last_mut() {
+                Some((text, style)) if *style == cell.style => cell.push_to(text),
+                _ => {
+                    let mut text = String::new();
+                    cell.push_to(&mut text);
+                    spans.push((text, cell.style));
+                }
+            }
+        }
+        spans
+    }
+
+    /// 修复被截断的宽字符：没有占位的宽字符与失去宽字符的占位都变为空白
+    fn repair_wide(&mut self) {
+        let len = self.cells.len();
+        for i in 0..len {
+            let orphan = match self.cells[i].width {
+                0 => i == 0 || self.cells[i - 1].width != 2,
+                2 => i + 1 >= len || !self.cells[i + 1].is_spacer(),
+                _ => false,
+            };
+            if orphan {
+                self.cells[i] = Cell::blank(self.cells[i].style);
+            }
+        }
+    }
+}
+
+/// 光标位置（屏幕坐标，从 0 开始）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Cursor {
+    pub row: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SavedCursor {
+    cursor: Cursor,
+    style: CellStyle,
+    graphics_charset: bool,
+}
+
+/// 全部行中的位置（行索引包含回滚历史）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Point {
+    pub line: usize,
+    pub col: usize,
+}
+
+/// 文本选区（锚点为拖动起点，头部跟随鼠标）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    pub anchor: Point,
+    pub head: Point,
+}
+
+impl Selection {
+    /// 按先后顺序返回（起点，终点），终点包含在内
+    pub fn range(&self) -> (Point, Point) {
+        if self.anchor <= self.head {
+            (self.anchor, self.head)
+        } else {
+            (self.head, self.anchor)
+        }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        let (start, end) = self.range();
+        start <= point && point <= end
+    }
+}
+
+/// 程序通过 OSC 52 发起的剪贴板请求
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardRequest {
+    /// 写入剪贴板
+    Set(String),
+    /// 读取剪贴板（应答需带上原始的选择区参数）
+    Get { selection: String },
+}
+
+/// 终端模式（由 SM/RM 与 DECSET/DECRST 控制）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalModes {
+    /// LNM：换行同时回到行首（无 PTY 的日志输出使用）
+    pub newline_mode: bool,
+    /// DECAWM：自动换行
+    pub autowrap: bool,
+    /// DECTCEM：显示光标
+    pub cursor_visible: bool,
+    /// DECCKM：应用光标键模式
+    pub app_cursor_keys: bool,
+    /// 当前处于备用屏幕
+    pub alt_screen: bool,
+    /// 括号粘贴模式（?2004）
+    pub bracketed_paste: bool,
+    /// 鼠标跟踪模式（?9 / ?1000 / ?1002 / ?1003）
+    pub mouse_tracking: MouseTracking,
+    /// SGR 鼠标编码（?1006）
+    pub sgr_mouse: bool,
+}
+
+impl Default for TerminalModes {
+    fn default() -> Self {
+        Self {
+            newline_mode: false,
+            autowrap: true,
+            cursor_visible: true,
+            app_cursor_keys: false,
+            alt_screen: false,
+            bracketed_paste: false,
+            mouse_tracking: MouseTracking::Off,
+            sgr_mouse: false,
+        }
+    }
+}
+
+/// 终端缓冲区
+///
+/// 一个 xterm 兼容的屏幕网格加回滚历史：解析 ANSI 输出，维护光标、
+/// 滚动区域与备用屏幕，并按单元格保存文本与样式，供终端面板渲染。
+#[derive(Debug, Clone)]
+pub struct TerminalBuffer {
+    scrollback: VecDeque<TerminalLine>,
+    screen: Vec<TerminalLine>,
+    /// 进入备用屏幕时保存的主屏幕
+    main_screen: Option<Vec<TerminalLine>>,
+    rows: usize,
+    cols: usize,
+    cursor: Cursor,
+    saved_cursor: Option<SavedCursor>,
+    /// 光标位于最后一列且已写入字符，下一个字符需要先换行
+    wrap_pending: bool,
+    scroll_top: usize,
+    scroll_bottom: usize,
+    style: CellStyle,
+    graphics_charset: bool,
+    parser: AnsiParser,
+    max_lines: usize,
+    pub modes: TerminalModes,
+    title: Option<String>,
+    bell: bool,
+    /// 需要回写给远端的应答（DSR、DA 等）
+    responses: Vec<u8>,
+    clipboard_requests: Vec<ClipboardRequest>,
+    selection: Option<Selection>,
+    /// OSC 133 划分的命令块
+    blocks: Vec<CommandBlock>,
+    /// 已结束、尚未被取走的命令（命令，退出码）
+    finished_commands: Vec<(String, Option<i32>)>,
+    /// 尚未凑成完整 UTF-8 字符的字节
+    utf8_pending: Vec<u8>,
+    /// 创建以来从开头丢弃的行数（回滚裁剪与清空）
+    dropped_lines: usize,
+    /// OSC 8 超链接地址，单元格中的编号从 1 开始
+    hyperlinks: Vec<String>,
+    /// 当前写入字符使用的超链接编号
+    current_link: u32,
+    /// OSC 7 报告的工作目录
+    working_directory: Option<String>,
+}
+
+impl TerminalBuffer {
+    /// 创建新的终端缓冲区（`max_lines` 为回滚行数上限）
+    pub fn new(max_lines: usize) -> Self {
+        Self::with_size(max_lines, DEFAULT_COLS, DEFAULT_ROWS)
+    }
+
+    /// 创建指定尺寸的终端缓冲区
+    pub fn with_size(max_lines: usize, cols: usize, rows: usize) -> Self {
+        let cols = cols.max(1);
+        let rows = rows.max(1);
+        Self {
+            scrollback: VecDeque::new(),
+            screen: vec![TerminalLine::default(); rows],
+            main_screen: None,
+            rows,
+            cols,
+            cursor: Cursor::default(),
+            saved_cursor: None,
+            wrap_pending: false,
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            style: CellStyle::default(),
+            graphics_charset: false,
+            parser: AnsiParser::new(),
+            max_lines: max_lines.max(1),
+            modes: TerminalModes::default(),
+            title: None,
+            bell: false,
+            responses: Vec::new(),
+            clipboard_requests: Vec::new(),
+            selection: None,
+            blocks: Vec::new(),
+            finished_commands: Vec::new(),
+            utf8_pending: Vec::new(),
+            dropped_lines: 0,
+            hyperlinks: Vec::new(),
+            current_link: 0,
+            working_directory: None,
+        }
+    }
+
+    /// 创建用于日志输出的缓冲区（`\n` 同时回到行首）
+    pub fn log(max_lines: usize) -> Self {
+        let mut buffer = Self::new(max_lines);
+        buffer.modes.newline_mode = true;
+        buffer
+    }
+
+    /// 设置回滚行数上限
+    pub fn set_max_lines(&mut self, max_lines: usize) {
+        self.max_lines = max_lines.max(1);
+        self.trim_scrollback();
+    }
+
+    /// 写入一段（可能包含转义序列的）输出
+    pub fn feed(&mut self, text: &str) {
+        for action in self.parser.advance(text) {
+            match action {
+                AnsiAction::Print(c) => self.put_char(c),
+                AnsiAction::Control(c) => self.control(c),
+                AnsiAction::Csi(csi) => self.csi(&csi),
+                AnsiAction::Osc(osc) => self.osc(&osc),
+                AnsiAction::Esc {
+                    intermediates,
+                    action,
+                } => self.esc(&intermediates, action),
+            }
+        }
+    }
+
+    /// 写入原始字节，跨数据块的 UTF-8 字符会被正确拼接
+    pub fn feed_bytes(&mut self, data: &[u8]) {
+        let mut bytes = std::mem::take(&mut self.utf8_pending);
+        bytes.extend_from_slice(data);
+
+        let mut rest = bytes.as_slice();
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    self.feed(text);
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    // valid_up_to 之前的字节保证是合法 UTF-8
+                    self.feed(std::str::from_utf8(valid).unwrap_or_default());
+                    match e.error_len() {
+                        Some(len) => {
+                            self.feed("\u{FFFD}");
+                            rest = &after[len..];
+                        }
+                        None => {
+                            self.utf8_pending = after.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// 总行数（回滚历史 + 屏幕）
+    pub fn line_count(&self) -> usize {
+        self.scrollback.len() + self.screen.len()
+    }
+
+    /// 按索引获取行（0 为最早的回滚行）
+    pub fn line(&self, index: usize) -> Option<&TerminalLine> {
+        if index < self.scrollback.len() {
+            self.scrollback.get(index)
+        } else {
+            self.screen.get(index - self.scrollback.len())
+        }
+    }
+
+    /// 迭代指定范围内的行
+    pub fn lines_in(&self, range: std::ops::Range<usize>) -> impl Iterator<Item = &TerminalLine> {
+        range.filter_map(move |i| self.line(i))
+    }
+
+    /// 屏幕第一行在全部行中的索引
+    pub fn screen_start(&self) -> usize {
+        self.scrollback.len()
+    }
+
+    /// 回滚历史行数
+    pub fn scrollback_len(&self) -> usize {
+        self.scrollback.len()
+    }
+
+    /// OSC 8 超链接编号对应的地址
+    pub fn hyperlink(&self, id: u32) -> Option<&str> {
+        let index = (id as usize).checked_sub(1)?;
+        self.hyperlinks.get(index).map(String::as_str)
+    }
+
+    /// OSC 7 报告的当前工作目录
+    pub fn working_directory(&self) -> Option<&str> {
+        self.working_directory.as_deref()
+    }
+
+    /// 已从开头丢弃的总行数，加上行索引即得到不随裁剪变化的行号
+    pub fn dropped_lines(&self) -> usize {
+        self.dropped_lines
+    }
+
+    /// 光标所在的绝对行号
+    pub fn cursor_line(&self) -> usize {
+        self.screen_start() + self.cursor.row
+    }
+
+    /// 以指定前景色高亮一行中的列范围（触发器）
+    pub fn highlight(&mut self, line: usize, cols: std::ops::Range<usize>, fg: TermColor) {
+        let scrollback_len = self.scrollback.len();
+        let line = if line < scrollback_len {
+            self.scrollback.get_mut(line)
+        } else {
+            self.screen.get_mut(line - scrollback_len)
+        };
+        if let Some(line) = line {
+            let end = cols.end.min(line.cells.len());
+            for cell in &mut line.cells[cols.start.min(end)..end] {
+                cell.style.fg = fg;
+            }
+        }
+    }
+
+    /// 终端尺寸（列，行）
+    pub fn size(&self) -> (usize, usize) {
+        (self.cols, self.rows)
+    }
+
+    /// 光标位置（屏幕坐标）
+    pub fn cursor(&self) -> Cursor {
+        self.cursor
+    }
+
+    /// 当前文本样式
+    pub fn current_style(&self) -> CellStyle {
+        self.style
+    }
+
+    /// 由 OSC 0/2 设置的窗口标题
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// 取出并清除响铃标记
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell)
+    }
+
+    /// 取出需要回写给远端的应答
+    pub fn take_responses(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.responses)
+    }
+
+    /// 取出 OSC 52 剪贴板请求
+    pub fn take_clipboard_requests(&mut self) -> Vec<ClipboardRequest> {
+        std::mem::take(&mut self.clipboard_requests)
+    }
+
+    /// 当前选区
+    pub fn selection(&self) -> Option<Selection> {
+        self.selection
+    }
+
+    pub fn set_selection(&mut self, selection: Option<Selection>) {
+        self.selection = selection;
+    }
+
+    /// 选区内的文本：自动换行的行直接拼接，行尾空白被去除
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection?.range();
+        Some(self.text_range(start, end))
+    }
+
+    /// `[start, end]` 之间的文本（终点包含在内）
+    fn text_range(&self, start: Point, end: Point) -> String {
+        let mut text = String::new();
+        for index in start.line..=end.line.min(self.line_count().saturating_sub(1)) {
+            let Some(line) = self.line(index) else { break };
+            let from = if index == start.line { start.col } else { 0 };
+            let to = if index == end.line {
+                end.col.saturating_add(1).min(line.cells.len())
+            } else {
+                line.cells.len()
+            };
+            let content = line.text_in(from..to);
+
+            if line.wrapped && index != end.line {
+                text.push_str(&content);
+            } else {
+                text.push_str(content.trim_end());
+                if index != end.line {
+                    text.push('\n');
+                }
+            }
+        }
+        text
+    }
+
+    // ------------------------------------------------------------------------
+    // 命令块 (OSC 133)
+    // ------------------------------------------------------------------------
+
+    /// 所有命令块（按出现顺序）
+    pub fn blocks(&self) -> &[CommandBlock] {
+        &self.blocks
+    }
+
+    /// 包含指定行的命令块
+    pub fn block_at(&self, line: usize) -> Option<usize> {
+        let line_count = self.line_count();
+        self.blocks
+            .iter()
+            .rposition(|b| b.prompt_line <= line && line < b.end_line(line_count).max(b.prompt_line + 1))
+    }
+
+    /// 折叠或展开命令块的输出
+    pub fn toggle_fold(&mut self, index: usize) {
+        if let Some(block) = self.blocks.get_mut(index) {
+            block.folded = !block.folded && block.output_range().is_some();
+        }
+    }
+
+    /// 被折叠隐藏的行范围
+    pub fn folded_ranges(&self) -> impl Iterator<Item = std::ops::Range<usize>> + '_ {
+        self.blocks
+            .iter()
+            .filter(|b| b.folded)
+            .filter_map(CommandBlock::output_range)
+    }
+
+    /// 命令块的输出文本
+    pub fn block_output(&self, index: usize) -> Option<String> {
+        let block = self.blocks.get(index)?;
+        let start = block.output_start?;
+        let end = block.end_line(self.line_count());
+        if end <= start {
+            return Some(String::new());
+        }
+        let text = self.text_range(
+            Point { line: start, col: 0 },
+            Point {
+                line: end - 1,
+                col: usize::MAX,
+            },
+        );
+        Some(text.trim_end_matches('\n').to_string())
+    }
+
+    /// 取出已结束的命令及其退出码
+    pub fn take_finished_commands(&mut self) -> Vec<(String, Option<i32>)> {
+        std::mem::take(&mut self.finished_commands)
+    }
+
+    /// 处理 OSC 133 语义提示符标记
+    fn semantic_prompt(&mut self, payload: &str) {
+        // 全屏程序运行期间忽略
+        if self.modes.alt_screen {
+            return;
+        }
+        let mut params = payload.split(';');
+        let kind = params.next().unwrap_or("");
+        let line = self.cursor_line();
+
+        match kind {
+            "A" => {
+                // 上一个提示符没有执行任何命令（直接回车）
+                if self.blocks.last().is_some_and(|b| b.output_start.is_none()) {
+                    self.blocks.pop();
+                }
+                // 没有收到 D 标记的命令在新提示符处结束
+                if let Some(block) = self.blocks.last_mut().filter(|b| b.is_running()) {
+                    block.output_end = Some(line);
+                    block.duration = block.started_at.map(|t| t.elapsed());
+                    self.finished_commands.push((block.command.clone(), None));
+                }
+                self.blocks.push(CommandBlock::new(line));
+            }
+            "B" => {
+                let point = Point {
+                    line,
+                    col: self.cursor.col,
+                };
+                match self.blocks.last_mut().filter(|b| b.output_start.is_none()) {
+                    Some(block) => block.input_start = Some(point),
+                    // 只有 B 标记的 shell 以命令输入行作为提示符行
+                    None => {
+                        let mut block = CommandBlock::new(line);
+                        block.input_start = Some(point);
+                        self.blocks.push(block);
+                    }
+                }
+            }
+            "C" => {
+                let Some(index) = self.blocks.iter().rposition(|b| b.output_start.is_none()) else {
+                    return;
+                };
+                let start = self.blocks[index].input_start.unwrap_or(Point {
+                    line: self.blocks[index].prompt_line,
+                    col: 0,
+                });
+                // 回车后光标通常已在下一行行首
+                let end = if self.cursor.col == 0 {
+                    Point {
+                        line: line.saturating_sub(1),
+                        col: usize::MAX,
+                    }
+                } else {
+                    Point {
+                        line,
+                        col: self.cursor.col - 1,
+                    }
+                };
+                let command = if end >= start {
+                    self.text_range(start, end).replace('\n', " ").trim().to_string()
+                } else {
+                    String::new()
+                };
+
+                // 空命令（直接回车）不形成命令块
+                if command.is_empty() {
+                    self.blocks.remove(index);
+                    return;
+                }
+                let block = &mut self.blocks[index];
+                block.command = command;
+                block.output_start = Some(line);
+                block.started_at = Some(std::time::Instant::now());
+            }
+            "D" => {
+                let Some(block) = self.blocks.last_mut().filter(|b| b.is_running()) else {
+                    return;
+                };
+                let end = if self.cursor.col > 0 { line + 1 } else { line };
+                block.output_end = Some(end.max(block.output_start.unwrap_or(end)));
+                block.exit_code = params.next().and_then(|code| code.trim().parse().ok());
+                block.duration = block.started_at.map(|t| t.elapsed());
+                self.finished_commands.push((block.command.clone(), block.exit_code));
+            }
+            _ => {}
+        }
+    }
+
+    /// 将粘贴文本编码为发送给 shell 的字节
+    ///
+    /// 换行统一为回车；括号粘贴模式下用 `ESC[200~`/`ESC[201~` 包裹，
+    /// 并移除文本中伪造的结束标记，防止粘贴内容提前跳出粘贴模式。
+    pub fn encode_paste(&self, text: &str) -> Vec<u8> {
+        let text = text.replace("\r\n", "\r").replace('\n', "\r");
+        if self.modes.bracketed_paste {
+            let text = text.replace("\x1b[201~", "");
+            format!("\x1b[200~{}\x1b[201~", text).into_bytes()
+        } else {
+            text.into_bytes()
+        }
+    }
+
+    /// 缓冲区的纯文本内容（不含转义序列）
+    ///
+    /// 自动换行的行会拼接为一行，末尾空白与空行被去除。
+    pub fn plain_text(&self) -> String {
+        let mut text = String::new();
+        for i in 0..self.line_count() {
+            let line = self.line(i).unwrap();
+            let content = line.text();
+            if line.wrapped {
+                text.push_str(&content);
+            } else {
+                text.push_str(content.trim_end());
+                text.push('\n');
+            }
+        }
+        text.trim_end_matches('\n').to_string()
+    }
+
+    /// 清空缓冲区
+    pub fn clear(&mut self) {
+        self.dropped_lines += self.line_count();
+        self.selection = None;
+        self.blocks.clear();
+        self.scrollback.clear();
+        for line in &mut self.screen {
+            *line = TerminalLine::default();
+        }
+        self.cursor = Cursor::default();
+        self.wrap_pending = false;
+    }
+
+    /// 调整终端尺寸
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        let cols = cols.max(1);
+        let rows = rows.max(1);
+        if cols == self.cols && rows == self.rows {
+            return;
+        }
+
+        if rows < self.rows {
+            // 优先把光标上方的行移入回滚历史，保持光标可见
+            let excess = self.rows - rows;
+            let from_top = excess.min(self.cursor.row);
+            for line in self.screen.drain(..from_top) {
+                if !self.modes.alt_screen {
+                    self.scrollback.push_back(line);
+                }
+            }
+            self.screen.truncate(rows);
+            self.cursor.row -= from_top;
+        } else {
+            self.screen.resize(rows, TerminalLine::default());
+        }
+
+        if let Some(main) = &mut self.main_screen {
+            main.resize(rows, TerminalLine::default());
+        }
+
+        for line in &mut self.screen {
+            line.cells.truncate(cols);
+            line.repair_wide();
+        }
+
+        self.rows = rows;
+        self.cols = cols;
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        self.cursor.row = self.cursor.row.min(rows - 1);
+        self.cursor.col = self.cursor.col.min(cols - 1);
+        self.wrap_pending = false;
+        self.trim_scrollback();
+    }
+
+    // ------------------------------------------------------------------------
+    // 字符与控制字符
+    // ------------------------------------------------------------------------
+
+    fn put_char(&mut self, c: char) {
+        let c = if self.graphics_charset {
+            dec_special_graphics(c)
+        } else {
+            c
+        };
+
+        let width = char_width(c);
+        // 组合字符、变体选择符等附着到前一个字符簇
+        if !c.is_ascii() && self.extend_cluster(c) {
+            return;
+        }
+        if width == 0 {
+            return;
+        }
+
+        if self.wrap_pending {
+            self.wrap_line();
+        }
+        // 宽字符放不下时整体换到下一行
+        if width == 2 && self.cursor.col + 1 >= self.cols {
+            if self.modes.autowrap && self.cols > 1 {
+                self.wrap_line();
+            } else {
+                self.cursor.col = self.cols.saturating_sub(2);
+            }
+        }
+
+        let Cursor { row, col } = self.cursor;
+        let width = width.min(self.cols);
+        let line = &mut self.screen[row];
+        if line.cells.len() < col + width {
+            line.cells.resize(col + width, Cell::blank(CellStyle::default()));
+        }
+        // 覆盖宽字符的一半时，清除另一半
+        if line.cells[col].is_spacer() && col > 0 {
+            line.cells[col - 1] = Cell::blank(line.cells[col - 1].style);
+        }
+        let last = col + width - 1;
+        if line.cells[last].width == 2 && last + 1 < line.cells.len() {
+            line.cells[last + 1] = Cell::blank(line.cells[last + 1].style);
+        }
+        line.cells[col] = Cell {
+            ch: c,
+            combining: None,
+            width: width as u8,
+            style: self.style,
+            link: self.current_link,
+        };
+        if width == 2 {
+            line.cells[col + 1] = Cell {
+                link: self.current_link,
+                ..Cell::spacer(self.style)
+            };
+        }
+
+        if col + width >= self.cols {
+            self.cursor.col = self.cols - 1;
+            if self.modes.autowrap {
+                self.wrap_pending = true;
+            }
+        } else {
+            self.cursor.col += width;
+        }
+    }
+
+    /// 自动换行到下一行行首
+    fn wrap_line(&mut self) {
+        self.screen[self.cursor.row].wrapped = true;
+        self.cursor.col = 0;
+        self.index();
+        self.wrap_pending = false;
+    }
+
+    /// 尝试把字符并入光标前的字符簇（按 Unicode 字符簇边界判断）
+    ///
+    /// 字符簇因此变宽时（如 emoji 变体选择符），光标随之后移一列。
+    fn extend_cluster(&mut self, c: char) -> bool {
+        let Cursor { row, col } = self.cursor;
+        let line = &mut self.screen[row];
+        let mut index = if self.wrap_pending { col } else { col.saturating_sub(1) };
+        if !self.wrap_pending && col == 0 {
+            return false;
+        }
+        if line.cells.get(index).is_some_and(Cell::is_spacer) && index > 0 {
+            index -= 1;
+        }
+        let Some(cell) = line.cells.get_mut(index) else {
+            return false;
+        };
+
+        let mut cluster = String::new();
+        cell.push_to(&mut cluster);
+        cluster.push(c);
+        if cluster.graphemes(true).nth(1).is_some() {
+            return false;
+        }
+
+        let mut combining = cell.combining.take().map(String::from).unwrap_or_default();
+        combining.push(c);
+        cell.combining = Some(combining.into_boxed_str());
+
+        // 窄字符簇变为宽字符簇
+        let new_width = cluster_width(&cluster);
+        if cell.width == 1 && new_width == 2 && index + 1 < self.cols && !self.wrap_pending {
+            cell.width = 2;
+            let style = cell.style;
+            if line.cells.len() <= index + 1 {
+                line.cells.push(Cell::spacer(style));
+            } else {
+                line.cells[index + 1] = Cell::spacer(style);
+            }
+            if index + 2 >= self.cols {
+                self.cursor.col = self.cols - 1;
+                self.wrap_pending = self.modes.autowrap;
+            } else {
+                self.cursor.col = index + 2;
+            }
+        }
+        true
+    }
+
+    fn control(&mut self, c: char) {
+        match c {
+            '\n' | '\x0b' | '\x0c' => {
+                if self.modes.newline_mode {
+                    self.cursor.col = 0;
+                }
+                self.wrap_pending = false;
+                self.index();
+            }
+            '\r' => {
+                self.cursor.col = 0;
+                self.wrap_pending = false;
+            }
+            '\x08' => {
+                self.cursor.col = self.cursor.col.saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            '\t' => {
+                let next = (self.cursor.col / TAB_WIDTH + 1) * TAB_WIDTH;
+                self.cursor.col = next.min(self.cols - 1);
+            }
+            '\x07' => self.bell = true,
+            _ => {}
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // 转义序列
+    // ------------------------------------------------------------------------
+
+    fn csi(&mut self, csi: &CsiSequence) {
+        match csi.private {
+            Some('?') => return self.private_mode(csi),
+            Some('>') => {
+                if csi.action == 'c' {
+                    // 次设备属性：报告为 xterm
+                    self.responses.extend_from_slice(b"\x1b[>0;276;0c");
+                }
+                return;
+            }
+            Some(_) => return,
+            None => {}
+        }
+        if !csi.intermediates.is_empty() {
+            return;
+        }
+
+        let n = csi.param(0, 1) as usize;
+        match csi.action {
+            'm' => self.style.apply_sgr(&csi.params),
+            'A' => self.move_cursor_up(n),
+            'B' | 'e' => self.move_cursor_down(n),
+            'C' | 'a' => self.set_cursor_col(self.cursor.col + n),
+            'D' => self.set_cursor_col(self.cursor.col.saturating_sub(n)),
+            'E' => {
+                self.move_cursor_down(n);
+                self.cursor.col = 0;
+            }
+            'F' => {
+                self.move_cursor_up(n);
+                self.cursor.col = 0;
+            }
+            'G' | '`' => self.set_cursor_col(n - 1),
+            'd' => self.set_cursor_row(n - 1),
+            'H' | 'f' => {
+                self.set_cursor_row(csi.param(0, 1) as usize - 1);
+                self.set_cursor_col(csi.param(1, 1) as usize - 1);
+            }
+            'J' => self.erase_in_display(csi.param(0, 0)),
+            'K' => self.erase_in_line(csi.param(0, 0)),
+            'X' => self.erase_chars(n),
+            '@' => self.insert_chars(n),
+            'P' => self.delete_chars(n),
+            'L' => self.insert_lines(n),
+            'M' => self.delete_lines(n),
+            'S' => self.scroll_up(n),
+            'T' => self.scroll_down(n),
+            'r' => {
+                let top = csi.param(0, 1) as usize - 1;
+                let bottom = (csi.param(1, self.rows as u16) as usize).min(self.rows) - 1;
+                if top < bottom {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.cursor = Cursor::default();
+                    self.wrap_pending = false;
+                }
+            }
+            's' => self.save_cursor(),
+            'u' => self.restore_cursor(),
+            'h' | 'l' if csi.param(0, 0) == 20 => {
+                self.modes.newline_mode = csi.action == 'h';
+            }
+            'n' => match csi.param(0, 0) {
+                5 => self.responses.extend_from_slice(b"\x1b[0n"),
+                6 => {
+                    let report = format!("\x1b[{};{}R", self.cursor.row + 1, self.cursor.col + 1);
+                    self.responses.extend_from_slice(report.as_bytes());
+                }
+                _ => {}
+            },
+            'c' => self.responses.extend_from_slice(b"\x1b[?1;2c"),
+            _ => {}
+        }
+    }
+
+    /// DECSET / DECRST
+    fn private_mode(&mut self, csi: &CsiSequence) {
+        let enable = match csi.action {
+            'h' => true,
+            'l' => false,
+            _ => return,
+        };
+        for group in &csi.params {
+            match group.first().copied().unwrap_or(0) {
+                1 => self.modes.app_cursor_keys = enable,
+                7 => self.modes.autowrap = enable,
+                25 => self.modes.cursor_visible = enable,
+                2004 => self.modes.bracketed_paste = enable,
+                mode @ (9 | 1000 | 1002 | 1003) => {
+                    self.modes.mouse_tracking = match (enable, mode) {
+                        (false, _) => MouseTracking::Off,
+                        (true, 9) => MouseTracking::X10,
+                        (true, 1000) => MouseTracking::Normal,
+                        (true, 1002) => MouseTracking::ButtonEvent,
+                        _ => MouseTracking::AnyEvent,
+                    }
+                }
+                1006 => self.modes.sgr_mouse = enable,
+                47 | 1047 => self.set_alt_screen(enable),
+                1049 => {
+                    if enable {
+                        self.save_cursor();
+                        self.set_alt_screen(true);
+                    } else {
+                        self.set_alt_screen(false);
+                        self.restore_cursor();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn osc(&mut self, osc: &str) {
+        let (code, payload) = osc.split_once(';').unwrap_or((osc, ""));
+        match code {
+            "0" | "2" => self.title = Some(payload.to_string()),
+            "133" => self.semantic_prompt(payload),
+            "8" => self.set_hyperlink(payload),
+            "7" => {
+                // file://host/path，主机名部分忽略
+                if let Some(rest) = payload.strip_prefix("file://") {
+                    let path = rest.find('/').map_or("/", |i| &rest[i..]);
+                    self.working_directory = Some(percent_decode(path));
+                }
+            }
+            "52" => {
+                let (selection, data) = payload.split_once(';').unwrap_or(("", payload));
+                let selection = if selection.is_empty() { "c" } else { selection };
+                if data == "?" {
+                    self.clipboard_requests.push(ClipboardRequest::Get {
+                        selection: selection.to_string(),
+                    });
+                } else if let Ok(bytes) = general_purpose::STANDARD.decode(data) {
+                    self.clipboard_requests
+                        .push(ClipboardRequest::Set(String::from_utf8_lossy(&bytes).into_owned()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// OSC 8 ; params ; URI — URI 为空时结束当前链接
+    fn set_hyperlink(&mut self, payload: &str) {
+        let uri = payload.split_once(';').map_or("", |(_, uri)| uri);
+        if uri.is_empty() {
+            self.current_link = 0;
+            return;
+        }
+        let index = match self.hyperlinks.iter().rposition(|u| u == uri) {
+            Some(index) => index,
+            None => {
+                self.hyperlinks.push(uri.to_string());
+                self.hyperlinks.len() - 1
+            }
+        };
+        self.current_link = index as u32 + 1;
+    }
+
+    fn esc(&mut self, intermediates: &str, action: char) {
+        match (intermediates, action) {
+            ("", '7') => self.save_cursor(),
+            ("", '8') => self.restore_cursor(),
+            ("", 'D') => self.index(),
+            ("", 'E') => {
+                self.cursor.col = 0;
+                self.index();
+            }
+            ("", 'M') => self.reverse_index(),
+            ("", 'c') => self.reset(),
+            ("(", '0') => self.graphics_charset = true,
+            ("(", _) => self.graphics_charset = false,
+            _ => {}
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // 光标
+    // ------------------------------------------------------------------------
+
+    fn set_cursor_col(&mut self, col: usize) {
+        self.cursor.col = col.min(self.cols - 1);
+        self.wrap_pending = false;
+    }
+
+    fn set_cursor_row(&mut self, row: usize) {
+        self.cursor.row = row.min(self.rows - 1);
+        self.wrap_pending = false;
+    }
+
+    fn move_cursor_up(&mut self, n: usize) {
+        let limit = if self.cursor.row >= self.scroll_top {
+            self.scroll_top
+        } else {
+            0
+        };
+        self.cursor.row = self.cursor.row.saturating_sub(n).max(limit);
+        self.wrap_pending = false;
+    }
+
+    fn move_cursor_down(&mut self, n: usize) {
+        let limit = if self.cursor.row <= self.scroll_bottom {
+            self.scroll_bottom
+        } else {
+            self.rows - 1
+        };
+        self.cursor.row = (self.cursor.row + n).min(limit);
+        self.wrap_pending = false;
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved_cursor = Some(SavedCursor {
+            cursor: self.cursor,
+            style: self.style,
+            graphics_charset: self.graphics_charset,
+        });
+    }
+
+    fn restore_cursor(&mut self) {
+        if let Some(saved) = self.saved_cursor {
+            self.cursor = Cursor {
+                row: saved.cursor.row.min(self.rows - 1),
+                col: saved.cursor.col.min(self.cols - 1),
+            };
+            self.style = saved.style;
+            self.graphics_charset = saved.graphics_charset;
+        }
+        self.wrap_pending = false;
+    }
+
+    // ------------------------------------------------------------------------
+    // 滚动
+    // ------------------------------------------------------------------------
+
+    /// IND：光标下移一行，位于滚动区域底部时向上滚动
+    fn index(&mut self) {
+        if self.cursor.row == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if self.cursor.row + 1 < self.rows {
+            self.cursor.row += 1;
+        }
+    }
+
+    /// RI：光标上移一行，位于滚动区域顶部时向下滚动
+    fn reverse_index(&mut self) {
+        if self.cursor.row == self.scroll_top {
+            self.scroll_down(1);
+        } else {
+            self.cursor.row = self.cursor.row.saturating_sub(1);
+        }
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        let n = n.min(self.scroll_bottom - self.scroll_top + 1);
+        for _ in 0..n {
+            let line = self.screen.remove(self.scroll_top);
+            self.screen
+                .insert(self.scroll_bottom, self.blank_line());
+            // 只有主屏幕整屏滚动时才进入回滚历史
+            if self.scroll_top == 0 && !self.modes.alt_screen {
+                self.scrollback.push_back(line);
+            }
+        }
+        self.trim_scrollback();
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        let n = n.min(self.scroll_bottom - self.scroll_top + 1);
+        for _ in 0..n {
+            self.screen.remove(self.scroll_bottom);
+            self.screen.insert(self.scroll_top, self.blank_line());
+        }
+    }
+
+    fn trim_scrollback(&mut self) {
+        let excess = self.scrollback.len().saturating_sub(self.max_lines);
+        self.drop_scrollback_front(excess);
+    }
+
+    /// 丢弃最旧的 `n` 行回滚历史，行索引整体前移，选区与命令块随之移动
+    fn drop_scrollback_front(&mut self, n: usize) {
+        if n == 0 {
+            return;
+        }
+        let n = n.min(self.scrollback.len());
+        self.scrollback.drain(..n);
+        self.dropped_lines += n;
+        self.selection = self.selection.and_then(|mut sel| {
+            sel.anchor.line = sel.anchor.line.checked_sub(n)?;
+            sel.head.line = sel.head.line.checked_sub(n)?;
+            Some(sel)
+        });
+        self.blocks.retain_mut(|block| block.shift_up(n));
+    }
+
+    // ------------------------------------------------------------------------
+    // 编辑
+    // ------------------------------------------------------------------------
+
+    /// 擦除使用的空白单元格（保留当前背景色）
+    fn blank_cell(&self) -> Cell {
+        Cell::blank(CellStyle {
+            bg: self.style.bg,
+            ..CellStyle::default()
+        })
+    }
+
+    fn blank_line(&self) -> TerminalLine {
+        let blank = self.blank_cell();
+        if blank.style == CellStyle::default() {
+            TerminalLine::default()
+        } else {
+            TerminalLine {
+                cells: vec![blank; self.cols],
+                wrapped: false,
+            }
+        }
+    }
+
+    /// 将一行中 `[start, end)` 范围清空
+    fn erase_range(&mut self, row: usize, start: usize, end: usize) {
+        let blank = self.blank_cell();
+        let cols = self.cols;
+        let line = &mut self.screen[row];
+        let end = end.min(cols);
+        if blank.style == CellStyle::default() && end >= line.cells.len() {
+            line.cells.truncate(start);
+        } else {
+            if line.cells.len() < end {
+                line.cells.resize(end, Cell::blank(CellStyle::default()));
+            }
+            for cell in &mut line.cells[start.min(end)..end] {
+                *cell = blank.clone();
+            }
+        }
+        line.repair_wide();
+        if end >= cols {
+            line.wrapped = false;
+        }
+    }
+
+    /// EL：清除行内容
+    fn erase_in_line(&mut self, mode: u16) {
+        let Cursor { row, col } = self.cursor;
+        match mode {
+            0 => self.erase_range(row, col, self.cols),
+            1 => self.erase_range(row, 0, col + 1),
+            _ => self.erase_range(row, 0, self.cols),
+        }
+    }
+
+    /// ED：清除屏幕内容
+    fn erase_in_display(&mut self, mode: u16) {
+        let row = self.cursor.row;
+        match mode {
+            0 => {
+                self.erase_in_line(0);
+                for r in row + 1..self.rows {
+                    self.erase_range(r, 0, self.cols);
+                }
+            }
+            1 => {
+                for r in 0..row {
+                    self.erase_range(r, 0, self.cols);
+                }
+                self.erase_in_line(1);
+            }
+            2 => {
+                for r in 0..self.rows {
+                    self.erase_range(r, 0, self.cols);
+                }
+            }
+            3 => self.drop_scrollback_front(self.scrollback.len()),
+            _ => {}
+        }
+    }
+
+    /// ECH：从光标处擦除 n 个字符
+    fn erase_chars(&mut self, n: usize) {
+        let Cursor { row, col } = self.cursor;
+        self.erase_range(row, col, col + n);
+    }
+
+    /// ICH：在光标处插入 n 个空白
+    fn insert_chars(&mut self, n: usize) {
+        let Cursor { row, col } = self.cursor;
+        let blank = self.blank_cell();
+        let cols = self.cols;
+        let line = &mut self.screen[row];
+        if col >= line.cells.len() {
+            return;
+        }
+        for _ in 0..n.min(cols - col) {
+            line.cells.insert(col, blank.clone());
+        }
+        line.cells.truncate(cols);
+        line.repair_wide();
+    }
+
+    /// DCH：删除光标处 n 个字符
+    fn delete_chars(&mut self, n: usize) {
+        let Cursor { row, col } = self.cursor;
+        let line = &mut self.screen[row];
+        if col < line.cells.len() {
+            let end = (col + n).min(line.cells.len());
+            line.cells.drain(col..end);
+            line.repair_wide();
+        }
+    }
+
+    /// IL：在光标所在行插入 n 行
+    fn insert_lines(&mut self, n: usize) {
+        let row = self.cursor.row;
+        if row < self.scroll_top || row > self.scroll_bottom {
+            return;
+        }
+        for _ in 0..n.min(self.scroll_bottom - row + 1) {
+            self.screen.remove(self.scroll_bottom);
+            self.screen.insert(row, self.blank_line());
+        }
+        self.cursor.col = 0;
+    }
+
+    /// DL：删除光标所在行起的 n 行
+    fn delete_lines(&mut self, n: usize) {
+        let row = self.cursor.row;
+        if row < self.scroll_top || row > self.scroll_bottom {
+            return;
+        }
+        for _ in 0..n.min(self.scroll_bottom - row + 1) {
+            self.screen.remove(row);
+            self.screen.insert(self.scroll_bottom, self.blank_line());
+        }
+        self.cursor.col = 0;
+    }
+
+    // ------------------------------------------------------------------------
+    // 屏幕切换与重置
+    // ------------------------------------------------------------------------
+
+    fn set_alt_screen(&mut self, enable: bool) {
+        if enable == self.modes.alt_screen {
+            return;
+        }
+        if enable {
+            let blank = vec![TerminalLine::default(); self.rows];
+            self.main_screen = Some(std::mem::replace(&mut self.screen, blank));
+        } else if let Some(main) = self.main_screen.take() {
+            self.screen = main;
+        }
+        self.selection = None;
+        self.modes.alt_screen = enable;
+        self.scroll_top = 0;
+        self.scroll_bottom = self.rows - 1;
+        self.wrap_pending = false;
+    }
+
+    /// RIS：完全重置（保留回滚历史与尺寸）
+    fn reset(&mut self) {
+        let newline_mode = self.modes.newline_mode;
+        self.set_alt_screen(false);
+        for line in &mut self.screen {
+            *line = TerminalLine::default();
+        }
+        self.cursor = Cursor::default();
+        self.saved_cursor = None;
+        self.style = CellStyle::default();
+        self.current_link = 0;
+        self.graphics_charset = false;
+        self.modes = TerminalModes {
+            newline_mode,
+            ..TerminalModes::default()
+        };
+        self.scroll_top = 0;
+        self.scroll_bottom = self.rows - 1;
+        self.wrap_pending = false;
+    }
+}
+
+impl Default for TerminalBuffer {
+    fn default() -> Self {
+        Self::log(10000)
+    }
+}
+
+/// DEC 特殊图形字符集（用于绘制边框）
+fn dec_special_graphics(c: char) -> char {
+    match c {
+        '`' => '◆',
+        'a' => '▒',
+        'f' => '°',
+        'g' => '±',
+        'j' => '┘',
+        'k' => '┐',
+        'l' => '┌',
+        'm' => '└',
+        'n' => '┼',
+        'o' => '⎺',
+        'p' => '⎻',
+        'q' => '─',
+        'r' => '⎼',
+        's' => '⎽',
+        't' => '├',
+        'u' => '┤',
+        'v' => '┴',
+        'w' => '┬',
+        'x' => '│',
+        'y' => '≤',
+        'z' => '≥',
+        '{' => 'π',
+        '|' => '≠',
+        '}' => '£',
+        '~' => '·',
+        other => other,
+    }
+}
+
+/// 解码 URI 中的 %XX 转义
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(b) = s.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::ansi::TermColor;
+
+    fn pty_buffer(cols: usize, rows: usize) -> TerminalBuffer {
+        TerminalBuffer::with_size(100, cols, rows)
+    }
+
+    fn screen_text(buffer: &TerminalBuffer) -> Vec<String> {
+        (buffer.screen_start()..buffer.line_count())
+            .map(|i| buffer.line(i).unwrap().text().trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_plain_text() {
+        let mut buffer = TerminalBuffer::default();
+        buffer.feed("hello\nworld");
+        assert_eq!(buffer.plain_text(), "hello\nworld");
+    }
+
+    #[test]
+    fn test_styled_spans() {
+        let mut buffer = TerminalBuffer::default();
+        buffer.feed("\x1b[01;34mdir\x1b[0m file\n");
+        let spans = buffer.line(0).unwrap().spans();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].0, "dir");
+        assert!(spans[0].1.bold);
+        assert_eq!(spans[0].1.fg, TermColor::Indexed(4));
+        assert_eq!(spans[1].0, " file");
+        assert_eq!(spans[1].1, CellStyle::default());
+    }
+
+    #[test]
+    fn test_carriage_return_overwrites() {
+        let mut buffer = TerminalBuffer::default();
+        buffer.feed("50%\r100%\r\n");
+        assert_eq!(buffer.line(0).unwrap().text(), "100%");
+
+        buffer.feed("abcdef\r\x1b[Kxy");
+        assert_eq!(buffer.line(1).unwrap().text(), "xy");
+    }
+
+    #[test]
+    fn test_backspace_and_tab() {
+        let mut buffer = TerminalBuffer::default();
+        buffer.feed("ab\x08c\td");
+        assert_eq!(buffer.plain_text(), "ac      d");
+    }
+
+    #[test]
+    fn test_scrollback_limit() {
+        let mut buffer = TerminalBuffer::with_size(3, 80, 2);
+        buffer.modes.newline_mode = true;
+        buffer.feed("1\n2\n3\n4\n5\n6");
+        assert_eq!(buffer.scrollback_len(), 3);
+        assert_eq!(buffer.plain_text(), "2\n3\n4\n5\n6");
+    }
+
+    #[test]
+    fn test_linefeed_without_newline_mode() {
+        let mut buffer = pty_buffer(10, 3);
+        buffer.feed("ab\ncd");
+        assert_eq!(screen_text(&buffer), vec!["ab", "  cd", ""]);
+    }
+
+    #[test]
+    fn test_autowrap() {
+        let mut buffer = pty_buffer(4, 3);
+        buffer.feed("abcdef");
+        assert_eq!(screen_text(&buffer), vec!["abcd", "ef", ""]);
+        assert!(buffer.line(0).unwrap().wrapped);
+        assert_eq!(buffer.plain_text(), "abcdef");
+    }
+
+    #[test]
+    fn test_cursor_movement_and_erase() {
+        let mut buffer = pty_buffer(10, 3);
+        buffer.feed("hello\r\nworld");
+        buffer.feed("\x1b[1;2H\x1b[KX");
+        assert_eq!(screen_text(&buffer), vec!["hX", "world", ""]);
+
+        buffer.feed("\x1b[2J\x1b[H");
+        assert_eq!(screen_text(&buffer), vec!["", "", ""]);
+        assert_eq!(buffer.cursor(), Cursor { row: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_insert_delete_chars() {
+        let mut buffer = pty_buffer(10, 2);
+        buffer.feed("abcdef\x1b[1;3H\x1b[2P");
+        assert_eq!(screen_text(&buffer)[0], "abef");
+        buffer.feed("\x1b[2@");
+        assert_eq!(screen_text(&buffer)[0], "ab  ef");
+    }
+
+    #[test]
+    fn test_scroll_region() {
+        let mut buffer = pty_buffer(10, 4);
+        buffer.feed("1\r\n2\r\n3\r\n4");
+        // 滚动区域为第 2-3 行，在底部换行只滚动该区域
+        buffer.feed("\x1b[2;3r\x1b[3;1H\n");
+        assert_eq!(screen_text(&buffer), vec!["1", "3", "", "4"]);
+        assert_eq!(buffer.scrollback_len(), 0);
+    }
+
+    #[test]
+    fn test_scrolling_into_scrollback() {
+        let mut buffer = pty_buffer(10, 2);
+        buffer.feed("1\r\n2\r\n3");
+        assert_eq!(buffer.scrollback_len(), 1);
+        assert_eq!(buffer.plain_text(), "1\n2\n3");
+    }
+
+    #[test]
+    fn test_alt_screen() {
+        let mut buffer = pty_buffer(10, 2);
+        buffer.feed("shell$ ");
+        buffer.feed("\x1b[?1049h\x1b[Hvim");
+        assert!(buffer.modes.alt_screen);
+        assert_eq!(screen_text(&buffer)[0], "vim");
+
+        buffer.feed("\x1b[?1049l");
+        assert!(!buffer.modes.alt_screen);
+        assert_eq!(screen_text(&buffer)[0], "shell$");
+        assert_eq!(buffer.cursor().col, 7);
+    }
+
+    #[test]
+    fn test_device_status_report() {
+        let mut buffer = pty_buffer(10, 5);
+        buffer.feed("\x1b[3;4H\x1b[6n");
+        assert_eq!(buffer.take_responses(), b"\x1b[3;4R".to_vec());
+        assert!(buffer.take_responses().is_empty());
+    }
+
+    #[test]
+    fn test_title_and_bell() {
+        let mut buffer = pty_buffer(10, 2);
+        buffer.feed("\x1b]0;user@host: ~\x07\x07");
+        assert_eq!(buffer.title(), Some("user@host: ~"));
+        assert!(buffer.take_bell());
+        assert!(!buffer.take_bell());
+    }
+
+    #[test]
+    fn test_feed_bytes_split_utf8() {
+        let mut buffer = TerminalBuffer::default();
+        let bytes = "中文".as_bytes();
+        buffer.feed_bytes(&bytes[..2]);
+        buffer.feed_bytes(&bytes[2..]);
+        assert_eq!(buffer.plain_text(), "中文");
+
+        buffer.feed_bytes(b"\xff!");
+        assert!(buffer.plain_text().ends_with("\u{FFFD}!"));
+    }
+
+    #[test]
+    fn test_resize_keeps_cursor_visible() {
+        let mut buffer = pty_buffer(10, 4);
+        buffer.feed("1\r\n2\r\n3\r\n4");
+        buffer.resize(10, 2);
+        assert_eq!(screen_text(&buffer), vec!["3", "4"]);
+        assert_eq!(buffer.cursor().row, 1);
+        assert_eq!(buffer.plain_text(), "1\n2\n3\n4");
+
+        buffer.resize(5, 3);
+        assert_eq!(buffer.size(), (5, 3));
+    }
+
+    #[test]
+    fn test_osc52_clipboard_requests() {
+        let mut buffer = pty_buffer(10, 2);
+        // "hello" 的 base64
+        buffer.feed("\x1b]52;c;aGVsbG8=\x07\x1b]52;p;?\x1b\\");
+        assert_eq!(
+            buffer.take_clipboard_requests(),
+            vec![
+                ClipboardRequest::Set("hello".to_string()),
+                ClipboardRequest::Get {
+                    selection: "p".to_string()
+                },
+            ]
+        );
+        buffer.feed("\x1b]52;c;!!invalid!!\x07");
+        assert!(buffer.take_clipboard_requests().is_empty());
+    }
+
+    #[test]
+    fn test_osc8_hyperlinks_and_osc7_cwd() {
+        let mut buffer = pty_buffer(20, 2);
+        buffer.feed("\x1b]8;id=1;https://example.com/a\x1b\\链接\x1b]8;;\x1b\\ x");
+        buffer.feed("\x1b]8;;https://example.com/a\x07y\x1b]8;;\x07");
+        let line = buffer.line(0).unwrap();
+        let id = line.cells[0].link;
+        assert_ne!(id, 0);
+        assert_eq!(line.cells[1].link, id);
+        assert_eq!(line.cells[3].link, id);
+        assert_eq!(line.cells[4].link, 0);
+        assert_eq!(line.cells[6].link, id);
+        assert_eq!(buffer.hyperlink(id), Some("https://example.com/a"));
+        assert_eq!(buffer.hyperlink(0), None);
+
+        buffer.feed("\x1b]7;file://host/home/me/my%20dir\x07");
+        assert_eq!(buffer.working_directory(), Some("/home/me/my dir"));
+    }
+
+    #[test]
+    fn test_bracketed_paste() {
+        let mut buffer = pty_buffer(10, 2);
+        assert_eq!(buffer.encode_paste("a\nb"), b"a\rb".to_vec());
+
+        buffer.feed("\x1b[?2004h");
+        assert!(buffer.modes.bracketed_paste);
+        assert_eq!(
+            buffer.encode_paste("ls\x1b[201~; rm -rf /\n"),
+            b"\x1b[200~ls; rm -rf /\r\x1b[201~".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_wide_characters() {
+        let mut buffer = pty_buffer(5, 3);
+        buffer.feed("中文a");
+        assert_eq!(buffer.cursor(), Cursor { row: 0, col: 4 });
+        let line = buffer.line(buffer.screen_start()).unwrap();
+        assert_eq!(line.cells.iter().map(|c| c.width).collect::<Vec<_>>(), vec![2, 0, 2, 0, 1]);
+        assert_eq!(line.text(), "中文a");
+
+        // 行尾只剩一列时宽字符整体换行
+        buffer.feed("\r\n1234字");
+        assert_eq!(screen_text(&buffer), vec!["中文a", "1234", "字"]);
+        assert_eq!(buffer.plain_text(), "中文a\n1234字");
+
+        // 覆盖宽字符的一半会清除另一半
+        buffer.feed("\x1b[1;2Hx");
+        assert_eq!(screen_text(&buffer)[0], " x文a");
+        buffer.feed("\x1b[1;3HY");
+        assert_eq!(screen_text(&buffer)[0], " xY a");
+    }
+
+    #[test]
+    fn test_combining_marks_and_emoji() {
+        let mut buffer = pty_buffer(10, 2);
+        // e + 组合重音符、ZWJ 家庭 emoji、国旗（两个区域指示符）
+        buffer.feed("e\u{301}|👨\u{200d}👩\u{200d}👧|🇨🇳|");
+        let line = buffer.line(buffer.screen_start()).unwrap();
+        assert_eq!(line.text(), "e\u{301}|👨\u{200d}👩\u{200d}👧|🇨🇳|");
+        assert_eq!(line.cells[0].combining.as_deref(), Some("\u{301}"));
+        assert_eq!(buffer.cursor().col, 8);
+
+        // 变体选择符让文本样式字符变为 emoji 宽度
+        buffer.feed("\r\n\u{2764}\u{fe0f}x");
+        assert_eq!(buffer.cursor().col, 3);
+
+        assert_eq!(str_width("中文abc"), 7);
+        assert_eq!(str_width("e\u{301}"), 1);
+    }
+
+    #[test]
+    fn test_wide_characters_survive_edits() {
+        let mut buffer = pty_buffer(6, 2);
+        buffer.feed("a中b\x1b[1;2H\x1b[P");
+        // 删除宽字符的左半部分后，右半部分不再残留
+        assert_eq!(screen_text(&buffer)[0], "a b");
+        buffer.feed("\x1b[2J\x1b[Hab中\x1b[5G");
+        buffer.feed("\x1b[1K");
+        assert!(buffer.line(buffer.screen_start()).unwrap().cells.iter().all(|c| c.width == 1));
+
+        buffer.feed("\x1b[H中文字");
+        buffer.resize(5, 2);
+        assert_eq!(screen_text(&buffer)[0], "中文");
+        buffer.set_selection(Some(Selection {
+            anchor: Point { line: buffer.screen_start(), col: 1 },
+            head: Point { line: buffer.screen_start(), col: 2 },
+        }));
+        assert_eq!(buffer.selected_text().unwrap(), "中文");
+    }
+
+    #[test]
+    fn test_semantic_prompt_blocks() {
+        let mut buffer = pty_buffer(20, 5);
+        let prompt = |b: &mut TerminalBuffer| b.feed("\x1b]133;A\x07$ \x1b]133;B\x07");
+
+        prompt(&mut buffer);
+        buffer.feed("ls\r\n\x1b]133;C\x07a.txt\r\nb.txt\r\n\x1b]133;D;0\x07");
+        // 直接回车不产生命令块
+        prompt(&mut buffer);
+        buffer.feed("\r\n");
+        prompt(&mut buffer);
+        buffer.feed("\r\n\x1b]133;C\x07\x1b]133;D;1\x07");
+        prompt(&mut buffer);
+        buffer.feed("false\r\n\x1b]133;C\x07\x1b]133;D;1\x07");
+        prompt(&mut buffer);
+
+        let blocks = buffer.blocks();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0].command, "ls");
+        assert_eq!(blocks[0].exit_code, Some(0));
+        assert_eq!(blocks[0].output_range(), Some(1..3));
+        assert_eq!(buffer.block_output(0).unwrap(), "a.txt\nb.txt");
+        assert_eq!(blocks[1].command, "false");
+        assert_eq!(blocks[1].exit_code, Some(1));
+        assert!(blocks[1].status_text().starts_with("✘ 1"));
+        assert!(!blocks[2].is_finished());
+        assert_eq!(
+            buffer.take_finished_commands(),
+            vec![("ls".to_string(), Some(0)), ("false".to_string(), Some(1))]
+        );
+
+        assert_eq!(buffer.block_at(2), Some(0));
+        buffer.toggle_fold(0);
+        assert_eq!(buffer.folded_ranges().collect::<Vec<_>>(), vec![1..3]);
+        // 没有输出的块不能折叠
+        buffer.toggle_fold(1);
+        assert_eq!(buffer.folded_ranges().count(), 1);
+
+        // 回滚历史被裁剪时块随之前移，提示符被移出的块被丢弃
+        buffer.feed("\r\n\r\n\r\n");
+        buffer.set_max_lines(1);
+        let first = &buffer.blocks()[0];
+        assert_eq!(first.command, "false");
+        assert_eq!(first.prompt_line, 1);
+    }
+
+    #[test]
+    fn test_mouse_tracking_modes() {
+        let mut buffer = pty_buffer(10, 2);
+        buffer.feed("\x1b[?1002h\x1b[?1006h");
+        assert_eq!(buffer.modes.mouse_tracking, MouseTracking::ButtonEvent);
+        assert!(buffer.modes.sgr_mouse);
+
+        buffer.feed("\x1b[?1003h");
+        assert_eq!(buffer.modes.mouse_tracking, MouseTracking::AnyEvent);
+        buffer.feed("\x1b[?1000l");
+        assert_eq!(buffer.modes.mouse_tracking, MouseTracking::Off);
+
+        buffer.feed("\x1b[?9h\x1bc");
+        assert_eq!(buffer.modes.mouse_tracking, MouseTracking::Off);
+        assert!(!buffer.modes.sgr_mouse);
+    }
+
+    #[test]
+    fn test_selection_text() {
+        let mut buffer = pty_buffer(6, 3);
+        buffer.feed("hello world\r\nfoo   \r\nbar");
+        // 第一行自动换行到第二行
+        buffer.set_selection(Some(Selection {
+            anchor: Point { line: 3, col: 1 },
+            head: Point { line: 0, col: 2 },
+        }));
+        assert_eq!(buffer.selected_text().unwrap(), "llo world\nfoo\nba");
+        assert!(buffer.selection().unwrap().contains(Point { line: 1, col: 0 }));
+
+        buffer.clear();
+        assert!(buffer.selection().is_none());
+    }
+
+    #[test]
+    fn test_selection_follows_scrollback_trim() {
+        let mut buffer = TerminalBuffer::with_size(2, 10, 2);
+        buffer.feed("a\r\nb\r\nc");
+        buffer.set_selection(Some(Selection {
+            anchor: Point { line: 1, col: 0 },
+            head: Point { line: 2, col: 0 },
+        }));
+        // 丢弃 "a" 后选区随内容前移
+        buffer.feed("\r\nd\r\ne");
+        assert_eq!(buffer.selected_text().unwrap(), "b\nc");
+
+        // 选区起点所在行被丢弃后清除选区
+        buffer.feed("\r\nf");
+        assert!(buffer.selection().is_none());
+    }
+
+    #[test]
+    fn test_line_drawing_charset() {
+        let mut buffer = pty_buffer(10, 2);
+        buffer.feed("\x1b(0lqk\x1b(Bx");
+        assert_eq!(screen_text(&buffer)[0], "┌─┐x");
+    }
+}
@@ -0,0 +1,172 @@
+use egui::{Key, Modifiers};
+
+// ============================================================================
+// 键盘输入编码（xterm 风格）
+// ============================================================================
+
+/// xterm 修饰键参数：1 + Shift(1) + Alt(2) + Ctrl(4)
+fn modifier_param(modifiers: Modifiers) -> u8 {
+    1 + modifiers.shift as u8 + 2 * modifiers.alt as u8 + 4 * modifiers.ctrl as u8
+}
+
+/// 光标类按键：`CSI 1;m X`，无修饰键时为 `CSI X` 或应用模式下的 `SS3 X`
+fn cursor_key(final_char: char, modifiers: Modifiers, app_cursor: bool) -> Vec<u8> {
+    let m = modifier_param(modifiers);
+    if m > 1 {
+        format!("\x1b[1;{}{}", m, final_char).into_bytes()
+    } else if app_cursor {
+        format!("\x1bO{}", final_char).into_bytes()
+    } else {
+        format!("\x1b[{}", final_char).into_bytes()
+    }
+}
+
+/// 编辑类按键：`CSI n ~`，带修饰键时为 `CSI n;m ~`
+fn tilde_key(code: u8, modifiers: Modifiers) -> Vec<u8> {
+    let m = modifier_param(modifiers);
+    if m > 1 {
+        format!("\x1b[{};{}~", code, m).into_bytes()
+    } else {
+        format!("\x1b[{}~", code).into_bytes()
+    }
+}
+
+/// 将按键编码为发送给 shell 的字节
+///
+/// 普通可打印字符通过文本事件输入，这里只处理控制键、功能键以及
+/// Ctrl/Alt 组合；无需特殊编码的按键返回 None。
+pub fn encode_key(key: Key, modifiers: Modifiers, app_cursor: bool) -> Option<Vec<u8>> {
+    let bytes = match key {
+        Key::Enter => b"\r".to_vec(),
+        Key::Tab if modifiers.shift => b"\x1b[Z".to_vec(),
+        Key::Tab => b"\t".to_vec(),
+        Key::Backspace if modifiers.ctrl => b"\x08".to_vec(),
+        Key::Backspace => b"\x7f".to_vec(),
+        Key::Escape => b"\x1b".to_vec(),
+        Key::ArrowUp => cursor_key('A', modifiers, app_cursor),
+        Key::ArrowDown => cursor_key('B', modifiers, app_cursor),
+        Key::ArrowRight => cursor_key('C', modifiers, app_cursor),
+        Key::ArrowLeft => cursor_key('D', modifiers, app_cursor),
+        Key::Home => cursor_key('H', modifiers, app_cursor),
+        Key::End => cursor_key('F', modifiers, app_cursor),
+        Key::Insert => tilde_key(2, modifiers),
+        Key::Delete => tilde_key(3, modifiers),
+        Key::PageUp => tilde_key(5, modifiers),
+        Key::PageDown => tilde_key(6, modifiers),
+        Key::F1 => cursor_key('P', modifiers, true),
+        Key::F2 => cursor_key('Q', modifiers, true),
+        Key::F3 => cursor_key('R', modifiers, true),
+        Key::F4 => cursor_key('S', modifiers, true),
+        Key::F5 => tilde_key(15, modifiers),
+        Key::F6 => tilde_key(17, modifiers),
+        Key::F7 => tilde_key(18, modifiers),
+        Key::F8 => tilde_key(19, modifiers),
+        Key::F9 => tilde_key(20, modifiers),
+        Key::F10 => tilde_key(21, modifiers),
+        Key::F11 => tilde_key(23, modifiers),
+        Key::F12 => tilde_key(24, modifiers),
+        _ => return encode_chord(key, modifiers),
+    };
+    Some(bytes)
+}
+
+/// Ctrl/Alt 与字符键的组合
+fn encode_chord(key: Key, modifiers: Modifiers) -> Option<Vec<u8>> {
+    if !modifiers.ctrl && !modifiers.alt {
+        return None;
+    }
+
+    let ch = match key {
+        Key::Space => ' ',
+        Key::OpenBracket => '[',
+        Key::CloseBracket => ']',
+        Key::Backslash => '\\',
+        Key::Slash => '/',
+        Key::Minus => '-',
+        _ => {
+            let mut chars = key.name().chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphanumeric() => c.to_ascii_lowercase(),
+                _ => return None,
+            }
+        }
+    };
+
+    let mut bytes = Vec::new();
+    if modifiers.alt {
+        bytes.push(0x1b);
+    }
+    if modifiers.ctrl {
+        let byte = match ch {
+            'a'..='z' => ch as u8 - b'a' + 1,
+            ' ' | '2' => 0,
+            '[' | '3' => 0x1b,
+            '\\' | '4' => 0x1c,
+            ']' | '5' => 0x1d,
+            '6' => 0x1e,
+            '/' | '-' | '7' => 0x1f,
+            _ => return None,
+        };
+        bytes.push(byte);
+    } else if modifiers.shift {
+        bytes.push(ch.to_ascii_uppercase() as u8);
+    } else {
+        bytes.push(ch as u8);
+    }
+    Some(bytes)
+}
+
+/// 粘贴内容是否需要确认：带换行的文本会被立即执行，包含 sudo 的命令会提权
+pub fn is_risky_paste(text: &str) -> bool {
+    text.contains(['\r', '\n'])
+        || text
+            .split(|c: char| !c.is_alphanumeric() && c != '_' && c != '-')
+            .any(|word| word == "sudo")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_control_keys() {
+        assert_eq!(encode_key(Key::Enter, Modifiers::NONE, false), Some(b"\r".to_vec()));
+        assert_eq!(encode_key(Key::Backspace, Modifiers::NONE, false), Some(b"\x7f".to_vec()));
+        assert_eq!(encode_key(Key::Tab, Modifiers::SHIFT, false), Some(b"\x1b[Z".to_vec()));
+        assert_eq!(encode_key(Key::A, Modifiers::NONE, false), None);
+    }
+
+    #[test]
+    fn test_cursor_keys() {
+        assert_eq!(encode_key(Key::ArrowUp, Modifiers::NONE, false), Some(b"\x1b[A".to_vec()));
+        assert_eq!(encode_key(Key::ArrowUp, Modifiers::NONE, true), Some(b"\x1bOA".to_vec()));
+        assert_eq!(
+            encode_key(Key::ArrowLeft, Modifiers::CTRL, false),
+            Some(b"\x1b[1;5D".to_vec())
+        );
+        assert_eq!(encode_key(Key::PageDown, Modifiers::NONE, false), Some(b"\x1b[6~".to_vec()));
+        assert_eq!(encode_key(Key::F1, Modifiers::NONE, false), Some(b"\x1bOP".to_vec()));
+        assert_eq!(encode_key(Key::F5, Modifiers::NONE, false), Some(b"\x1b[15~".to_vec()));
+    }
+
+    #[test]
+    fn test_ctrl_and_alt_chords() {
+        assert_eq!(encode_key(Key::C, Modifiers::CTRL, false), Some(vec![0x03]));
+        assert_eq!(encode_key(Key::D, Modifiers::CTRL, false), Some(vec![0x04]));
+        assert_eq!(encode_key(Key::OpenBracket, Modifiers::CTRL, false), Some(vec![0x1b]));
+        assert_eq!(encode_key(Key::B, Modifiers::ALT, false), Some(b"\x1bb".to_vec()));
+        assert_eq!(
+            encode_key(Key::X, Modifiers::ALT | Modifiers::CTRL, false),
+            Some(vec![0x1b, 0x18])
+        );
+    }
+
+    #[test]
+    fn test_risky_paste() {
+        assert!(!is_risky_paste("ls -la"));
+        assert!(is_risky_paste("ls -la\n"));
+        assert!(is_risky_paste("cd /tmp\nrm -rf *"));
+        assert!(is_risky_paste("echo ok; sudo reboot"));
+        assert!(!is_risky_paste("pseudocode"));
+    }
+}
@@ -0,0 +1,334 @@
+use super::buffer::{Point, TerminalBuffer, TerminalLine};
+use regex::Regex;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+// ============================================================================
+// 终端输出中的链接识别（URL、带行号的文件路径、OSC 8 超链接）
+// ============================================================================
+
+/// 链接指向的目标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    Url(String),
+    /// 文件路径，可带 `:行[:列]` 后缀
+    Path {
+        path: String,
+        line: Option<u32>,
+        column: Option<u32>,
+    },
+}
+
+impl LinkTarget {
+    /// 悬停时显示的实际目标
+    pub fn text(&self) -> String {
+        match self {
+            LinkTarget::Url(url) => url.clone(),
+            LinkTarget::Path { path, line, column } => match (line, column) {
+                (Some(line), Some(column)) => format!("{}:{}:{}", path, line, column),
+                (Some(line), None) => format!("{}:{}", path, line),
+                _ => path.clone(),
+            },
+        }
+    }
+}
+
+/// 一行中识别出的链接
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedLink {
+    /// 占用的单元格列范围
+    pub cols: Range<usize>,
+    pub target: LinkTarget,
+}
+
+fn url_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r#"\b(?:https?|ftp|file)://[^\s<>"'`]+"#).unwrap())
+}
+
+fn path_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"(?:~|\.{1,2})?/?[\w.\-+@]+(?:/[\w.\-+@]+)*/?(?::(\d+)(?::(\d+))?)?").unwrap()
+    })
+}
+
+/// 去掉 URL 末尾的标点和不成对的右括号
+fn trim_url(url: &str) -> &str {
+    let mut url = url;
+    loop {
+        let Some(last) = url.chars().last() else {
+            return url;
+        };
+        let unbalanced = |open: char, close: char| {
+            last == close && url.matches(close).count() > url.matches(open).count()
+        };
+        if ".,;:!?".contains(last) || unbalanced('(', ')') || unbalanced('[', ']') {
+            url = &url[..url.len() - last.len_utf8()];
+        } else {
+            return url;
+        }
+    }
+}
+
+/// 判断匹配到的文本是否像文件路径
+///
+/// 带行号时要求含有 `/` 或扩展名（如 `src/main.rs:42`），
+/// 不带行号时只接受至少两级的绝对路径或 `~/`、`./` 开头的路径。
+fn is_path(path: &str, has_line: bool) -> bool {
+    if !path.chars().any(|c| c.is_alphabetic()) {
+        return false;
+    }
+    if has_line {
+        return path.contains('/') || path.trim_start_matches('.').contains('.');
+    }
+    let rest = ["~/", "./", "../", "/"]
+        .iter()
+        .find_map(|prefix| path.strip_prefix(prefix));
+    rest.is_some_and(|rest| rest.trim_end_matches('/').contains('/') || !path.starts_with('/'))
+}
+
+/// 识别一行文本中的 URL 与文件路径
+pub fn detect_links(line: &TerminalLine) -> Vec<DetectedLink> {
+    let text = line.text();
+    let mut found: Vec<(Range<usize>, LinkTarget)> = Vec::new();
+
+    for m in url_regex().find_iter(&text) {
+        let url = trim_url(m.as_str());
+        found.push((
+            m.start()..m.start() + url.len(),
+            LinkTarget::Url(url.to_string()),
+        ));
+    }
+    for caps in path_regex().captures_iter(&text) {
+        let m = caps.get(0).unwrap();
+        if found
+            .iter()
+            .any(|(range, _)| range.start < m.end() && m.start() < range.end)
+        {
+            continue;
+        }
+        // 前面紧挨字母数字时是更长单词的一部分（如 `a:b/c`）
+        if text[..m.start()]
+            .chars()
+            .last()
+            .is_some_and(|c| c.is_alphanumeric() || c == ':')
+        {
+            continue;
+        }
+        let number = |i: usize| caps.get(i).and_then(|n| n.as_str().parse().ok());
+        let (line, column) = (number(1), number(2));
+        let path_end = caps.get(1).map_or(m.end(), |n| n.start() - 1);
+        let path = &text[m.start()..path_end];
+        if is_path(path, line.is_some()) {
+            let target = LinkTarget::Path {
+                path: path.to_string(),
+                line,
+                column,
+            };
+            found.push((m.range(), target));
+        }
+    }
+
+    found.sort_by_key(|(range, _)| range.start);
+    found
+        .into_iter()
+        .map(|(range, target)| DetectedLink {
+            cols: line.column_range(range),
+            target,
+        })
+        .collect()
+}
+
+/// 查找指定位置的链接，OSC 8 超链接优先于文本识别
+pub fn link_at(terminal: &TerminalBuffer, point: Point) -> Option<DetectedLink> {
+    let line = terminal.line(point.line)?;
+    let link = line.cells.get(point.col).map_or(0, |cell| cell.link);
+    if let Some(target) = terminal.hyperlink(link).and_then(hyperlink_target) {
+        let start = (0..point.col)
+            .rev()
+            .take_while(|&col| line.cells[col].link == link)
+            .last()
+            .unwrap_or(point.col);
+        let end = (point.col..line.cells.len())
+            .take_while(|&col| line.cells[col].link == link)
+            .last()
+            .map_or(point.col + 1, |col| col + 1);
+        return Some(DetectedLink {
+            cols: start..end,
+            target,
+        });
+    }
+    detect_links(line)
+        .into_iter()
+        .find(|link| link.cols.contains(&point.col))
+}
+
+/// OSC 8 链接允许直接打开的协议
+const HYPERLINK_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// OSC 8 的 file:// 链接按路径处理，http/https/mailto 作为 URL，其余协议忽略
+///
+/// 链接文字由远端程序任意指定，不能让它借此打开本机的其他协议处理程序。
+fn hyperlink_target(uri: &str) -> Option<LinkTarget> {
+    if let Some(rest) = uri.strip_prefix("file://") {
+        return Some(LinkTarget::Path {
+            path: rest.find('/').map_or("/", |i| &rest[i..]).to_string(),
+            line: None,
+            column: None,
+        });
+    }
+    let (scheme, _) = uri.split_once(':')?;
+    HYPERLINK_SCHEMES
+        .iter()
+        .any(|s| s.eq_ignore_ascii_case(scheme))
+        .then(|| LinkTarget::Url(uri.to_string()))
+}
+
+/// 规范化终端中显示的路径
+///
+/// 相对路径基于 `cwd`（OSC 7 报告的目录），`~` 基于 `home`；
+/// 两者未知时返回相对路径（SFTP 会相对登录目录解析）。
+pub fn resolve_path(path: &str, cwd: Option<&str>, home: Option<&str>) -> String {
+    let joined = match (path.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) => format!("{}/{}", home, rest),
+        (Some(rest), None) => rest.trim_start_matches('/').to_string(),
+        (None, _) if path.starts_with('/') => path.to_string(),
+        (None, _) => match cwd.or(home) {
+            Some(base) => format!("{}/{}", base, path),
+            None => path.to_string(),
+        },
+    };
+    let absolute = joined.starts_with('/');
+    let mut parts: Vec<&str> = Vec::new();
+    for part in joined.split('/') {
+        match part {
+            "" | "." => {}
+            ".." if parts.last().is_some_and(|p| *p != "..") => {
+                parts.pop();
+            }
+            ".." if absolute => {}
+            part => parts.push(part),
+        }
+    }
+    match (absolute, parts.is_empty()) {
+        (true, _) => format!("/{}", parts.join("/")),
+        (false, true) => ".".to_string(),
+        (false, false) => parts.join("/"),
+    }
+}
+
+/// 路径所在的目录（与 `resolve_path` 的结果配合使用）
+pub fn parent_dir(path: &str) -> String {
+    match path.rsplit_once('/') {
+        Some(("", _)) => "/".to_string(),
+        Some((parent, _)) => parent.to_string(),
+        None => ".".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(text: &str) -> Vec<(Range<usize>, LinkTarget)> {
+        let mut terminal = TerminalBuffer::with_size(100, 120, 5);
+        terminal.feed(text);
+        detect_links(terminal.line(0).unwrap())
+            .into_iter()
+            .map(|link| (link.cols, link.target))
+            .collect()
+    }
+
+    fn path(path: &str, line: Option<u32>, column: Option<u32>) -> LinkTarget {
+        LinkTarget::Path {
+            path: path.to_string(),
+            line,
+            column,
+        }
+    }
+
+    #[test]
+    fn test_detect_urls() {
+        assert_eq!(
+            links("见 https://example.com/a_(b)?q=1. 以及 (http://x.io/y)"),
+            vec![
+                (
+                    3..32,
+                    LinkTarget::Url("https://example.com/a_(b)?q=1".to_string())
+                ),
+                (40..53, LinkTarget::Url("http://x.io/y".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detect_paths_with_line_numbers() {
+        assert_eq!(
+            links("error: src/main.rs:42:5 and /etc/nginx/nginx.conf"),
+            vec![
+                (7..23, path("src/main.rs", Some(42), Some(5))),
+                (28..49, path("/etc/nginx/nginx.conf", None, None)),
+            ]
+        );
+        // 时间、版本号和单独的单词不是路径
+        assert!(links("at 12:30:01 v1.2.3 build/ ok").is_empty());
+        assert_eq!(
+            links("~/notes.md:3"),
+            vec![(0..12, path("~/notes.md", Some(3), None))]
+        );
+    }
+
+    #[test]
+    fn test_link_at_prefers_osc8() {
+        let mut terminal = TerminalBuffer::with_size(100, 40, 5);
+        terminal.feed("ab \x1b]8;;https://example.com\x07点这里\x1b]8;;\x07 /var/log/syslog");
+        let link = link_at(&terminal, Point { line: 0, col: 5 }).unwrap();
+        assert_eq!(link.cols, 3..9);
+        assert_eq!(
+            link.target,
+            LinkTarget::Url("https://example.com".to_string())
+        );
+        let link = link_at(&terminal, Point { line: 0, col: 12 }).unwrap();
+        assert_eq!(link.target, path("/var/log/syslog", None, None));
+        assert!(link_at(&terminal, Point { line: 0, col: 0 }).is_none());
+    }
+
+    #[test]
+    fn test_osc8_scheme_allowlist() {
+        let mut terminal = TerminalBuffer::with_size(100, 40, 5);
+        terminal.feed(
+            "\x1b]8;;MAILTO:a@b.c\x07mail\x1b]8;;\x07 \x1b]8;;smb://evil/share\x07docs\x1b]8;;\x07 \
+             \x1b]8;;file://host/etc/hosts\x07hosts\x1b]8;;\x07",
+        );
+        let link = link_at(&terminal, Point { line: 0, col: 1 }).unwrap();
+        assert_eq!(link.target, LinkTarget::Url("MAILTO:a@b.c".to_string()));
+        assert_eq!(link.target.text(), "MAILTO:a@b.c");
+        // 其他协议不作为链接
+        assert!(link_at(&terminal, Point { line: 0, col: 6 }).is_none());
+        let link = link_at(&terminal, Point { line: 0, col: 11 }).unwrap();
+        assert_eq!(link.target, path("/etc/hosts", None, None));
+        assert_eq!(path("a.rs", Some(3), Some(7)).text(), "a.rs:3:7");
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let home = Some("/home/me");
+        assert_eq!(
+            resolve_path("src/../lib.rs", Some("/srv/app"), home),
+            "/srv/app/lib.rs"
+        );
+        assert_eq!(resolve_path("~/a/./b", None, home), "/home/me/a/b");
+        assert_eq!(resolve_path("x.txt", None, home), "/home/me/x.txt");
+        assert_eq!(
+            resolve_path("/etc/../../hosts", Some("/tmp"), home),
+            "/hosts"
+        );
+        // 远端家目录未知时保持相对路径
+        assert_eq!(resolve_path("~/notes/a.md", None, None), "notes/a.md");
+        assert_eq!(resolve_path("../x", None, None), "../x");
+        assert_eq!(parent_dir("/etc/hosts"), "/etc");
+        assert_eq!(parent_dir("/hosts"), "/");
+        assert_eq!(parent_dir("a.md"), ".");
+    }
+}
@@ -0,0 +1,20 @@
+// ============================================================================
+// 终端模拟 (v0.4.0)
+// ============================================================================
+
+pub mod ansi;
+pub mod blocks;
+pub mod buffer;
+pub mod input;
+pub mod links;
+pub mod mouse;
+
+pub use ansi::{AnsiAction, AnsiParser, CellStyle, CsiSequence, TermColor};
+pub use blocks::{format_duration, CommandBlock};
+pub use buffer::{
+    char_width, str_width, Cell, ClipboardRequest, Cursor, Point, Selection, TerminalBuffer,
+    TerminalLine, TerminalModes,
+};
+pub use input::{encode_key, is_risky_paste};
+pub use links::{detect_links, link_at, parent_dir, resolve_path, DetectedLink, LinkTarget};
+pub use mouse::{encode_mouse, MouseButton, MouseEvent, MouseEventKind, MouseTracking};
@@ -0,0 +1,183 @@
+use egui::Modifiers;
+
+// ============================================================================
+// 鼠标事件上报（xterm 鼠标跟踪）
+// ============================================================================
+
+/// 程序通过 DECSET 开启的鼠标跟踪模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MouseTracking {
+    #[default]
+    Off,
+    /// ?9：只上报按下
+    X10,
+    /// ?1000：上报按下与释放
+    Normal,
+    /// ?1002：另外上报按住按键时的移动
+    ButtonEvent,
+    /// ?1003：上报所有移动
+    AnyEvent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseEventKind {
+    Press(MouseButton),
+    Release(MouseButton),
+    /// 指针移动，附带当前按住的按键
+    Motion(Option<MouseButton>),
+}
+
+/// 屏幕上的一次鼠标事件（行列均从 0 开始）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    pub kind: MouseEventKind,
+    pub col: usize,
+    pub row: usize,
+    pub modifiers: Modifiers,
+}
+
+fn button_code(button: MouseButton) -> u32 {
+    match button {
+        MouseButton::Left => 0,
+        MouseButton::Middle => 1,
+        MouseButton::Right => 2,
+        MouseButton::WheelUp => 64,
+        MouseButton::WheelDown => 65,
+    }
+}
+
+/// 按当前跟踪模式编码鼠标事件，模式不上报该事件时返回 None
+///
+/// `sgr` 为真时使用 SGR-1006 编码 `CSI < b ; x ; y M/m`，否则使用
+/// 传统的 `CSI M b x y`（坐标超过 223 时无法表示）。
+pub fn encode_mouse(event: MouseEvent, tracking: MouseTracking, sgr: bool) -> Option<Vec<u8>> {
+    let reported = match (tracking, event.kind) {
+        (MouseTracking::Off, _) => false,
+        (MouseTracking::X10, MouseEventKind::Press(_)) => true,
+        (MouseTracking::X10, _) => false,
+        (_, MouseEventKind::Press(_)) => true,
+        (_, MouseEventKind::Release(button)) => {
+            !matches!(button, MouseButton::WheelUp | MouseButton::WheelDown)
+        }
+        (MouseTracking::ButtonEvent, MouseEventKind::Motion(held)) => held.is_some(),
+        (MouseTracking::AnyEvent, MouseEventKind::Motion(_)) => true,
+        (MouseTracking::Normal, MouseEventKind::Motion(_)) => false,
+    };
+    if !reported {
+        return None;
+    }
+
+    let mut code = match event.kind {
+        MouseEventKind::Press(button) => button_code(button),
+        // 传统编码无法区分释放的是哪个键
+        MouseEventKind::Release(button) if sgr => button_code(button),
+        MouseEventKind::Release(_) => 3,
+        MouseEventKind::Motion(held) => 32 + held.map(button_code).unwrap_or(3),
+    };
+    if tracking != MouseTracking::X10 {
+        let m = event.modifiers;
+        code += 4 * m.shift as u32 + 8 * m.alt as u32 + 16 * m.ctrl as u32;
+    }
+
+    let (x, y) = (event.col + 1, event.row + 1);
+    if sgr {
+        let final_char = if matches!(event.kind, MouseEventKind::Release(_)) { 'm' } else { 'M' };
+        return Some(format!("\x1b[<{};{};{}{}", code, x, y, final_char).into_bytes());
+    }
+
+    if x > 223 || y > 223 {
+        return None;
+    }
+    Some(vec![
+        0x1b,
+        b'[',
+        b'M',
+        32 + code as u8,
+        32 + x as u8,
+        32 + y as u8,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: MouseEventKind, col: usize, row: usize) -> MouseEvent {
+        MouseEvent {
+            kind,
+            col,
+            row,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn test_tracking_modes_filter_events() {
+        let press = event(MouseEventKind::Press(MouseButton::Left), 0, 0);
+        let release = event(MouseEventKind::Release(MouseButton::Left), 0, 0);
+        let drag = event(MouseEventKind::Motion(Some(MouseButton::Left)), 1, 0);
+        let hover = event(MouseEventKind::Motion(None), 1, 0);
+
+        assert_eq!(encode_mouse(press, MouseTracking::Off, true), None);
+        assert!(encode_mouse(press, MouseTracking::X10, false).is_some());
+        assert_eq!(encode_mouse(release, MouseTracking::X10, false), None);
+        assert!(encode_mouse(release, MouseTracking::Normal, false).is_some());
+        assert_eq!(encode_mouse(drag, MouseTracking::Normal, false), None);
+        assert!(encode_mouse(drag, MouseTracking::ButtonEvent, false).is_some());
+        assert_eq!(encode_mouse(hover, MouseTracking::ButtonEvent, false), None);
+        assert!(encode_mouse(hover, MouseTracking::AnyEvent, false).is_some());
+    }
+
+    #[test]
+    fn test_sgr_encoding() {
+        let press = event(MouseEventKind::Press(MouseButton::Right), 9, 4);
+        assert_eq!(
+            encode_mouse(press, MouseTracking::Normal, true),
+            Some(b"\x1b[<2;10;5M".to_vec())
+        );
+        let release = event(MouseEventKind::Release(MouseButton::Right), 9, 4);
+        assert_eq!(
+            encode_mouse(release, MouseTracking::Normal, true),
+            Some(b"\x1b[<2;10;5m".to_vec())
+        );
+        let wheel = MouseEvent {
+            modifiers: Modifiers::CTRL,
+            ..event(MouseEventKind::Press(MouseButton::WheelDown), 300, 0)
+        };
+        assert_eq!(
+            encode_mouse(wheel, MouseTracking::Normal, true),
+            Some(b"\x1b[<81;301;1M".to_vec())
+        );
+        let drag = event(MouseEventKind::Motion(Some(MouseButton::Left)), 0, 0);
+        assert_eq!(
+            encode_mouse(drag, MouseTracking::ButtonEvent, true),
+            Some(b"\x1b[<32;1;1M".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_legacy_encoding() {
+        let press = event(MouseEventKind::Press(MouseButton::Left), 0, 0);
+        assert_eq!(
+            encode_mouse(press, MouseTracking::Normal, false),
+            Some(vec![0x1b, b'[', b'M', 32, 33, 33])
+        );
+        let release = event(MouseEventKind::Release(MouseButton::Left), 2, 1);
+        assert_eq!(
+            encode_mouse(release, MouseTracking::Normal, false),
+            Some(vec![0x1b, b'[', b'M', 35, 35, 34])
+        );
+        // 超出传统编码范围的坐标不上报
+        let far = event(MouseEventKind::Press(MouseButton::Left), 230, 0);
+        assert_eq!(encode_mouse(far, MouseTracking::Normal, false), None);
+    }
+}
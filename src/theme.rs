@@ -1,17 +1,28 @@
+use crate::color_scheme::RgbColor;
+use crate::terminal::{CellStyle, TermColor};
+use crate::types::Result;
 use egui::{Color32, Context, Visuals};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 /// 主题类型
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum Theme {
+    #[default]
     Dark,
     Light,
     Custom,
 }
 
-impl Default for Theme {
-    fn default() -> Self {
-        Theme::Dark
+impl Theme {
+    /// 从设置中的主题名称解析
+    pub fn from_name(theme_name: &str) -> Self {
+        match theme_name {
+            "light" => Theme::Light,
+            "dark" => Theme::Dark,
+            "custom" => Theme::Custom,
+            _ => Theme::Dark,
+        }
     }
 }
 
@@ -25,26 +36,156 @@ impl std::fmt::Display for Theme {
     }
 }
 
+/// 内置主题名称，用户主题不能使用
+const RESERVED_THEME_NAMES: [&str; 3] = ["dark", "light", "custom"];
+
+/// 用户自定义主题（保存在配置中，可导出为独立 TOML 文件）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomTheme {
+    pub name: String,
+    /// 基于深色（否则浅色）视觉样式
+    pub dark_base: bool,
+    pub panel_fill: RgbColor,
+    pub window_fill: RgbColor,
+    pub text_color: RgbColor,
+    pub accent: RgbColor,
+    pub selection: RgbColor,
+    pub widget_rounding: f32,
+    pub item_spacing: [f32; 2],
+}
+
+impl Default for CustomTheme {
+    fn default() -> Self {
+        Self {
+            name: "My Theme".to_string(),
+            dark_base: true,
+            panel_fill: RgbColor(30, 30, 40),
+            window_fill: RgbColor(25, 25, 35),
+            text_color: RgbColor(220, 220, 230),
+            accent: RgbColor(100, 149, 237),
+            selection: RgbColor(100, 149, 237),
+            widget_rounding: 2.0,
+            item_spacing: [8.0, 3.0],
+        }
+    }
+}
+
+impl CustomTheme {
+    /// 检查主题名称是否可用
+    pub fn validate(&self) -> Result<()> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("Theme name cannot be empty".into());
+        }
+        if RESERVED_THEME_NAMES.contains(&name) {
+            return Err(format!("Theme name '{}' is reserved", name).into());
+        }
+        Ok(())
+    }
+
+    /// 生成 egui 视觉样式
+    pub fn to_visuals(&self) -> Visuals {
+        let mut visuals = if self.dark_base {
+            Visuals::dark()
+        } else {
+            Visuals::light()
+        };
+
+        let text = self.text_color.to_color32();
+        let accent = self.accent.to_color32();
+        let rounding = egui::Rounding::same(self.widget_rounding);
+
+        visuals.panel_fill = self.panel_fill.to_color32();
+        visuals.window_fill = self.window_fill.to_color32();
+        visuals.hyperlink_color = accent;
+        visuals.selection.bg_fill = self.selection.to_color32();
+        visuals.selection.stroke.color = text;
+
+        visuals.widgets.noninteractive.fg_stroke.color = text;
+        visuals.widgets.inactive.fg_stroke.color = text;
+        visuals.widgets.hovered.bg_stroke.color = accent;
+        visuals.widgets.active.bg_fill = accent;
+        visuals.widgets.active.weak_bg_fill = accent;
+
+        for widget in [
+            &mut visuals.widgets.noninteractive,
+            &mut visuals.widgets.inactive,
+            &mut visuals.widgets.hovered,
+            &mut visuals.widgets.active,
+            &mut visuals.widgets.open,
+        ] {
+            widget.rounding = rounding;
+        }
+        visuals.menu_rounding = rounding;
+
+        visuals
+    }
+
+    /// 序列化为 TOML
+    pub fn to_toml_string(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// 从 TOML 解析
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        let theme: CustomTheme = toml::from_str(content)?;
+        theme.validate()?;
+        Ok(theme)
+    }
+
+    /// 导出到文件
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_toml_string()?)?;
+        Ok(())
+    }
+
+    /// 从文件导入
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        Self::from_toml_str(&std::fs::read_to_string(path)?)
+    }
+}
+
 /// 主题管理器
 pub struct ThemeManager;
 
 impl ThemeManager {
     /// 应用主题到上下文
-    pub fn apply(ctx: &Context, theme_name: &str) {
-        let theme = match theme_name {
-            "light" => Theme::Light,
-            "dark" => Theme::Dark,
-            "custom" => Theme::Custom,
-            _ => Theme::Dark,
-        };
+    ///
+    /// `theme_name` 可以是内置主题名称，也可以是用户自定义主题的名称。
+    pub fn apply(ctx: &Context, theme_name: &str, custom_themes: &[CustomTheme]) {
+        if let Some(theme) = Self::find_custom(theme_name, custom_themes) {
+            Self::apply_custom(ctx, theme);
+            return;
+        }
 
-        let visuals = match theme {
+        let visuals = match Theme::from_name(theme_name) {
             Theme::Dark => Self::dark_theme(),
             Theme::Light => Self::light_theme(),
             Theme::Custom => Self::custom_theme(),
         };
 
         ctx.set_visuals(visuals);
+        Self::apply_item_spacing(ctx, egui::style::Spacing::default().item_spacing);
+    }
+
+    /// 应用用户自定义主题（也用于编辑器实时预览）
+    pub fn apply_custom(ctx: &Context, theme: &CustomTheme) {
+        ctx.set_visuals(theme.to_visuals());
+        Self::apply_item_spacing(ctx, egui::vec2(theme.item_spacing[0], theme.item_spacing[1]));
+    }
+
+    fn apply_item_spacing(ctx: &Context, spacing: egui::Vec2) {
+        if ctx.style().spacing.item_spacing != spacing {
+            ctx.style_mut(|style| style.spacing.item_spacing = spacing);
+        }
+    }
+
+    /// 按名称查找用户自定义主题
+    pub fn find_custom<'a>(theme_name: &str, custom_themes: &'a [CustomTheme]) -> Option<&'a CustomTheme> {
+        if RESERVED_THEME_NAMES.contains(&theme_name) {
+            return None;
+        }
+        custom_themes.iter().find(|t| t.name == theme_name)
     }
 
     /// 深色主题
@@ -104,6 +245,34 @@ impl ThemeManager {
         visuals
     }
 
+    /// 获取主题对应的终端调色板
+    pub fn terminal_palette(theme_name: &str, custom_themes: &[CustomTheme]) -> TerminalPalette {
+        if let Some(theme) = Self::find_custom(theme_name, custom_themes) {
+            let base = if theme.dark_base {
+                TerminalPalette::dark()
+            } else {
+                TerminalPalette::light()
+            };
+            return TerminalPalette {
+                background: theme.window_fill.to_color32(),
+                foreground: theme.text_color.to_color32(),
+                cursor: theme.accent.to_color32(),
+                ..base
+            };
+        }
+
+        match Theme::from_name(theme_name) {
+            Theme::Dark => TerminalPalette::dark(),
+            Theme::Light => TerminalPalette::light(),
+            Theme::Custom => TerminalPalette {
+                background: Color32::from_rgb(25, 25, 35),
+                foreground: Color32::from_rgb(220, 220, 230),
+                cursor: Color32::from_rgb(100, 149, 237),
+                ..TerminalPalette::dark()
+            },
+        }
+    }
+
     /// 获取主题名称列表
     pub fn theme_names() -> Vec<&'static str> {
         vec!["dark", "light", "custom"]
@@ -128,6 +297,128 @@ impl ThemeManager {
     }
 }
 
+/// 终端调色板（16 色 + 前景/背景/光标）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerminalPalette {
+    pub foreground: Color32,
+    pub background: Color32,
+    pub cursor: Color32,
+    pub ansi: [Color32; 16],
+}
+
+impl TerminalPalette {
+    /// 深色调色板
+    pub fn dark() -> Self {
+        Self {
+            foreground: Color32::from_rgb(204, 204, 204),
+            background: Color32::from_rgb(24, 24, 24),
+            cursor: Color32::from_rgb(220, 220, 220),
+            ansi: [
+                Color32::from_rgb(0, 0, 0),
+                Color32::from_rgb(205, 49, 49),
+                Color32::from_rgb(13, 188, 121),
+                Color32::from_rgb(229, 229, 16),
+                Color32::from_rgb(36, 114, 200),
+                Color32::from_rgb(188, 63, 188),
+                Color32::from_rgb(17, 168, 205),
+                Color32::from_rgb(229, 229, 229),
+                Color32::from_rgb(102, 102, 102),
+                Color32::from_rgb(241, 76, 76),
+                Color32::from_rgb(35, 209, 139),
+                Color32::from_rgb(245, 245, 67),
+                Color32::from_rgb(59, 142, 234),
+                Color32::from_rgb(214, 112, 214),
+                Color32::from_rgb(41, 184, 219),
+                Color32::from_rgb(255, 255, 255),
+            ],
+        }
+    }
+
+    /// 浅色调色板
+    pub fn light() -> Self {
+        Self {
+            foreground: Color32::from_rgb(51, 51, 51),
+            background: Color32::from_rgb(252, 252, 252),
+            cursor: Color32::from_rgb(51, 51, 51),
+            ansi: [
+                Color32::from_rgb(0, 0, 0),
+                Color32::from_rgb(205, 49, 49),
+                Color32::from_rgb(0, 133, 76),
+                Color32::from_rgb(148, 120, 0),
+                Color32::from_rgb(4, 81, 165),
+                Color32::from_rgb(188, 5, 188),
+                Color32::from_rgb(5, 128, 160),
+                Color32::from_rgb(85, 85, 85),
+                Color32::from_rgb(102, 102, 102),
+                Color32::from_rgb(205, 49, 49),
+                Color32::from_rgb(20, 160, 100),
+                Color32::from_rgb(181, 148, 0),
+                Color32::from_rgb(4, 81, 165),
+                Color32::from_rgb(188, 5, 188),
+                Color32::from_rgb(5, 128, 160),
+                Color32::from_rgb(165, 165, 165),
+            ],
+        }
+    }
+
+    /// 将 256 色索引转换为颜色
+    pub fn indexed(&self, index: u8) -> Color32 {
+        match index {
+            0..=15 => self.ansi[index as usize],
+            16..=231 => {
+                // 6x6x6 颜色立方体
+                let i = index - 16;
+                let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                Color32::from_rgb(level(i / 36), level((i / 6) % 6), level(i % 6))
+            }
+            232..=255 => {
+                // 24 级灰度
+                let gray = 8 + (index - 232) * 10;
+                Color32::from_rgb(gray, gray, gray)
+            }
+        }
+    }
+
+    /// 解析单元格样式的最终前景色与背景色
+    ///
+    /// 处理反显、粗体高亮（基本色 0-7 提升为亮色）、暗淡与隐藏属性。
+    pub fn resolve(&self, style: &CellStyle) -> (Color32, Color32) {
+        let fg_color = match style.fg {
+            TermColor::Indexed(i) if style.bold && i < 8 => TermColor::Indexed(i + 8),
+            other => other,
+        };
+
+        let mut fg = self.color(fg_color, self.foreground);
+        let mut bg = self.color(style.bg, self.background);
+
+        if style.inverse {
+            std::mem::swap(&mut fg, &mut bg);
+        }
+        if style.dim {
+            fg = fg.gamma_multiply(0.6);
+        }
+        if style.hidden {
+            fg = bg;
+        }
+
+        (fg, bg)
+    }
+
+    fn color(&self, color: TermColor, default: Color32) -> Color32 {
+        match color {
+            TermColor::Default => default,
+            TermColor::Indexed(i) => self.indexed(i),
+            TermColor::Rgb(r, g, b) => Color32::from_rgb(r, g, b),
+        }
+    }
+}
+
+impl Default for TerminalPalette {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -147,4 +438,90 @@ mod tests {
         assert!(names.contains(&"light"));
         assert!(names.contains(&"custom"));
     }
+
+    #[test]
+    fn test_terminal_palette_follows_theme() {
+        assert_eq!(ThemeManager::terminal_palette("dark", &[]), TerminalPalette::dark());
+        assert_eq!(ThemeManager::terminal_palette("light", &[]), TerminalPalette::light());
+        assert_eq!(ThemeManager::terminal_palette("unknown", &[]), TerminalPalette::dark());
+
+        let custom = CustomTheme::default();
+        let palette = ThemeManager::terminal_palette(&custom.name, std::slice::from_ref(&custom));
+        assert_eq!(palette.background, custom.window_fill.to_color32());
+    }
+
+    #[test]
+    fn test_custom_theme_toml_roundtrip() {
+        let theme = CustomTheme {
+            name: "Ocean".to_string(),
+            widget_rounding: 6.0,
+            item_spacing: [10.0, 4.0],
+            ..Default::default()
+        };
+        let text = theme.to_toml_string().unwrap();
+        assert!(text.contains("accent = \"#6495ed\""));
+        assert_eq!(CustomTheme::from_toml_str(&text).unwrap(), theme);
+    }
+
+    #[test]
+    fn test_custom_theme_validation() {
+        let mut theme = CustomTheme::default();
+        assert!(theme.validate().is_ok());
+        theme.name = "dark".to_string();
+        assert!(theme.validate().is_err());
+        theme.name = "  ".to_string();
+        assert!(theme.validate().is_err());
+    }
+
+    #[test]
+    fn test_custom_theme_visuals() {
+        let theme = CustomTheme {
+            dark_base: false,
+            widget_rounding: 5.0,
+            ..Default::default()
+        };
+        let visuals = theme.to_visuals();
+        assert!(!visuals.dark_mode);
+        assert_eq!(visuals.panel_fill, theme.panel_fill.to_color32());
+        assert_eq!(visuals.widgets.inactive.rounding, egui::Rounding::same(5.0));
+        assert_eq!(visuals.selection.bg_fill, theme.selection.to_color32());
+
+        // 保留名称不会被当作自定义主题
+        let reserved = CustomTheme {
+            name: "dark".to_string(),
+            ..Default::default()
+        };
+        assert!(ThemeManager::find_custom("dark", &[reserved]).is_none());
+    }
+
+    #[test]
+    fn test_palette_indexed_colors() {
+        let palette = TerminalPalette::dark();
+        assert_eq!(palette.indexed(1), palette.ansi[1]);
+        assert_eq!(palette.indexed(16), Color32::from_rgb(0, 0, 0));
+        assert_eq!(palette.indexed(196), Color32::from_rgb(255, 0, 0));
+        assert_eq!(palette.indexed(232), Color32::from_rgb(8, 8, 8));
+        assert_eq!(palette.indexed(255), Color32::from_rgb(238, 238, 238));
+    }
+
+    #[test]
+    fn test_palette_resolve_style() {
+        let palette = TerminalPalette::dark();
+
+        let bold_red = CellStyle {
+            fg: TermColor::Indexed(1),
+            bold: true,
+            ..Default::default()
+        };
+        assert_eq!(palette.resolve(&bold_red).0, palette.ansi[9]);
+
+        let inverse = CellStyle {
+            inverse: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            palette.resolve(&inverse),
+            (palette.background, palette.foreground)
+        );
+    }
 }
@@ -0,0 +1,903 @@
+use crate::checksum::{ChecksumAlgorithm, Checksums};
+use crate::sftp::{is_connection_error, SftpClient, TransferAborted};
+use crate::ssh::SshSession;
+use crate::types::{Result, SftpMessage, TransferDirection, TransferOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+// ============================================================================
+// 传输队列：并发工作线程、暂停/继续/取消/重试与持久化
+// ============================================================================
+
+/// 工作线程之间发送进度的最小间隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 计算瞬时速度的滑动窗口
+const SPEED_WINDOW: Duration = Duration::from_secs(3);
+
+const CONTROL_RUN: u8 = 0;
+const CONTROL_PAUSE: u8 = 1;
+const CONTROL_CANCEL: u8 = 2;
+
+/// 校验和不一致时自动从头重传的次数
+pub const MAX_CHECKSUM_RETRIES: u32 = 2;
+
+/// 传输项的状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum TransferState {
+    Queued,
+    Running,
+    /// 传输完成，正在比较校验和
+    Verifying,
+    Paused,
+    /// 连接中断，重连后自动续传
+    Interrupted,
+    Failed {
+        error: String,
+    },
+    Cancelled,
+    Completed,
+}
+
+impl TransferState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TransferState::Queued => "等待中",
+            TransferState::Running => "传输中",
+            TransferState::Verifying => "校验中",
+            TransferState::Paused => "已暂停",
+            TransferState::Interrupted => "等待重连",
+            TransferState::Failed { .. } => "失败",
+            TransferState::Cancelled => "已取消",
+            TransferState::Completed => "已完成",
+        }
+    }
+
+    /// 已完成或已取消，不会再自动运行
+    pub fn is_finished(&self) -> bool {
+        matches!(self, TransferState::Completed | TransferState::Cancelled)
+    }
+}
+
+/// 一项传输的进度事件
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransferProgress {
+    pub id: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// 最近几秒的速度（字节/秒）
+    pub speed: f64,
+    /// 本次运行开始以来的平均速度（字节/秒，不含续传前已有的部分）
+    pub average_speed: f64,
+    /// 预计剩余时间，速度未知时为 `None`
+    pub eta: Option<Duration>,
+}
+
+/// 根据进度采样计算速度与剩余时间
+#[derive(Debug, Clone)]
+pub struct ThroughputMeter {
+    started: Instant,
+    start_bytes: u64,
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl ThroughputMeter {
+    pub fn new(bytes_done: u64, now: Instant) -> Self {
+        Self {
+            started: now,
+            start_bytes: bytes_done,
+            samples: VecDeque::from([(now, bytes_done)]),
+        }
+    }
+
+    /// 记录一次进度
+    pub fn sample(&mut self, id: u64, bytes_done: u64, bytes_total: u64, now: Instant) -> TransferProgress {
+        self.samples.push_back((now, bytes_done));
+        // 保留窗口起点之前的最后一个采样作为基准
+        while self.samples.len() > 2 && now.duration_since(self.samples[1].0) >= SPEED_WINDOW {
+            self.samples.pop_front();
+        }
+        let rate = |bytes: u64, since: Instant| {
+            let secs = now.duration_since(since).as_secs_f64();
+            if secs > 0.0 {
+                bytes as f64 / secs
+            } else {
+                0.0
+            }
+        };
+        let (window_start, window_bytes) = self.samples[0];
+        let speed = rate(bytes_done.saturating_sub(window_bytes), window_start);
+        let average_speed = rate(bytes_done.saturating_sub(self.start_bytes), self.started);
+        let basis = if speed > 0.0 { speed } else { average_speed };
+        let remaining = bytes_total.saturating_sub(bytes_done);
+        let eta = if remaining == 0 {
+            Some(Duration::ZERO)
+        } else {
+            (basis > 0.0).then(|| Duration::from_secs_f64(remaining as f64 / basis))
+        };
+        TransferProgress {
+            id,
+            bytes_done,
+            bytes_total,
+            speed,
+            average_speed,
+            eta,
+        }
+    }
+}
+
+/// 多项运行中传输的汇总
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransferSummary {
+    pub count: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub speed: f64,
+    pub eta: Option<Duration>,
+}
+
+impl TransferSummary {
+    pub fn fraction(&self) -> f32 {
+        if self.bytes_total > 0 {
+            (self.bytes_done as f32 / self.bytes_total as f32).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// 队列中的一项传输
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferItem {
+    pub id: u64,
+    /// 连接名称（重启后据此找回连接）
+    pub connection: String,
+    pub direction: TransferDirection,
+    pub is_dir: bool,
+    pub local_path: PathBuf,
+    pub remote_path: String,
+    #[serde(default)]
+    pub options: TransferOptions,
+    pub state: TransferState,
+    #[serde(default)]
+    pub bytes_done: u64,
+    #[serde(default)]
+    pub bytes_total: u64,
+    /// 已经开始写入过目标，再次运行时从已有部分续传
+    #[serde(default)]
+    pub started: bool,
+    /// 校验和不一致的次数
+    #[serde(default)]
+    pub checksum_failures: u32,
+    /// 校验通过时使用的算法
+    #[serde(default)]
+    pub verified: Option<String>,
+    /// 运行中的速度与剩余时间
+    #[serde(skip)]
+    pub progress: Option<TransferProgress>,
+}
+
+impl TransferItem {
+    /// 显示用的名称（源文件或目录名）
+    pub fn name(&self) -> String {
+        match self.direction {
+            TransferDirection::Upload => self
+                .local_path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| self.local_path.display().to_string()),
+            TransferDirection::Download => self
+                .remote_path
+                .trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .unwrap_or(&self.remote_path)
+                .to_string(),
+        }
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.state == TransferState::Completed {
+            1.0
+        } else if self.bytes_total > 0 {
+            (self.bytes_done as f32 / self.bytes_total as f32).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// 工作线程发回的更新
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferUpdate {
+    Progress(TransferProgress),
+    Finished {
+        id: u64,
+    },
+    /// 传输完成，开始校验
+    Verifying {
+        id: u64,
+    },
+    /// 校验和一致
+    Verified {
+        id: u64,
+        algorithm: ChecksumAlgorithm,
+    },
+    ChecksumMismatch {
+        id: u64,
+        checksums: Checksums,
+    },
+    /// 因暂停或取消而停止
+    Stopped {
+        id: u64,
+    },
+    /// 连接中断
+    Interrupted {
+        id: u64,
+    },
+    Failed {
+        id: u64,
+        error: String,
+    },
+}
+
+impl TransferUpdate {
+    pub fn id(&self) -> u64 {
+        match self {
+            TransferUpdate::Progress(TransferProgress { id, .. })
+            | TransferUpdate::Finished { id }
+            | TransferUpdate::Verifying { id }
+            | TransferUpdate::Verified { id, .. }
+            | TransferUpdate::ChecksumMismatch { id, .. }
+            | TransferUpdate::Stopped { id }
+            | TransferUpdate::Interrupted { id }
+            | TransferUpdate::Failed { id, .. } => *id,
+        }
+    }
+
+    /// 工作线程发出此更新后即退出
+    fn is_final(&self) -> bool {
+        !matches!(self, TransferUpdate::Progress(_) | TransferUpdate::Verifying { .. })
+    }
+}
+
+/// 传输管理器
+///
+/// 只维护队列状态；工作线程由界面按 [`TransferManager::startable`] 的结果启动，
+/// 并通过 [`TransferUpdate`] 回报进度。
+#[derive(Debug, Default)]
+pub struct TransferManager {
+    items: Vec<TransferItem>,
+    /// 运行中工作线程的控制标志
+    controls: HashMap<u64, Arc<AtomicU8>>,
+    next_id: u64,
+    /// 队列结构或状态发生变化，需要保存
+    dirty: bool,
+}
+
+impl TransferManager {
+    /// 从保存的队列恢复，上次退出时正在运行的项重新排队
+    pub fn from_items(mut items: Vec<TransferItem>) -> Self {
+        for item in &mut items {
+            if matches!(item.state, TransferState::Running | TransferState::Verifying) {
+                item.state = TransferState::Queued;
+            }
+        }
+        let next_id = items.iter().map(|i| i.id + 1).max().unwrap_or(1);
+        Self {
+            items,
+            controls: HashMap::new(),
+            next_id,
+            dirty: false,
+        }
+    }
+
+    /// 从文件加载队列
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(Self::from_items(serde_json::from_str(&content)?))
+    }
+
+    /// 保存未完成的传输
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let unfinished: Vec<&TransferItem> = self.items.iter().filter(|i| !i.state.is_finished()).collect();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&unfinished)?)?;
+        Ok(())
+    }
+
+    pub fn items(&self) -> &[TransferItem] {
+        &self.items
+    }
+
+    pub fn get(&self, id: u64) -> Option<&TransferItem> {
+        self.items.iter().find(|i| i.id == id)
+    }
+
+    fn get_mut(&mut self, id: u64) -> Option<&mut TransferItem> {
+        self.items.iter_mut().find(|i| i.id == id)
+    }
+
+    /// 加入队列，返回传输编号
+    pub fn enqueue(
+        &mut self,
+        connection: &str,
+        direction: TransferDirection,
+        is_dir: bool,
+        local_path: PathBuf,
+        remote_path: String,
+        options: TransferOptions,
+    ) -> u64 {
+        let id = self.next_id.max(1);
+        self.next_id = id + 1;
+        self.items.push(TransferItem {
+            id,
+            connection: connection.to_string(),
+            direction,
+            is_dir,
+            local_path,
+            remote_path,
+            options,
+            state: TransferState::Queued,
+            bytes_done: 0,
+            bytes_total: 0,
+            started: false,
+            checksum_failures: 0,
+            verified: None,
+            progress: None,
+        });
+        self.dirty = true;
+        id
+    }
+
+    /// 运行中的传输数
+    pub fn running(&self) -> usize {
+        self.items.iter().filter(|i| i.state == TransferState::Running).count()
+    }
+
+    /// 尚未完成（含暂停、失败）的传输数
+    pub fn unfinished(&self) -> usize {
+        self.items.iter().filter(|i| !i.state.is_finished()).count()
+    }
+
+    /// 运行中传输的总体进度，没有运行中的传输时为 `None`
+    pub fn running_summary(&self) -> Option<TransferSummary> {
+        let mut summary = TransferSummary {
+            count: 0,
+            bytes_done: 0,
+            bytes_total: 0,
+            speed: 0.0,
+            eta: None,
+        };
+        for item in self.items.iter().filter(|i| i.state == TransferState::Running) {
+            summary.count += 1;
+            summary.bytes_done += item.bytes_done;
+            summary.bytes_total += item.bytes_total;
+            summary.speed += item.progress.map_or(0.0, |p| p.speed);
+        }
+        if summary.count == 0 {
+            return None;
+        }
+        let remaining = summary.bytes_total.saturating_sub(summary.bytes_done);
+        summary.eta = (summary.speed > 0.0).then(|| Duration::from_secs_f64(remaining as f64 / summary.speed));
+        Some(summary)
+    }
+
+    /// 按队列顺序选出可以启动的传输
+    ///
+    /// 运行数（含已暂停但工作线程尚未退出的）不超过 `concurrency`，
+    /// 且只启动 `is_ready` 认为已连接的连接上的传输。
+    pub fn startable(&self, concurrency: usize, is_ready: impl Fn(&str) -> bool) -> Vec<u64> {
+        let busy = self.controls.len();
+        self.items
+            .iter()
+            .filter(|i| i.state == TransferState::Queued && !self.controls.contains_key(&i.id))
+            .filter(|i| is_ready(&i.connection))
+            .take(concurrency.max(1).saturating_sub(busy))
+            .map(|i| i.id)
+            .collect()
+    }
+
+    /// 标记为运行中，返回交给工作线程的项（`started` 为再次运行前的值）和控制标志
+    pub fn start(&mut self, id: u64) -> Option<(TransferItem, Arc<AtomicU8>)> {
+        let item = self.get_mut(id)?;
+        if item.state != TransferState::Queued {
+            return None;
+        }
+        let snapshot = item.clone();
+        item.state = TransferState::Running;
+        item.started = true;
+        let control = Arc::new(AtomicU8::new(CONTROL_RUN));
+        self.controls.insert(id, Arc::clone(&control));
+        self.dirty = true;
+        Some((snapshot, control))
+    }
+
+    fn signal(&self, id: u64, control: u8) {
+        if let Some(flag) = self.controls.get(&id) {
+            flag.store(control, Ordering::Relaxed);
+        }
+    }
+
+    pub fn pause(&mut self, id: u64) {
+        if let Some(item) = self.get_mut(id) {
+            if matches!(
+                item.state,
+                TransferState::Queued | TransferState::Running | TransferState::Interrupted
+            ) {
+                item.state = TransferState::Paused;
+                self.dirty = true;
+                self.signal(id, CONTROL_PAUSE);
+            }
+        }
+    }
+
+    /// 继续已暂停或等待重连的传输
+    pub fn resume(&mut self, id: u64) {
+        if let Some(item) = self.get_mut(id) {
+            if matches!(item.state, TransferState::Paused | TransferState::Interrupted) {
+                item.state = TransferState::Queued;
+                self.dirty = true;
+            }
+        }
+    }
+
+    pub fn cancel(&mut self, id: u64) {
+        if let Some(item) = self.get_mut(id) {
+            if !item.state.is_finished() {
+                item.state = TransferState::Cancelled;
+                self.dirty = true;
+                self.signal(id, CONTROL_CANCEL);
+            }
+        }
+    }
+
+    /// 重新运行失败或已取消的传输（从头开始）
+    pub fn retry(&mut self, id: u64) {
+        if let Some(item) = self.get_mut(id) {
+            if matches!(item.state, TransferState::Failed { .. } | TransferState::Cancelled) {
+                item.state = TransferState::Queued;
+                item.bytes_done = 0;
+                item.started = false;
+                item.checksum_failures = 0;
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// 从列表移除（运行中的项需先取消）
+    pub fn remove(&mut self, id: u64) {
+        let before = self.items.len();
+        self.items
+            .retain(|i| i.id != id || i.state == TransferState::Running || self.controls.contains_key(&i.id));
+        self.dirty |= self.items.len() != before;
+    }
+
+    /// 清除已完成和已取消的项
+    pub fn clear_finished(&mut self) {
+        let controls = &self.controls;
+        self.items
+            .retain(|i| !i.state.is_finished() || controls.contains_key(&i.id));
+        self.dirty = true;
+    }
+
+    /// 连接重新建立后，该连接上等待重连的传输重新排队
+    pub fn reconnected(&mut self, connection: &str) {
+        for item in &mut self.items {
+            if item.connection == connection && item.state == TransferState::Interrupted {
+                item.state = TransferState::Queued;
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// 处理工作线程的更新，返回更新后的项
+    pub fn apply(&mut self, update: TransferUpdate) -> Option<&TransferItem> {
+        let id = update.id();
+        let is_final = update.is_final();
+        if is_final {
+            self.controls.remove(&id);
+            self.dirty = true;
+        }
+        let item = self.items.iter_mut().find(|i| i.id == id)?;
+        let running = item.state == TransferState::Running;
+        let active = running || item.state == TransferState::Verifying;
+        if is_final {
+            item.progress = None;
+        }
+        match update {
+            TransferUpdate::Progress(progress) => {
+                item.bytes_done = progress.bytes_done;
+                item.bytes_total = progress.bytes_total;
+                item.progress = running.then_some(progress);
+            }
+            TransferUpdate::Verifying { .. } if running => {
+                item.state = TransferState::Verifying;
+                item.progress = None;
+            }
+            TransferUpdate::Finished { .. } if active => {
+                item.state = TransferState::Completed;
+                item.bytes_done = item.bytes_total;
+            }
+            TransferUpdate::Verified { algorithm, .. } if active => {
+                item.state = TransferState::Completed;
+                item.bytes_done = item.bytes_total;
+                item.verified = Some(algorithm.name().to_string());
+            }
+            TransferUpdate::ChecksumMismatch { checksums, .. } if active => {
+                item.checksum_failures += 1;
+                if item.checksum_failures <= MAX_CHECKSUM_RETRIES {
+                    // 从头重新传输
+                    item.state = TransferState::Queued;
+                    item.bytes_done = 0;
+                    item.started = false;
+                } else {
+                    item.state = TransferState::Failed {
+                        error: format!(
+                            "{} 校验和不一致（已重试 {} 次）：本地 {}… ≠ 远程 {}…",
+                            checksums.algorithm.name(),
+                            MAX_CHECKSUM_RETRIES,
+                            &checksums.local[..12.min(checksums.local.len())],
+                            &checksums.remote[..12.min(checksums.remote.len())]
+                        ),
+                    };
+                }
+            }
+            // 暂停和取消时状态已由界面设置
+            TransferUpdate::Stopped { .. } => {}
+            TransferUpdate::Interrupted { .. } if active => item.state = TransferState::Interrupted,
+            TransferUpdate::Failed { error, .. } if active => item.state = TransferState::Failed { error },
+            TransferUpdate::Verifying { .. }
+            | TransferUpdate::Finished { .. }
+            | TransferUpdate::Verified { .. }
+            | TransferUpdate::ChecksumMismatch { .. }
+            | TransferUpdate::Interrupted { .. }
+            | TransferUpdate::Failed { .. } => {}
+        }
+        Some(item)
+    }
+
+    /// 返回并清除“需要保存”标记
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+}
+
+/// 在后台线程中执行一项传输
+///
+/// 每个工作线程打开独立的 SFTP 通道，只在打开时短暂持有会话锁，
+/// 因此多个传输可以在同一连接上并行。启用校验时，单文件传输完成后
+/// 再通过 exec 通道计算远程校验和并与本地比较。
+///
+/// 未解析的符号链接以文件入队，下载前先 stat 远程路径，指向目录时按目录递归下载。
+pub fn spawn_worker(
+    mut item: TransferItem,
+    session: Arc<Mutex<SshSession>>,
+    control: Arc<AtomicU8>,
+    chunk_size: usize,
+    verify_tail: bool,
+    tx: mpsc::UnboundedSender<SftpMessage>,
+) {
+    std::thread::spawn(move || {
+        let id = item.id;
+        let sftp = session
+            .lock()
+            .unwrap()
+            .sftp()
+            .map(|sftp| sftp.with_chunk_size(chunk_size));
+        let result = sftp.and_then(|sftp| {
+            if item.direction == TransferDirection::Download && !item.is_dir {
+                item.is_dir = sftp.stat(&item.remote_path).is_ok_and(|entry| entry.is_dir);
+            }
+            run_transfer(&sftp, &item, &control, verify_tail, &tx)
+        });
+        let result = match result {
+            Ok(()) if item.options.verify_checksum && !item.is_dir => {
+                let _ = tx.send(SftpMessage::Transfer(TransferUpdate::Verifying { id }));
+                crate::checksum::compare(&session, &item.local_path, &item.remote_path).map(Some)
+            }
+            result => result.map(|()| None),
+        };
+        let update = match result {
+            Ok(None) => TransferUpdate::Finished { id },
+            Ok(Some(checksums)) if checksums.matches() => TransferUpdate::Verified {
+                id,
+                algorithm: checksums.algorithm,
+            },
+            Ok(Some(checksums)) => TransferUpdate::ChecksumMismatch { id, checksums },
+            Err(e) if e.downcast_ref::<TransferAborted>().is_some() => TransferUpdate::Stopped { id },
+            Err(e) if is_connection_error(e.as_ref()) => TransferUpdate::Interrupted { id },
+            Err(e) => TransferUpdate::Failed {
+                id,
+                error: e.to_string(),
+            },
+        };
+        let _ = tx.send(SftpMessage::Transfer(update));
+    });
+}
+
+fn run_transfer(
+    sftp: &SftpClient,
+    item: &TransferItem,
+    control: &AtomicU8,
+    verify_tail: bool,
+    tx: &mpsc::UnboundedSender<SftpMessage>,
+) -> Result<()> {
+    let mut last_sent: Option<Instant> = None;
+    let mut meter: Option<ThroughputMeter> = None;
+    let mut report = |bytes_done: u64, bytes_total: u64| {
+        let now = Instant::now();
+        // 第一次回调时的字节数是续传起点，不计入速度
+        let meter = meter.get_or_insert_with(|| ThroughputMeter::new(bytes_done, now));
+        let finished = bytes_done >= bytes_total;
+        if finished || last_sent.is_none_or(|t| now.duration_since(t) >= PROGRESS_INTERVAL) {
+            last_sent = Some(now);
+            let progress = meter.sample(item.id, bytes_done, bytes_total, now);
+            let _ = tx.send(SftpMessage::Transfer(TransferUpdate::Progress(progress)));
+        }
+        control.load(Ordering::Relaxed) == CONTROL_RUN
+    };
+
+    let result = match (item.direction, item.is_dir) {
+        (TransferDirection::Upload, false) => sftp
+            .resume_upload(&item.local_path, &item.remote_path, item.started, verify_tail, report)
+            .map(drop),
+        (TransferDirection::Download, false) => sftp
+            .resume_download(&item.remote_path, &item.local_path, item.started, verify_tail, report)
+            .map(drop),
+        (TransferDirection::Upload, true) => sftp
+            .upload_dir(&item.local_path, &item.remote_path, item.options, |p| {
+                report(p.bytes_done, p.bytes_total)
+            })
+            .map(drop),
+        (TransferDirection::Download, true) => sftp
+            .download_dir(&item.remote_path, &item.local_path, item.options, |p| {
+                report(p.bytes_done, p.bytes_total)
+            })
+            .map(drop),
+    };
+
+    // 取消的单文件传输删除不完整的目标
+    if result.is_err() && !item.is_dir && control.load(Ordering::Relaxed) == CONTROL_CANCEL {
+        match item.direction {
+            TransferDirection::Upload => {
+                let _ = sftp.delete(&item.remote_path);
+            }
+            TransferDirection::Download => {
+                let _ = std::fs::remove_file(&item.local_path);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager_with(count: usize) -> TransferManager {
+        let mut manager = TransferManager::default();
+        for n in 0..count {
+            manager.enqueue(
+                if n % 2 == 0 { "web" } else { "db" },
+                TransferDirection::Download,
+                false,
+                PathBuf::from(format!("/tmp/f{}", n)),
+                format!("/srv/f{}", n),
+                TransferOptions::default(),
+            );
+        }
+        manager
+    }
+
+    fn progress(id: u64, bytes_done: u64, bytes_total: u64, speed: f64) -> TransferUpdate {
+        TransferUpdate::Progress(TransferProgress {
+            id,
+            bytes_done,
+            bytes_total,
+            speed,
+            average_speed: speed,
+            eta: None,
+        })
+    }
+
+    #[test]
+    fn test_throughput_meter() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        // 从 1000 字节处续传，之前的部分不计入速度
+        let mut meter = ThroughputMeter::new(1000, start);
+        let p = meter.sample(7, 3000, 11_000, at(1));
+        assert_eq!((p.id, p.speed, p.average_speed), (7, 2000.0, 2000.0));
+        assert_eq!(p.eta, Some(Duration::from_secs(4)));
+
+        // 瞬时速度只看最近的窗口，平均速度覆盖整个运行
+        meter.sample(7, 5000, 11_000, at(2));
+        meter.sample(7, 5000, 11_000, at(4));
+        let p = meter.sample(7, 5000, 11_000, at(6));
+        assert_eq!(p.speed, 0.0);
+        assert!((p.average_speed - 4000.0 / 6.0).abs() < 1e-9);
+        assert_eq!(p.eta.map(|eta| eta.as_secs_f64().round()), Some(9.0));
+
+        let p = meter.sample(7, 11_000, 11_000, at(7));
+        assert_eq!(p.eta, Some(Duration::ZERO));
+        assert_eq!(p.speed, 6000.0 / 3.0);
+    }
+
+    #[test]
+    fn test_concurrency_and_connection_readiness() {
+        let mut manager = manager_with(4);
+        assert_eq!(manager.startable(2, |_| true), vec![1, 2]);
+        assert_eq!(manager.startable(8, |c| c == "db"), vec![2, 4]);
+
+        manager.start(1).unwrap();
+        assert_eq!(manager.startable(2, |_| true), vec![2]);
+
+        // 暂停后工作线程退出前仍占用并发名额
+        manager.pause(1);
+        assert_eq!(manager.get(1).unwrap().state, TransferState::Paused);
+        assert_eq!(manager.startable(2, |_| true), vec![2]);
+        manager.apply(TransferUpdate::Stopped { id: 1 });
+        assert_eq!(manager.get(1).unwrap().state, TransferState::Paused);
+        assert_eq!(manager.startable(2, |_| true), vec![2, 3]);
+
+        // 继续时从已写入的部分续传
+        manager.resume(1);
+        let (item, _) = manager.start(1).unwrap();
+        assert!(item.started);
+        let (item, _) = manager.start(2).unwrap();
+        assert!(!item.started);
+    }
+
+    #[test]
+    fn test_updates_cancel_and_retry() {
+        let mut manager = manager_with(3);
+        let (_, control) = manager.start(1).unwrap();
+        manager.apply(progress(1, 50, 200, 10.0));
+        assert_eq!(manager.get(1).unwrap().fraction(), 0.25);
+        let summary = manager.running_summary().unwrap();
+        assert_eq!(summary.fraction(), 0.25);
+        assert_eq!(summary.eta, Some(Duration::from_secs(15)));
+        manager.apply(TransferUpdate::Finished { id: 1 });
+        assert_eq!(manager.get(1).unwrap().state, TransferState::Completed);
+        assert_eq!(control.load(Ordering::Relaxed), CONTROL_RUN);
+
+        let (_, control) = manager.start(2).unwrap();
+        manager.cancel(2);
+        assert_eq!(control.load(Ordering::Relaxed), CONTROL_CANCEL);
+        manager.apply(TransferUpdate::Stopped { id: 2 });
+        assert_eq!(manager.get(2).unwrap().state, TransferState::Cancelled);
+        manager.retry(2);
+        assert_eq!(manager.get(2).unwrap().state, TransferState::Queued);
+
+        manager.start(3).unwrap();
+        manager.apply(TransferUpdate::Interrupted { id: 3 });
+        assert_eq!(manager.get(3).unwrap().state, TransferState::Interrupted);
+        manager.reconnected("db");
+        assert_eq!(manager.get(3).unwrap().state, TransferState::Interrupted);
+        manager.reconnected("web");
+        assert_eq!(manager.get(3).unwrap().state, TransferState::Queued);
+
+        manager.start(3).unwrap();
+        manager.apply(TransferUpdate::Failed {
+            id: 3,
+            error: "权限不足".to_string(),
+        });
+        assert_eq!(
+            manager.get(3).unwrap().state,
+            TransferState::Failed {
+                error: "权限不足".to_string()
+            }
+        );
+
+        manager.clear_finished();
+        assert_eq!(manager.items().iter().map(|i| i.id).collect::<Vec<_>>(), vec![2, 3]);
+        manager.remove(3);
+        assert_eq!(manager.items().len(), 1);
+    }
+
+    #[test]
+    fn test_finish_after_cancel_or_pause_keeps_user_state() {
+        let mut manager = manager_with(2);
+        // 最后一块写完时用户恰好取消或暂停，工作线程随后报告完成
+        manager.start(1).unwrap();
+        manager.cancel(1);
+        manager.apply(TransferUpdate::Finished { id: 1 });
+        assert_eq!(manager.get(1).unwrap().state, TransferState::Cancelled);
+
+        manager.start(2).unwrap();
+        manager.pause(2);
+        manager.apply(TransferUpdate::Finished { id: 2 });
+        assert_eq!(manager.get(2).unwrap().state, TransferState::Paused);
+        assert_eq!(manager.startable(2, |_| true), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn test_checksum_mismatch_retries_then_fails() {
+        let mut manager = manager_with(2);
+        let mismatch = |id| TransferUpdate::ChecksumMismatch {
+            id,
+            checksums: Checksums {
+                algorithm: ChecksumAlgorithm::Sha256,
+                local: "aaaaaaaaaaaaaaaa".to_string(),
+                remote: "bbbbbbbbbbbbbbbb".to_string(),
+            },
+        };
+
+        for attempt in 1..=MAX_CHECKSUM_RETRIES {
+            manager.start(1).unwrap();
+            manager.apply(progress(1, 100, 100, 1.0));
+            manager.apply(TransferUpdate::Verifying { id: 1 });
+            assert_eq!(manager.get(1).unwrap().state, TransferState::Verifying);
+            assert_eq!(manager.running_summary(), None);
+            let item = manager.apply(mismatch(1)).unwrap();
+            // 自动从头重新排队
+            assert_eq!(item.state, TransferState::Queued);
+            assert_eq!((item.checksum_failures, item.bytes_done, item.started), (attempt, 0, false));
+        }
+        manager.start(1).unwrap();
+        manager.apply(TransferUpdate::Verifying { id: 1 });
+        let item = manager.apply(mismatch(1)).unwrap();
+        assert!(matches!(&item.state, TransferState::Failed { error } if error.contains("aaaaaaaaaaaa…")));
+
+        // 手动重试时重新计数
+        manager.retry(1);
+        assert_eq!(manager.get(1).unwrap().checksum_failures, 0);
+
+        manager.start(2).unwrap();
+        manager.apply(TransferUpdate::Verifying { id: 2 });
+        let item = manager
+            .apply(TransferUpdate::Verified {
+                id: 2,
+                algorithm: ChecksumAlgorithm::Md5,
+            })
+            .unwrap();
+        assert_eq!(item.state, TransferState::Completed);
+        assert_eq!(item.verified.as_deref(), Some("md5"));
+        assert_eq!(manager.startable(2, |_| true), vec![1]);
+    }
+
+    #[test]
+    fn test_unfinished_items_survive_restart() {
+        let mut manager = manager_with(3);
+        manager.start(1).unwrap();
+        manager.apply(progress(1, 10, 20, 1.0));
+        manager.start(2).unwrap();
+        manager.apply(TransferUpdate::Finished { id: 2 });
+        manager.pause(3);
+        assert!(manager.take_dirty());
+        assert!(!manager.take_dirty());
+
+        let path = std::env::temp_dir().join(format!("transfers_{}.json", rand::random::<u32>()));
+        manager.save(&path).unwrap();
+        let restored = TransferManager::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let states: Vec<_> = restored.items().iter().map(|i| (i.id, i.state.clone())).collect();
+        assert_eq!(states, vec![(1, TransferState::Queued), (3, TransferState::Paused)]);
+        assert!(restored.get(1).unwrap().started);
+        assert_eq!(restored.get(1).unwrap().bytes_done, 10);
+        let mut restored = restored;
+        let id = restored.enqueue(
+            "web",
+            TransferDirection::Upload,
+            true,
+            PathBuf::from("/home/me/site"),
+            "/srv/site".to_string(),
+            TransferOptions::default(),
+        );
+        assert_eq!(id, 4);
+        assert_eq!(restored.get(id).unwrap().name(), "site");
+    }
+}
@@ -0,0 +1,252 @@
+use crate::terminal::{TermColor, TerminalBuffer};
+use crate::types::{TriggerAction, TriggerRule};
+use regex::Regex;
+use std::time::{Duration, Instant};
+
+// ============================================================================
+// 终端输出触发器
+// ============================================================================
+
+/// 每个输出块（或每帧）的匹配时间预算，超出后剩余的行留到下一帧继续
+pub const TRIGGER_BUDGET: Duration = Duration::from_millis(4);
+
+/// 编译后的触发器规则
+#[derive(Default)]
+pub struct TriggerSet {
+    source: Vec<TriggerRule>,
+    compiled: Vec<(usize, Regex)>,
+    errors: Vec<(usize, String)>,
+}
+
+impl TriggerSet {
+    pub fn compile(rules: &[TriggerRule]) -> Self {
+        let mut set = Self {
+            source: rules.to_vec(),
+            ..Self::default()
+        };
+        for (index, rule) in rules.iter().enumerate() {
+            if !rule.enabled || rule.pattern.is_empty() {
+                continue;
+            }
+            match Regex::new(&rule.pattern) {
+                Ok(regex) => set.compiled.push((index, regex)),
+                Err(e) => set.errors.push((index, e.to_string())),
+            }
+        }
+        set
+    }
+
+    /// 规则发生变化时重新编译
+    pub fn sync(&mut self, rules: &[TriggerRule]) {
+        if self.source != rules {
+            *self = Self::compile(rules);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.compiled.is_empty()
+    }
+
+    /// 规则的正则表达式错误
+    pub fn error(&self, index: usize) -> Option<&str> {
+        self.errors
+            .iter()
+            .find(|(i, _)| *i == index)
+            .map(|(_, e)| e.as_str())
+    }
+}
+
+/// 一个标签的扫描进度
+#[derive(Debug, Default)]
+pub struct TriggerScan {
+    /// 下一个待扫描的行（加上 `dropped_lines` 的稳定行号）
+    next_line: usize,
+    /// 光标所在的未完成行上已经触发过的（规则，匹配起点）
+    partial: Option<(usize, Vec<(usize, usize)>)>,
+    /// 因超出时间预算而推迟的次数
+    pub deferred: usize,
+}
+
+impl TriggerScan {
+    /// 是否还有已完成但未扫描的行（超出时间预算后推迟的部分）
+    pub fn is_pending(&self, set: &TriggerSet, terminal: &TerminalBuffer) -> bool {
+        !set.is_empty()
+            && !terminal.modes.alt_screen
+            && self.next_line.saturating_sub(terminal.dropped_lines()) < terminal.cursor_line()
+    }
+}
+
+/// 需要由界面执行的触发结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TriggerEffect {
+    Notify { rule: String, text: String },
+    Sound,
+    Reply(String),
+    RunSnippet(String),
+}
+
+/// 扫描新输出的行并执行触发器
+///
+/// 光标所在的行可能还未输出完整：非高亮动作在该行上每个匹配只触发一次，
+/// 高亮在行完成前会重复应用以覆盖新输出的部分。全屏程序运行期间不扫描。
+pub fn scan(
+    set: &TriggerSet,
+    progress: &mut TriggerScan,
+    terminal: &mut TerminalBuffer,
+    connection: &str,
+    budget: Duration,
+) -> Vec<TriggerEffect> {
+    let mut effects = Vec::new();
+    if set.is_empty() || terminal.modes.alt_screen {
+        return effects;
+    }
+
+    let base = terminal.dropped_lines();
+    let cursor_line = terminal.cursor_line();
+    // 光标回到已扫描位置之前（如清屏后）时从光标行重新开始
+    let start = progress.next_line.saturating_sub(base).min(cursor_line);
+    let started = Instant::now();
+
+    for line in start..=cursor_line {
+        if started.elapsed() > budget {
+            progress.deferred += 1;
+            break;
+        }
+        let Some(text) = terminal.line(line).map(|l| l.text()) else {
+            break;
+        };
+        let stable = base + line;
+        let complete = line < cursor_line;
+        let mut fired = match progress.partial.take() {
+            Some((partial_line, fired)) if partial_line == stable => fired,
+            _ => Vec::new(),
+        };
+
+        for (index, regex) in &set.compiled {
+            let rule = &set.source[*index];
+            if !rule.connection.is_empty() && rule.connection != connection {
+                continue;
+            }
+            for m in regex.find_iter(&text) {
+                if m.as_str().trim().is_empty() {
+                    continue;
+                }
+                if let TriggerAction::Highlight { color: [r, g, b] } = rule.action {
+                    let cols = terminal.line(line).map_or(0..0, |l| l.column_range(m.range()));
+                    terminal.highlight(line, cols, TermColor::Rgb(r, g, b));
+                    continue;
+                }
+                if fired.contains(&(*index, m.start())) {
+                    continue;
+                }
+                fired.push((*index, m.start()));
+                effects.push(match &rule.action {
+                    TriggerAction::Notify => TriggerEffect::Notify {
+                        rule: rule.name.clone(),
+                        text: text.trim().to_string(),
+                    },
+                    TriggerAction::Sound => TriggerEffect::Sound,
+                    TriggerAction::AutoReply { text } => TriggerEffect::Reply(text.clone()),
+                    TriggerAction::RunSnippet { snippet } => TriggerEffect::RunSnippet(snippet.clone()),
+                    TriggerAction::Highlight { .. } => unreachable!(),
+                });
+            }
+        }
+
+        if complete {
+            progress.next_line = stable + 1;
+        } else {
+            progress.partial = Some((stable, fired));
+        }
+    }
+    effects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, action: TriggerAction) -> TriggerRule {
+        TriggerRule {
+            name: pattern.to_string(),
+            pattern: pattern.to_string(),
+            connection: String::new(),
+            action,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_highlight_and_notify() {
+        let set = TriggerSet::compile(&[
+            rule("ERROR|FATAL|panicked", TriggerAction::Highlight { color: [255, 0, 0] }),
+            rule("Build finished", TriggerAction::Notify),
+        ]);
+        let mut progress = TriggerScan::default();
+        let mut terminal = TerminalBuffer::with_size(100, 40, 5);
+
+        terminal.feed("ok\r\n中文 ERROR here\r\nBuild fin");
+        let effects = scan(&set, &mut progress, &mut terminal, "本地", TRIGGER_BUDGET);
+        assert!(effects.is_empty());
+        let line = terminal.line(1).unwrap();
+        assert_eq!(line.cells[4].style.fg, TermColor::Default);
+        assert!((5..10).all(|c| line.cells[c].style.fg == TermColor::Rgb(255, 0, 0)));
+        assert_eq!(line.cells[10].style.fg, TermColor::Default);
+
+        // 跨输出块完成的行只通知一次
+        terminal.feed("ished\r\n");
+        let effects = scan(&set, &mut progress, &mut terminal, "本地", TRIGGER_BUDGET);
+        assert_eq!(
+            effects,
+            vec![TriggerEffect::Notify {
+                rule: "Build finished".to_string(),
+                text: "Build finished".to_string()
+            }]
+        );
+        assert!(scan(&set, &mut progress, &mut terminal, "本地", TRIGGER_BUDGET).is_empty());
+    }
+
+    #[test]
+    fn test_partial_line_auto_reply_fires_once() {
+        let mut scoped = rule("continue\\? \\(y/n\\)", TriggerAction::AutoReply { text: "y".to_string() });
+        scoped.connection = "prod".to_string();
+        let set = TriggerSet::compile(&[scoped]);
+        let mut terminal = TerminalBuffer::with_size(100, 40, 5);
+
+        // 其他连接不触发
+        let mut progress = TriggerScan::default();
+        terminal.feed("continue? (y/n)");
+        assert!(scan(&set, &mut progress, &mut terminal, "本地", TRIGGER_BUDGET).is_empty());
+
+        let mut progress = TriggerScan::default();
+        let effects = scan(&set, &mut progress, &mut terminal, "prod", TRIGGER_BUDGET);
+        assert_eq!(effects, vec![TriggerEffect::Reply("y".to_string())]);
+        terminal.feed(" y\r\n");
+        assert!(scan(&set, &mut progress, &mut terminal, "prod", TRIGGER_BUDGET).is_empty());
+    }
+
+    #[test]
+    fn test_budget_defers_remaining_lines() {
+        let set = TriggerSet::compile(&[rule("done", TriggerAction::Sound)]);
+        let mut progress = TriggerScan::default();
+        let mut terminal = TerminalBuffer::with_size(100, 40, 5);
+        terminal.feed("done\r\ndone\r\n");
+
+        assert!(scan(&set, &mut progress, &mut terminal, "", Duration::ZERO).is_empty());
+        assert_eq!(progress.deferred, 1);
+        assert!(progress.is_pending(&set, &terminal));
+        let effects = scan(&set, &mut progress, &mut terminal, "", TRIGGER_BUDGET);
+        assert_eq!(effects, vec![TriggerEffect::Sound, TriggerEffect::Sound]);
+        assert!(!progress.is_pending(&set, &terminal));
+    }
+
+    #[test]
+    fn test_invalid_and_disabled_rules() {
+        let mut disabled = rule("x", TriggerAction::Sound);
+        disabled.enabled = false;
+        let set = TriggerSet::compile(&[rule("(", TriggerAction::Sound), disabled]);
+        assert!(set.is_empty());
+        assert!(set.error(0).is_some());
+        assert!(set.error(1).is_none());
+    }
+}
@@ -1,4 +1,7 @@
+use crate::color_scheme::ColorScheme;
+use crate::theme::CustomTheme;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 /// SSH 认证方法
@@ -13,6 +16,37 @@ pub enum AuthMethod {
     },
 }
 
+/// 连接协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Ssh,
+    Telnet,
+    /// 原始 TCP（不做任何协议处理，适用于串口服务器等设备）
+    Raw,
+}
+
+impl Protocol {
+    pub const ALL: [Protocol; 3] = [Protocol::Ssh, Protocol::Telnet, Protocol::Raw];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Protocol::Ssh => "SSH",
+            Protocol::Telnet => "Telnet",
+            Protocol::Raw => "Raw TCP",
+        }
+    }
+
+    pub fn default_port(self) -> u16 {
+        match self {
+            Protocol::Ssh => 22,
+            Protocol::Telnet => 23,
+            Protocol::Raw => 2000,
+        }
+    }
+}
+
 /// SSH 连接配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SshConfig {
@@ -28,6 +62,18 @@ pub struct SshConfig {
     pub key_path: Option<String>,
     /// 私钥密码（加密）
     pub key_passphrase_encrypted: Option<String>,
+    /// 终端配色方案名称（为空时跟随主题）
+    #[serde(default)]
+    pub color_scheme: Option<String>,
+    /// 连接协议
+    #[serde(default)]
+    pub protocol: Protocol,
+    /// expect 自动化脚本
+    #[serde(default)]
+    pub expect_scripts: Vec<ExpectScript>,
+    /// 脚本可发送的密钥（加密保存）
+    #[serde(default)]
+    pub secrets: Vec<StoredSecret>,
 }
 
 impl SshConfig {
@@ -41,6 +87,138 @@ impl SshConfig {
             password_encrypted: None,
             key_path: None,
             key_passphrase_encrypted: None,
+            color_scheme: None,
+            protocol: Protocol::Ssh,
+            expect_scripts: Vec::new(),
+            secrets: Vec::new(),
+        }
+    }
+
+    /// 按名称查找脚本可用的密钥，`password` 未单独保存时使用登录密码
+    pub fn secret(&self, name: &str) -> Option<&str> {
+        match self.secrets.iter().find(|s| s.name == name) {
+            Some(secret) => Some(&secret.value),
+            None if name == LOGIN_PASSWORD_SECRET => match &self.auth {
+                Some(AuthMethod::Password(password)) => Some(password),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+}
+
+/// 指向连接登录密码的密钥名称
+pub const LOGIN_PASSWORD_SECRET: &str = "password";
+
+/// 连接保存的命名密钥（如 sudo 或 enable 密码）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StoredSecret {
+    pub name: String,
+    #[serde(skip)] // 不序列化，从加密字段读取
+    pub value: String,
+    #[serde(default)]
+    pub value_encrypted: Option<String>,
+}
+
+/// expect 自动化脚本：按步骤等待输出匹配并自动应答
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectScript {
+    pub name: String,
+    /// 会话建立时自动运行（否则只能手动运行）
+    #[serde(default = "default_true")]
+    pub auto_start: bool,
+    #[serde(default)]
+    pub steps: Vec<ExpectStep>,
+}
+
+/// 脚本的一步：等待任一分支匹配，超时后按 `on_timeout` 继续
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectStep {
+    #[serde(default)]
+    pub branches: Vec<ExpectBranch>,
+    /// 超时秒数，0 表示一直等待
+    #[serde(default = "default_expect_timeout")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_expect_on_timeout")]
+    pub on_timeout: ExpectNext,
+}
+
+impl Default for ExpectStep {
+    fn default() -> Self {
+        Self {
+            branches: vec![ExpectBranch::default()],
+            timeout_secs: default_expect_timeout(),
+            on_timeout: default_expect_on_timeout(),
+        }
+    }
+}
+
+fn default_expect_timeout() -> u64 {
+    30
+}
+
+fn default_expect_on_timeout() -> ExpectNext {
+    ExpectNext::Fail
+}
+
+/// 等待的分支：输出匹配正则表达式（为空时立即匹配）后发送内容并跳转
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectBranch {
+    #[serde(default)]
+    pub pattern: String,
+    #[serde(default)]
+    pub send: Option<ExpectSend>,
+    #[serde(default)]
+    pub next: ExpectNext,
+}
+
+/// 匹配后发送的内容
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExpectSend {
+    /// 文本加回车
+    Line { text: String },
+    /// 连接保存的密钥加回车，日志中不显示内容
+    Secret { name: String },
+    /// 原样发送的按键，支持 `\r`、`\n`、`\t`、`\e` 与 `\xHH` 转义
+    Keys { text: String },
+}
+
+impl ExpectSend {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExpectSend::Line { .. } => "文本",
+            ExpectSend::Secret { .. } => "密钥",
+            ExpectSend::Keys { .. } => "按键",
+        }
+    }
+}
+
+/// 分支匹配或超时后的去向
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExpectNext {
+    /// 执行下一步，最后一步之后结束
+    #[default]
+    Next,
+    /// 留在本步继续等待（持续自动应答）
+    Repeat,
+    /// 跳转到第 `step` 步（从 1 开始）
+    Goto { step: usize },
+    /// 脚本成功结束
+    Finish,
+    /// 脚本失败结束
+    Fail,
+}
+
+impl ExpectNext {
+    pub fn label(&self) -> String {
+        match self {
+            ExpectNext::Next => "下一步".to_string(),
+            ExpectNext::Repeat => "重复本步".to_string(),
+            ExpectNext::Goto { step } => format!("跳到第 {} 步", step),
+            ExpectNext::Finish => "结束".to_string(),
+            ExpectNext::Fail => "失败".to_string(),
         }
     }
 }
@@ -164,6 +342,9 @@ pub struct Settings {
     pub theme: String,
     pub font_size: f32,
     pub terminal_font_size: f32,
+    /// CJK 后备字体文件，留空时自动查找系统字体
+    #[serde(default)]
+    pub cjk_font_path: String,
     
     // 行为设置
     pub auto_save_config: bool,
@@ -176,6 +357,76 @@ pub struct Settings {
     // 历史设置
     pub history_max_size: usize,
     pub save_history_on_exit: bool,
+
+    // 快捷键设置（动作 ID -> 快捷键，空字符串表示取消绑定）
+    #[serde(default)]
+    pub keybindings: BTreeMap<String, String>,
+    /// 终端获得焦点时，不带 Shift/Alt 的快捷键交给 shell
+    #[serde(default = "default_true")]
+    pub terminal_passthrough: bool,
+
+    /// 打开 shell 标签时注入 OSC 133 shell 集成脚本（bash / zsh）
+    #[serde(default)]
+    pub shell_integration: bool,
+
+    // 剪贴板设置
+    /// 在终端中选中文本后自动复制
+    #[serde(default)]
+    pub copy_on_select: bool,
+    /// 在终端中右键直接粘贴（否则弹出菜单）
+    #[serde(default)]
+    pub right_click_paste: bool,
+    /// 粘贴多行或包含 sudo 的文本前确认
+    #[serde(default = "default_true")]
+    pub paste_protection: bool,
+    /// 各主机的 OSC 52 剪贴板权限（主机 -> 权限）
+    #[serde(default)]
+    pub clipboard_access: BTreeMap<String, ClipboardAccess>,
+    /// 导出终端内容时用于脱敏的正则表达式
+    #[serde(default = "crate::export::default_redaction_patterns")]
+    pub export_redaction_patterns: Vec<String>,
+    /// 传输目标已存在时的处理方式
+    #[serde(default)]
+    pub transfer_conflict: ConflictPolicy,
+    /// 目录传输中符号链接的处理方式
+    #[serde(default)]
+    pub transfer_symlinks: SymlinkPolicy,
+    /// 断点续传前校验已传输部分的末尾
+    #[serde(default = "default_true")]
+    pub transfer_verify_tail: bool,
+    /// 同时运行的传输数
+    #[serde(default = "default_transfer_concurrency")]
+    pub transfer_concurrency: usize,
+    /// SFTP 每次读写的块大小（KB），越大在途请求越多
+    #[serde(default = "default_transfer_chunk_kb")]
+    pub transfer_chunk_kb: usize,
+    /// 传输完成后校验本地与远程文件的 SHA-256/MD5
+    #[serde(default)]
+    pub transfer_verify_checksum: bool,
+    /// 编辑远程文件时使用的外部编辑器命令，为空时使用系统默认程序
+    #[serde(default)]
+    pub external_editor: String,
+}
+
+fn default_transfer_concurrency() -> usize {
+    2
+}
+
+fn default_transfer_chunk_kb() -> usize {
+    crate::sftp::DEFAULT_CHUNK_SIZE / 1024
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 远程程序通过 OSC 52 访问本地剪贴板的权限，None 表示每次询问
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipboardAccess {
+    #[serde(default)]
+    pub write: Option<bool>,
+    #[serde(default)]
+    pub read: Option<bool>,
 }
 
 impl Default for Settings {
@@ -185,12 +436,28 @@ impl Default for Settings {
             theme: "dark".to_string(),
             font_size: 14.0,
             terminal_font_size: 14.0,
+            cjk_font_path: String::new(),
             auto_save_config: true,
             confirm_before_delete: true,
             terminal_scrollback: 10000,
             terminal_word_wrap: false,
             history_max_size: 1000,
             save_history_on_exit: true,
+            keybindings: BTreeMap::new(),
+            terminal_passthrough: true,
+            shell_integration: false,
+            copy_on_select: false,
+            right_click_paste: false,
+            paste_protection: true,
+            clipboard_access: BTreeMap::new(),
+            export_redaction_patterns: crate::export::default_redaction_patterns(),
+            transfer_conflict: ConflictPolicy::default(),
+            transfer_symlinks: SymlinkPolicy::default(),
+            transfer_verify_tail: true,
+            transfer_concurrency: default_transfer_concurrency(),
+            transfer_chunk_kb: default_transfer_chunk_kb(),
+            transfer_verify_checksum: false,
+            external_editor: String::new(),
         }
     }
 }
@@ -202,6 +469,18 @@ pub struct AppConfig {
     pub connections: Vec<SshConfig>,
     pub ai: AiConfig,
     pub settings: Settings,
+    /// 用户导入的终端配色方案
+    #[serde(default)]
+    pub color_schemes: Vec<ColorScheme>,
+    /// 用户自定义主题
+    #[serde(default)]
+    pub custom_themes: Vec<CustomTheme>,
+    /// 命令片段
+    #[serde(default)]
+    pub snippets: Vec<Snippet>,
+    /// 终端输出触发器
+    #[serde(default)]
+    pub triggers: Vec<TriggerRule>,
 }
 
 impl Default for AppConfig {
@@ -211,6 +490,58 @@ impl Default for AppConfig {
             connections: Vec::new(),
             ai: AiConfig::default(),
             settings: Settings::default(),
+            color_schemes: Vec::new(),
+            custom_themes: Vec::new(),
+            snippets: Vec::new(),
+            triggers: Vec::new(),
+        }
+    }
+}
+
+/// 命令片段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snippet {
+    pub name: String,
+    pub command: String,
+}
+
+/// 终端输出触发器：输出中的行匹配正则表达式时执行动作
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriggerRule {
+    pub name: String,
+    pub pattern: String,
+    /// 生效的连接名称（本地 shell 为“本地”），为空时对所有会话生效
+    #[serde(default)]
+    pub connection: String,
+    pub action: TriggerAction,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// 触发器动作
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TriggerAction {
+    /// 以指定颜色高亮匹配的文本
+    Highlight { color: [u8; 3] },
+    /// 桌面通知
+    Notify,
+    /// 提示音
+    Sound,
+    /// 向会话发送文本（自动加回车）
+    AutoReply { text: String },
+    /// 在会话中运行指定名称的命令片段
+    RunSnippet { snippet: String },
+}
+
+impl TriggerAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TriggerAction::Highlight { .. } => "高亮",
+            TriggerAction::Notify => "桌面通知",
+            TriggerAction::Sound => "提示音",
+            TriggerAction::AutoReply { .. } => "自动回复",
+            TriggerAction::RunSnippet { .. } => "运行片段",
         }
     }
 }
@@ -237,8 +568,11 @@ pub enum SettingsPage {
     General,
     Appearance,
     Terminal,
+    Keybindings,
     Ai,
     History,
+    Triggers,
+    Automation,
 }
 
 // ============================================================================
@@ -247,15 +581,69 @@ pub enum SettingsPage {
 
 use std::time::SystemTime;
 
+/// 文件类型（不跟随符号链接）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileKind {
+    #[default]
+    File,
+    Directory,
+    Symlink,
+    Socket,
+    Fifo,
+    CharDevice,
+    BlockDevice,
+    Other,
+}
+
+impl FileKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FileKind::File => "文件",
+            FileKind::Directory => "目录",
+            FileKind::Symlink => "符号链接",
+            FileKind::Socket => "套接字",
+            FileKind::Fifo => "命名管道",
+            FileKind::CharDevice => "字符设备",
+            FileKind::BlockDevice => "块设备",
+            FileKind::Other => "其他",
+        }
+    }
+
+    /// `ls -l` 权限前的类型字符
+    pub fn type_char(&self) -> char {
+        match self {
+            FileKind::File => '-',
+            FileKind::Directory => 'd',
+            FileKind::Symlink => 'l',
+            FileKind::Socket => 's',
+            FileKind::Fifo => 'p',
+            FileKind::CharDevice => 'c',
+            FileKind::BlockDevice => 'b',
+            FileKind::Other => '?',
+        }
+    }
+}
+
 /// 文件条目（本地或远程）
 #[derive(Debug, Clone)]
 pub struct FileEntry {
     pub name: String,
     pub path: String,
+    /// 能否进入（指向目录的符号链接也为 true）
     pub is_dir: bool,
     pub size: u64,
     pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    /// 八进制的 mode（含文件类型位）
     pub permissions: Option<String>,
+    pub kind: FileKind,
+    /// 符号链接指向的路径
+    pub link_target: Option<String>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    /// 由远程 passwd/group 解析出的属主名和组名
+    pub owner: Option<String>,
+    pub group: Option<String>,
 }
 
 impl FileEntry {
@@ -266,11 +654,195 @@ impl FileEntry {
             is_dir,
             size: 0,
             modified: None,
+            accessed: None,
             permissions: None,
+            kind: if is_dir { FileKind::Directory } else { FileKind::File },
+            link_target: None,
+            uid: None,
+            gid: None,
+            owner: None,
+            group: None,
+        }
+    }
+
+    /// `ls -l` 形式的权限，如 `drwxr-xr-x`
+    pub fn mode_string(&self) -> Option<String> {
+        let mode = u32::from_str_radix(self.permissions.as_deref()?, 8).ok()?;
+        Some(format!("{}{}", self.kind.type_char(), crate::file_ops::mode_string(mode)))
+    }
+
+    /// 属主名，无法解析时为数字 uid
+    pub fn owner_label(&self) -> String {
+        id_label(&self.owner, self.uid)
+    }
+
+    /// 组名，无法解析时为数字 gid
+    pub fn group_label(&self) -> String {
+        id_label(&self.group, self.gid)
+    }
+
+    pub fn resolve_names(&mut self, names: &crate::file_ops::IdNames) {
+        self.owner = self.uid.and_then(|uid| names.user(uid)).map(str::to_string);
+        self.group = self.gid.and_then(|gid| names.group(gid)).map(str::to_string);
+    }
+}
+
+fn id_label(name: &Option<String>, id: Option<u32>) -> String {
+    match (name, id) {
+        (Some(name), _) => name.clone(),
+        (None, Some(id)) => id.to_string(),
+        (None, None) => String::new(),
+    }
+}
+
+/// 文件列表的排序列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileColumn {
+    #[default]
+    Name,
+    Size,
+    Modified,
+    Accessed,
+    Permissions,
+    Owner,
+    Group,
+}
+
+impl FileColumn {
+    pub const ALL: [FileColumn; 7] = [
+        FileColumn::Name,
+        FileColumn::Size,
+        FileColumn::Modified,
+        FileColumn::Accessed,
+        FileColumn::Permissions,
+        FileColumn::Owner,
+        FileColumn::Group,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FileColumn::Name => "名称",
+            FileColumn::Size => "大小",
+            FileColumn::Modified => "修改时间",
+            FileColumn::Accessed => "访问时间",
+            FileColumn::Permissions => "权限",
+            FileColumn::Owner => "属主",
+            FileColumn::Group => "属组",
+        }
+    }
+}
+
+/// 文件列表的排序方式（目录始终在前）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FileSort {
+    pub column: FileColumn,
+    pub descending: bool,
+}
+
+impl FileSort {
+    /// 点击列标题：同一列切换方向，其他列从升序开始
+    pub fn toggle(&mut self, column: FileColumn) {
+        if self.column == column {
+            self.descending = !self.descending;
+        } else {
+            *self = FileSort {
+                column,
+                descending: false,
+            };
+        }
+    }
+
+    pub fn sort(&self, entries: &mut [FileEntry]) {
+        entries.sort_by(|a, b| {
+            let order = match self.column {
+                FileColumn::Name => std::cmp::Ordering::Equal,
+                FileColumn::Size => a.size.cmp(&b.size),
+                FileColumn::Modified => a.modified.cmp(&b.modified),
+                FileColumn::Accessed => a.accessed.cmp(&b.accessed),
+                FileColumn::Permissions => a.mode_string().cmp(&b.mode_string()),
+                FileColumn::Owner => a.owner_label().cmp(&b.owner_label()),
+                FileColumn::Group => a.group_label().cmp(&b.group_label()),
+            }
+            .then_with(|| a.name.cmp(&b.name));
+            let order = if self.descending { order.reverse() } else { order };
+            b.is_dir.cmp(&a.is_dir).then(order)
+        });
+    }
+}
+
+/// 传输目标已存在时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    #[default]
+    Overwrite,
+    Skip,
+    /// 以 `name.1`、`name.2` 等新名称保存
+    Rename,
+    /// 仅当源文件比目标新时覆盖
+    NewerOnly,
+}
+
+impl ConflictPolicy {
+    pub const ALL: [ConflictPolicy; 4] = [
+        ConflictPolicy::Overwrite,
+        ConflictPolicy::Skip,
+        ConflictPolicy::Rename,
+        ConflictPolicy::NewerOnly,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Overwrite => "覆盖",
+            ConflictPolicy::Skip => "跳过",
+            ConflictPolicy::Rename => "重命名",
+            ConflictPolicy::NewerOnly => "仅更新较新的",
+        }
+    }
+}
+
+/// 目录传输中符号链接的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    /// 传输链接指向的内容
+    #[default]
+    Follow,
+    Skip,
+    /// 在目标端重建链接
+    Preserve,
+}
+
+impl SymlinkPolicy {
+    pub const ALL: [SymlinkPolicy; 3] = [SymlinkPolicy::Follow, SymlinkPolicy::Skip, SymlinkPolicy::Preserve];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SymlinkPolicy::Follow => "跟随链接",
+            SymlinkPolicy::Skip => "跳过链接",
+            SymlinkPolicy::Preserve => "保留链接",
         }
     }
 }
 
+/// 递归传输的选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransferOptions {
+    pub conflict: ConflictPolicy,
+    pub symlinks: SymlinkPolicy,
+    /// 单文件传输完成后比较本地与远程的校验和
+    pub verify_checksum: bool,
+}
+
+/// 传输方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferDirection {
+    Upload,
+    Download,
+}
+
 /// SFTP 操作
 #[derive(Debug, Clone)]
 pub enum SftpOperation {
@@ -282,6 +854,18 @@ pub enum SftpOperation {
         remote_path: String,
         local_path: PathBuf,
     },
+    /// 递归上传本地目录
+    UploadDir {
+        local_path: PathBuf,
+        remote_path: String,
+        options: TransferOptions,
+    },
+    /// 递归下载远程目录
+    DownloadDir {
+        remote_path: String,
+        local_path: PathBuf,
+        options: TransferOptions,
+    },
     List {
         path: String,
     },
@@ -296,8 +880,92 @@ pub enum SftpOperation {
 /// SFTP 消息（后台 -> UI）
 #[derive(Debug, Clone)]
 pub enum SftpMessage {
+    /// 列出的目录解析后的绝对路径
+    Directory(String),
     FileList(Vec<FileEntry>),
-    Progress(f32),
+    /// 进行中的状态说明
+    Status(String),
+    /// 传输队列工作线程的更新
+    Transfer(crate::transfer::TransferUpdate),
+    /// 远程编辑会话的下载/上传结果
+    Edit(crate::remote_edit::EditUpdate),
+    /// 只读查看器读取的一页
+    Viewer(crate::editor::ViewerUpdate),
+    /// 文件管理操作修改了远程文件（附结果说明），需要刷新列表
+    Changed(String),
+    /// 按需解析的符号链接目标（读取失败时为 None）
+    LinkResolved {
+        path: String,
+        target: Option<String>,
+        is_dir: bool,
+    },
+    /// 连接的远程用户名和组名
+    Ids {
+        connection: String,
+        names: crate::file_ops::IdNames,
+    },
     Complete,
     Error(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, kind: FileKind, size: u64, perm: &str, uid: u32) -> FileEntry {
+        FileEntry {
+            size,
+            permissions: Some(perm.to_string()),
+            kind,
+            uid: Some(uid),
+            ..FileEntry::new(name.to_string(), format!("/srv/{}", name), kind == FileKind::Directory)
+        }
+    }
+
+    #[test]
+    fn test_file_entry_metadata() {
+        let mut link = entry("current", FileKind::Symlink, 12, "120777", 0);
+        assert_eq!(link.mode_string().as_deref(), Some("lrwxrwxrwx"));
+        let mut dir = entry("logs", FileKind::Directory, 4096, "42775", 1000);
+        assert_eq!(dir.mode_string().as_deref(), Some("drwxrwsr-x"));
+        assert_eq!(FileEntry::new("x".into(), "/x".into(), false).mode_string(), None);
+
+        let names = crate::file_ops::IdNames::parse("root:x:0:0::/root:/bin/sh\n", "root:x:0:\n");
+        link.resolve_names(&names);
+        dir.resolve_names(&names);
+        assert_eq!((link.owner_label(), link.group_label()), ("root".to_string(), String::new()));
+        // 无法解析的 uid 显示为数字
+        assert_eq!(dir.owner_label(), "1000");
+    }
+
+    #[test]
+    fn test_file_sort() {
+        let mut entries = vec![
+            entry("b.log", FileKind::File, 300, "100644", 0),
+            entry("www", FileKind::Directory, 4096, "40755", 33),
+            entry("a.sock", FileKind::Socket, 0, "140755", 33),
+            entry("etc", FileKind::Directory, 4096, "40700", 0),
+            entry("c.txt", FileKind::File, 20, "100600", 0),
+        ];
+        let names = |entries: &[FileEntry]| entries.iter().map(|e| e.name.clone()).collect::<Vec<_>>();
+
+        let mut sort = FileSort::default();
+        sort.sort(&mut entries);
+        assert_eq!(names(&entries), ["etc", "www", "a.sock", "b.log", "c.txt"]);
+
+        sort.toggle(FileColumn::Size);
+        sort.sort(&mut entries);
+        assert_eq!(names(&entries), ["etc", "www", "a.sock", "c.txt", "b.log"]);
+
+        // 反向排序时目录仍在前
+        sort.toggle(FileColumn::Size);
+        assert!(sort.descending);
+        sort.sort(&mut entries);
+        assert_eq!(names(&entries), ["www", "etc", "b.log", "c.txt", "a.sock"]);
+
+        sort.toggle(FileColumn::Permissions);
+        assert!(!sort.descending);
+        sort.sort(&mut entries);
+        assert_eq!(names(&entries), ["etc", "www", "c.txt", "b.log", "a.sock"]);
+    }
+}
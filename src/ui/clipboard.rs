@@ -0,0 +1,105 @@
+use crate::app::{answer_clipboard_prompt, deliver_paste};
+use crate::state::AppState;
+use crate::terminal::ClipboardRequest;
+use eframe::egui;
+
+// ============================================================================
+// 剪贴板：OSC 52 权限提示与粘贴确认
+// ============================================================================
+
+/// 远程程序请求访问剪贴板时询问用户（每次处理队首的一个请求）
+pub fn render_clipboard_prompt(state: &mut AppState, ctx: &egui::Context) {
+    let Some(prompt) = state.clipboard_prompts.front().cloned() else {
+        return;
+    };
+
+    let question = match &prompt.request {
+        ClipboardRequest::Set(text) => format!(
+            "{} 上的程序请求写入本地剪贴板（{} 个字符）。",
+            prompt.host,
+            text.chars().count()
+        ),
+        ClipboardRequest::Get { .. } => format!("{} 上的程序请求读取本地剪贴板内容。", prompt.host),
+    };
+
+    let mut answer = None;
+    egui::Window::new("📋 剪贴板访问")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label(question);
+            if let ClipboardRequest::Set(text) = &prompt.request {
+                let preview: String = text.chars().take(200).collect();
+                ui.label(egui::RichText::new(preview).monospace().weak());
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("允许一次").clicked() {
+                    answer = Some((true, false));
+                }
+                if ui.button("始终允许").clicked() {
+                    answer = Some((true, true));
+                }
+                if ui.button("拒绝").clicked() {
+                    answer = Some((false, false));
+                }
+                if ui.button("始终拒绝").clicked() {
+                    answer = Some((false, true));
+                }
+            });
+        });
+
+    if let Some((allow, remember)) = answer {
+        state.clipboard_prompts.pop_front();
+        answer_clipboard_prompt(state, prompt, allow, remember);
+    }
+}
+
+/// 多行或包含 sudo 的粘贴在发送前确认
+pub fn render_paste_confirmation(state: &mut AppState, ctx: &egui::Context) {
+    let Some(pending) = state.pending_paste.clone() else {
+        return;
+    };
+
+    let line_count = pending.text.lines().count();
+    let mut paste = None;
+    let mut cancel = false;
+    egui::Window::new("⚠️ 确认粘贴")
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label(format!("即将粘贴 {} 行文本，换行会让命令立即执行：", line_count));
+            if pending.text.contains("sudo") {
+                ui.colored_label(egui::Color32::from_rgb(255, 170, 0), "内容包含 sudo 命令");
+            }
+            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                ui.label(egui::RichText::new(&pending.text).monospace());
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("粘贴").clicked() {
+                    paste = Some(pending.text.clone());
+                }
+                if ui.button("合并为一行").clicked() {
+                    let joined: Vec<&str> = pending
+                        .text
+                        .lines()
+                        .map(str::trim)
+                        .filter(|l| !l.is_empty())
+                        .collect();
+                    paste = Some(joined.join(" "));
+                }
+                if ui.button("取消").clicked() {
+                    cancel = true;
+                }
+            });
+        });
+
+    if let Some(text) = paste {
+        state.pending_paste = None;
+        deliver_paste(state, pending.target, text);
+    } else if cancel {
+        state.pending_paste = None;
+    }
+}
@@ -0,0 +1,97 @@
+use crate::actions::{search_palette, Keymap};
+use crate::app::run_palette_item;
+use crate::state::AppState;
+use eframe::egui;
+
+// ============================================================================
+// 命令面板 (Ctrl+Shift+P)
+// ============================================================================
+
+pub fn render_command_palette(state: &mut AppState, ctx: &egui::Context) {
+    if !state.show_command_palette {
+        return;
+    }
+
+    let keymap = Keymap::from_overrides(&state.config.settings.keybindings);
+    let results = search_palette(
+        &state.command_palette_query,
+        &keymap,
+        &state.connections,
+        &state.config.snippets,
+    );
+
+    // 键盘导航（消费按键，避免终端输入框等同时响应 Enter）
+    let (up, down, enter, escape) = ctx.input_mut(|i| {
+        let none = egui::Modifiers::NONE;
+        (
+            i.consume_key(none, egui::Key::ArrowUp),
+            i.consume_key(none, egui::Key::ArrowDown),
+            i.consume_key(none, egui::Key::Enter),
+            i.consume_key(none, egui::Key::Escape),
+        )
+    });
+
+    if escape {
+        state.show_command_palette = false;
+        return;
+    }
+    if down && state.command_palette_selected + 1 < results.len() {
+        state.command_palette_selected += 1;
+    }
+    if up {
+        state.command_palette_selected = state.command_palette_selected.saturating_sub(1);
+    }
+    state.command_palette_selected = state
+        .command_palette_selected
+        .min(results.len().saturating_sub(1));
+
+    let mut chosen = None;
+    if enter {
+        chosen = results.get(state.command_palette_selected).map(|e| e.item);
+    }
+
+    egui::Window::new("🎯 命令面板")
+        .title_bar(false)
+        .collapsible(false)
+        .resizable(false)
+        .fixed_size([500.0, 360.0])
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+        .show(ctx, |ui| {
+            let resp = ui.add(
+                egui::TextEdit::singleline(&mut state.command_palette_query)
+                    .hint_text("搜索动作、连接或片段...")
+                    .desired_width(f32::INFINITY),
+            );
+            resp.request_focus();
+            if resp.changed() {
+                state.command_palette_selected = 0;
+            }
+
+            ui.separator();
+
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                if results.is_empty() {
+                    ui.label("无匹配项");
+                }
+                for (idx, entry) in results.iter().enumerate() {
+                    let selected = idx == state.command_palette_selected;
+                    ui.horizontal(|ui| {
+                        let resp = ui.selectable_label(selected, &entry.label);
+                        if selected {
+                            resp.scroll_to_me(None);
+                        }
+                        if resp.clicked() {
+                            chosen = Some(entry.item);
+                        }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.label(egui::RichText::new(&entry.detail).small().weak());
+                        });
+                    });
+                }
+            });
+        });
+
+    if let Some(item) = chosen {
+        run_palette_item(state, ctx, item);
+    }
+}
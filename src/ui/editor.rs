@@ -0,0 +1,489 @@
+use crate::editor::{FileViewer, FindState, Language, LineEnding, TextEncoding, TokenKind, MAX_EDIT_SIZE};
+use crate::remote_edit::{EditSession, EditorBuffer};
+use crate::state::AppState;
+use crate::types::FileEntry;
+use eframe::egui;
+use egui::text::{CCursor, CCursorRange, LayoutJob};
+use egui::util::cache::{ComputerMut, FrameCache};
+
+// ============================================================================
+// 内置编辑器与大文件查看窗口
+// ============================================================================
+
+/// 编辑器窗口需要外部处理的操作
+pub enum EditorRequest {
+    Save,
+    Close,
+}
+
+/// 用内置编辑器打开远程文件，过大的文件改为只读分页查看
+pub fn open_file(state: &mut AppState, entry: &FileEntry) {
+    if entry.size <= MAX_EDIT_SIZE {
+        crate::ui::remote_edit::open_remote_file(state, &entry.path, true);
+        return;
+    }
+    let Some(connection) = crate::ui::file_browser::selected_connection_name(state) else {
+        return;
+    };
+    if state
+        .file_viewers
+        .iter()
+        .any(|v| v.connection == connection && v.remote_path == entry.path)
+    {
+        state.sftp_status = format!("{} 已在查看中", entry.name);
+        return;
+    }
+    let Some(session) = crate::app::connected_session(state, &connection) else {
+        state.sftp_status = format!("❌ 连接 {} 未连接", connection);
+        return;
+    };
+    let id = state.file_viewers.iter().map(|v| v.id).max().unwrap_or(0) + 1;
+    let viewer = FileViewer::new(id, &connection, &entry.path, entry.size);
+    if let Some(offset) = viewer.loading() {
+        crate::editor::spawn_viewer_read(session, id, entry.path.clone(), offset, state.sftp_msg_tx.clone());
+    }
+    state.sftp_status = format!(
+        "{} 超过 {}，以只读方式分页查看",
+        entry.name,
+        crate::ui::transfers::format_bytes(MAX_EDIT_SIZE)
+    );
+    state.file_viewers.push(viewer);
+}
+
+pub fn render_editor_window(
+    ctx: &egui::Context,
+    buffer: &mut EditorBuffer,
+    edit: &EditSession,
+    status: egui::RichText,
+) -> Option<EditorRequest> {
+    let mut request = None;
+    let mut open = true;
+    let text_id = egui::Id::new(("remote_editor_text", edit.id));
+    let title = format!("📝 {}{}", edit.name(), if buffer.modified { " *" } else { "" });
+    egui::Window::new(title)
+        .id(egui::Id::new(("remote_editor", edit.id)))
+        .open(&mut open)
+        .default_size([720.0, 520.0])
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let save = ui
+                    .add_enabled(!buffer.read_only(), egui::Button::new("💾 保存"))
+                    .on_hover_text("保存后自动上传 (Ctrl+S)");
+                if save.clicked() {
+                    request = Some(EditorRequest::Save);
+                }
+                if ui
+                    .selectable_label(buffer.find.visible, "🔍 查找")
+                    .on_hover_text("Ctrl+F")
+                    .clicked()
+                {
+                    buffer.find.visible = !buffer.find.visible;
+                }
+                ui.weak(&edit.remote_path);
+                ui.label(status);
+            });
+            if buffer.read_only() {
+                ui.colored_label(
+                    egui::Color32::YELLOW,
+                    format!(
+                        "⚠ 按 {} 解码时遇到无法识别的字节，已切换为只读，请在下方选择正确的编码",
+                        buffer.document.encoding.label()
+                    ),
+                );
+            }
+
+            let mut select = false;
+            if buffer.find.visible {
+                let replaced;
+                (select, replaced) = find_bar(ui, &mut buffer.find, Some(&mut buffer.document.text));
+                buffer.modified |= replaced;
+            }
+            ui.separator();
+
+            egui::TopBottomPanel::bottom(egui::Id::new(("remote_editor_status", edit.id)))
+                .frame(egui::Frame::none())
+                .show_inside(ui, |ui| {
+                    ui.add_space(4.0);
+                    ui.horizontal(|ui| editor_status_bar(ui, buffer, edit));
+                });
+
+            egui::ScrollArea::both().auto_shrink([false; 2]).show(ui, |ui| {
+                let (language, lines, editable) = (buffer.language, buffer.document.line_count(), !buffer.read_only());
+                let output = code_view(ui, text_id, &mut buffer.document.text, language, lines, 1, editable);
+                if output.response.changed() {
+                    buffer.modified = true;
+                    buffer.find.current = None;
+                }
+                // 只响应正在编辑的窗口的快捷键
+                if output.response.has_focus() {
+                    ui.input_mut(|i| {
+                        if i.consume_key(egui::Modifiers::COMMAND, egui::Key::S) {
+                            request = Some(EditorRequest::Save);
+                        }
+                        if i.consume_key(egui::Modifiers::COMMAND, egui::Key::F) {
+                            buffer.find.visible = true;
+                        }
+                    });
+                }
+                if let Some(range) = buffer.find.current.as_ref().filter(|_| select) {
+                    select_range(ui, text_id, &buffer.document.text, range, &output);
+                }
+            });
+        });
+    if !open {
+        request = Some(EditorRequest::Close);
+    }
+    request
+}
+
+fn editor_status_bar(ui: &mut egui::Ui, buffer: &mut EditorBuffer, edit: &EditSession) {
+    let encoding = buffer.document.encoding;
+    let mut selected = encoding;
+    ui.add_enabled_ui(!buffer.modified, |ui| {
+        egui::ComboBox::from_id_salt(("editor_encoding", edit.id))
+            .selected_text(selected.label())
+            .show_ui(ui, |ui| {
+                for option in TextEncoding::ALL {
+                    ui.selectable_value(&mut selected, option, option.label());
+                }
+            })
+            .response
+            .on_hover_text("按所选编码重新读取（需先保存修改）");
+    });
+    if selected != encoding {
+        if let Err(e) = buffer.reload_as(edit, selected) {
+            eprintln!(
+                "Failed to reload {} as {}: {}",
+                edit.local_path.display(),
+                selected.label(),
+                e
+            );
+        }
+    }
+
+    let mut line_ending = buffer.document.line_ending;
+    egui::ComboBox::from_id_salt(("editor_line_ending", edit.id))
+        .selected_text(line_ending.label())
+        .show_ui(ui, |ui| {
+            for option in LineEnding::ALL {
+                ui.selectable_value(&mut line_ending, option, option.label());
+            }
+        })
+        .response
+        .on_hover_text("保存时使用的换行符");
+    if line_ending != buffer.document.line_ending {
+        buffer.document.line_ending = line_ending;
+        buffer.document.mixed_line_endings = false;
+        buffer.modified = true;
+    }
+    if buffer.document.mixed_line_endings {
+        ui.colored_label(egui::Color32::YELLOW, "⚠ 混合换行符，保存时将统一");
+    }
+
+    language_combo(ui, ("editor_language", edit.id), &mut buffer.language);
+    ui.weak(format!("{} 行", buffer.document.line_count()));
+}
+
+pub fn render_file_viewers(state: &mut AppState, ctx: &egui::Context) {
+    let mut requests = Vec::new();
+    let mut closed = Vec::new();
+    for viewer in &mut state.file_viewers {
+        let mut open = true;
+        render_viewer_window(ctx, viewer, &mut open, &mut requests);
+        if !open {
+            closed.push(viewer.id);
+        }
+    }
+    state.file_viewers.retain(|v| !closed.contains(&v.id));
+
+    for (id, offset) in requests {
+        let Some(viewer) = state.file_viewers.iter().find(|v| v.id == id) else {
+            continue;
+        };
+        let (connection, remote_path) = (viewer.connection.clone(), viewer.remote_path.clone());
+        match crate::app::connected_session(state, &connection) {
+            Some(session) => {
+                crate::editor::spawn_viewer_read(session, id, remote_path, offset, state.sftp_msg_tx.clone())
+            }
+            None => {
+                if let Some(viewer) = state.file_viewers.iter_mut().find(|v| v.id == id) {
+                    viewer.apply(crate::editor::ViewerUpdate::Failed {
+                        id,
+                        error: format!("连接 {} 未连接", connection),
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn render_viewer_window(ctx: &egui::Context, viewer: &mut FileViewer, open: &mut bool, requests: &mut Vec<(u64, u64)>) {
+    let text_id = egui::Id::new(("file_viewer_text", viewer.id));
+    egui::Window::new(format!("👁 {} (只读)", viewer.name()))
+        .id(egui::Id::new(("file_viewer", viewer.id)))
+        .open(open)
+        .default_size([720.0, 520.0])
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let id = viewer.id;
+                let page = |ui: &mut egui::Ui, text: &str, hover: &str, enabled: bool| {
+                    ui.add_enabled(enabled, egui::Button::new(text))
+                        .on_hover_text(hover)
+                        .clicked()
+                };
+                let loaded = viewer.loading().is_none();
+                if page(ui, "⏮", "文件开头", loaded && viewer.offset > 0) {
+                    requests.extend(viewer.first_page().map(|offset| (id, offset)));
+                }
+                if page(ui, "◀", "上一页", viewer.has_previous()) {
+                    requests.extend(viewer.previous_page().map(|offset| (id, offset)));
+                }
+                if page(ui, "▶", "下一页", viewer.has_next()) {
+                    requests.extend(viewer.next_page().map(|offset| (id, offset)));
+                }
+                if page(ui, "⏭", "文件末尾", viewer.has_next()) {
+                    requests.extend(viewer.last_page().map(|offset| (id, offset)));
+                }
+                if ui
+                    .selectable_label(viewer.find.visible, "🔍 查找")
+                    .on_hover_text("在当前页中查找")
+                    .clicked()
+                {
+                    viewer.find.visible = !viewer.find.visible;
+                }
+                ui.weak(&viewer.remote_path);
+                if viewer.loading().is_some() {
+                    ui.spinner();
+                }
+            });
+            if let Some(error) = &viewer.error {
+                ui.colored_label(egui::Color32::RED, format!("❌ {}", error));
+            }
+
+            let mut select = false;
+            if viewer.find.visible {
+                select = find_bar(ui, &mut viewer.find, None).0;
+            }
+            ui.separator();
+
+            egui::TopBottomPanel::bottom(egui::Id::new(("file_viewer_status", viewer.id)))
+                .frame(egui::Frame::none())
+                .show_inside(ui, |ui| {
+                    ui.add_space(4.0);
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "字节 {}–{} / {}",
+                            viewer.offset,
+                            viewer.offset + viewer.page_len,
+                            crate::ui::transfers::format_bytes(viewer.size)
+                        ));
+                        if let Some(encoding) = viewer.encoding {
+                            ui.weak(encoding.label());
+                        }
+                        language_combo(ui, ("viewer_language", viewer.id), &mut viewer.language);
+                    });
+                });
+
+            egui::ScrollArea::both()
+                .id_salt(("file_viewer_scroll", viewer.id, viewer.offset))
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    let lines = viewer.text.lines().count().max(1);
+                    let first_line = viewer.first_line;
+                    let output = code_view(
+                        ui,
+                        text_id,
+                        &mut viewer.text,
+                        viewer.language,
+                        lines,
+                        first_line.unwrap_or(0),
+                        false,
+                    );
+                    if output.response.has_focus()
+                        && ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::F))
+                    {
+                        viewer.find.visible = true;
+                    }
+                    if let Some(range) = viewer.find.current.as_ref().filter(|_| select) {
+                        select_range(ui, text_id, &viewer.text, range, &output);
+                    }
+                });
+        });
+}
+
+/// 查找（`text` 可写时还能替换）栏，返回（是否需要选中当前匹配，文本是否被修改）
+fn find_bar(ui: &mut egui::Ui, find: &mut FindState, text: Option<&mut String>) -> (bool, bool) {
+    let mut select = false;
+    let mut replaced = false;
+    ui.horizontal(|ui| {
+        let query = ui.add(
+            egui::TextEdit::singleline(&mut find.query)
+                .hint_text("查找")
+                .desired_width(180.0),
+        );
+        if query.changed() {
+            find.current = None;
+        }
+        let enter = query.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        ui.checkbox(&mut find.options.case_sensitive, "Aa")
+            .on_hover_text("区分大小写");
+        ui.checkbox(&mut find.options.regex, ".*").on_hover_text("正则表达式");
+        let current = text.as_deref().map_or("", |t| t.as_str());
+        if ui.small_button("⬆").on_hover_text("上一个").clicked() {
+            select = find.find(current, true).is_some();
+        }
+        if ui.small_button("⬇").on_hover_text("下一个 (Enter)").clicked() || enter {
+            select = find.find(current, false).is_some();
+            if enter {
+                query.request_focus();
+            }
+        }
+        ui.weak(&find.message);
+    });
+    if let Some(text) = text {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut find.replacement)
+                    .hint_text("替换为")
+                    .desired_width(180.0),
+            );
+            if ui.small_button("替换").clicked() && find.replace(text) {
+                replaced = true;
+                select = find.current.is_some();
+            }
+            if ui.small_button("全部替换").clicked() && find.replace_all(text) > 0 {
+                replaced = true;
+            }
+        });
+    }
+    (select, replaced)
+}
+
+/// 带行号和语法高亮的代码区域
+fn code_view(
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    text: &mut String,
+    language: Language,
+    lines: usize,
+    first_line: usize,
+    editable: bool,
+) -> egui::text_edit::TextEditOutput {
+    let mut layouter = |ui: &egui::Ui, text: &str, _wrap_width: f32| {
+        let font = egui::TextStyle::Monospace.resolve(ui.style());
+        let key = (text, language, ui.visuals().dark_mode, ui.visuals().text_color(), &font);
+        let job = ui.ctx().memory_mut(|mem| mem.caches.cache::<HighlightCache>().get(key));
+        ui.fonts(|f| f.layout_job(job))
+    };
+    ui.horizontal_top(|ui| {
+        if first_line > 0 {
+            let last = first_line + lines - 1;
+            let width = last.to_string().len();
+            let numbers = (first_line..=last)
+                .map(|n| format!("{:>width$}", n))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let digit = ui.fonts(|f| f.glyph_width(&egui::TextStyle::Monospace.resolve(ui.style()), '0'));
+            ui.add(
+                egui::TextEdit::multiline(&mut numbers.as_str())
+                    .code_editor()
+                    .interactive(false)
+                    .frame(false)
+                    .desired_width(width as f32 * digit)
+                    .text_color(ui.visuals().weak_text_color()),
+            );
+        }
+        let mut read_only = text.as_str();
+        let text_edit = if editable {
+            egui::TextEdit::multiline(text)
+        } else {
+            egui::TextEdit::multiline(&mut read_only)
+        };
+        text_edit
+            .id(id)
+            .code_editor()
+            .frame(false)
+            .desired_width(f32::INFINITY)
+            .desired_rows(24)
+            .layouter(&mut layouter)
+            .show(ui)
+    })
+    .inner
+}
+
+/// 选中匹配的文本并滚动到可见处
+fn select_range(
+    ui: &egui::Ui,
+    id: egui::Id,
+    text: &str,
+    range: &std::ops::Range<usize>,
+    output: &egui::text_edit::TextEditOutput,
+) {
+    let start = CCursor::new(text[..range.start].chars().count());
+    let end = CCursor::new(text[..range.end].chars().count());
+    let mut state = output.state.clone();
+    state.cursor.set_char_range(Some(CCursorRange::two(start, end)));
+    state.store(ui.ctx(), id);
+    ui.memory_mut(|mem| mem.request_focus(id));
+    let rect = output
+        .galley
+        .pos_from_ccursor(start)
+        .translate(output.galley_pos.to_vec2());
+    ui.scroll_to_rect(rect, Some(egui::Align::Center));
+}
+
+fn language_combo(ui: &mut egui::Ui, id: impl std::hash::Hash, language: &mut Language) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(language.label())
+        .show_ui(ui, |ui| {
+            for option in Language::ALL {
+                ui.selectable_value(language, option, option.label());
+            }
+        });
+}
+
+// ----------------------------------------------------------------------------
+// 语法高亮（按文本缓存排版结果）
+// ----------------------------------------------------------------------------
+
+type HighlightKey<'a> = (&'a str, Language, bool, egui::Color32, &'a egui::FontId);
+
+#[derive(Default)]
+struct Highlighter;
+
+type HighlightCache = FrameCache<LayoutJob, Highlighter>;
+
+impl ComputerMut<HighlightKey<'_>, LayoutJob> for Highlighter {
+    fn compute(&mut self, (text, language, dark, color, font): HighlightKey<'_>) -> LayoutJob {
+        let format = |color| egui::TextFormat::simple(font.clone(), color);
+        let mut job = LayoutJob::default();
+        let mut end = 0;
+        for token in crate::editor::highlight(text, language) {
+            if token.range.start < end {
+                continue;
+            }
+            job.append(&text[end..token.range.start], 0.0, format(color));
+            job.append(&text[token.range.clone()], 0.0, format(token_color(token.kind, dark)));
+            end = token.range.end;
+        }
+        job.append(&text[end..], 0.0, format(color));
+        job.wrap.max_width = f32::INFINITY;
+        job
+    }
+}
+
+fn token_color(kind: TokenKind, dark: bool) -> egui::Color32 {
+    let (r, g, b) = match (kind, dark) {
+        (TokenKind::Keyword, true) => (86, 156, 214),
+        (TokenKind::Keyword, false) => (0, 0, 200),
+        (TokenKind::String, true) => (206, 145, 120),
+        (TokenKind::String, false) => (163, 21, 21),
+        (TokenKind::Number, true) => (181, 206, 168),
+        (TokenKind::Number, false) => (9, 134, 88),
+        (TokenKind::Comment, true) => (106, 153, 85),
+        (TokenKind::Comment, false) => (0, 128, 0),
+        (TokenKind::Key, true) => (156, 220, 254),
+        (TokenKind::Key, false) => (0, 16, 128),
+    };
+    egui::Color32::from_rgb(r, g, b)
+}
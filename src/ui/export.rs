@@ -0,0 +1,68 @@
+use crate::app::export_terminal;
+use crate::export::ExportFormat;
+use crate::state::AppState;
+use eframe::egui;
+
+// ============================================================================
+// 终端内容导出对话框
+// ============================================================================
+
+pub fn render_export_dialog(state: &mut AppState, ctx: &egui::Context) {
+    let Some(mut dialog) = state.export_dialog.clone() else {
+        return;
+    };
+
+    let title = if dialog.block.is_some() { "📤 导出命令块" } else { "📤 导出会话" };
+    let mut action = None;
+    let mut open = true;
+    egui::Window::new(title)
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label("格式:");
+            for format in ExportFormat::ALL {
+                ui.radio_value(&mut dialog.format, format, format.label());
+            }
+            ui.add_space(5.0);
+            let pattern_count = state.config.settings.export_redaction_patterns.len();
+            ui.checkbox(&mut dialog.redact, format!("隐藏敏感内容（{} 条规则）", pattern_count))
+                .on_hover_text("脱敏规则可在 设置 → 终端 中修改");
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("💾 保存…").clicked() {
+                    action = Some(true);
+                }
+                if ui.button("📋 复制").clicked() {
+                    action = Some(false);
+                }
+            });
+            if !dialog.status.is_empty() {
+                ui.label(&dialog.status);
+            }
+        });
+
+    if let Some(save) = action {
+        match export_terminal(state, &dialog) {
+            None => dialog.status = "❌ 终端或命令块已不存在".to_string(),
+            Some((file_name, content)) if save => {
+                let path = rfd::FileDialog::new()
+                    .set_file_name(&file_name)
+                    .add_filter(dialog.format.label(), &[dialog.format.extension()])
+                    .save_file();
+                if let Some(path) = path {
+                    dialog.status = match std::fs::write(&path, content) {
+                        Ok(()) => format!("✅ 已保存到 {}", path.display()),
+                        Err(e) => format!("❌ 保存失败: {}", e),
+                    };
+                }
+            }
+            Some((_, content)) => {
+                ctx.copy_text(content);
+                dialog.status = "✅ 已复制到剪贴板".to_string();
+            }
+        }
+    }
+
+    state.export_dialog = open.then_some(dialog);
+}
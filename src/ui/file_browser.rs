@@ -1,5 +1,9 @@
-use crate::state::AppState;
-use crate::types::ConnectionStatus;
+use crate::state::{AppState, LinkRequest};
+use crate::ui::transfers::{format_eta, format_speed};
+use crate::types::{
+    ConflictPolicy, ConnectionStatus, FileColumn, FileEntry, FileKind, SymlinkPolicy, TransferDirection,
+    TransferOptions,
+};
 use eframe::egui;
 
 // ============================================================================
@@ -63,7 +67,7 @@ pub fn render_file_browser(state: &mut AppState, ctx: &egui::Context) {
             ui.heading("☁️ Remote Files");
             ui.separator();
 
-            egui::ScrollArea::vertical()
+            egui::ScrollArea::both()
                 .max_height(450.0)
                 .show(ui, |ui| {
                     render_remote_files(state, ui);
@@ -77,7 +81,7 @@ pub fn render_file_browser(state: &mut AppState, ctx: &egui::Context) {
                 let download_enabled = !state.selected_remote_files.is_empty();
                 if ui
                     .add_enabled(download_enabled, egui::Button::new("⬇️ Download"))
-                    .on_hover_text("Download selected file(s) to Downloads folder")
+                    .on_hover_text("Download selected file(s) and folder(s) to Downloads folder")
                     .clicked()
                 {
                     download_selected_files(state);
@@ -86,13 +90,32 @@ pub fn render_file_browser(state: &mut AppState, ctx: &egui::Context) {
                 // 上传按钮
                 if ui.button("⬆️ Upload").on_hover_text("Upload file to current directory").clicked() {
                     // 使用 rfd (rusty file dialog) 选择文件
-                    if let Some(file_path) = rfd::FileDialog::new().pick_file() {
-                        upload_file(state, file_path);
+                    if let Some(file_paths) = rfd::FileDialog::new().pick_files() {
+                        upload_paths(state, file_paths);
                     }
                 }
 
+                if ui.button("📁 Upload Folder").on_hover_text("Upload a folder recursively to current directory").clicked() {
+                    if let Some(dir_path) = rfd::FileDialog::new().pick_folder() {
+                        upload_paths(state, vec![dir_path]);
+                    }
+                }
+
+                ui.separator();
+                render_transfer_options(state, ui);
+
                 ui.separator();
 
+                let unfinished = state.transfers.unfinished();
+                let text = if unfinished > 0 {
+                    format!("📦 Transfers ({})", unfinished)
+                } else {
+                    "📦 Transfers".to_string()
+                };
+                if ui.button(text).clicked() {
+                    state.show_transfers = !state.show_transfers;
+                }
+
                 if ui.button("❌ Close").clicked() {
                     state.show_file_browser = false;
                 }
@@ -104,13 +127,17 @@ pub fn render_file_browser(state: &mut AppState, ctx: &egui::Context) {
                 }
             });
 
-            // 进度条
-            if state.sftp_progress > 0.0 && state.sftp_progress < 1.0 {
+            // 进度条（运行中传输的总体进度）
+            if let Some(summary) = state.transfers.running_summary() {
                 ui.separator();
-                ui.add(
-                    egui::ProgressBar::new(state.sftp_progress)
-                        .text(format!("{:.0}%", state.sftp_progress * 100.0)),
+                let text = format!(
+                    "{} 个传输 · {:.0}% · {} · 剩余 {}",
+                    summary.count,
+                    summary.fraction() * 100.0,
+                    format_speed(summary.speed),
+                    format_eta(summary.eta),
                 );
+                ui.add(egui::ProgressBar::new(summary.fraction()).text(text));
             }
         });
 }
@@ -120,6 +147,27 @@ pub fn render_file_browser(state: &mut AppState, ctx: &egui::Context) {
 // ============================================================================
 
 fn render_remote_files(state: &mut AppState, ui: &mut egui::Ui) {
+    egui::Grid::new("remote_files")
+        .num_columns(FileColumn::ALL.len())
+        .striped(true)
+        .show(ui, |ui| render_remote_rows(state, ui));
+}
+
+fn render_remote_rows(state: &mut AppState, ui: &mut egui::Ui) {
+    // 列标题：点击排序，再次点击反向
+    for column in FileColumn::ALL {
+        let mut text = column.label().to_string();
+        if state.remote_sort.column == column {
+            text.push_str(if state.remote_sort.descending { " ⏷" } else { " ⏶" });
+        }
+        if ui.add(egui::Button::new(egui::RichText::new(text).strong()).frame(false)).clicked() {
+            state.remote_sort.toggle(column);
+            let sort = state.remote_sort;
+            sort.sort(&mut state.remote_files);
+        }
+    }
+    ui.end_row();
+
     // ".." 返回上级目录
     if state.remote_current_path != "/" {
         if ui
@@ -129,46 +177,119 @@ fn render_remote_files(state: &mut AppState, ui: &mut egui::Ui) {
         {
             go_parent_dir(state);
         }
+        ui.end_row();
     }
 
     // 显示远程文件列表
     for entry in state.remote_files.clone() {
-        let icon = if entry.is_dir { "📁" } else { "📄" };
         let size_str = if entry.size > 0 {
             format_size(entry.size)
         } else {
             String::new()
         };
-        let label = format!("{} {}  {}", icon, entry.name, size_str);
+        let label = match &entry.link_target {
+            Some(target) => format!("{} {} → {}", kind_icon(&entry), entry.name, target),
+            None => format!("{} {}", kind_icon(&entry), entry.name),
+        };
 
         let is_selected = state.selected_remote_files.contains(&entry.path);
 
-        let response = ui.selectable_label(is_selected, label)
-            .on_hover_text(&entry.path);
+        let response = ui
+            .selectable_label(is_selected, label)
+            .on_hover_text(format!("{}\n{}", entry.path, entry.kind.label()));
+        // 链接目标在悬停时才读取，避免每次列目录都逐个查询
+        if response.hovered() && entry.kind == FileKind::Symlink {
+            resolve_link(state, &entry.path, false);
+        }
+
+        if entry.is_dir {
+            // 目录单击进入，通过右键菜单选中以便整体下载
+            response.context_menu(|ui| {
+                let text = if is_selected { "☐ 取消选中" } else { "☑ 选中目录" };
+                if ui.button(text).clicked() {
+                    if is_selected {
+                        state.selected_remote_files.retain(|p| p != &entry.path);
+                    } else {
+                        state.selected_remote_files.push(entry.path.clone());
+                    }
+                    ui.close_menu();
+                }
+                ui.separator();
+                crate::ui::file_ops::context_menu(ui, state, &entry);
+            });
+        } else {
+            response.context_menu(|ui| {
+                if ui.button("✏ 编辑").on_hover_text("在外部编辑器中打开，保存后自动上传").clicked() {
+                    crate::ui::remote_edit::open_remote_file(state, &entry.path, false);
+                    ui.close_menu();
+                }
+                let builtin = ui.button("📝 内置编辑器").on_hover_text(format!(
+                    "超过 {} 的文件以只读方式分页查看",
+                    crate::ui::transfers::format_bytes(crate::editor::MAX_EDIT_SIZE)
+                ));
+                if builtin.clicked() {
+                    crate::ui::editor::open_file(state, &entry);
+                    ui.close_menu();
+                }
+                ui.separator();
+                crate::ui::file_ops::context_menu(ui, state, &entry);
+            });
+        }
+
+        ui.label(size_str);
+        ui.label(format_time(entry.modified));
+        ui.label(format_time(entry.accessed));
+        ui.monospace(entry.mode_string().unwrap_or_default());
+        ui.label(entry.owner_label());
+        ui.label(entry.group_label());
+        ui.end_row();
 
         if response.clicked() {
-            if entry.is_dir {
-                // 进入目录
-                state.remote_current_path = entry.path.clone();
-                request_file_list(state);
+            let resolved = state.remote_link_requests.get(&entry.path) == Some(&LinkRequest::Done);
+            if entry.kind == FileKind::Symlink && !resolved {
+                // 尚不知道链接是否指向目录，解析后再打开
+                resolve_link(state, &entry.path, true);
             } else {
-                // 切换文件选择状态
-                if is_selected {
-                    state.selected_remote_files.retain(|p| p != &entry.path);
-                } else {
-                    state.selected_remote_files.push(entry.path.clone());
-                }
+                open_entry(state, &entry);
             }
         }
     }
 }
 
+/// 单击列表项：进入目录，或切换文件的选择状态
+pub fn open_entry(state: &mut AppState, entry: &FileEntry) {
+    if entry.is_dir {
+        state.remote_current_path = entry.path.clone();
+        request_file_list(state);
+    } else if state.selected_remote_files.contains(&entry.path) {
+        state.selected_remote_files.retain(|p| p != &entry.path);
+    } else {
+        state.selected_remote_files.push(entry.path.clone());
+    }
+}
+
+/// 在后台解析当前目录中的符号链接，每个链接只请求一次
+fn resolve_link(state: &mut AppState, path: &str, open: bool) {
+    match state.remote_link_requests.get_mut(path) {
+        Some(LinkRequest::Pending { open: pending }) => *pending |= open,
+        Some(LinkRequest::Done) => {}
+        None => {
+            let session = state.selected_connection.and_then(|i| state.ssh_sessions.get(i)).cloned().flatten();
+            let Some(session) = session else {
+                return;
+            };
+            state.remote_link_requests.insert(path.to_string(), LinkRequest::Pending { open });
+            crate::file_ops::spawn_resolve_link(session, path.to_string(), state.sftp_msg_tx.clone());
+        }
+    }
+}
+
 // ============================================================================
 // 辅助函数
 // ============================================================================
 
 /// 请求远程文件列表
-fn request_file_list(state: &mut AppState) {
+pub fn request_file_list(state: &mut AppState) {
     use std::sync::Arc;
     
     if let Some(selected_idx) = state.selected_connection {
@@ -176,6 +297,8 @@ fn request_file_list(state: &mut AppState) {
             let session_clone = Arc::clone(session);
             let path = state.remote_current_path.clone();
             let tx = state.sftp_msg_tx.clone();
+            let connection = selected_connection_name(state).unwrap_or_default();
+            let names = state.remote_ids.get(&connection).cloned();
 
             state.sftp_status = "Loading...".to_string();
 
@@ -184,8 +307,33 @@ fn request_file_list(state: &mut AppState) {
                 let session = session_clone.lock().unwrap();
                 match session.sftp() {
                     Ok(sftp_client) => {
+                        // 相对路径（如从终端链接打开的 `~/x`）换成绝对路径，便于返回上级
+                        let path = match sftp_client.realpath(&path) {
+                            Ok(resolved) if resolved != path => {
+                                let _ = tx.send(crate::types::SftpMessage::Directory(resolved.clone()));
+                                resolved
+                            }
+                            _ => path,
+                        };
                         match sftp_client.list_dir(&path) {
-                            Ok(files) => {
+                            Ok(mut files) => {
+                                // 第一次列目录时读取远程的用户和组，之后使用缓存
+                                let names = names.or_else(|| match crate::file_ops::IdNames::fetch(&session) {
+                                    Ok(names) => {
+                                        let _ = tx.send(crate::types::SftpMessage::Ids {
+                                            connection,
+                                            names: names.clone(),
+                                        });
+                                        Some(names)
+                                    }
+                                    Err(e) => {
+                                        eprintln!("Failed to read remote users and groups: {}", e);
+                                        None
+                                    }
+                                });
+                                if let Some(names) = &names {
+                                    files.iter_mut().for_each(|file| file.resolve_names(names));
+                                }
                                 let _ = tx.send(crate::types::SftpMessage::FileList(files));
                             }
                             Err(e) => {
@@ -245,139 +393,143 @@ fn format_size(size: u64) -> String {
     }
 }
 
-/// 上传文件到远程服务器
-fn upload_file(state: &mut AppState, local_path: std::path::PathBuf) {
-    use std::sync::Arc;
-    
-    if let Some(selected_idx) = state.selected_connection {
-        if let Some(Some(session)) = state.ssh_sessions.get(selected_idx) {
-            let session_clone = Arc::clone(session);
-            let remote_path = state.remote_current_path.clone();
-            let tx = state.sftp_msg_tx.clone();
-            
-            // 获取文件名
-            let file_name = local_path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("unknown")
-                .to_string();
-            
-            // 构建完整的远程路径
-            let remote_file_path = if remote_path.ends_with('/') {
-                format!("{}{}", remote_path, file_name)
-            } else {
-                format!("{}/{}", remote_path, file_name)
-            };
-            
-            state.sftp_status = format!("Uploading {}...", file_name);
-            state.sftp_progress = 0.0;
-            
-            // 在后台线程执行上传
-            std::thread::spawn(move || {
-                let session = session_clone.lock().unwrap();
-                match session.sftp() {
-                    Ok(sftp_client) => {
-                        let tx_clone = tx.clone();
-                        let result = sftp_client.upload_file(
-                            &local_path,
-                            &remote_file_path,
-                            move |progress| {
-                                let _ = tx_clone.send(crate::types::SftpMessage::Progress(progress));
-                            }
-                        );
-                        
-                        match result {
-                            Ok(_) => {
-                                let _ = tx.send(crate::types::SftpMessage::Complete);
-                            }
-                            Err(e) => {
-                                let _ = tx.send(crate::types::SftpMessage::Error(format!(
-                                    "Upload failed: {}",
-                                    e
-                                )));
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        let _ = tx.send(crate::types::SftpMessage::Error(format!(
-                            "SFTP connection failed: {}",
-                            e
-                        )));
-                    }
-                }
-            });
-        }
+/// 按类型区分的图标（指向目录的链接显示为目录）
+fn kind_icon(entry: &FileEntry) -> &'static str {
+    match entry.kind {
+        FileKind::Directory => "📁",
+        FileKind::Symlink if entry.is_dir => "📁🔗",
+        FileKind::Symlink => "🔗",
+        FileKind::File => "📄",
+        FileKind::Socket | FileKind::Fifo | FileKind::CharDevice | FileKind::BlockDevice | FileKind::Other => "⚙",
+    }
+}
+
+/// 本地时区的时间，未知时为空
+fn format_time(time: Option<std::time::SystemTime>) -> String {
+    time.map(|time| chrono::DateTime::<chrono::Local>::from(time).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// 当前连接的名称（传输队列据此找回连接）
+pub fn selected_connection_name(state: &AppState) -> Option<String> {
+    let idx = state.selected_connection?;
+    state.ssh_sessions.get(idx)?.as_ref()?;
+    state.connections.get(idx).map(|c| c.name.clone())
+}
+
+/// 远程目录下的路径
+fn remote_child(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+/// 上传文件或目录到当前远程目录（加入传输队列）
+fn upload_paths(state: &mut AppState, local_paths: Vec<std::path::PathBuf>) {
+    let Some(connection) = selected_connection_name(state) else {
+        return;
+    };
+    let options = transfer_options(state);
+    for local_path in &local_paths {
+        let name = local_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("upload")
+            .to_string();
+        state.transfers.enqueue(
+            &connection,
+            TransferDirection::Upload,
+            local_path.is_dir(),
+            local_path.clone(),
+            remote_child(&state.remote_current_path, &name),
+            options,
+        );
     }
+    state.sftp_status = format!("📦 已加入传输队列: {} 项", local_paths.len());
 }
 
-/// 下载选中的文件到本地 Downloads 文件夹
+/// 下载选中的文件和目录到本地 Downloads 文件夹（加入传输队列）
 fn download_selected_files(state: &mut AppState) {
-    use std::sync::Arc;
-    
     if state.selected_remote_files.is_empty() {
         return;
     }
-    
+    let Some(connection) = selected_connection_name(state) else {
+        return;
+    };
+
     // 获取下载目录（使用 Downloads 文件夹）
     let download_dir = dirs::download_dir().unwrap_or_else(|| {
         std::env::current_dir().unwrap_or_default()
     });
-    
-    if let Some(selected_idx) = state.selected_connection {
-        if let Some(Some(session)) = state.ssh_sessions.get(selected_idx) {
-            let session_clone = Arc::clone(session);
-            let remote_files = state.selected_remote_files.clone();
-            let tx = state.sftp_msg_tx.clone();
-            
-            state.sftp_status = format!("Downloading {} file(s)...", remote_files.len());
-            state.sftp_progress = 0.0;
-            
-            // 在后台线程执行下载
-            std::thread::spawn(move || {
-                let session = session_clone.lock().unwrap();
-                match session.sftp() {
-                    Ok(sftp_client) => {
-                        let total_files = remote_files.len();
-                        for (idx, remote_path) in remote_files.iter().enumerate() {
-                            // 提取文件名
-                            let file_name = std::path::Path::new(remote_path)
-                                .file_name()
-                                .and_then(|n| n.to_str())
-                                .unwrap_or("download")
-                                .to_string();
-                            
-                            let local_path = download_dir.join(&file_name);
-                            
-                            let tx_clone = tx.clone();
-                            let result = sftp_client.download_file(
-                                remote_path,
-                                &local_path,
-                                move |progress| {
-                                    // 计算总体进度（考虑多个文件）
-                                    let total_progress = (idx as f32 + progress) / total_files as f32;
-                                    let _ = tx_clone.send(crate::types::SftpMessage::Progress(total_progress));
-                                }
-                            );
-                            
-                            if let Err(e) = result {
-                                let _ = tx.send(crate::types::SftpMessage::Error(format!(
-                                    "Failed to download {}: {}",
-                                    file_name, e
-                                )));
-                                return;
-                            }
-                        }
-                        
-                        let _ = tx.send(crate::types::SftpMessage::Complete);
-                    }
-                    Err(e) => {
-                        let _ = tx.send(crate::types::SftpMessage::Error(format!(
-                            "SFTP connection failed: {}",
-                            e
-                        )));
-                    }
-                }
-            });
-        }
+    let options = transfer_options(state);
+
+    for remote_path in state.selected_remote_files.clone() {
+        let is_dir = state.remote_files.iter().any(|e| e.path == remote_path && e.is_dir);
+        let file_name = std::path::Path::new(&remote_path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("download")
+            .to_string();
+        state.transfers.enqueue(
+            &connection,
+            TransferDirection::Download,
+            is_dir,
+            download_dir.join(&file_name),
+            remote_path,
+            options,
+        );
+    }
+    state.sftp_status = format!(
+        "📦 已加入传输队列: {} 项",
+        state.selected_remote_files.len()
+    );
+}
+
+/// 目录传输的冲突与符号链接策略（保存在设置中）
+fn render_transfer_options(state: &mut AppState, ui: &mut egui::Ui) {
+    let settings = &mut state.config.settings;
+    let before = (
+        settings.transfer_conflict,
+        settings.transfer_symlinks,
+        settings.transfer_verify_tail,
+        settings.transfer_verify_checksum,
+    );
+    egui::ComboBox::from_id_salt("transfer_conflict")
+        .selected_text(settings.transfer_conflict.label())
+        .show_ui(ui, |ui| {
+            for policy in ConflictPolicy::ALL {
+                ui.selectable_value(&mut settings.transfer_conflict, policy, policy.label());
+            }
+        })
+        .response
+        .on_hover_text("目标已存在时");
+    egui::ComboBox::from_id_salt("transfer_symlinks")
+        .selected_text(settings.transfer_symlinks.label())
+        .show_ui(ui, |ui| {
+            for policy in SymlinkPolicy::ALL {
+                ui.selectable_value(&mut settings.transfer_symlinks, policy, policy.label());
+            }
+        })
+        .response
+        .on_hover_text("目录中的符号链接");
+    ui.checkbox(&mut settings.transfer_verify_tail, "续传校验")
+        .on_hover_text("断点续传前比较已传输部分末尾的 SHA-256，不一致时重新传输");
+    ui.checkbox(&mut settings.transfer_verify_checksum, "完成后校验")
+        .on_hover_text("文件传输完成后用远程 sha256sum/md5sum 比较校验和，不一致时自动重传");
+    if before
+        != (
+            settings.transfer_conflict,
+            settings.transfer_symlinks,
+            settings.transfer_verify_tail,
+            settings.transfer_verify_checksum,
+        )
+    {
+        crate::app::save_config(state);
+    }
+}
+
+fn transfer_options(state: &AppState) -> TransferOptions {
+    TransferOptions {
+        conflict: state.config.settings.transfer_conflict,
+        symlinks: state.config.settings.transfer_symlinks,
+        verify_checksum: state.config.settings.transfer_verify_checksum,
     }
 }
@@ -0,0 +1,256 @@
+use crate::file_ops::{mode_string, FileOp, IdNames};
+use crate::state::{AppState, FileOpDialog, FileOpDialogKind};
+use crate::types::{FileEntry, FileKind};
+use eframe::egui;
+
+// ============================================================================
+// 文件管理：右键菜单与对话框
+// ============================================================================
+
+/// 文件浏览器右键菜单中的文件管理项
+pub fn context_menu(ui: &mut egui::Ui, state: &mut AppState, entry: &FileEntry) {
+    let mut dialog = None;
+    if ui.button("✏️ 重命名/移动…").clicked() {
+        dialog = Some(FileOpDialogKind::Rename { to: entry.path.clone() });
+    }
+    if ui
+        .button("📋 复制…")
+        .on_hover_text("在服务器上复制，不经过本机")
+        .clicked()
+    {
+        dialog = Some(FileOpDialogKind::Copy {
+            to: format!("{}.copy", entry.path),
+        });
+    }
+    if ui.button("🔒 权限…").clicked() {
+        dialog = Some(FileOpDialogKind::Chmod {
+            mode: current_mode(entry).unwrap_or(if entry.is_dir { 0o755 } else { 0o644 }),
+        });
+    }
+    if ui.button("👤 属主…").clicked() {
+        fetch_ids(state);
+        dialog = Some(FileOpDialogKind::Chown {
+            owner: String::new(),
+            group: String::new(),
+        });
+    }
+    if ui
+        .button("🔗 创建链接…")
+        .on_hover_text("创建指向此项的符号链接")
+        .clicked()
+    {
+        dialog = Some(FileOpDialogKind::Symlink {
+            link: format!("{}.link", entry.path),
+        });
+    }
+    if entry.kind == FileKind::Symlink && ui.button("↪ 读取链接").clicked() {
+        start(
+            state,
+            FileOp::Readlink {
+                path: entry.path.clone(),
+            },
+        );
+        ui.close_menu();
+    }
+
+    if let Some(kind) = dialog {
+        state.file_op_dialog = Some(FileOpDialog {
+            entry: entry.clone(),
+            kind,
+            error: String::new(),
+        });
+        ui.close_menu();
+    }
+}
+
+pub fn render_file_op_dialog(state: &mut AppState, ctx: &egui::Context) {
+    let Some(mut dialog) = state.file_op_dialog.clone() else {
+        return;
+    };
+    let names = crate::ui::file_browser::selected_connection_name(state).and_then(|c| state.remote_ids.get(&c));
+
+    let title = match dialog.kind {
+        FileOpDialogKind::Rename { .. } => "✏️ 重命名/移动",
+        FileOpDialogKind::Copy { .. } => "📋 复制",
+        FileOpDialogKind::Chmod { .. } => "🔒 修改权限",
+        FileOpDialogKind::Chown { .. } => "👤 修改属主",
+        FileOpDialogKind::Symlink { .. } => "🔗 创建符号链接",
+    };
+    let mut submit = false;
+    let mut open = true;
+    egui::Window::new(title)
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(&dialog.entry.path);
+            ui.add_space(5.0);
+            match &mut dialog.kind {
+                FileOpDialogKind::Rename { to } | FileOpDialogKind::Copy { to } => {
+                    ui.label("目标路径:");
+                    let response = ui.add(egui::TextEdit::singleline(to).desired_width(360.0));
+                    submit |= response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                }
+                FileOpDialogKind::Chmod { mode } => mode_editor(ui, mode),
+                FileOpDialogKind::Chown { owner, group } => {
+                    egui::Grid::new("chown_grid").num_columns(3).show(ui, |ui| {
+                        ui.label("属主:");
+                        ui.add(egui::TextEdit::singleline(owner).hint_text("留空不修改"));
+                        id_combo(ui, "chown_owner", owner, names.map(|n| &n.users));
+                        ui.end_row();
+                        ui.label("属组:");
+                        ui.add(egui::TextEdit::singleline(group).hint_text("留空不修改"));
+                        id_combo(ui, "chown_group", group, names.map(|n| &n.groups));
+                        ui.end_row();
+                    });
+                    if names.is_none() {
+                        ui.weak("正在读取远程用户和组，也可直接输入数字 id");
+                    }
+                }
+                FileOpDialogKind::Symlink { link } => {
+                    ui.label("链接路径:");
+                    let response = ui.add(egui::TextEdit::singleline(link).desired_width(360.0));
+                    submit |= response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                }
+            }
+            if !dialog.error.is_empty() {
+                ui.colored_label(egui::Color32::RED, &dialog.error);
+            }
+            ui.separator();
+            submit |= ui.button("✅ 确定").clicked();
+        });
+
+    if submit {
+        let empty = IdNames::default();
+        match build_op(&dialog, names.unwrap_or(&empty)) {
+            Ok(Some(op)) => {
+                start(state, op);
+                open = false;
+            }
+            Ok(None) => open = false,
+            Err(e) => dialog.error = format!("❌ {}", e),
+        }
+    }
+
+    state.file_op_dialog = open.then_some(dialog);
+}
+
+/// 属主/属组/其他 × 读/写/执行 的复选框
+fn mode_editor(ui: &mut egui::Ui, mode: &mut u32) {
+    egui::Grid::new("chmod_grid").num_columns(4).show(ui, |ui| {
+        ui.label("");
+        for text in ["读", "写", "执行"] {
+            ui.label(text);
+        }
+        ui.end_row();
+        for (who, shift) in [("属主", 6), ("属组", 3), ("其他", 0)] {
+            ui.label(who);
+            for bit in [0o4, 0o2, 0o1] {
+                let mask = bit << shift;
+                let mut set = *mode & mask != 0;
+                if ui.checkbox(&mut set, "").changed() {
+                    *mode ^= mask;
+                }
+            }
+            ui.end_row();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("八进制:");
+        let mut octal = format!("{:04o}", mode);
+        if ui
+            .add(egui::TextEdit::singleline(&mut octal).desired_width(48.0))
+            .changed()
+        {
+            if let Ok(value) = u32::from_str_radix(octal.trim(), 8) {
+                *mode = value & 0o7777;
+            }
+        }
+        ui.monospace(mode_string(*mode));
+    });
+}
+
+fn id_combo(ui: &mut egui::Ui, id: &str, value: &mut String, names: Option<&std::collections::BTreeMap<u32, String>>) {
+    let Some(names) = names else {
+        ui.spinner();
+        return;
+    };
+    egui::ComboBox::from_id_salt(id)
+        .selected_text("选择")
+        .show_ui(ui, |ui| {
+            for (id, name) in names {
+                if ui
+                    .selectable_label(value == name, format!("{} ({})", name, id))
+                    .clicked()
+                {
+                    *value = name.clone();
+                }
+            }
+        });
+}
+
+/// 把对话框的输入转为操作；没有需要修改的内容时返回 None
+fn build_op(dialog: &FileOpDialog, names: &IdNames) -> crate::types::Result<Option<FileOp>> {
+    let path = dialog.entry.path.clone();
+    let target = |to: &str| -> crate::types::Result<String> {
+        let to = to.trim();
+        if to.is_empty() {
+            return Err("路径不能为空".into());
+        }
+        Ok(to.to_string())
+    };
+    Ok(match &dialog.kind {
+        FileOpDialogKind::Rename { to } if to.trim() == path => None,
+        FileOpDialogKind::Rename { to } => Some(FileOp::Rename {
+            from: path,
+            to: target(to)?,
+        }),
+        FileOpDialogKind::Copy { to } => Some(FileOp::Copy {
+            from: path,
+            to: target(to)?,
+        }),
+        FileOpDialogKind::Chmod { mode } => Some(FileOp::Chmod { path, mode: *mode }),
+        FileOpDialogKind::Chown { owner, group } => {
+            let uid = (!owner.trim().is_empty())
+                .then(|| names.resolve_user(owner))
+                .transpose()?;
+            let gid = (!group.trim().is_empty())
+                .then(|| names.resolve_group(group))
+                .transpose()?;
+            (uid.is_some() || gid.is_some()).then_some(FileOp::Chown { path, uid, gid })
+        }
+        FileOpDialogKind::Symlink { link } => Some(FileOp::Symlink {
+            target: path,
+            link: target(link)?,
+        }),
+    })
+}
+
+/// 列表中的权限（`stat` 的八进制 mode，含文件类型位）
+fn current_mode(entry: &FileEntry) -> Option<u32> {
+    let permissions = entry.permissions.as_deref()?;
+    u32::from_str_radix(permissions, 8).ok().map(|mode| mode & 0o7777)
+}
+
+fn fetch_ids(state: &mut AppState) {
+    let Some(connection) = crate::ui::file_browser::selected_connection_name(state) else {
+        return;
+    };
+    if state.remote_ids.contains_key(&connection) {
+        return;
+    }
+    if let Some(session) = crate::app::connected_session(state, &connection) {
+        crate::file_ops::spawn_fetch_ids(session, connection, state.sftp_msg_tx.clone());
+    }
+}
+
+fn start(state: &mut AppState, op: FileOp) {
+    let connection = crate::ui::file_browser::selected_connection_name(state);
+    match connection.and_then(|c| crate::app::connected_session(state, &c)) {
+        Some(session) => {
+            state.sftp_status = format!("正在{}...", op.name());
+            crate::file_ops::spawn(session, op, state.sftp_msg_tx.clone());
+        }
+        None => state.sftp_status = "❌ 未连接".to_string(),
+    }
+}
@@ -0,0 +1,60 @@
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+// ============================================================================
+// CJK 字体加载
+// ============================================================================
+
+/// 常见的系统 CJK 字体（按优先级排列）
+const CJK_FONT_CANDIDATES: &[&str] = &[
+    // Linux
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/opentype/noto/NotoSansMonoCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-zenhei.ttc",
+    "/usr/share/fonts/adobe-source-han-sans/SourceHanSansCN-Regular.otf",
+    // macOS
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Light.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    // Windows
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\simhei.ttf",
+    "C:\\Windows\\Fonts\\simsun.ttc",
+];
+
+/// 查找可用的 CJK 字体：优先使用用户指定的文件，否则搜索系统字体
+pub fn find_cjk_font(custom: &str) -> Option<PathBuf> {
+    let custom = custom.trim();
+    if !custom.is_empty() {
+        return Some(PathBuf::from(custom)).filter(|p| p.is_file());
+    }
+    CJK_FONT_CANDIDATES
+        .iter()
+        .map(Path::new)
+        .find(|p| p.is_file())
+        .map(Path::to_path_buf)
+}
+
+/// 将 CJK 字体作为后备字体加入界面与终端字体族
+///
+/// 返回实际加载的字体文件；找不到字体时保持 egui 默认字体。
+pub fn install_cjk_font(ctx: &egui::Context, custom: &str) -> Option<PathBuf> {
+    let path = find_cjk_font(custom)?;
+    let data = std::fs::read(&path)
+        .map_err(|e| eprintln!("Failed to load CJK font {}: {}", path.display(), e))
+        .ok()?;
+
+    let mut fonts = egui::FontDefinitions::default();
+    fonts
+        .font_data
+        .insert("cjk".to_string(), egui::FontData::from_owned(data));
+    for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+        fonts.families.entry(family).or_default().push("cjk".to_string());
+    }
+    ctx.set_fonts(fonts);
+    Some(path)
+}
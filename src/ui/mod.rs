@@ -1,3 +1,12 @@
+pub mod clipboard;
+pub mod command_palette;
+pub mod editor;
+pub mod export;
 pub mod file_browser;
+pub mod file_ops;
+pub mod fonts;
 pub mod panels;
+pub mod remote_edit;
 pub mod settings_panel;
+pub mod terminal_view;
+pub mod transfers;
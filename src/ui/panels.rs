@@ -1,8 +1,17 @@
+use crate::actions::{Action, Keymap};
 use crate::app::{
-    connect_ssh, create_connection, disconnect_ssh, execute_ssh_command, send_ai_message,
+    can_submit_command, close_tab, connect_ssh, create_connection, disconnect_ssh,
+    open_local_tab, open_connection_tab, paste_text, read_clipboard, send_ai_message, submit_command,
+};
+use crate::session::TabKind;
+use crate::state::{AppState, PasteTarget};
+use crate::terminal::{
+    encode_mouse, is_risky_paste, MouseButton, MouseEvent, MouseEventKind, MouseTracking, Point,
+    Selection,
 };
-use crate::state::AppState;
 use crate::types::*;
+use crate::ui::terminal_view;
+use crate::zmodem::Direction;
 use eframe::egui;
 
 pub fn render_connections_panel(state: &mut AppState, ctx: &egui::Context) {
@@ -21,6 +30,9 @@ pub fn render_connections_panel(state: &mut AppState, ctx: &egui::Context) {
 
             let mut connect_idx = None;
             let mut disconnect_idx = None;
+            let mut shell_idx = None;
+            let mut scheme_change: Option<(usize, Option<String>)> = None;
+            let scheme_names = crate::app::color_scheme_names(state);
 
             for (index, conn) in state.connections.iter().enumerate() {
                 let status = state
@@ -36,15 +48,42 @@ pub fn render_connections_panel(state: &mut AppState, ctx: &egui::Context) {
                     ConnectionStatus::Error => "❌",
                 };
 
-                let response = ui.selectable_label(
-                    state.selected_connection == Some(index),
-                    format!("{} {}", status_icon, conn.name),
-                );
+                let label = match conn.protocol {
+                    Protocol::Ssh => format!("{} {}", status_icon, conn.name),
+                    protocol => format!("{} {} [{}]", status_icon, conn.name, protocol.label()),
+                };
+                let response =
+                    ui.selectable_label(state.selected_connection == Some(index), label);
 
                 if response.clicked() {
                     state.selected_connection = Some(index);
                 }
 
+                response.context_menu(|ui| {
+                    if ui.button("🖥️ 打开会话标签").clicked() {
+                        shell_idx = Some(index);
+                        ui.close_menu();
+                    }
+                    ui.menu_button("🎨 配色方案", |ui| {
+                        if ui
+                            .radio(conn.color_scheme.is_none(), "跟随主题")
+                            .clicked()
+                        {
+                            scheme_change = Some((index, None));
+                            ui.close_menu();
+                        }
+                        for name in &scheme_names {
+                            if ui
+                                .radio(conn.color_scheme.as_ref() == Some(name), name)
+                                .clicked()
+                            {
+                                scheme_change = Some((index, Some(name.clone())));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+
                 ui.horizontal(|ui| {
                     if ui.button("Connect").clicked() {
                         connect_idx = Some(index);
@@ -56,9 +95,19 @@ pub fn render_connections_panel(state: &mut AppState, ctx: &egui::Context) {
                 ui.separator();
             }
 
+            if let Some((idx, scheme)) = scheme_change {
+                state.connections[idx].color_scheme = scheme;
+                if state.config.settings.auto_save_config {
+                    crate::app::save_config(state);
+                }
+            }
             if let Some(idx) = connect_idx {
+                state.selected_connection = Some(idx);
                 connect_ssh(state, idx);
             }
+            if let Some(idx) = shell_idx {
+                open_connection_tab(state, idx);
+            }
             if let Some(idx) = disconnect_idx {
                 disconnect_ssh(state, idx);
             }
@@ -210,56 +259,147 @@ pub fn render_terminal_panel(state: &mut AppState, ctx: &egui::Context) {
             ui.heading("💻 Terminal");
             
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                // 历史搜索按钮
-                if ui.button("🔍 History (Ctrl+R)").clicked() 
-                    || ui.input(|i| i.key_pressed(egui::Key::R) && i.modifiers.ctrl) {
+                // 历史搜索按钮（快捷键由动作注册表统一处理）
+                let keymap = Keymap::from_overrides(&state.config.settings.keybindings);
+                let label = match keymap.binding(Action::HistorySearch) {
+                    Some(binding) => format!("🔍 History ({})", binding),
+                    None => "🔍 History".to_string(),
+                };
+                if ui.button(label).clicked() {
                     state.show_history_search = !state.show_history_search;
                 }
+
+                // 通过 ZMODEM 上传（在远端执行 rz）
+                let upload_tab = state
+                    .active_tab
+                    .filter(|&i| state.tabs.get(i).is_some_and(|tab| tab.is_live() && tab.zmodem.is_none()));
+                let picking = upload_tab.is_some_and(|i| state.tabs[i].zmodem_picking);
+                if ui
+                    .add_enabled(upload_tab.is_some() && !picking, egui::Button::new("⬆️ rz"))
+                    .on_hover_text("选择文件并通过 ZMODEM 上传到远端当前目录")
+                    .clicked()
+                {
+                    if let Some(index) = upload_tab {
+                        crate::app::pick_zmodem_files(state, index);
+                    }
+                }
+
+                render_expect_menu(state, ui);
             });
         });
         
         ui.separator();
+        render_terminal_tabs(state, ui);
+        render_zmodem_bar(state, ui);
+        render_expect_log(state, ui);
 
-        egui::ScrollArea::vertical()
-            .min_scrolled_height(ui.available_height() - 60.0)
-            .show(ui, |ui| {
-                ui.add(
-                    egui::TextEdit::multiline(&mut state.terminal_output)
-                        .code_editor()
-                        .desired_rows(10)
-                        .lock_focus(true),
-                );
-            });
+        // 终端输出（ANSI 颜色按当前主题渲染）
+        let palette = crate::app::active_terminal_palette(state);
+        let settings = &state.config.settings;
+        let options = terminal_view::TerminalViewOptions {
+            font_size: settings.terminal_font_size,
+            word_wrap: settings.terminal_word_wrap,
+            height: ui.available_height() - 60.0,
+            show_cursor: state.active_tab.is_some(),
+            // 全屏程序开启鼠标跟踪时上报鼠标事件，按住 Shift 仍可本地选择
+            mouse_reporting: state
+                .active_tab
+                .and_then(|i| state.tabs.get(i))
+                .is_some_and(|tab| tab.is_live() && tab.terminal.modes.mouse_tracking != MouseTracking::Off)
+                && !ui.input(|i| i.modifiers.shift),
+        };
+        let scrollback = settings.terminal_scrollback;
+
+        // 终端尺寸跟随面板大小
+        let (cols, rows) = terminal_view::grid_size(
+            ui,
+            options.font_size,
+            egui::vec2(ui.available_width(), options.height),
+        );
+        state.terminal.set_max_lines(scrollback);
+        match state.active_tab.and_then(|i| state.tabs.get_mut(i)) {
+            Some(tab) => {
+                tab.terminal.set_max_lines(scrollback);
+                tab.resize(cols, rows);
+            }
+            None => state.terminal.resize(cols, rows),
+        }
+
+        let view_id = match state.active_tab.and_then(|i| state.tabs.get(i)) {
+            Some(tab) => egui::Id::new(("terminal_tab", tab.id)),
+            None => egui::Id::new("terminal_console"),
+        };
+        let output = terminal_view::render_terminal_view(
+            ui,
+            view_id,
+            crate::app::active_terminal(state),
+            &palette,
+            &options,
+        );
+        if options.mouse_reporting {
+            report_mouse(state, ui, &output);
+        } else {
+            handle_terminal_pointer(state, ui, &output);
+        }
+
+        // 终端获得焦点时，键盘输入直接发送给 shell
+        let view_focused = output.response.has_focus();
+        let target = state.active_tab.and_then(|i| state.tabs.get(i)).map(|tab| (tab.id, tab.is_live()));
+        if view_focused {
+            let app_cursor = crate::app::active_terminal(state).modes.app_cursor_keys;
+            let input = terminal_view::terminal_input(ui, app_cursor);
+            if input.copy {
+                copy_selection(state, ui.ctx());
+            }
+            if let Some((id, true)) = target {
+                if let Some(tab) = state.tabs.iter().find(|t| t.id == id) {
+                    if !input.bytes.is_empty() {
+                        let _ = tab.write(&input.bytes);
+                    }
+                }
+                if let Some(text) = input.paste {
+                    paste_text(state, PasteTarget::Tab(id), text);
+                }
+            }
+        }
 
         ui.separator();
         ui.horizontal(|ui| {
-            ui.add(
-                egui::TextEdit::singleline(&mut state.command_input).hint_text("Enter command..."),
-            );
-                if (ui.button("Execute").clicked() || ui.input(|i| i.key_pressed(egui::Key::Enter)))
-                    && !state.command_input.trim().is_empty() {
-                        // 添加到历史
-                        let connection_name = state.selected_connection
-                            .and_then(|idx| state.connections.get(idx))
-                            .map(|c| c.name.clone())
-                            .unwrap_or_else(|| "未连接".to_string());
-                        
-                        state.command_history.add(
-                            state.command_input.clone(),
-                            connection_name
-                        );
-                        
-                        execute_ssh_command(state, state.command_input.clone());
-                        state.command_input.clear();
-                    }
+            // 多行或危险的粘贴先经过粘贴保护，不直接交给输入框
+            let input_id = egui::Id::new("terminal_command_input");
+            if ui.memory(|m| m.has_focus(input_id)) {
+                let pasted = ui.input_mut(|i| {
+                    let mut pasted = None;
+                    i.events.retain(|event| match event {
+                        egui::Event::Paste(text) if is_risky_paste(text) => {
+                            pasted = Some(text.clone());
+                            false
+                        }
+                        _ => true,
+                    });
+                    pasted
+                });
+                if let Some(text) = pasted {
+                    paste_text(state, PasteTarget::CommandInput, text);
+                }
+            }
 
-            let connected = state
-                .selected_connection
-                .and_then(|i| state.connection_status.get(i))
-                .map(|s| *s == ConnectionStatus::Connected)
-                .unwrap_or(false);
+            let input = ui.add(
+                egui::TextEdit::singleline(&mut state.command_input)
+                    .id(input_id)
+                    .hint_text("Enter command..."),
+            );
+            state.terminal_focused = input.has_focus() || view_focused;
+            let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (ui.button("Execute").clicked() || submitted) && !state.command_input.trim().is_empty() {
+                let command = std::mem::take(&mut state.command_input);
+                submit_command(state, command);
+                if submitted {
+                    input.request_focus();
+                }
+            }
 
-            ui.label(if connected {
+            ui.label(if can_submit_command(state) {
                 "🟢 Connected"
             } else {
                 "🔴 Disconnected"
@@ -271,9 +411,431 @@ pub fn render_terminal_panel(state: &mut AppState, ctx: &egui::Context) {
     render_history_search_window(state, ctx);
 }
 
+/// 手动运行或停止当前标签所属连接的 expect 脚本
+fn render_expect_menu(state: &mut AppState, ui: &mut egui::Ui) {
+    let Some(index) = state.active_tab.filter(|&i| state.tabs.get(i).is_some_and(|t| t.is_live())) else {
+        return;
+    };
+    let tab = &state.tabs[index];
+    let TabKind::Remote { connection } = tab.kind else {
+        return;
+    };
+    let scripts: Vec<String> = state
+        .connections
+        .get(connection)
+        .map(|c| c.expect_scripts.iter().map(|s| s.name.clone()).collect())
+        .unwrap_or_default();
+    let running: Vec<String> = tab.expect.iter().map(|r| r.name().to_string()).collect();
+    if scripts.is_empty() && running.is_empty() {
+        return;
+    }
+
+    let label = if running.is_empty() { "🤖 脚本".to_string() } else { format!("🤖 脚本 ({})", running.len()) };
+    ui.menu_button(label, |ui| {
+        for (script, name) in scripts.iter().enumerate() {
+            if ui.button(format!("▶ {}", name)).clicked() {
+                crate::app::run_expect_script(state, index, script);
+                ui.close_menu();
+            }
+        }
+        if !running.is_empty() {
+            ui.separator();
+            ui.label(format!("运行中: {}", running.join(", ")));
+            if ui.button("■ 停止全部").clicked() {
+                state.tabs[index].stop_expect();
+                ui.close_menu();
+            }
+        }
+    });
+}
+
+/// 当前标签的 expect 脚本日志：折叠时只显示最后一行
+fn render_expect_log(state: &mut AppState, ui: &mut egui::Ui) {
+    let Some(tab) = state.active_tab.and_then(|i| state.tabs.get_mut(i)) else {
+        return;
+    };
+    let Some(last) = tab.expect_log.back().cloned() else {
+        return;
+    };
+    let mut clear = false;
+    egui::CollapsingHeader::new(format!("🤖 脚本日志: {}", last))
+        .id_salt(("expect_log", tab.id))
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(120.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for line in &tab.expect_log {
+                        ui.monospace(line);
+                    }
+                });
+            clear = ui.small_button("清除").clicked();
+        });
+    if clear {
+        tab.expect_log.clear();
+    }
+}
+
+/// 当前标签的 ZMODEM 传输：进度、取消，以及远端先执行 `rz` 时选择文件
+fn render_zmodem_bar(state: &mut AppState, ui: &mut egui::Ui) {
+    let Some(tab) = state.active_tab.and_then(|i| state.tabs.get_mut(i)) else {
+        return;
+    };
+    let Some(transfer) = tab.zmodem.as_mut() else {
+        return;
+    };
+
+    if transfer.needs_files() {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label("⬆️ 远端 rz 正在等待，请在对话框中选择文件");
+        });
+        if let Some(index) = state.active_tab {
+            crate::app::pick_zmodem_files(state, index);
+        }
+        return;
+    }
+
+    let mut cancel = false;
+    ui.horizontal(|ui| {
+        let label = match transfer.direction() {
+            Direction::Download => "⬇️ ZMODEM 接收",
+            Direction::Upload => "⬆️ ZMODEM 发送",
+        };
+        ui.label(label);
+        ui.add(
+            egui::ProgressBar::new(state.zmodem_progress)
+                .desired_width(240.0)
+                .text(format!("{:.0}%", state.zmodem_progress * 100.0)),
+        );
+        ui.label(&state.sftp_status);
+        cancel = ui.button("取消").clicked();
+    });
+    if cancel {
+        transfer.cancel();
+    }
+}
+
+/// 将指针事件编码为鼠标跟踪序列发送给当前标签的程序
+fn report_mouse(state: &AppState, ui: &egui::Ui, output: &terminal_view::TerminalViewOutput) {
+    let Some(tab) = state.active_tab.and_then(|i| state.tabs.get(i)) else {
+        return;
+    };
+    let modes = &tab.terminal.modes;
+    let (tracking, sgr) = (modes.mouse_tracking, modes.sgr_mouse);
+    let (cols, rows) = tab.terminal.size();
+    let screen_start = tab.terminal.screen_start();
+    // 屏幕上的（列，行），超出屏幕时取最近的单元格
+    let to_screen = |pos: egui::Pos2| {
+        output.cell_at(pos).map(|p| {
+            (
+                p.col.min(cols - 1),
+                p.line.saturating_sub(screen_start).min(rows - 1),
+            )
+        })
+    };
+    let map_button = |button: egui::PointerButton| match button {
+        egui::PointerButton::Primary => Some(MouseButton::Left),
+        egui::PointerButton::Middle => Some(MouseButton::Middle),
+        egui::PointerButton::Secondary => Some(MouseButton::Right),
+        _ => None,
+    };
+
+    let response = &output.response;
+    let hovered = response.contains_pointer();
+    let last_cell_id = response.id.with("mouse_last_cell");
+    let wheel_id = response.id.with("mouse_wheel");
+    let mut last_cell: Option<(usize, usize)> = ui.data(|d| d.get_temp(last_cell_id));
+    let mut wheel: f32 = ui.data(|d| d.get_temp(wheel_id)).unwrap_or_default();
+    let row_height = ui.fonts(|f| f.row_height(&egui::FontId::monospace(state.config.settings.terminal_font_size)));
+
+    let mut events = Vec::new();
+    ui.input(|i| {
+        let held = [
+            egui::PointerButton::Primary,
+            egui::PointerButton::Middle,
+            egui::PointerButton::Secondary,
+        ]
+        .into_iter()
+        .find(|b| i.pointer.button_down(*b))
+        .and_then(map_button);
+
+        for event in &i.events {
+            match event {
+                egui::Event::PointerButton {
+                    pos,
+                    button,
+                    pressed,
+                    modifiers,
+                } => {
+                    // 按下必须落在终端内，释放则始终上报，避免程序认为按键未松开
+                    if *pressed && !hovered {
+                        continue;
+                    }
+                    let (Some(button), Some((col, row))) = (map_button(*button), to_screen(*pos)) else {
+                        continue;
+                    };
+                    let kind = if *pressed {
+                        MouseEventKind::Press(button)
+                    } else {
+                        MouseEventKind::Release(button)
+                    };
+                    events.push(MouseEvent {
+                        kind,
+                        col,
+                        row,
+                        modifiers: *modifiers,
+                    });
+                }
+                egui::Event::PointerMoved(pos) if hovered || held.is_some() => {
+                    let Some(cell) = to_screen(*pos) else { continue };
+                    // 只在跨越单元格时上报
+                    if last_cell == Some(cell) {
+                        continue;
+                    }
+                    last_cell = Some(cell);
+                    events.push(MouseEvent {
+                        kind: MouseEventKind::Motion(held),
+                        col: cell.0,
+                        row: cell.1,
+                        modifiers: i.modifiers,
+                    });
+                }
+                egui::Event::MouseWheel {
+                    unit,
+                    delta,
+                    modifiers,
+                } if hovered => {
+                    wheel += match unit {
+                        egui::MouseWheelUnit::Line => delta.y,
+                        egui::MouseWheelUnit::Point => delta.y / row_height,
+                        egui::MouseWheelUnit::Page => delta.y * rows as f32,
+                    };
+                    let Some((col, row)) = i.pointer.hover_pos().and_then(to_screen) else {
+                        continue;
+                    };
+                    while wheel.abs() >= 1.0 {
+                        let button = if wheel > 0.0 {
+                            MouseButton::WheelUp
+                        } else {
+                            MouseButton::WheelDown
+                        };
+                        wheel -= wheel.signum();
+                        events.push(MouseEvent {
+                            kind: MouseEventKind::Press(button),
+                            col,
+                            row,
+                            modifiers: *modifiers,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+    ui.data_mut(|d| {
+        d.insert_temp(last_cell_id, last_cell);
+        d.insert_temp(wheel_id, wheel);
+    });
+
+    let bytes: Vec<u8> = events
+        .into_iter()
+        .filter_map(|event| encode_mouse(event, tracking, sgr))
+        .flatten()
+        .collect();
+    if !bytes.is_empty() {
+        let _ = tab.write(&bytes);
+    }
+}
+
+/// 终端鼠标交互：拖动选择文本、单击清除选区、右键粘贴或弹出菜单
+fn handle_terminal_pointer(state: &mut AppState, ui: &egui::Ui, output: &terminal_view::TerminalViewOutput) {
+    let response = &output.response;
+
+    if let Some(cell) = output.pointer_cell {
+        let terminal = crate::app::active_terminal_mut(state);
+        if response.drag_started_by(egui::PointerButton::Primary) {
+            terminal.set_selection(Some(Selection {
+                anchor: cell,
+                head: cell,
+            }));
+        } else if response.dragged_by(egui::PointerButton::Primary) {
+            if let Some(selection) = terminal.selection() {
+                terminal.set_selection(Some(Selection { head: cell, ..selection }));
+            }
+        }
+    }
+    if response.drag_stopped_by(egui::PointerButton::Primary) && state.config.settings.copy_on_select {
+        copy_selection(state, ui.ctx());
+    }
+    let pointer = ui.input(|i| i.pointer.interact_pos());
+    if let Some(link) = output.hovered_link.clone().filter(|_| response.clicked()) {
+        crate::app::open_terminal_link(state, ui.ctx(), link.target);
+    } else if response.clicked() {
+        // 点击命令块的状态标记折叠或展开输出
+        match pointer.and_then(|pos| output.block_badge_at(pos)) {
+            Some(block) => crate::app::active_terminal_mut(state).toggle_fold(block),
+            None => crate::app::active_terminal_mut(state).set_selection(None),
+        }
+    }
+
+    let paste_target = state
+        .active_tab
+        .and_then(|i| state.tabs.get(i))
+        .filter(|tab| tab.is_live())
+        .map(|tab| PasteTarget::Tab(tab.id));
+    let right_click_paste = state.config.settings.right_click_paste;
+
+    // 右键所在的命令块（右键粘贴开启时只有点在状态标记上才弹出菜单）
+    let menu_block_id = response.id.with("menu_block");
+    if response.secondary_clicked() {
+        let block = if right_click_paste {
+            pointer.and_then(|pos| output.block_badge_at(pos))
+        } else {
+            output
+                .pointer_cell
+                .and_then(|cell| crate::app::active_terminal(state).block_at(cell.line))
+        };
+        ui.data_mut(|d| d.insert_temp(menu_block_id, block));
+        if right_click_paste && block.is_none() {
+            if let Some(target) = paste_target {
+                paste_text(state, target, read_clipboard().unwrap_or_default());
+            }
+        }
+    }
+    let menu_block: Option<usize> = ui.data(|d| d.get_temp(menu_block_id)).flatten();
+    if right_click_paste && menu_block.is_none() {
+        return;
+    }
+
+    response.context_menu(|ui| {
+        if let Some(block) = menu_block {
+            let folded = crate::app::active_terminal(state)
+                .blocks()
+                .get(block)
+                .is_some_and(|b| b.folded);
+            if ui.button(if folded { "▾ 展开输出" } else { "▸ 折叠输出" }).clicked() {
+                crate::app::active_terminal_mut(state).toggle_fold(block);
+                ui.close_menu();
+            }
+            if ui.button("📋 复制输出").clicked() {
+                if let Some(text) = crate::app::active_terminal(state).block_output(block) {
+                    ui.ctx().copy_text(text);
+                }
+                ui.close_menu();
+            }
+            if ui.button("🤖 发送给 AI").clicked() {
+                crate::app::send_block_to_ai(state, block);
+                ui.close_menu();
+            }
+            if ui.button("📤 导出命令块…").clicked() {
+                crate::app::open_export_dialog(state, Some(block));
+                ui.close_menu();
+            }
+            ui.separator();
+        }
+        let has_selection = crate::app::active_terminal(state).selection().is_some();
+        if ui.add_enabled(has_selection, egui::Button::new("📋 复制")).clicked() {
+            copy_selection(state, ui.ctx());
+            ui.close_menu();
+        }
+        if ui.add_enabled(paste_target.is_some(), egui::Button::new("📥 粘贴")).clicked() {
+            if let Some(target) = paste_target {
+                paste_text(state, target, read_clipboard().unwrap_or_default());
+            }
+            ui.close_menu();
+        }
+        if ui.button("🔲 全选").clicked() {
+            let terminal = crate::app::active_terminal_mut(state);
+            let last = terminal.line_count().saturating_sub(1);
+            let cols = terminal.size().0;
+            terminal.set_selection(Some(Selection {
+                anchor: Point::default(),
+                head: Point { line: last, col: cols },
+            }));
+            ui.close_menu();
+        }
+        if ui.button("📤 导出会话…").clicked() {
+            crate::app::open_export_dialog(state, None);
+            ui.close_menu();
+        }
+        if ui.button("🗑️ 清空").clicked() {
+            crate::app::active_terminal_mut(state).clear();
+            ui.close_menu();
+        }
+    });
+}
+
+/// 复制当前终端的选中文本
+fn copy_selection(state: &AppState, ctx: &egui::Context) {
+    if let Some(text) = crate::app::active_terminal(state).selected_text() {
+        if !text.is_empty() {
+            ctx.copy_text(text);
+        }
+    }
+}
+
+/// 终端标签栏：日志控制台 + 各 shell 标签
+fn render_terminal_tabs(state: &mut AppState, ui: &mut egui::Ui) {
+    let mut close = None;
+    ui.horizontal_wrapped(|ui| {
+        if ui
+            .selectable_label(state.active_tab.is_none(), "📋 Console")
+            .clicked()
+        {
+            state.active_tab = None;
+        }
+
+        for (index, tab) in state.tabs.iter().enumerate() {
+            let icon = match tab.status {
+                ConnectionStatus::Connected => "🟢",
+                ConnectionStatus::Connecting => "🟡",
+                ConnectionStatus::Disconnected => "⚪",
+                ConnectionStatus::Error => "❌",
+            };
+            let title: String = tab.display_title().chars().take(24).collect();
+            if ui
+                .selectable_label(state.active_tab == Some(index), format!("{} {}", icon, title))
+                .clicked()
+            {
+                state.active_tab = Some(index);
+            }
+            if ui.small_button("✖").on_hover_text("关闭标签").clicked() {
+                close = Some(index);
+            }
+            ui.separator();
+        }
+
+        ui.menu_button("➕", |ui| {
+            if ui.button("🖥️ 本地 Shell").clicked() {
+                open_local_tab(state);
+                ui.close_menu();
+            }
+            let connected: Vec<(usize, String)> = state
+                .connections
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| state.connection_status.get(*i) == Some(&ConnectionStatus::Connected))
+                .map(|(i, c)| (i, c.name.clone()))
+                .collect();
+            for (index, name) in connected {
+                if ui.button(format!("🔗 {}", name)).clicked() {
+                    open_connection_tab(state, index);
+                    ui.close_menu();
+                }
+            }
+        });
+    });
+
+    if let Some(index) = close {
+        close_tab(state, index);
+    }
+    ui.separator();
+}
+
 pub fn render_new_connection_dialog(state: &mut AppState, ctx: &egui::Context) {
     if state.show_new_connection {
-        egui::Window::new("New SSH Connection")
+        egui::Window::new("New Connection")
             .collapsible(false)
             .show(ctx, |ui| {
                 ui.heading("Connection Details");
@@ -282,28 +844,68 @@ pub fn render_new_connection_dialog(state: &mut AppState, ctx: &egui::Context) {
                 ui.label("Name:");
                 ui.text_edit_singleline(&mut state.new_conn_name);
 
+                ui.label("Protocol:");
+                let previous = state.new_conn_protocol;
+                egui::ComboBox::from_id_salt("new_conn_protocol")
+                    .selected_text(previous.label())
+                    .show_ui(ui, |ui| {
+                        for protocol in Protocol::ALL {
+                            ui.selectable_value(&mut state.new_conn_protocol, protocol, protocol.label());
+                        }
+                    });
+                // 端口仍为旧协议默认值时跟随切换
+                if state.new_conn_protocol != previous
+                    && state.new_conn_port == previous.default_port().to_string()
+                {
+                    state.new_conn_port = state.new_conn_protocol.default_port().to_string();
+                }
+
                 ui.label("Host:");
                 ui.text_edit_singleline(&mut state.new_conn_host);
 
                 ui.label("Port:");
                 ui.text_edit_singleline(&mut state.new_conn_port);
 
-                ui.label("Username:");
-                ui.text_edit_singleline(&mut state.new_conn_user);
+                if state.new_conn_protocol == Protocol::Ssh {
+                    ui.label("Username:");
+                    ui.text_edit_singleline(&mut state.new_conn_user);
 
-                ui.checkbox(&mut state.new_conn_use_key, "Use Private Key");
+                    ui.checkbox(&mut state.new_conn_use_key, "Use Private Key");
 
-                if state.new_conn_use_key {
-                    ui.label("Key Path:");
-                    ui.text_edit_singleline(&mut state.new_conn_key_path);
+                    if state.new_conn_use_key {
+                        ui.label("Key Path:");
+                        ui.text_edit_singleline(&mut state.new_conn_key_path);
 
-                    ui.label("Passphrase (optional):");
-                    ui.add(egui::TextEdit::singleline(&mut state.new_conn_password).password(true));
+                        ui.label("Passphrase (optional):");
+                        ui.add(egui::TextEdit::singleline(&mut state.new_conn_password).password(true));
+                    } else {
+                        ui.label("Password:");
+                        ui.add(egui::TextEdit::singleline(&mut state.new_conn_password).password(true));
+                    }
                 } else {
-                    ui.label("Password:");
-                    ui.add(egui::TextEdit::singleline(&mut state.new_conn_password).password(true));
+                    ui.label(egui::RichText::new("登录在终端中交互完成").weak());
                 }
 
+                ui.label("Color Scheme:");
+                let scheme_names = crate::app::color_scheme_names(state);
+                egui::ComboBox::from_id_salt("new_conn_color_scheme")
+                    .selected_text(
+                        state
+                            .new_conn_color_scheme
+                            .clone()
+                            .unwrap_or_else(|| "Follow theme".to_string()),
+                    )
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut state.new_conn_color_scheme, None, "Follow theme");
+                        for name in scheme_names {
+                            ui.selectable_value(
+                                &mut state.new_conn_color_scheme,
+                                Some(name.clone()),
+                                name,
+                            );
+                        }
+                    });
+
                 ui.separator();
                 ui.horizontal(|ui| {
                     if ui.button("Save").clicked() {
@@ -380,10 +982,23 @@ fn render_history_search_window(state: &mut AppState, ctx: &egui::Context) {
                                             .weak()
                                     );
                                     ui.label(
-                                        egui::RichText::new(&format!("📡 {}", entry.connection))
+                                        egui::RichText::new(format!("📡 {}", entry.connection))
                                             .small()
                                             .weak()
                                     );
+                                    match entry.exit_code {
+                                        Some(0) => {
+                                            ui.label(egui::RichText::new("✔").small().color(egui::Color32::GREEN));
+                                        }
+                                        Some(code) => {
+                                            ui.label(
+                                                egui::RichText::new(format!("✘ {}", code))
+                                                    .small()
+                                                    .color(egui::Color32::RED),
+                                            );
+                                        }
+                                        None => {}
+                                    }
                                 });                          });
                             
                             ui.separator();
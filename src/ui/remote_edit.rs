@@ -0,0 +1,179 @@
+use crate::remote_edit::EditState;
+use crate::ui::editor::EditorRequest;
+use crate::state::AppState;
+use eframe::egui;
+
+// ============================================================================
+// 远程编辑：会话列表与内置编辑器
+// ============================================================================
+
+enum EditAction {
+    Overwrite(u64),
+    Reload(u64),
+    Retry(u64),
+    Relaunch(u64),
+    Save(u64),
+    Close(u64),
+}
+
+/// 在外部或内置编辑器中打开远程文件（当前连接）
+pub fn open_remote_file(state: &mut AppState, remote_path: &str, builtin: bool) {
+    let Some(connection) = crate::ui::file_browser::selected_connection_name(state) else {
+        return;
+    };
+    if let Some(edit) = state.remote_edits.find(&connection, remote_path) {
+        state.sftp_status = format!("{} 已在编辑中", edit.name());
+        return;
+    }
+    let Some(session) = crate::app::connected_session(state, &connection) else {
+        state.sftp_status = format!("❌ 连接 {} 未连接", connection);
+        return;
+    };
+    match state.remote_edits.open(&connection, remote_path, builtin) {
+        Ok(edit) => {
+            state.sftp_status = format!("正在下载 {} 以编辑...", edit.name());
+            crate::remote_edit::spawn_download(session, edit, state.sftp_msg_tx.clone());
+        }
+        Err(e) => state.sftp_status = format!("❌ 无法创建编辑工作区: {}", e),
+    }
+}
+
+pub fn render_remote_edits(state: &mut AppState, ctx: &egui::Context) {
+    let mut actions = Vec::new();
+    render_sessions_window(state, ctx, &mut actions);
+    for buffer in &mut state.editor_buffers {
+        let Some(edit) = state.remote_edits.get(buffer.edit_id) else {
+            continue;
+        };
+        match crate::ui::editor::render_editor_window(ctx, buffer, edit, state_text(&edit.state, edit.uploads)) {
+            Some(EditorRequest::Save) => actions.push(EditAction::Save(edit.id)),
+            Some(EditorRequest::Close) => actions.push(EditAction::Close(edit.id)),
+            None => {}
+        }
+    }
+
+    for action in actions {
+        run_action(state, action);
+    }
+}
+
+fn render_sessions_window(state: &AppState, ctx: &egui::Context, actions: &mut Vec<EditAction>) {
+    if state.remote_edits.sessions().is_empty() {
+        return;
+    }
+    egui::Window::new("✏ 远程编辑").default_width(480.0).show(ctx, |ui| {
+        for edit in state.remote_edits.sessions() {
+            ui.horizontal(|ui| {
+                ui.label(format!("📄 {}", edit.name())).on_hover_text(format!(
+                    "{}\n本地副本: {}",
+                    edit.remote_path,
+                    edit.local_path.display()
+                ));
+                ui.weak(&edit.connection);
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let mut button = |ui: &mut egui::Ui, text: &str, hover: &str, action: EditAction| {
+                        if ui.small_button(text).on_hover_text(hover).clicked() {
+                            actions.push(action);
+                        }
+                    };
+                    button(ui, "✖", "结束编辑并删除本地副本", EditAction::Close(edit.id));
+                    match &edit.state {
+                        EditState::Conflict { .. } => {
+                            button(ui, "⬇ 重新下载", "放弃本地修改", EditAction::Reload(edit.id));
+                            button(
+                                ui,
+                                "⬆ 覆盖远程",
+                                "用本地内容覆盖远程修改",
+                                EditAction::Overwrite(edit.id),
+                            );
+                        }
+                        EditState::Failed { .. } => button(ui, "🔁", "重试", EditAction::Retry(edit.id)),
+                        EditState::Watching if !edit.builtin => {
+                            button(ui, "📝", "再次打开编辑器", EditAction::Relaunch(edit.id))
+                        }
+                        _ => {}
+                    }
+                    ui.label(state_text(&edit.state, edit.uploads));
+                });
+            });
+        }
+    });
+}
+
+fn state_text(state: &EditState, uploads: u32) -> egui::RichText {
+    match state {
+        EditState::Downloading => egui::RichText::new("下载中..."),
+        EditState::Watching if uploads > 0 => egui::RichText::new(format!("✅ 已上传 {} 次", uploads)),
+        EditState::Watching => egui::RichText::new("等待保存"),
+        EditState::Uploading => egui::RichText::new("上传中..."),
+        EditState::Conflict { .. } => egui::RichText::new("⚠ 远程文件已被修改").color(egui::Color32::YELLOW),
+        EditState::Failed { error } => egui::RichText::new(format!("❌ {}", error)).color(egui::Color32::RED),
+    }
+}
+
+fn run_action(state: &mut AppState, action: EditAction) {
+    match action {
+        EditAction::Overwrite(id) => {
+            if let Some(edit) = state.remote_edits.overwrite(id) {
+                start_upload(state, edit, true);
+            }
+        }
+        EditAction::Retry(id) => {
+            if let Some(edit) = state.remote_edits.retry(id) {
+                match edit.state {
+                    EditState::Uploading => start_upload(state, edit, false),
+                    _ => start_download(state, edit),
+                }
+            }
+        }
+        EditAction::Reload(id) => {
+            if let Some(edit) = state.remote_edits.reload(id) {
+                start_download(state, edit);
+            }
+        }
+        EditAction::Relaunch(id) => {
+            if let Some(edit) = state.remote_edits.get(id) {
+                if let Err(e) =
+                    crate::remote_edit::launch_editor(&state.config.settings.external_editor, &edit.local_path)
+                {
+                    state.sftp_status = format!("❌ 无法启动编辑器: {}", e);
+                }
+            }
+        }
+        EditAction::Save(id) => {
+            let Some(edit) = state.remote_edits.get(id) else {
+                return;
+            };
+            if let Some(buffer) = state.editor_buffers.iter_mut().find(|b| b.edit_id == id) {
+                if let Err(e) = buffer.save(edit) {
+                    state.sftp_status = format!("❌ 保存失败: {}", e);
+                }
+            }
+        }
+        EditAction::Close(id) => {
+            state.editor_buffers.retain(|b| b.edit_id != id);
+            state.remote_edits.close(id);
+        }
+    }
+}
+
+fn start_upload(state: &mut AppState, edit: crate::remote_edit::EditSession, force: bool) {
+    match crate::app::connected_session(state, &edit.connection) {
+        Some(session) => crate::remote_edit::spawn_upload(session, edit, force, state.sftp_msg_tx.clone()),
+        None => fail(state, edit.id, &edit.connection),
+    }
+}
+
+fn start_download(state: &mut AppState, edit: crate::remote_edit::EditSession) {
+    match crate::app::connected_session(state, &edit.connection) {
+        Some(session) => crate::remote_edit::spawn_download(session, edit, state.sftp_msg_tx.clone()),
+        None => fail(state, edit.id, &edit.connection),
+    }
+}
+
+fn fail(state: &mut AppState, id: u64, connection: &str) {
+    state.remote_edits.apply(crate::remote_edit::EditUpdate::Failed {
+        id,
+        error: format!("连接 {} 未连接", connection),
+    });
+}
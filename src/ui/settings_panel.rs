@@ -22,8 +22,11 @@ pub fn render_settings_window(state: &mut AppState, ctx: &Context) {
                         ui.selectable_value(&mut state.settings_page, SettingsPage::General, "🎨 常规");
                         ui.selectable_value(&mut state.settings_page, SettingsPage::Appearance, "🖌️ 外观");
                         ui.selectable_value(&mut state.settings_page, SettingsPage::Terminal, "💻 终端");
+                        ui.selectable_value(&mut state.settings_page, SettingsPage::Keybindings, "⌨️ 快捷键");
                         ui.selectable_value(&mut state.settings_page, SettingsPage::Ai, "🤖 AI");
                         ui.selectable_value(&mut state.settings_page, SettingsPage::History, "📜 历史");
+                        ui.selectable_value(&mut state.settings_page, SettingsPage::Triggers, "⚡ 触发器");
+                        ui.selectable_value(&mut state.settings_page, SettingsPage::Automation, "🤖 自动化");
                     });
 
                 // 右侧内容区
@@ -33,8 +36,11 @@ pub fn render_settings_window(state: &mut AppState, ctx: &Context) {
                             SettingsPage::General => render_general_settings(state, ui),
                             SettingsPage::Appearance => render_appearance_settings(state, ui),
                             SettingsPage::Terminal => render_terminal_settings(state, ui),
+                            SettingsPage::Keybindings => render_keybinding_settings(state, ui),
                             SettingsPage::Ai => render_ai_settings(state, ui),
                             SettingsPage::History => render_history_settings(state, ui),
+                            SettingsPage::Triggers => render_trigger_settings(state, ui),
+                            SettingsPage::Automation => render_automation_settings(state, ui),
                         }
                     });
                 });
@@ -45,14 +51,24 @@ pub fn render_settings_window(state: &mut AppState, ctx: &Context) {
             // 底部按钮
             ui.horizontal(|ui| {
                 if ui.button("✅ 保存").clicked() {
+                    state.theme_editor = None;
                     save_settings(state);
                     state.show_settings = false;
                 }
                 if ui.button("❌ 取消").clicked() {
+                    state.theme_editor = None;
+                    state.keybinding_capture = None;
                     // 重新加载配置以撤销更改
                     if let Ok(config) = state.config_manager.load_config() {
                         state.config = config;
                     }
+                    // 自动化脚本直接编辑在连接上，同样撤销
+                    for (conn, saved) in state.connections.iter_mut().zip(&state.config.connections) {
+                        if conn.name == saved.name {
+                            conn.expect_scripts = saved.expect_scripts.clone();
+                            conn.secrets = saved.secrets.clone();
+                        }
+                    }
                     state.show_settings = false;
                 }
                 if ui.button("🔄 恢复默认").clicked() {
@@ -81,6 +97,15 @@ fn render_general_settings(state: &mut AppState, ui: &mut egui::Ui) {
         "删除前确认"
     );
     ui.label("在删除文件或连接时显示确认对话框");
+
+    ui.add_space(10.0);
+
+    ui.label("外部编辑器:");
+    ui.add(
+        egui::TextEdit::singleline(&mut state.config.settings.external_editor)
+            .hint_text("如 code --wait，留空使用系统默认程序"),
+    );
+    ui.label("编辑远程文件时打开本地副本，保存后自动上传");
 }
 
 /// 外观设置
@@ -90,13 +115,21 @@ fn render_appearance_settings(state: &mut AppState, ui: &mut egui::Ui) {
     ui.add_space(10.0);
 
     ui.label("主题:");
-    ui.horizontal(|ui| {
+    ui.horizontal_wrapped(|ui| {
         ui.selectable_value(&mut state.config.settings.theme, "dark".to_string(), "🌙 深色");
         ui.selectable_value(&mut state.config.settings.theme, "light".to_string(), "☀️ 浅色");
+        ui.selectable_value(&mut state.config.settings.theme, "custom".to_string(), "🎨 自定义");
+        for theme in &state.config.custom_themes {
+            ui.selectable_value(&mut state.config.settings.theme, theme.name.clone(), &theme.name);
+        }
     });
 
     ui.add_space(10.0);
 
+    render_theme_editor(state, ui);
+
+    ui.add_space(10.0);
+
     ui.label("字体大小:");
     ui.add(egui::Slider::new(&mut state.config.settings.font_size, 10.0..=24.0).text("pt"));
 
@@ -104,6 +137,207 @@ fn render_appearance_settings(state: &mut AppState, ui: &mut egui::Ui) {
 
     ui.label("终端字体大小:");
     ui.add(egui::Slider::new(&mut state.config.settings.terminal_font_size, 10.0..=24.0).text("pt"));
+
+    ui.add_space(10.0);
+
+    ui.label("CJK 字体:");
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::TextEdit::singleline(&mut state.config.settings.cjk_font_path)
+                .hint_text("留空自动查找系统字体"),
+        );
+        if ui.button("📂").clicked() {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("Font", &["ttf", "ttc", "otf"])
+                .pick_file()
+            {
+                state.config.settings.cjk_font_path = path.display().to_string();
+            }
+        }
+        if ui.button("应用").clicked() {
+            state.cjk_font =
+                crate::ui::fonts::install_cjk_font(ui.ctx(), &state.config.settings.cjk_font_path);
+        }
+    });
+    match &state.cjk_font {
+        Some(path) => ui.label(format!("当前字体: {}", path.display())),
+        None => ui.colored_label(egui::Color32::from_rgb(255, 170, 0), "未找到 CJK 字体，中文可能显示为方框"),
+    };
+
+    ui.add_space(10.0);
+
+    render_color_scheme_settings(state, ui);
+}
+
+/// 自定义主题编辑器
+fn render_theme_editor(state: &mut AppState, ui: &mut egui::Ui) {
+    use crate::theme::{CustomTheme, ThemeManager};
+
+    ui.group(|ui| {
+        ui.label(egui::RichText::new("主题编辑器").strong());
+        ui.separator();
+
+        let Some(draft) = state.theme_editor.as_mut() else {
+            ui.horizontal(|ui| {
+                if ui.button("➕ 新建主题").clicked() {
+                    state.theme_editor = Some(CustomTheme::default());
+                    state.theme_editor_status.clear();
+                }
+                let current = ThemeManager::find_custom(
+                    &state.config.settings.theme,
+                    &state.config.custom_themes,
+                )
+                .cloned();
+                if let Some(theme) = current {
+                    if ui.button("✏️ 编辑当前主题").clicked() {
+                        state.theme_editor = Some(theme.clone());
+                        state.theme_editor_status.clear();
+                    }
+                    if ui.button("🗑️ 删除当前主题").clicked() {
+                        state.config.custom_themes.retain(|t| t.name != theme.name);
+                        state.config.settings.theme = "dark".to_string();
+                    }
+                    if ui.button("📤 导出").clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("Theme", &["toml"])
+                            .set_file_name(format!("{}.toml", theme.name))
+                            .save_file()
+                        {
+                            state.theme_editor_status = match theme.save_to_file(&path) {
+                                Ok(_) => format!("✅ 已导出到 {}", path.display()),
+                                Err(e) => format!("❌ 导出失败: {}", e),
+                            };
+                        }
+                    }
+                }
+                if ui.button("📥 导入").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().add_filter("Theme", &["toml"]).pick_file() {
+                        crate::app::import_custom_theme(state, &path);
+                    }
+                }
+            });
+            if !state.theme_editor_status.is_empty() {
+                ui.label(&state.theme_editor_status);
+            }
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("名称:");
+            ui.text_edit_singleline(&mut draft.name);
+        });
+        ui.checkbox(&mut draft.dark_base, "基于深色样式");
+
+        egui::Grid::new("theme_editor_colors").num_columns(2).show(ui, |ui| {
+            color_row(ui, "面板背景", &mut draft.panel_fill);
+            color_row(ui, "窗口背景", &mut draft.window_fill);
+            color_row(ui, "文本", &mut draft.text_color);
+            color_row(ui, "强调色", &mut draft.accent);
+            color_row(ui, "选中", &mut draft.selection);
+        });
+
+        ui.add(egui::Slider::new(&mut draft.widget_rounding, 0.0..=12.0).text("控件圆角"));
+        ui.add(egui::Slider::new(&mut draft.item_spacing[0], 0.0..=20.0).text("水平间距"));
+        ui.add(egui::Slider::new(&mut draft.item_spacing[1], 0.0..=20.0).text("垂直间距"));
+
+        // 预览（编辑期间整个界面已应用草稿）
+        ui.add_space(5.0);
+        ui.label(egui::RichText::new("预览").weak());
+        ui.horizontal(|ui| {
+            let _ = ui.button("按钮");
+            let _ = ui.selectable_label(true, "选中项");
+            let _ = ui.selectable_label(false, "未选中");
+            ui.hyperlink_to("链接", "https://github.com");
+        });
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            if ui.button("💾 保存主题").clicked() {
+                crate::app::save_custom_theme(state);
+            }
+            if ui.button("❌ 取消编辑").clicked() {
+                state.theme_editor = None;
+                state.theme_editor_status.clear();
+            }
+        });
+        if !state.theme_editor_status.is_empty() {
+            ui.label(&state.theme_editor_status);
+        }
+    });
+}
+
+/// 颜色编辑行
+fn color_row(ui: &mut egui::Ui, label: &str, color: &mut crate::color_scheme::RgbColor) {
+    ui.label(label);
+    let mut rgb = [color.0, color.1, color.2];
+    if egui::color_picker::color_edit_button_srgb(ui, &mut rgb).changed() {
+        *color = crate::color_scheme::RgbColor(rgb[0], rgb[1], rgb[2]);
+    }
+    ui.end_row();
+}
+
+/// 终端配色方案管理
+fn render_color_scheme_settings(state: &mut AppState, ui: &mut egui::Ui) {
+    ui.group(|ui| {
+        ui.label(egui::RichText::new("终端配色方案").strong());
+        ui.separator();
+
+        for scheme in crate::color_scheme::ColorScheme::builtin() {
+            ui.horizontal(|ui| {
+                render_scheme_swatch(ui, &scheme);
+                ui.label(format!("{} (内置)", scheme.name));
+            });
+        }
+
+        let mut remove_idx = None;
+        for (idx, scheme) in state.config.color_schemes.iter().enumerate() {
+            ui.horizontal(|ui| {
+                render_scheme_swatch(ui, scheme);
+                ui.label(&scheme.name);
+                if ui.small_button("🗑️").clicked() {
+                    remove_idx = Some(idx);
+                }
+            });
+        }
+        if let Some(idx) = remove_idx {
+            state.config.color_schemes.remove(idx);
+        }
+
+        ui.add_space(5.0);
+        if ui
+            .button("📥 导入配色方案")
+            .on_hover_text("支持 .itermcolors、Windows Terminal JSON、base16 YAML、Alacritty TOML")
+            .clicked()
+        {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("Color Scheme", &["itermcolors", "json", "yaml", "yml", "toml"])
+                .pick_file()
+            {
+                crate::app::import_color_scheme(state, &path);
+            }
+        }
+
+        if !state.color_scheme_status.is_empty() {
+            ui.label(&state.color_scheme_status);
+        }
+        ui.label("在连接列表中右键连接可为其选择配色方案");
+    });
+}
+
+/// 绘制配色方案预览色块
+fn render_scheme_swatch(ui: &mut egui::Ui, scheme: &crate::color_scheme::ColorScheme) {
+    let size = egui::vec2(10.0, 14.0);
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(size.x * 17.0, size.y), egui::Sense::hover());
+    let painter = ui.painter();
+    painter.rect_filled(
+        egui::Rect::from_min_size(rect.min, size),
+        0.0,
+        scheme.background.to_color32(),
+    );
+    for (i, color) in scheme.ansi.iter().enumerate() {
+        let min = rect.min + egui::vec2(size.x * (i + 1) as f32, 0.0);
+        painter.rect_filled(egui::Rect::from_min_size(min, size), 0.0, color.to_color32());
+    }
 }
 
 /// 终端设置
@@ -123,6 +357,568 @@ fn render_terminal_settings(state: &mut AppState, ui: &mut egui::Ui) {
         "自动换行"
     );
     ui.label("长行是否自动换行显示");
+
+    ui.add_space(10.0);
+
+    ui.checkbox(
+        &mut state.config.settings.shell_integration,
+        "Shell 集成"
+    );
+    ui.label("打开 shell 标签时注入 OSC 133 脚本（bash/zsh），划分命令块并记录退出码");
+
+    ui.add_space(10.0);
+
+    // 剪贴板
+    ui.group(|ui| {
+        ui.label(egui::RichText::new("剪贴板").strong());
+        ui.separator();
+
+        let settings = &mut state.config.settings;
+        ui.checkbox(&mut settings.copy_on_select, "选中即复制");
+        ui.checkbox(&mut settings.right_click_paste, "右键粘贴");
+        ui.label("关闭时右键弹出复制/粘贴菜单");
+        ui.checkbox(&mut settings.paste_protection, "粘贴保护");
+        ui.label("粘贴多行或包含 sudo 的文本前确认");
+
+        ui.add_space(5.0);
+        ui.label("远程程序剪贴板权限 (OSC 52):");
+        if settings.clipboard_access.is_empty() {
+            ui.label(egui::RichText::new("尚未记录任何主机").weak());
+        }
+        let describe = |access: Option<bool>| match access {
+            Some(true) => "允许",
+            Some(false) => "拒绝",
+            None => "询问",
+        };
+        let mut reset = None;
+        for (host, access) in &settings.clipboard_access {
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(host).monospace());
+                ui.label(format!("写入: {}  读取: {}", describe(access.write), describe(access.read)));
+                if ui.small_button("↺ 重置").clicked() {
+                    reset = Some(host.clone());
+                }
+            });
+        }
+        if let Some(host) = reset {
+            settings.clipboard_access.remove(&host);
+        }
+    });
+
+    ui.add_space(10.0);
+
+    // 导出脱敏规则
+    ui.group(|ui| {
+        ui.label(egui::RichText::new("导出脱敏").strong());
+        ui.label("导出会话时隐藏匹配的内容；规则含捕获组时只隐藏第一个捕获组");
+        ui.separator();
+
+        let patterns = &mut state.config.settings.export_redaction_patterns;
+        let mut remove_idx = None;
+        for (idx, pattern) in patterns.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(pattern)
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(380.0),
+                );
+                if ui.small_button("🗑️").clicked() {
+                    remove_idx = Some(idx);
+                }
+            });
+        }
+        if let Some(idx) = remove_idx {
+            patterns.remove(idx);
+        }
+        let (_, errors) = crate::export::Redactor::compile(patterns);
+        for error in errors {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        ui.horizontal(|ui| {
+            if ui.button("➕ 添加规则").clicked() {
+                patterns.push(String::new());
+            }
+            if ui.button("恢复默认规则").clicked() {
+                *patterns = crate::export::default_redaction_patterns();
+            }
+        });
+    });
+
+    ui.add_space(10.0);
+
+    // 命令片段
+    ui.group(|ui| {
+        ui.label(egui::RichText::new("命令片段").strong());
+        ui.label("片段可在命令面板中搜索并运行");
+        ui.separator();
+
+        let mut remove_idx = None;
+        for (idx, snippet) in state.config.snippets.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(&snippet.name).strong());
+                ui.label(egui::RichText::new(&snippet.command).monospace());
+                if ui.small_button("🗑️").clicked() {
+                    remove_idx = Some(idx);
+                }
+            });
+        }
+        if let Some(idx) = remove_idx {
+            state.config.snippets.remove(idx);
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut state.new_snippet_name).hint_text("名称").desired_width(120.0));
+            ui.add(egui::TextEdit::singleline(&mut state.new_snippet_command).hint_text("命令"));
+            let can_add = !state.new_snippet_name.trim().is_empty()
+                && !state.new_snippet_command.trim().is_empty();
+            if ui.add_enabled(can_add, egui::Button::new("➕ 添加")).clicked() {
+                state.config.snippets.push(crate::types::Snippet {
+                    name: state.new_snippet_name.trim().to_string(),
+                    command: state.new_snippet_command.trim().to_string(),
+                });
+                state.new_snippet_name.clear();
+                state.new_snippet_command.clear();
+            }
+        });
+    });
+}
+
+/// 输出触发器设置
+fn render_trigger_settings(state: &mut AppState, ui: &mut egui::Ui) {
+    use crate::types::{TriggerAction, TriggerRule};
+
+    ui.heading("输出触发器");
+    ui.label("终端输出中的行匹配正则表达式时执行动作");
+    ui.add_space(10.0);
+
+    let scopes: Vec<String> = std::iter::once("本地".to_string())
+        .chain(state.config.connections.iter().map(|c| c.name.clone()))
+        .collect();
+    let snippets: Vec<String> = state.config.snippets.iter().map(|s| s.name.clone()).collect();
+
+    let mut remove_idx = None;
+    for (idx, rule) in state.config.triggers.iter_mut().enumerate() {
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut rule.enabled, "");
+                ui.add(egui::TextEdit::singleline(&mut rule.name).hint_text("名称").desired_width(160.0));
+                if ui.small_button("🗑️").clicked() {
+                    remove_idx = Some(idx);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("正则:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut rule.pattern)
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(300.0),
+                );
+            });
+            if let Some(error) = state.triggers.error(idx) {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            ui.horizontal(|ui| {
+                ui.label("范围:");
+                let scope_text = if rule.connection.is_empty() { "全局" } else { rule.connection.as_str() };
+                egui::ComboBox::from_id_salt(("trigger_scope", idx))
+                    .selected_text(scope_text.to_string())
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut rule.connection, String::new(), "全局");
+                        for scope in &scopes {
+                            ui.selectable_value(&mut rule.connection, scope.clone(), scope);
+                        }
+                    });
+
+                ui.label("动作:");
+                let actions = [
+                    TriggerAction::Highlight { color: [255, 85, 85] },
+                    TriggerAction::Notify,
+                    TriggerAction::Sound,
+                    TriggerAction::AutoReply { text: String::new() },
+                    TriggerAction::RunSnippet {
+                        snippet: snippets.first().cloned().unwrap_or_default(),
+                    },
+                ];
+                egui::ComboBox::from_id_salt(("trigger_action", idx))
+                    .selected_text(rule.action.label())
+                    .show_ui(ui, |ui| {
+                        for action in actions {
+                            let selected = std::mem::discriminant(&action) == std::mem::discriminant(&rule.action);
+                            if ui.selectable_label(selected, action.label()).clicked() && !selected {
+                                rule.action = action;
+                            }
+                        }
+                    });
+
+                match &mut rule.action {
+                    TriggerAction::Highlight { color } => {
+                        ui.color_edit_button_srgb(color);
+                    }
+                    TriggerAction::AutoReply { text } => {
+                        ui.add(egui::TextEdit::singleline(text).hint_text("发送的文本").desired_width(140.0));
+                    }
+                    TriggerAction::RunSnippet { snippet } => {
+                        egui::ComboBox::from_id_salt(("trigger_snippet", idx))
+                            .selected_text(snippet.clone())
+                            .show_ui(ui, |ui| {
+                                for name in &snippets {
+                                    ui.selectable_value(snippet, name.clone(), name);
+                                }
+                            });
+                    }
+                    TriggerAction::Notify | TriggerAction::Sound => {}
+                }
+            });
+        });
+    }
+    if let Some(idx) = remove_idx {
+        state.config.triggers.remove(idx);
+    }
+
+    ui.add_space(5.0);
+    ui.horizontal(|ui| {
+        let presets = [
+            ("➕ 添加规则", "新规则", "", TriggerAction::Highlight { color: [255, 85, 85] }),
+            ("🔴 错误高亮", "错误高亮", "ERROR|FATAL|panicked", TriggerAction::Highlight { color: [255, 85, 85] }),
+            ("🔔 构建完成通知", "构建完成", "Build finished", TriggerAction::Notify),
+        ];
+        for (button, name, pattern, action) in presets {
+            if ui.button(button).clicked() {
+                state.config.triggers.push(TriggerRule {
+                    name: name.to_string(),
+                    pattern: pattern.to_string(),
+                    connection: String::new(),
+                    action,
+                    enabled: true,
+                });
+            }
+        }
+    });
+}
+
+/// expect 自动化脚本设置（按连接保存）
+fn render_automation_settings(state: &mut AppState, ui: &mut egui::Ui) {
+    use crate::types::{ExpectBranch, ExpectNext, ExpectScript, ExpectSend, ExpectStep, StoredSecret, LOGIN_PASSWORD_SECRET};
+
+    ui.heading("自动化脚本");
+    ui.label("会话建立后按步骤等待输出匹配，自动发送文本或保存的密钥；运行过程记录在会话中");
+    ui.add_space(10.0);
+
+    if state.connections.is_empty() {
+        ui.label("暂无连接");
+        return;
+    }
+    let selected_id = ui.id().with("expect_connection");
+    let mut selected: usize = ui.data(|d| d.get_temp(selected_id)).unwrap_or(0);
+    selected = selected.min(state.connections.len() - 1);
+    ui.horizontal(|ui| {
+        ui.label("连接:");
+        egui::ComboBox::from_id_salt("expect_connection")
+            .selected_text(state.connections[selected].name.clone())
+            .show_ui(ui, |ui| {
+                for (index, conn) in state.connections.iter().enumerate() {
+                    ui.selectable_value(&mut selected, index, &conn.name);
+                }
+            });
+    });
+    ui.data_mut(|d| d.insert_temp(selected_id, selected));
+    let conn = &mut state.connections[selected];
+    ui.add_space(5.0);
+
+    ui.group(|ui| {
+        ui.label("🔑 密钥");
+        ui.small(format!("未单独保存时，密钥 “{}” 使用登录密码", LOGIN_PASSWORD_SECRET));
+        let mut remove_idx = None;
+        for (idx, secret) in conn.secrets.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut secret.name).hint_text("名称").desired_width(120.0));
+                ui.add(egui::TextEdit::singleline(&mut secret.value).password(true).desired_width(160.0));
+                if ui.small_button("🗑️").clicked() {
+                    remove_idx = Some(idx);
+                }
+            });
+        }
+        if let Some(idx) = remove_idx {
+            conn.secrets.remove(idx);
+        }
+        if ui.button("➕ 添加密钥").clicked() {
+            conn.secrets.push(StoredSecret::default());
+        }
+    });
+    ui.add_space(5.0);
+
+    let mut secret_names: Vec<String> = conn.secrets.iter().map(|s| s.name.clone()).collect();
+    if !secret_names.iter().any(|n| n == LOGIN_PASSWORD_SECRET) {
+        secret_names.push(LOGIN_PASSWORD_SECRET.to_string());
+    }
+
+    let mut remove_script = None;
+    for (si, script) in conn.expect_scripts.iter_mut().enumerate() {
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut script.name).hint_text("脚本名称").desired_width(160.0));
+                ui.checkbox(&mut script.auto_start, "连接时运行");
+                if ui.small_button("🗑️").clicked() {
+                    remove_script = Some(si);
+                }
+            });
+
+            let step_count = script.steps.len();
+            let mut remove_step = None;
+            for (step_idx, step) in script.steps.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.strong(format!("第 {} 步", step_idx + 1));
+                    ui.label("超时(秒):");
+                    ui.add(egui::DragValue::new(&mut step.timeout_secs).range(0..=3600))
+                        .on_hover_text("0 表示一直等待");
+                    ui.label("超时后:");
+                    expect_next_combo(ui, (si, step_idx, "timeout"), &mut step.on_timeout, step_count);
+                    if ui.small_button("🗑️").clicked() {
+                        remove_step = Some(step_idx);
+                    }
+                });
+
+                let mut remove_branch = None;
+                for (bi, branch) in step.branches.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add_space(20.0);
+                        ui.label("匹配:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut branch.pattern)
+                                .font(egui::TextStyle::Monospace)
+                                .hint_text("留空立即执行")
+                                .desired_width(160.0),
+                        );
+                        let sends = [
+                            None,
+                            Some(ExpectSend::Line { text: String::new() }),
+                            Some(ExpectSend::Secret {
+                                name: secret_names.first().cloned().unwrap_or_default(),
+                            }),
+                            Some(ExpectSend::Keys { text: String::new() }),
+                        ];
+                        let send_label = |send: &Option<ExpectSend>| send.as_ref().map_or("不发送", ExpectSend::label);
+                        egui::ComboBox::from_id_salt(("expect_send", si, step_idx, bi))
+                            .selected_text(send_label(&branch.send))
+                            .width(70.0)
+                            .show_ui(ui, |ui| {
+                                for send in sends {
+                                    let selected = branch.send.as_ref().map(std::mem::discriminant)
+                                        == send.as_ref().map(std::mem::discriminant);
+                                    if ui.selectable_label(selected, send_label(&send)).clicked() && !selected {
+                                        branch.send = send;
+                                    }
+                                }
+                            });
+                        match &mut branch.send {
+                            Some(ExpectSend::Line { text }) => {
+                                ui.add(egui::TextEdit::singleline(text).desired_width(100.0));
+                            }
+                            Some(ExpectSend::Keys { text }) => {
+                                ui.add(egui::TextEdit::singleline(text).hint_text("\\r \\t \\x03").desired_width(100.0));
+                            }
+                            Some(ExpectSend::Secret { name }) => {
+                                egui::ComboBox::from_id_salt(("expect_secret", si, step_idx, bi))
+                                    .selected_text(name.clone())
+                                    .width(100.0)
+                                    .show_ui(ui, |ui| {
+                                        for secret in &secret_names {
+                                            ui.selectable_value(name, secret.clone(), secret);
+                                        }
+                                    });
+                            }
+                            None => {}
+                        }
+                        ui.label("然后:");
+                        expect_next_combo(ui, (si, step_idx, bi), &mut branch.next, step_count);
+                        if ui.small_button("🗑️").clicked() {
+                            remove_branch = Some(bi);
+                        }
+                    });
+                    if let Err(e) = regex::Regex::new(&branch.pattern) {
+                        ui.colored_label(egui::Color32::RED, e.to_string());
+                    }
+                }
+                if let Some(bi) = remove_branch {
+                    step.branches.remove(bi);
+                }
+                ui.horizontal(|ui| {
+                    ui.add_space(20.0);
+                    if ui.small_button("➕ 分支").clicked() {
+                        step.branches.push(ExpectBranch::default());
+                    }
+                });
+            }
+            if let Some(step_idx) = remove_step {
+                script.steps.remove(step_idx);
+            }
+            if ui.button("➕ 添加步骤").clicked() {
+                script.steps.push(ExpectStep::default());
+            }
+        });
+    }
+    if let Some(si) = remove_script {
+        conn.expect_scripts.remove(si);
+    }
+
+    ui.add_space(5.0);
+    ui.horizontal(|ui| {
+        if ui.button("➕ 添加脚本").clicked() {
+            conn.expect_scripts.push(ExpectScript {
+                name: "新脚本".to_string(),
+                auto_start: true,
+                steps: vec![ExpectStep::default()],
+            });
+        }
+        // 持续应答 sudo 密码提示与主机指纹确认
+        if ui.button("🔐 sudo 与确认提示").clicked() {
+            let branch = |pattern: &str, send: ExpectSend| ExpectBranch {
+                pattern: pattern.to_string(),
+                send: Some(send),
+                next: ExpectNext::Repeat,
+            };
+            conn.expect_scripts.push(ExpectScript {
+                name: "自动应答".to_string(),
+                auto_start: true,
+                steps: vec![ExpectStep {
+                    branches: vec![
+                        branch(
+                            r"\[sudo\] password for \S+:",
+                            ExpectSend::Secret {
+                                name: LOGIN_PASSWORD_SECRET.to_string(),
+                            },
+                        ),
+                        branch(r"\(yes/no(/\[fingerprint\])?\)\?", ExpectSend::Line { text: "yes".to_string() }),
+                    ],
+                    timeout_secs: 0,
+                    on_timeout: ExpectNext::Fail,
+                }],
+            });
+        }
+    });
+}
+
+/// expect 分支或超时的去向选择
+fn expect_next_combo(
+    ui: &mut egui::Ui,
+    id_salt: impl std::hash::Hash,
+    next: &mut crate::types::ExpectNext,
+    step_count: usize,
+) {
+    use crate::types::ExpectNext;
+
+    let options = [ExpectNext::Next, ExpectNext::Repeat, ExpectNext::Finish, ExpectNext::Fail]
+        .into_iter()
+        .chain((1..=step_count).map(|step| ExpectNext::Goto { step }));
+    egui::ComboBox::from_id_salt(("expect_next", id_salt))
+        .selected_text(next.label())
+        .width(90.0)
+        .show_ui(ui, |ui| {
+            for option in options {
+                ui.selectable_value(next, option, option.label());
+            }
+        });
+}
+
+/// 快捷键设置
+fn render_keybinding_settings(state: &mut AppState, ui: &mut egui::Ui) {
+    use crate::actions::{Action, KeyBinding, Keymap};
+
+    ui.heading("快捷键设置");
+    ui.separator();
+    ui.add_space(10.0);
+
+    ui.checkbox(
+        &mut state.config.settings.terminal_passthrough,
+        "终端快捷键直通"
+    );
+    ui.label("终端输入框获得焦点时，不带 Shift/Alt 的快捷键（如 Ctrl+R）交给 shell 处理");
+
+    ui.add_space(10.0);
+
+    // 录制快捷键
+    if let Some(action) = state.keybinding_capture {
+        let captured = ui.input(|i| {
+            i.events.iter().find_map(|event| match event {
+                egui::Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => Some((*key, *modifiers)),
+                _ => None,
+            })
+        });
+        match captured {
+            Some((egui::Key::Escape, modifiers)) if modifiers.is_none() => {
+                state.keybinding_capture = None;
+            }
+            Some((key, modifiers)) => {
+                let binding = KeyBinding::from_event(key, modifiers);
+                state
+                    .config
+                    .settings
+                    .keybindings
+                    .insert(action.id().to_string(), binding.to_string());
+                state.keybinding_capture = None;
+            }
+            None => {}
+        }
+    }
+
+    let keymap = Keymap::from_overrides(&state.config.settings.keybindings);
+    let conflicts = keymap.conflicts();
+
+    egui::Grid::new("keybindings_grid")
+        .num_columns(4)
+        .striped(true)
+        .show(ui, |ui| {
+            for action in Action::ALL {
+                ui.label(action.label());
+
+                let capturing = state.keybinding_capture == Some(action);
+                let text = if capturing {
+                    "按下快捷键... (Esc 取消)".to_string()
+                } else {
+                    match keymap.binding(action) {
+                        Some(binding) => binding.to_string(),
+                        None => "未绑定".to_string(),
+                    }
+                };
+                let in_conflict = conflicts.iter().any(|c| c.actions.contains(&action));
+                let text = if in_conflict {
+                    egui::RichText::new(text).color(egui::Color32::RED)
+                } else {
+                    egui::RichText::new(text).monospace()
+                };
+                if ui.selectable_label(capturing, text).clicked() {
+                    state.keybinding_capture = Some(action);
+                }
+
+                if ui.small_button("↺ 默认").clicked() {
+                    state.config.settings.keybindings.remove(action.id());
+                }
+                if ui.small_button("✖ 清除").clicked() {
+                    state
+                        .config
+                        .settings
+                        .keybindings
+                        .insert(action.id().to_string(), String::new());
+                }
+                ui.end_row();
+            }
+        });
+
+    if !conflicts.is_empty() {
+        ui.add_space(10.0);
+        for conflict in &conflicts {
+            let names: Vec<&str> = conflict.actions.iter().map(|a| a.label()).collect();
+            ui.colored_label(
+                egui::Color32::RED,
+                format!("⚠️ {} 冲突: {}", conflict.binding, names.join(", ")),
+            );
+        }
+    }
 }
 
 /// AI 设置
@@ -168,10 +964,10 @@ fn render_ai_settings(state: &mut AppState, ui: &mut egui::Ui) {
             ui.add(egui::TextEdit::singleline(key).password(true));
         } else {
             let mut temp_key = String::new();
-            if ui.add(egui::TextEdit::singleline(&mut temp_key).password(true).hint_text("输入 API Key")).changed() {
-                if !temp_key.is_empty() {
-                    state.config.ai.openai.api_key = Some(temp_key);
-                }
+            if ui.add(egui::TextEdit::singleline(&mut temp_key).password(true).hint_text("输入 API Key")).changed()
+                && !temp_key.is_empty()
+            {
+                state.config.ai.openai.api_key = Some(temp_key);
             }
         }
 
@@ -193,10 +989,10 @@ fn render_ai_settings(state: &mut AppState, ui: &mut egui::Ui) {
             ui.add(egui::TextEdit::singleline(key).password(true));
         } else {
             let mut temp_key = String::new();
-            if ui.add(egui::TextEdit::singleline(&mut temp_key).password(true).hint_text("输入 API Key")).changed() {
-                if !temp_key.is_empty() {
-                    state.config.ai.google.api_key = Some(temp_key);
-                }
+            if ui.add(egui::TextEdit::singleline(&mut temp_key).password(true).hint_text("输入 API Key")).changed()
+                && !temp_key.is_empty()
+            {
+                state.config.ai.google.api_key = Some(temp_key);
             }
         }
 
@@ -244,6 +1040,8 @@ fn render_history_settings(state: &mut AppState, ui: &mut egui::Ui) {
 
 /// 保存设置
 fn save_settings(state: &mut AppState) {
+    state.config.connections = state.connections.clone();
+
     // 更新命令历史最大大小
     state.command_history = state.command_history.clone().with_max_size(state.config.settings.history_max_size);
     
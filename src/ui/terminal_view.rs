@@ -0,0 +1,406 @@
+use crate::terminal::{link_at, CellStyle, CommandBlock, DetectedLink, Point, TerminalBuffer, TerminalLine};
+use crate::theme::TerminalPalette;
+use eframe::egui;
+use egui::text::{Fonts, LayoutJob, TextFormat};
+
+// ============================================================================
+// 终端输出渲染
+// ============================================================================
+
+fn text_format(style: &CellStyle, palette: &TerminalPalette, font_id: &egui::FontId) -> TextFormat {
+    let (fg, bg) = palette.resolve(style);
+    let stroke = egui::Stroke::new(1.0, fg);
+    TextFormat {
+        font_id: font_id.clone(),
+        color: fg,
+        background: if bg == palette.background {
+            egui::Color32::TRANSPARENT
+        } else {
+            bg
+        },
+        italics: style.italic,
+        underline: if style.underline { stroke } else { egui::Stroke::NONE },
+        strikethrough: if style.strikethrough { stroke } else { egui::Stroke::NONE },
+        ..Default::default()
+    }
+}
+
+/// 将一行终端内容转换为带样式的 LayoutJob
+///
+/// 每个字符簇都对齐到自己的网格列：CJK、emoji 等字形来自后备字体，
+/// 宽度与等宽字体的整数倍不同，这些字符簇单独成段，并用下一段的
+/// 前导空白校正累计偏差。
+pub fn layout_line(
+    line: &TerminalLine,
+    palette: &TerminalPalette,
+    font_id: &egui::FontId,
+    fonts: &Fonts,
+) -> LayoutJob {
+    let cell_width = fonts.glyph_width(font_id, 'M');
+    let mut job = LayoutJob::default();
+    // 已排版内容的实际宽度与下一个单元格所在列
+    let mut x = 0.0;
+    let mut col = 0usize;
+    // 当前片段：（文本，样式，前导空白，是否可以继续追加）
+    let mut run: Option<(String, CellStyle, f32, bool)> = None;
+
+    let flush = |job: &mut LayoutJob, run: &mut Option<(String, CellStyle, f32, bool)>| {
+        if let Some((text, style, leading, _)) = run.take() {
+            job.append(&text, leading, text_format(&style, palette, font_id));
+        }
+    };
+
+    for cell in line.cells.iter().filter(|c| !c.is_spacer()) {
+        let mut cluster = String::new();
+        cell.push_to(&mut cluster);
+        let advance: f32 = cluster.chars().map(|c| fonts.glyph_width(font_id, c)).sum();
+        let expected = cell.width as f32 * cell_width;
+        let aligned = (advance - expected).abs() < 0.01;
+
+        match &mut run {
+            Some((text, style, _, true)) if *style == cell.style && aligned => text.push_str(&cluster),
+            _ => {
+                flush(&mut job, &mut run);
+                let leading = (col as f32 * cell_width - x).max(0.0);
+                x += leading;
+                run = Some((cluster, cell.style, leading, aligned));
+            }
+        }
+        x += advance;
+        col += cell.width as usize;
+    }
+    flush(&mut job, &mut run);
+
+    // 空行也需要占据一行高度
+    if job.sections.is_empty() {
+        job.append(
+            " ",
+            0.0,
+            TextFormat {
+                font_id: font_id.clone(),
+                color: palette.foreground,
+                ..Default::default()
+            },
+        );
+    }
+
+    job
+}
+
+/// 终端视图的显示选项
+pub struct TerminalViewOptions {
+    pub font_size: f32,
+    pub word_wrap: bool,
+    pub height: f32,
+    /// 绘制光标（仅交互式会话需要）
+    pub show_cursor: bool,
+    /// 鼠标事件上报给程序时，滚轮不再滚动回滚历史
+    pub mouse_reporting: bool,
+}
+
+/// 内边距
+const MARGIN: f32 = 4.0;
+
+/// 按可用区域计算终端网格尺寸（列，行）
+pub fn grid_size(ui: &egui::Ui, font_size: f32, size: egui::Vec2) -> (usize, usize) {
+    let font_id = egui::FontId::monospace(font_size);
+    let (glyph_width, row_height) =
+        ui.fonts(|f| (f.glyph_width(&font_id, 'M'), f.row_height(&font_id)));
+    // 预留垂直滚动条的宽度
+    let width = size.x - 2.0 * MARGIN - ui.spacing().scroll.bar_width;
+    let height = size.y - 2.0 * MARGIN;
+    (
+        ((width / glyph_width).floor() as usize).max(1),
+        ((height / row_height).floor() as usize).max(1),
+    )
+}
+
+/// 命令块的标记颜色：运行中、成功、失败、未知
+fn block_color(block: &CommandBlock, palette: &TerminalPalette) -> egui::Color32 {
+    match block.exit_code {
+        _ if block.is_running() => palette.cursor,
+        Some(0) => egui::Color32::from_rgb(80, 200, 120),
+        Some(_) => egui::Color32::from_rgb(230, 80, 80),
+        None => egui::Color32::GRAY,
+    }
+}
+
+/// 终端视图的交互结果
+pub struct TerminalViewOutput {
+    /// 终端区域（可获取焦点，支持点击与拖动）
+    pub response: egui::Response,
+    /// 指针所在的单元格（拖动到区域外时取最近的单元格）
+    pub pointer_cell: Option<Point>,
+    /// 命令块状态标记（块索引，位置）
+    pub block_badges: Vec<(usize, egui::Rect)>,
+    /// 按住 Ctrl 时指针下的链接
+    pub hovered_link: Option<DetectedLink>,
+    /// 本帧渲染的行及其位置
+    line_rects: Vec<(usize, egui::Rect)>,
+    glyph_width: f32,
+}
+
+impl TerminalViewOutput {
+    /// 指定位置上的命令块状态标记
+    pub fn block_badge_at(&self, pos: egui::Pos2) -> Option<usize> {
+        self.block_badges
+            .iter()
+            .find(|(_, rect)| rect.expand(2.0).contains(pos))
+            .map(|(index, _)| *index)
+    }
+
+    /// 屏幕坐标对应的单元格，超出区域时取最近的单元格
+    pub fn cell_at(&self, pos: egui::Pos2) -> Option<Point> {
+        let (index, rect) = self
+            .line_rects
+            .iter()
+            .find(|(_, rect)| pos.y < rect.bottom())
+            .or(self.line_rects.last())?;
+        let col = ((pos.x - rect.left()) / self.glyph_width).floor().max(0.0) as usize;
+        Some(Point { line: *index, col })
+    }
+}
+
+/// 渲染终端缓冲区
+///
+/// 点击终端即获得焦点；拖动选区等交互由调用方根据返回值处理。
+pub fn render_terminal_view(
+    ui: &mut egui::Ui,
+    id_salt: impl std::hash::Hash,
+    buffer: &TerminalBuffer,
+    palette: &TerminalPalette,
+    options: &TerminalViewOptions,
+) -> TerminalViewOutput {
+    let id = ui.make_persistent_id(id_salt);
+    let focused = ui.memory(|m| m.has_focus(id));
+    let font_id = egui::FontId::monospace(options.font_size);
+    let (glyph_width, row_height) =
+        ui.fonts(|f| (f.glyph_width(&font_id, 'M'), f.row_height(&font_id)));
+
+    let cursor = buffer.cursor();
+    let cursor_line = (options.show_cursor && buffer.modes.cursor_visible)
+        .then(|| buffer.screen_start() + cursor.row);
+    let selection = buffer.selection().map(|s| s.range());
+    let selection_fill = ui.visuals().selection.bg_fill.gamma_multiply(0.6);
+
+    let badge_font = egui::FontId::proportional(options.font_size * 0.8);
+
+    // 返回命令块状态标记的位置（可点击折叠）
+    let decorate = |ui: &egui::Ui, index: usize, line: &TerminalLine, line_rect: egui::Rect| {
+        let cell_rect = |from: usize, to: usize| {
+            egui::Rect::from_min_size(
+                egui::pos2(line_rect.left() + from as f32 * glyph_width, line_rect.top()),
+                egui::vec2((to - from) as f32 * glyph_width, row_height),
+            )
+        };
+
+        if let Some((start, end)) = selection {
+            if (start.line..=end.line).contains(&index) {
+                let from = if index == start.line { start.col } else { 0 };
+                let to = if index == end.line {
+                    end.col + 1
+                } else {
+                    line.cells.len().max(from + 1)
+                };
+                if to > from {
+                    ui.painter().rect_filled(cell_rect(from, to), 0.0, selection_fill);
+                }
+            }
+        }
+
+        if cursor_line == Some(index) {
+            // 宽字符上的光标占两列
+            let width = line.cells.get(cursor.col).map_or(1, |c| c.width.max(1) as usize);
+            let rect = cell_rect(cursor.col, cursor.col + width);
+            if focused {
+                ui.painter()
+                    .rect_filled(rect, 0.0, palette.cursor.gamma_multiply(0.7));
+            } else {
+                ui.painter()
+                    .rect_stroke(rect, 0.0, egui::Stroke::new(1.0, palette.cursor));
+            }
+        }
+
+        // 命令块：左侧色条标出范围，命令行右侧显示退出状态与耗时
+        let block_index = buffer.block_at(index)?;
+        let block = &buffer.blocks()[block_index];
+        let color = block_color(block, palette);
+        let x = line_rect.left() - MARGIN / 2.0;
+        ui.painter()
+            .with_clip_rect(ui.clip_rect().expand2(egui::vec2(MARGIN, 0.0)))
+            .line_segment(
+                [egui::pos2(x, line_rect.top()), egui::pos2(x, line_rect.bottom())],
+                egui::Stroke::new(2.0, color),
+            );
+
+        if index != block.command_line() {
+            return None;
+        }
+        let mut text = block.status_text();
+        if let Some(range) = block.output_range().filter(|_| block.folded) {
+            text.push_str(&format!("  ▸ {} 行已折叠", range.len()));
+        }
+        if text.is_empty() {
+            return None;
+        }
+        let galley = ui.painter().layout_no_wrap(text, badge_font.clone(), color);
+        let pos = egui::pos2(ui.clip_rect().right() - MARGIN, line_rect.top());
+        let rect = egui::Align2::RIGHT_TOP.anchor_size(pos, galley.size());
+        ui.painter()
+            .rect_filled(rect.expand(2.0), 3.0, palette.background.gamma_multiply(0.9));
+        ui.painter().galley(rect.min, galley, color);
+        Some((block_index, rect))
+    };
+
+    // 折叠的命令块输出不显示
+    let folded: Vec<std::ops::Range<usize>> = buffer.folded_ranges().collect();
+    let visible: Option<Vec<usize>> = (!folded.is_empty()).then(|| {
+        (0..buffer.line_count())
+            .filter(|i| !folded.iter().any(|r| r.contains(i)))
+            .collect()
+    });
+    let visible_count = visible.as_ref().map_or(buffer.line_count(), Vec::len);
+    let line_index = |row: usize| visible.as_ref().map_or(row, |v| v[row]);
+
+    let mut line_rects: Vec<(usize, egui::Rect)> = Vec::new();
+    let mut block_badges: Vec<(usize, egui::Rect)> = Vec::new();
+    let frame = egui::Frame::none()
+        .fill(palette.background)
+        .inner_margin(egui::Margin::same(MARGIN))
+        .show(ui, |ui| {
+            ui.set_min_height(options.height);
+            ui.spacing_mut().item_spacing.y = 0.0;
+
+            let wrap_mode = if options.word_wrap {
+                egui::TextWrapMode::Wrap
+            } else {
+                egui::TextWrapMode::Extend
+            };
+
+            // 拖动用于选择文本，不用于滚动
+            let scroll = egui::ScrollArea::both()
+                .id_salt(id.with("scroll"))
+                .auto_shrink([false, false])
+                .max_height(options.height)
+                .drag_to_scroll(false)
+                .enable_scrolling(!options.mouse_reporting)
+                .stick_to_bottom(true);
+
+            let mut render_rows = |ui: &mut egui::Ui, rows: std::ops::Range<usize>| {
+                for index in rows.map(line_index) {
+                    let Some(line) = buffer.line(index) else { continue };
+                    let job = ui.fonts(|f| layout_line(line, palette, &font_id, f));
+                    let label = egui::Label::new(job)
+                        .wrap_mode(wrap_mode)
+                        .selectable(false)
+                        .sense(egui::Sense::hover());
+                    let rect = ui.add(label).rect;
+                    block_badges.extend(decorate(ui, index, line, rect));
+                    line_rects.push((index, rect));
+                }
+            };
+
+            if options.word_wrap {
+                // 换行后每行高度不同，无法只渲染可见行
+                scroll.show(ui, |ui| render_rows(ui, 0..visible_count))
+            } else {
+                scroll.show_rows(ui, row_height, visible_count, render_rows)
+            }
+        });
+
+    let response = ui.interact(frame.inner.inner_rect, id, egui::Sense::click_and_drag());
+    if response.contains_pointer() && ui.input(|i| i.pointer.any_pressed()) {
+        response.request_focus();
+    }
+    if response.has_focus() {
+        // 方向键、Tab 与 Esc 交给终端而不是用于切换焦点
+        ui.memory_mut(|m| {
+            m.set_focus_lock_filter(
+                id,
+                egui::EventFilter {
+                    tab: true,
+                    horizontal_arrows: true,
+                    vertical_arrows: true,
+                    escape: true,
+                },
+            )
+        });
+    }
+
+    let mut output = TerminalViewOutput {
+        response,
+        pointer_cell: None,
+        block_badges,
+        hovered_link: None,
+        line_rects,
+        glyph_width,
+    };
+    output.pointer_cell = ui.input(|i| i.pointer.interact_pos()).and_then(|pos| output.cell_at(pos));
+
+    // 按住 Ctrl（macOS 为 Cmd）悬停在链接上时显示下划线和手形指针
+    let link_hover = !options.mouse_reporting && output.response.hovered() && ui.input(|i| i.modifiers.command);
+    if let Some(cell) = output.pointer_cell.filter(|_| link_hover) {
+        let hovered = link_at(buffer, cell);
+        let rect = output.line_rects.iter().find(|(index, _)| *index == cell.line);
+        if let (Some(link), Some((_, rect))) = (&hovered, rect) {
+            let y = rect.top() + row_height - 1.0;
+            let x = |col: usize| rect.left() + col as f32 * glyph_width;
+            ui.painter().line_segment(
+                [egui::pos2(x(link.cols.start), y), egui::pos2(x(link.cols.end), y)],
+                egui::Stroke::new(1.0, palette.foreground),
+            );
+            ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+            // OSC 8 链接的文字可以任意指定，打开前显示实际目标
+            output.response = output.response.clone().on_hover_text_at_pointer(link.target.text());
+        }
+        output.hovered_link = hovered;
+    }
+    output
+}
+
+/// 本帧发往终端的键盘输入
+#[derive(Default)]
+pub struct TerminalInput {
+    /// 直接写入会话的字节
+    pub bytes: Vec<u8>,
+    /// 剪贴板粘贴的文本（需经过粘贴保护与括号粘贴编码）
+    pub paste: Option<String>,
+    /// 请求复制当前选区
+    pub copy: bool,
+}
+
+/// 收集本帧发往终端的键盘输入
+///
+/// Ctrl+C/X/V 由 egui 转换为复制/剪切/粘贴事件：不带 Shift 时按控制字符
+/// 发送给 shell，带 Shift（或 macOS 的 Cmd）时保留剪贴板语义。
+pub fn terminal_input(ui: &egui::Ui, app_cursor: bool) -> TerminalInput {
+    ui.input(|i| {
+        let clipboard_chord = i.modifiers.shift || i.modifiers.mac_cmd;
+        // Alt 组合键由 encode_key 以 ESC 前缀发送，忽略其文本事件（AltGr 除外）
+        let alt_chord = i.modifiers.alt && !i.modifiers.ctrl;
+
+        let mut input = TerminalInput::default();
+        for event in &i.events {
+            match event {
+                egui::Event::Text(text) if !alt_chord => input.bytes.extend_from_slice(text.as_bytes()),
+                egui::Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => {
+                    if let Some(encoded) = crate::terminal::encode_key(*key, *modifiers, app_cursor) {
+                        input.bytes.extend(encoded);
+                    }
+                }
+                egui::Event::Copy if !clipboard_chord => input.bytes.push(0x03),
+                egui::Event::Cut if !clipboard_chord => input.bytes.push(0x18),
+                egui::Event::Paste(_) if !clipboard_chord => input.bytes.push(0x16),
+                egui::Event::Copy | egui::Event::Cut => input.copy = true,
+                egui::Event::Paste(text) => input.paste.get_or_insert_with(String::new).push_str(text),
+                _ => {}
+            }
+        }
+        input
+    })
+}
@@ -0,0 +1,187 @@
+use crate::state::AppState;
+use crate::transfer::{TransferItem, TransferState};
+use crate::types::TransferDirection;
+use eframe::egui;
+
+// ============================================================================
+// 传输队列面板
+// ============================================================================
+
+enum ItemAction {
+    Pause,
+    Resume,
+    Cancel,
+    Retry,
+    Remove,
+}
+
+pub fn render_transfers_window(state: &mut AppState, ctx: &egui::Context) {
+    if !state.show_transfers {
+        return;
+    }
+
+    let mut open = true;
+    let mut actions: Vec<(u64, ItemAction)> = Vec::new();
+    egui::Window::new("📦 传输队列")
+        .open(&mut open)
+        .default_width(640.0)
+        .default_height(360.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("并发数:");
+                let settings = &mut state.config.settings;
+                let before = (settings.transfer_concurrency, settings.transfer_chunk_kb);
+                ui.add(egui::DragValue::new(&mut settings.transfer_concurrency).range(1..=8));
+                ui.label("块大小:");
+                let chunk_kb =
+                    crate::sftp::CHUNK_SIZE_RANGE.start() / 1024..=crate::sftp::CHUNK_SIZE_RANGE.end() / 1024;
+                ui.add(
+                    egui::DragValue::new(&mut settings.transfer_chunk_kb)
+                        .range(chunk_kb)
+                        .suffix(" KB"),
+                )
+                .on_hover_text("高延迟链路上调大可提高吞吐量（新开始的传输生效）");
+                ui.label(format!(
+                    "运行中 {} · 未完成 {}",
+                    state.transfers.running(),
+                    state.transfers.unfinished()
+                ));
+                if ui.button("🧹 清除已完成").clicked() {
+                    state.transfers.clear_finished();
+                }
+                let settings = &state.config.settings;
+                if before != (settings.transfer_concurrency, settings.transfer_chunk_kb) {
+                    crate::app::save_config(state);
+                }
+            });
+            ui.separator();
+
+            if state.transfers.items().is_empty() {
+                ui.label("队列为空。在文件浏览器中上传或下载的文件会显示在这里。");
+                return;
+            }
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for item in state.transfers.items() {
+                    render_item(ui, item, &mut actions);
+                    ui.separator();
+                }
+            });
+        });
+
+    for (id, action) in actions {
+        match action {
+            ItemAction::Pause => state.transfers.pause(id),
+            ItemAction::Resume => state.transfers.resume(id),
+            ItemAction::Cancel => state.transfers.cancel(id),
+            ItemAction::Retry => state.transfers.retry(id),
+            ItemAction::Remove => state.transfers.remove(id),
+        }
+    }
+    state.show_transfers = open;
+}
+
+fn render_item(ui: &mut egui::Ui, item: &TransferItem, actions: &mut Vec<(u64, ItemAction)>) {
+    let (arrow, source, target) = match item.direction {
+        TransferDirection::Upload => ("⬆", item.local_path.display().to_string(), item.remote_path.clone()),
+        TransferDirection::Download => ("⬇", item.remote_path.clone(), item.local_path.display().to_string()),
+    };
+    let icon = if item.is_dir { "📁" } else { "📄" };
+
+    ui.horizontal(|ui| {
+        ui.label(format!("{} {} {}", arrow, icon, item.name()))
+            .on_hover_text(format!("{}\n→ {}", source, target));
+        ui.weak(&item.connection);
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            let mut button = |ui: &mut egui::Ui, text: &str, hover: &str, action: ItemAction| {
+                if ui.small_button(text).on_hover_text(hover).clicked() {
+                    actions.push((item.id, action));
+                }
+            };
+            match &item.state {
+                TransferState::Queued | TransferState::Running => {
+                    button(ui, "✖", "取消", ItemAction::Cancel);
+                    button(ui, "⏸", "暂停", ItemAction::Pause);
+                }
+                TransferState::Verifying => button(ui, "✖", "取消", ItemAction::Cancel),
+                TransferState::Paused | TransferState::Interrupted => {
+                    button(ui, "✖", "取消", ItemAction::Cancel);
+                    button(ui, "▶", "继续", ItemAction::Resume);
+                }
+                TransferState::Failed { .. } | TransferState::Cancelled => {
+                    button(ui, "🗑", "移除", ItemAction::Remove);
+                    button(ui, "🔁", "重试", ItemAction::Retry);
+                }
+                TransferState::Completed => button(ui, "🗑", "移除", ItemAction::Remove),
+            }
+            let label = match &item.state {
+                TransferState::Failed { error } => {
+                    egui::RichText::new(format!("❌ {}", error)).color(egui::Color32::RED)
+                }
+                TransferState::Completed => match &item.verified {
+                    Some(algorithm) => {
+                        egui::RichText::new(format!("✅ 已校验 ({})", algorithm)).color(egui::Color32::GREEN)
+                    }
+                    None => egui::RichText::new("✅ 已完成").color(egui::Color32::GREEN),
+                },
+                TransferState::Queued | TransferState::Running if item.checksum_failures > 0 => egui::RichText::new(format!(
+                    "⚠ 校验和不一致，第 {}/{} 次重传",
+                    item.checksum_failures,
+                    crate::transfer::MAX_CHECKSUM_RETRIES
+                ))
+                .color(egui::Color32::YELLOW),
+                state => egui::RichText::new(state.label()),
+            };
+            ui.label(label);
+        });
+    });
+
+    let mut text = if item.bytes_total > 0 {
+        format!("{} / {}", format_bytes(item.bytes_done), format_bytes(item.bytes_total))
+    } else {
+        String::new()
+    };
+    if let Some(progress) = &item.progress {
+        text.push_str(&format!(
+            " · {}（平均 {}）· 剩余 {}",
+            format_speed(progress.speed),
+            format_speed(progress.average_speed),
+            format_eta(progress.eta)
+        ));
+    }
+    ui.add(egui::ProgressBar::new(item.fraction()).text(text));
+}
+
+/// 传输速度，如 `1.5 MB/s`
+pub fn format_speed(bytes_per_sec: f64) -> String {
+    format!("{}/s", format_bytes(bytes_per_sec.max(0.0) as u64))
+}
+
+/// 剩余时间，如 `01:05` 或 `1:02:03`，未知时为 `--:--`
+pub fn format_eta(eta: Option<std::time::Duration>) -> String {
+    let Some(eta) = eta else {
+        return "--:--".to_string();
+    };
+    let secs = eta.as_secs_f64().ceil() as u64;
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
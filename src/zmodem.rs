@@ -0,0 +1,1254 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+// ============================================================================
+// ZMODEM 文件传输 (rz / sz)
+// ============================================================================
+//
+// 在 shell 输出流中识别 ZMODEM 起始序列后，由 `ZmodemSession` 接管该
+// 标签的数据流：远端 `sz` 时接收文件，远端 `rz` 时发送文件。会话本身
+// 不做 I/O，调用方负责把收到的字节喂给它，并把它产生的字节写回会话。
+
+const ZPAD: u8 = b'*';
+const ZDLE: u8 = 0x18;
+const ZBIN: u8 = b'A';
+const ZHEX: u8 = b'B';
+const ZBIN32: u8 = b'C';
+
+/// 数据子包结束标记
+const ZCRCE: u8 = b'h';
+const ZCRCG: u8 = b'i';
+const ZCRCQ: u8 = b'j';
+const ZCRCW: u8 = b'k';
+const ZRUB0: u8 = b'l';
+const ZRUB1: u8 = b'm';
+
+/// ZRINIT 标志：全双工、可边收边写盘、支持 CRC-32
+const CANFDX: u8 = 0x01;
+const CANOVIO: u8 = 0x02;
+const CANFC32: u8 = 0x20;
+
+/// 发送时每个数据子包的大小
+const SUBPACKET_SIZE: usize = 1024;
+
+/// 中止传输：连续的 CAN 加退格（清除对端可能回显的字符）
+const ABORT_SEQUENCE: &[u8] = b"\x18\x18\x18\x18\x18\x18\x18\x18\x08\x08\x08\x08\x08\x08\x08\x08";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameType {
+    RqInit,
+    RInit,
+    SInit,
+    Ack,
+    File,
+    Skip,
+    Nak,
+    Abort,
+    Fin,
+    RPos,
+    Data,
+    Eof,
+    FErr,
+    Crc,
+    Challenge,
+    Compl,
+    Can,
+    FreeCnt,
+    Command,
+}
+
+impl FrameType {
+    const ALL: [FrameType; 19] = [
+        FrameType::RqInit,
+        FrameType::RInit,
+        FrameType::SInit,
+        FrameType::Ack,
+        FrameType::File,
+        FrameType::Skip,
+        FrameType::Nak,
+        FrameType::Abort,
+        FrameType::Fin,
+        FrameType::RPos,
+        FrameType::Data,
+        FrameType::Eof,
+        FrameType::FErr,
+        FrameType::Crc,
+        FrameType::Challenge,
+        FrameType::Compl,
+        FrameType::Can,
+        FrameType::FreeCnt,
+        FrameType::Command,
+    ];
+
+    fn code(self) -> u8 {
+        Self::ALL.iter().position(|f| *f == self).unwrap() as u8
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.get(code as usize).copied()
+    }
+}
+
+/// ZMODEM 帧头：类型加 4 字节参数（位置或标志）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub frame: FrameType,
+    pub data: [u8; 4],
+}
+
+impl Header {
+    pub fn new(frame: FrameType) -> Self {
+        Self {
+            frame,
+            data: [0; 4],
+        }
+    }
+
+    /// 携带文件位置的帧头（ZRPOS、ZDATA、ZEOF、ZACK）
+    pub fn with_pos(frame: FrameType, pos: u64) -> Self {
+        Self {
+            frame,
+            data: (pos as u32).to_le_bytes(),
+        }
+    }
+
+    pub fn pos(&self) -> u64 {
+        u32::from_le_bytes(self.data) as u64
+    }
+
+    /// 标志字节 ZF0（位于最后一个字节）
+    pub fn flags(&self) -> u8 {
+        self.data[3]
+    }
+
+    fn bytes(&self) -> [u8; 5] {
+        let [a, b, c, d] = self.data;
+        [self.frame.code(), a, b, c, d]
+    }
+
+    /// 十六进制帧头：`** ZDLE B` + 十六进制内容 + CRC-16
+    pub fn encode_hex(&self) -> Vec<u8> {
+        let bytes = self.bytes();
+        let crc = crc16(&bytes);
+        let mut out = vec![ZPAD, ZPAD, ZDLE, ZHEX];
+        for byte in bytes.iter().chain(&crc.to_be_bytes()) {
+            out.extend_from_slice(format!("{:02x}", byte).as_bytes());
+        }
+        out.extend_from_slice(b"\r\x8a");
+        // ZFIN 与 ZACK 之后不发送 XON
+        if !matches!(self.frame, FrameType::Fin | FrameType::Ack) {
+            out.push(0x11);
+        }
+        out
+    }
+
+    /// 二进制帧头（CRC-16 或 CRC-32）
+    pub fn encode_bin(&self, crc32: bool) -> Vec<u8> {
+        let bytes = self.bytes();
+        let mut out = vec![ZPAD, ZDLE, if crc32 { ZBIN32 } else { ZBIN }];
+        escape_into(&bytes, &mut out);
+        if crc32 {
+            escape_into(&crc32_ieee(&bytes).to_le_bytes(), &mut out);
+        } else {
+            escape_into(&crc16(&bytes).to_be_bytes(), &mut out);
+        }
+        out
+    }
+}
+
+/// 编码数据子包：ZDLE 转义的数据 + 结束标记 + CRC
+pub fn encode_subpacket(data: &[u8], end: u8, crc32: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 8 + 8);
+    escape_into(data, &mut out);
+    out.push(ZDLE);
+    out.push(end);
+    if crc32 {
+        let mut crc = Crc32::new();
+        crc.update(data);
+        crc.update(&[end]);
+        escape_into(&crc.finish().to_le_bytes(), &mut out);
+    } else {
+        let mut covered = data.to_vec();
+        covered.push(end);
+        escape_into(&crc16(&covered).to_be_bytes(), &mut out);
+    }
+    out
+}
+
+/// 需要 ZDLE 转义的字节（ZDLE 本身、XON/XOFF、DLE 与回车）
+fn needs_escape(byte: u8) -> bool {
+    matches!(byte & 0x7f, 0x18 | 0x10 | 0x11 | 0x13 | 0x0d)
+}
+
+fn escape_into(data: &[u8], out: &mut Vec<u8>) {
+    for &byte in data {
+        match byte {
+            0x7f => out.extend_from_slice(&[ZDLE, ZRUB0]),
+            0xff => out.extend_from_slice(&[ZDLE, ZRUB1]),
+            b if needs_escape(b) => out.extend_from_slice(&[ZDLE, b ^ 0x40]),
+            b => out.push(b),
+        }
+    }
+}
+
+/// CRC-16/XMODEM
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// CRC-32/IEEE（增量计算）
+struct Crc32(u32);
+
+impl Crc32 {
+    fn new() -> Self {
+        Self(0xffff_ffff)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.0 ^= byte as u32;
+            for _ in 0..8 {
+                self.0 = if self.0 & 1 != 0 {
+                    (self.0 >> 1) ^ 0xedb8_8320
+                } else {
+                    self.0 >> 1
+                };
+            }
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+pub fn crc32_ieee(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finish()
+}
+
+// ----------------------------------------------------------------------------
+// 解码
+// ----------------------------------------------------------------------------
+
+/// 解码出的数据单元
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Packet {
+    /// 帧头及其格式（数据子包的 CRC 类型跟随帧头）
+    Header(Header, bool),
+    Data {
+        data: Vec<u8>,
+        end: u8,
+    },
+    /// 校验失败
+    BadCrc,
+    /// 对方发送了连续的 CAN
+    Cancelled,
+}
+
+/// 从缓冲区中读取 ZDLE 转义的字节
+struct Unescaper<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+/// 转义解码的结果
+enum Escaped {
+    Byte(u8),
+    /// 数据子包结束标记
+    End(u8),
+    Cancelled,
+}
+
+impl Unescaper<'_> {
+    /// 下一个字节，数据不完整时返回 None
+    fn next(&mut self) -> Option<Escaped> {
+        loop {
+            let byte = *self.buf.get(self.pos)?;
+            self.pos += 1;
+            match byte {
+                // 流控字符不属于数据
+                0x11 | 0x13 | 0x91 | 0x93 => continue,
+                ZDLE => {}
+                b => return Some(Escaped::Byte(b)),
+            }
+
+            let mut cans = 1;
+            loop {
+                let next = *self.buf.get(self.pos)?;
+                self.pos += 1;
+                return Some(match next {
+                    ZDLE => {
+                        cans += 1;
+                        if cans >= 5 {
+                            Escaped::Cancelled
+                        } else {
+                            continue;
+                        }
+                    }
+                    ZCRCE | ZCRCG | ZCRCQ | ZCRCW => Escaped::End(next),
+                    ZRUB0 => Escaped::Byte(0x7f),
+                    ZRUB1 => Escaped::Byte(0xff),
+                    0x11 | 0x13 | 0x91 | 0x93 => continue,
+                    b => Escaped::Byte(b ^ 0x40),
+                });
+            }
+        }
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Option<Result<[u8; N], Escaped>> {
+        let mut out = [0u8; N];
+        for slot in &mut out {
+            match self.next()? {
+                Escaped::Byte(b) => *slot = b,
+                other => return Some(Err(other)),
+            }
+        }
+        Some(Ok(out))
+    }
+}
+
+/// 增量解码器：缓存收到的字节，按当前期望解出帧头或数据子包
+struct Decoder {
+    buf: Vec<u8>,
+    /// 正在接收数据子包（值为是否使用 CRC-32）
+    data_crc32: Option<bool>,
+}
+
+impl Decoder {
+    fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            data_crc32: None,
+        }
+    }
+
+    fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    fn next_packet(&mut self) -> Option<Packet> {
+        match self.data_crc32 {
+            Some(crc32) => self.next_subpacket(crc32),
+            None => self.next_header(),
+        }
+    }
+
+    fn next_header(&mut self) -> Option<Packet> {
+        loop {
+            // 丢弃帧头之前的无关字节
+            let start = self.buf.iter().position(|&b| b == ZPAD || b == ZDLE)?;
+            self.buf.drain(..start);
+            if self.buf.starts_with(&[ZDLE; 5]) {
+                self.buf.drain(..5);
+                return Some(Packet::Cancelled);
+            }
+
+            let pads = self.buf.iter().take_while(|&&b| b == ZPAD).count();
+            let format = self.buf.get(pads + 1).copied();
+            match (self.buf.get(pads).copied(), format) {
+                (None, _) | (Some(ZDLE), None) => return None,
+                (Some(ZDLE), Some(ZHEX)) if pads > 0 => return self.hex_header(pads + 2),
+                (Some(ZDLE), Some(f @ (ZBIN | ZBIN32))) if pads > 0 => {
+                    return self.bin_header(pads + 2, f == ZBIN32)
+                }
+                _ => {
+                    self.buf.drain(..pads.max(1));
+                }
+            }
+        }
+    }
+
+    fn hex_header(&mut self, start: usize) -> Option<Packet> {
+        let digits = self.buf.get(start..start + 14)?;
+        let parsed: Option<Vec<u8>> = digits
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+            .collect();
+        let mut end = start + 14;
+        // 帧头后的 CR LF 与 XON
+        while end < self.buf.len()
+            && end < start + 17
+            && matches!(self.buf[end], b'\r' | b'\n' | 0x8a | 0x11)
+        {
+            end += 1;
+        }
+        self.buf.drain(..end);
+
+        let Some(bytes) = parsed else {
+            return Some(Packet::BadCrc);
+        };
+        if crc16(&bytes[..5]) != u16::from_be_bytes([bytes[5], bytes[6]]) {
+            return Some(Packet::BadCrc);
+        }
+        self.header_packet(&bytes[..5], false)
+    }
+
+    fn bin_header(&mut self, start: usize, crc32: bool) -> Option<Packet> {
+        let mut reader = Unescaper {
+            buf: &self.buf[start..],
+            pos: 0,
+        };
+        let bytes = match reader.bytes::<5>()? {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                let consumed = start + reader.pos;
+                self.buf.drain(..consumed);
+                return Some(Self::escape_error(e));
+            }
+        };
+        let valid = if crc32 {
+            match reader.bytes::<4>()? {
+                Ok(crc) => crc32_ieee(&bytes) == u32::from_le_bytes(crc),
+                Err(_) => false,
+            }
+        } else {
+            match reader.bytes::<2>()? {
+                Ok(crc) => crc16(&bytes) == u16::from_be_bytes(crc),
+                Err(_) => false,
+            }
+        };
+        let consumed = start + reader.pos;
+        self.buf.drain(..consumed);
+        if !valid {
+            return Some(Packet::BadCrc);
+        }
+        self.header_packet(&bytes, crc32)
+    }
+
+    fn header_packet(&mut self, bytes: &[u8], crc32: bool) -> Option<Packet> {
+        let Some(frame) = FrameType::from_code(bytes[0]) else {
+            return Some(Packet::BadCrc);
+        };
+        let header = Header {
+            frame,
+            data: [bytes[1], bytes[2], bytes[3], bytes[4]],
+        };
+        // ZFILE、ZDATA、ZSINIT、ZCOMMAND 之后紧跟数据子包
+        if matches!(
+            frame,
+            FrameType::File | FrameType::Data | FrameType::SInit | FrameType::Command
+        ) {
+            self.data_crc32 = Some(crc32);
+        }
+        Some(Packet::Header(header, crc32))
+    }
+
+    fn next_subpacket(&mut self, crc32: bool) -> Option<Packet> {
+        let mut reader = Unescaper {
+            buf: &self.buf,
+            pos: 0,
+        };
+        let mut data = Vec::new();
+        let end = loop {
+            match reader.next()? {
+                Escaped::Byte(b) => data.push(b),
+                Escaped::End(end) => break end,
+                Escaped::Cancelled => {
+                    let consumed = reader.pos;
+                    self.buf.drain(..consumed);
+                    self.data_crc32 = None;
+                    return Some(Packet::Cancelled);
+                }
+            }
+        };
+        let valid = if crc32 {
+            match reader.bytes::<4>()? {
+                Ok(crc) => {
+                    let mut expected = Crc32::new();
+                    expected.update(&data);
+                    expected.update(&[end]);
+                    expected.finish() == u32::from_le_bytes(crc)
+                }
+                Err(_) => false,
+            }
+        } else {
+            match reader.bytes::<2>()? {
+                Ok(crc) => {
+                    let mut covered = data.clone();
+                    covered.push(end);
+                    crc16(&covered) == u16::from_be_bytes(crc)
+                }
+                Err(_) => false,
+            }
+        };
+        let consumed = reader.pos;
+        self.buf.drain(..consumed);
+
+        if !valid {
+            self.data_crc32 = None;
+            return Some(Packet::BadCrc);
+        }
+        // ZCRCE 与 ZCRCW 结束当前帧，之后等待新的帧头
+        if matches!(end, ZCRCE | ZCRCW) {
+            self.data_crc32 = None;
+        }
+        Some(Packet::Data { data, end })
+    }
+
+    fn escape_error(e: Escaped) -> Packet {
+        match e {
+            Escaped::Cancelled => Packet::Cancelled,
+            _ => Packet::BadCrc,
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// 会话
+// ----------------------------------------------------------------------------
+
+/// 传输方向（从本地角度）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// 远端执行 `sz`，本地接收
+    Download,
+    /// 远端执行 `rz`，本地发送
+    Upload,
+}
+
+/// ZMODEM 起始序列（`**` ZDLE `B0` 加上 ZRQINIT/ZRINIT 的类型位）
+const DETECT_START: &[u8] = b"**\x18B0";
+
+/// [`detect`] 识别的序列长度；分块读取时需保留上一块末尾的 `DETECT_LEN - 1` 字节
+pub const DETECT_LEN: usize = DETECT_START.len() + 1;
+
+/// 在 shell 输出中查找 ZMODEM 起始序列，返回其偏移与方向
+pub fn detect(data: &[u8]) -> Option<(usize, Direction)> {
+    data.windows(DETECT_LEN)
+        .position(|w| w.starts_with(DETECT_START) && matches!(w[DETECT_START.len()], b'0' | b'1'))
+        .map(|offset| {
+            let direction = if data[offset + DETECT_START.len()] == b'0' {
+                Direction::Download
+            } else {
+                Direction::Upload
+            };
+            (offset, direction)
+        })
+}
+
+/// 传输过程中的事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ZmodemEvent {
+    FileStarted {
+        name: String,
+        size: Option<u64>,
+    },
+    Progress {
+        name: String,
+        transferred: u64,
+        size: Option<u64>,
+    },
+    FileCompleted {
+        name: String,
+        path: PathBuf,
+    },
+    FileSkipped {
+        name: String,
+    },
+    Finished,
+    Error(String),
+}
+
+struct IncomingFile {
+    name: String,
+    path: PathBuf,
+    size: Option<u64>,
+    file: File,
+    received: u64,
+}
+
+struct OutgoingFile {
+    name: String,
+    size: u64,
+    file: File,
+    sent: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// 接收端：等待 ZFILE 或 ZFIN
+    WaitFile,
+    /// 接收端：等待 ZFILE 后的文件信息子包
+    FileInfo,
+    /// 接收端：等待 ZSINIT / ZCOMMAND 的子包
+    Control(FrameType),
+    /// 接收端：等待 ZDATA
+    WaitData,
+    /// 接收端：正在接收数据子包
+    Receiving,
+    /// 接收端：已回复 ZFIN，等待对方的 "OO"
+    Closing,
+    /// 发送端：等待 ZRINIT
+    WaitReady,
+    /// 发送端：已发送 ZFILE，等待 ZRPOS
+    WaitPos,
+    /// 发送端：正在发送数据
+    Sending,
+    /// 发送端：已发送 ZEOF，等待 ZRINIT
+    WaitEofAck,
+    /// 发送端：已发送 ZFIN，等待对方的 ZFIN
+    WaitFin,
+    Done,
+}
+
+/// 一次 ZMODEM 传输（接收一批文件或发送一批文件）
+pub struct ZmodemSession {
+    direction: Direction,
+    state: State,
+    decoder: Decoder,
+    output: Vec<u8>,
+    events: Vec<ZmodemEvent>,
+    /// 传输结束后剩余的字节（属于终端输出）
+    leftover: Vec<u8>,
+    /// 接收：保存目录与当前文件
+    dir: PathBuf,
+    incoming: Option<IncomingFile>,
+    /// 发送：待发送文件、当前文件与是否使用 CRC-32
+    queue: VecDeque<PathBuf>,
+    outgoing: Option<OutgoingFile>,
+    crc32: bool,
+    /// 发送：远端已启动 `rz`，等待用户选择文件
+    awaiting_files: bool,
+    /// 等待选择文件期间已收到 ZRINIT
+    peer_ready: bool,
+}
+
+impl ZmodemSession {
+    fn new(direction: Direction, state: State) -> Self {
+        Self {
+            direction,
+            state,
+            decoder: Decoder::new(),
+            output: Vec::new(),
+            events: Vec::new(),
+            leftover: Vec::new(),
+            dir: PathBuf::new(),
+            incoming: None,
+            queue: VecDeque::new(),
+            outgoing: None,
+            crc32: false,
+            awaiting_files: false,
+            peer_ready: false,
+        }
+    }
+
+    /// 接收远端 `sz` 发送的文件，保存到 `dir`
+    pub fn receive(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            ..Self::new(Direction::Download, State::WaitFile)
+        }
+    }
+
+    /// 向远端 `rz` 发送文件
+    pub fn send(files: Vec<PathBuf>) -> Self {
+        Self {
+            queue: files.into(),
+            ..Self::new(Direction::Upload, State::WaitReady)
+        }
+    }
+
+    /// 远端已执行 `rz` 但尚未选择文件，调用 [`Self::provide_files`] 后开始发送
+    pub fn awaiting_files() -> Self {
+        Self {
+            awaiting_files: true,
+            ..Self::send(Vec::new())
+        }
+    }
+
+    pub fn needs_files(&self) -> bool {
+        self.awaiting_files && self.state != State::Done
+    }
+
+    /// 提供要发送的文件（为空时结束传输）
+    pub fn provide_files(&mut self, files: Vec<PathBuf>) {
+        if !self.needs_files() {
+            return;
+        }
+        self.awaiting_files = false;
+        self.queue = files.into();
+        if self.peer_ready {
+            self.send_next_file();
+        }
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state == State::Done
+    }
+
+    /// 取出需要写回会话的字节
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+
+    pub fn take_events(&mut self) -> Vec<ZmodemEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// 传输结束后应交还给终端显示的字节
+    pub fn take_leftover(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.leftover)
+    }
+
+    /// 用户取消传输
+    pub fn cancel(&mut self) {
+        if self.state != State::Done {
+            self.output.extend_from_slice(ABORT_SEQUENCE);
+            self.fail("传输已取消".to_string());
+        }
+    }
+
+    /// 处理从会话收到的字节
+    pub fn feed(&mut self, data: &[u8]) {
+        if self.state == State::Done {
+            self.leftover.extend_from_slice(data);
+            return;
+        }
+        self.decoder.push(data);
+
+        if self.state == State::Closing {
+            self.finish_closing();
+            return;
+        }
+        while self.state != State::Done && self.state != State::Closing {
+            let Some(packet) = self.decoder.next_packet() else {
+                break;
+            };
+            self.handle(packet);
+        }
+        if self.state == State::Closing {
+            self.finish_closing();
+        }
+    }
+
+    /// 发送端：生成最多 `budget` 字节的文件数据（分帧发送，避免阻塞界面）
+    pub fn poll(&mut self, budget: usize) {
+        if self.state != State::Sending {
+            return;
+        }
+        let Some(file) = self.outgoing.as_mut() else {
+            return;
+        };
+
+        let mut produced = 0;
+        let mut chunk = vec![0u8; SUBPACKET_SIZE];
+        while produced < budget {
+            let n = match file.file.read(&mut chunk) {
+                Ok(n) => n,
+                Err(e) => {
+                    let message = format!("读取 {} 失败: {}", file.name, e);
+                    self.output.extend_from_slice(ABORT_SEQUENCE);
+                    return self.fail(message);
+                }
+            };
+            file.sent += n as u64;
+            let at_end = n == 0 || file.sent >= file.size;
+            let end = if at_end { ZCRCE } else { ZCRCG };
+            let packet = encode_subpacket(&chunk[..n], end, self.crc32);
+            produced += packet.len();
+            self.output.extend_from_slice(&packet);
+
+            if at_end {
+                self.output
+                    .extend_from_slice(&Header::with_pos(FrameType::Eof, file.sent).encode_hex());
+                self.state = State::WaitEofAck;
+                break;
+            }
+        }
+        self.events.push(ZmodemEvent::Progress {
+            name: file.name.clone(),
+            transferred: file.sent,
+            size: Some(file.size),
+        });
+    }
+
+    fn handle(&mut self, packet: Packet) {
+        let header = match packet {
+            Packet::Cancelled => return self.fail("对方取消了传输".to_string()),
+            Packet::BadCrc => return self.on_bad_crc(),
+            Packet::Data { data, end } => return self.on_data(data, end),
+            Packet::Header(header, _) => header,
+        };
+
+        match header.frame {
+            FrameType::Abort | FrameType::FErr | FrameType::Can => {
+                self.fail("对方中止了传输".to_string())
+            }
+            _ if self.direction == Direction::Download => self.receiver_header(header),
+            _ => self.sender_header(header),
+        }
+    }
+
+    fn fail(&mut self, message: String) {
+        if let Some(incoming) = self.incoming.take() {
+            let _ = incoming.file.sync_all();
+        }
+        self.outgoing = None;
+        self.state = State::Done;
+        self.events.push(ZmodemEvent::Error(message));
+    }
+
+    fn send_hex(&mut self, header: Header) {
+        self.output.extend_from_slice(&header.encode_hex());
+    }
+
+    /// 接收端的 ZRINIT
+    fn send_rinit(&mut self) {
+        let mut header = Header::new(FrameType::RInit);
+        header.data[3] = CANFDX | CANOVIO | CANFC32;
+        self.send_hex(header);
+    }
+
+    fn received(&self) -> u64 {
+        self.incoming.as_ref().map_or(0, |f| f.received)
+    }
+
+    // ------------------------------------------------------------------------
+    // 接收端
+    // ------------------------------------------------------------------------
+
+    fn receiver_header(&mut self, header: Header) {
+        match header.frame {
+            FrameType::RqInit => self.send_rinit(),
+            FrameType::SInit | FrameType::Command => self.state = State::Control(header.frame),
+            FrameType::File => self.state = State::FileInfo,
+            FrameType::Data if self.incoming.is_some() => {
+                if header.pos() == self.received() {
+                    self.state = State::Receiving;
+                } else {
+                    // 位置不一致：丢弃本帧数据，请求从已收到的位置重传
+                    self.decoder.data_crc32 = None;
+                    self.send_hex(Header::with_pos(FrameType::RPos, self.received()));
+                    self.state = State::WaitData;
+                }
+            }
+            FrameType::Eof if self.incoming.is_some() => {
+                if header.pos() != self.received() {
+                    return;
+                }
+                let incoming = self.incoming.take().unwrap();
+                if let Err(e) = incoming.file.sync_all() {
+                    return self.fail(format!("写入 {} 失败: {}", incoming.name, e));
+                }
+                self.events.push(ZmodemEvent::FileCompleted {
+                    name: incoming.name,
+                    path: incoming.path,
+                });
+                self.state = State::WaitFile;
+                self.send_rinit();
+            }
+            FrameType::Fin => {
+                self.send_hex(Header::new(FrameType::Fin));
+                self.events.push(ZmodemEvent::Finished);
+                self.state = State::Closing;
+            }
+            _ => {}
+        }
+    }
+
+    fn on_data(&mut self, data: Vec<u8>, end: u8) {
+        match self.state {
+            State::FileInfo => self.start_incoming(&data),
+            // 只确认 ZSINIT，不执行远端发来的命令
+            State::Control(frame) => {
+                let reply = if frame == FrameType::SInit {
+                    FrameType::Ack
+                } else {
+                    FrameType::Compl
+                };
+                self.send_hex(Header::new(reply));
+                self.state = State::WaitFile;
+            }
+            State::Receiving => {
+                let Some(incoming) = self.incoming.as_mut() else {
+                    return;
+                };
+                if let Err(e) = incoming.file.write_all(&data) {
+                    let message = format!("写入 {} 失败: {}", incoming.name, e);
+                    self.output.extend_from_slice(ABORT_SEQUENCE);
+                    return self.fail(message);
+                }
+                incoming.received += data.len() as u64;
+                self.events.push(ZmodemEvent::Progress {
+                    name: incoming.name.clone(),
+                    transferred: incoming.received,
+                    size: incoming.size,
+                });
+                if matches!(end, ZCRCW | ZCRCQ) {
+                    self.send_hex(Header::with_pos(FrameType::Ack, self.received()));
+                }
+                if matches!(end, ZCRCE | ZCRCW) {
+                    self.state = State::WaitData;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 解析 ZFILE 的文件信息并创建本地文件，成功时请求从头发送
+    fn start_incoming(&mut self, info: &[u8]) {
+        let mut fields = info.split(|&b| b == 0);
+        let raw_name = String::from_utf8_lossy(fields.next().unwrap_or_default()).into_owned();
+        let size = fields
+            .next()
+            .and_then(|meta| std::str::from_utf8(meta).ok())
+            .and_then(|meta| meta.split_whitespace().next())
+            .and_then(|size| size.parse().ok());
+
+        // 只使用文件名部分，防止写到保存目录之外
+        let name = Path::new(&raw_name.replace('\\', "/"))
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| "zmodem.bin".to_string());
+        let path = unique_path(&self.dir, &name);
+        let file = match File::create(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                // 无法保存时跳过该文件
+                self.events.push(ZmodemEvent::Error(format!(
+                    "无法创建 {}: {}",
+                    path.display(),
+                    e
+                )));
+                self.events.push(ZmodemEvent::FileSkipped { name });
+                self.send_hex(Header::new(FrameType::Skip));
+                self.state = State::WaitFile;
+                return;
+            }
+        };
+
+        self.events.push(ZmodemEvent::FileStarted {
+            name: name.clone(),
+            size,
+        });
+        self.incoming = Some(IncomingFile {
+            name,
+            path,
+            size,
+            file,
+            received: 0,
+        });
+        self.send_hex(Header::with_pos(FrameType::RPos, 0));
+        self.state = State::WaitData;
+    }
+
+    fn on_bad_crc(&mut self) {
+        match self.direction {
+            Direction::Download if self.incoming.is_some() => {
+                self.send_hex(Header::with_pos(FrameType::RPos, self.received()));
+                self.state = State::WaitData;
+            }
+            Direction::Download => self.send_hex(Header::new(FrameType::Nak)),
+            Direction::Upload => {}
+        }
+    }
+
+    /// 对方回复 ZFIN 后会发送 "OO"，吞掉它们后把剩余字节交还给终端
+    fn finish_closing(&mut self) {
+        let buf = std::mem::take(&mut self.decoder.buf);
+        let rest = buf.strip_prefix(b"OO").or_else(|| buf.strip_prefix(b"O"));
+        match rest {
+            Some(rest) => self.leftover.extend_from_slice(rest),
+            // 还没有收到任何字节
+            None if buf.is_empty() => return,
+            None => self.leftover.extend_from_slice(&buf),
+        }
+        self.state = State::Done;
+    }
+
+    // ------------------------------------------------------------------------
+    // 发送端
+    // ------------------------------------------------------------------------
+
+    fn sender_header(&mut self, header: Header) {
+        match (header.frame, self.state) {
+            (FrameType::RInit, State::WaitReady | State::WaitEofAck | State::WaitPos) => {
+                if self.state == State::WaitEofAck {
+                    if let Some(file) = self.outgoing.take() {
+                        self.events.push(ZmodemEvent::FileCompleted {
+                            name: file.name,
+                            path: PathBuf::new(),
+                        });
+                    }
+                }
+                if self.state != State::WaitPos {
+                    self.crc32 = header.flags() & CANFC32 != 0;
+                    if self.awaiting_files {
+                        self.peer_ready = true;
+                    } else {
+                        self.send_next_file();
+                    }
+                }
+            }
+            (FrameType::RPos, State::WaitPos | State::Sending | State::WaitEofAck) => {
+                let Some(file) = self.outgoing.as_mut() else {
+                    return;
+                };
+                let pos = header.pos();
+                if let Err(e) = file.file.seek(SeekFrom::Start(pos)) {
+                    let message = format!("读取 {} 失败: {}", file.name, e);
+                    return self.fail(message);
+                }
+                file.sent = pos;
+                self.output.extend_from_slice(
+                    &Header::with_pos(FrameType::Data, pos).encode_bin(self.crc32),
+                );
+                self.state = State::Sending;
+            }
+            (FrameType::Skip, _) => {
+                if let Some(file) = self.outgoing.take() {
+                    self.events
+                        .push(ZmodemEvent::FileSkipped { name: file.name });
+                }
+                self.state = State::WaitReady;
+            }
+            (FrameType::Fin, State::WaitFin) => {
+                self.output.extend_from_slice(b"OO");
+                self.events.push(ZmodemEvent::Finished);
+                self.state = State::Done;
+                let rest = std::mem::take(&mut self.decoder.buf);
+                self.leftover.extend_from_slice(&rest);
+            }
+            _ => {}
+        }
+    }
+
+    /// 发送下一个文件的 ZFILE，没有文件时发送 ZFIN
+    fn send_next_file(&mut self) {
+        while let Some(path) = self.queue.pop_front() {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let opened = File::open(&path).and_then(|f| Ok((f.metadata()?.len(), f)));
+            let (size, file) = match opened {
+                Ok(opened) => opened,
+                Err(e) => {
+                    self.events.push(ZmodemEvent::Error(format!(
+                        "无法打开 {}: {}",
+                        path.display(),
+                        e
+                    )));
+                    continue;
+                }
+            };
+
+            self.events.push(ZmodemEvent::FileStarted {
+                name: name.clone(),
+                size: Some(size),
+            });
+            let info = format!("{}\0{} 0 0 0\0", name, size);
+            self.output
+                .extend_from_slice(&Header::new(FrameType::File).encode_bin(self.crc32));
+            self.output
+                .extend_from_slice(&encode_subpacket(info.as_bytes(), ZCRCW, self.crc32));
+            self.outgoing = Some(OutgoingFile {
+                name,
+                size,
+                file,
+                sent: 0,
+            });
+            self.state = State::WaitPos;
+            return;
+        }
+
+        self.send_hex(Header::new(FrameType::Fin));
+        self.state = State::WaitFin;
+    }
+}
+
+/// 目录中不与现有文件重名的路径（`name`、`name.1`、`name.2` …）
+fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+    (1..)
+        .map(|i| dir.join(format!("{}.{}", name, i)))
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_header_matches_lrzsz() {
+        // lrzsz 的 rz 启动时发送的 ZRINIT
+        let mut header = Header::new(FrameType::RInit);
+        header.data[3] = 0x23;
+        let encoded = header.encode_hex();
+        assert!(encoded.starts_with(b"**\x18B0100000023be50"));
+        assert_eq!(detect(&encoded), Some((0, Direction::Upload)));
+
+        let mut decoder = Decoder::new();
+        decoder.push(b"rz\r");
+        decoder.push(&encoded);
+        assert_eq!(decoder.next_packet(), Some(Packet::Header(header, false)));
+        assert_eq!(decoder.next_packet(), None);
+    }
+
+    #[test]
+    fn test_escaping_round_trip() {
+        let data: Vec<u8> = (0..=255).collect();
+        for crc32 in [false, true] {
+            let mut decoder = Decoder::new();
+            decoder.push(&Header::with_pos(FrameType::Data, 1234).encode_bin(crc32));
+            decoder.push(&encode_subpacket(&data, ZCRCE, crc32));
+            assert_eq!(
+                decoder.next_packet(),
+                Some(Packet::Header(
+                    Header::with_pos(FrameType::Data, 1234),
+                    crc32
+                ))
+            );
+            assert_eq!(
+                decoder.next_packet(),
+                Some(Packet::Data {
+                    data: data.clone(),
+                    end: ZCRCE
+                })
+            );
+        }
+
+        // 损坏的数据被识别
+        let mut decoder = Decoder::new();
+        decoder.push(&Header::with_pos(FrameType::Data, 0).encode_bin(false));
+        let mut packet = encode_subpacket(b"hello", ZCRCE, false);
+        packet[0] = b'j';
+        decoder.push(&packet);
+        decoder.next_packet();
+        assert_eq!(decoder.next_packet(), Some(Packet::BadCrc));
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let rand_val: u32 = rand::Rng::gen(&mut rand::thread_rng());
+        let dir = std::env::temp_dir()
+            .join("ishell_zmodem_test")
+            .join(format!("{}_{}", name, rand_val));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 在发送端与接收端之间来回传递数据，直到两端都结束
+    fn run_transfer(sender: &mut ZmodemSession, receiver: &mut ZmodemSession, chunk: usize) {
+        // 接收端（远端 rz）先发送 ZRINIT
+        receiver.send_rinit();
+        for _ in 0..10_000 {
+            let to_sender = receiver.take_output();
+            for piece in to_sender.chunks(chunk) {
+                sender.feed(piece);
+            }
+            sender.poll(4096);
+            let to_receiver = sender.take_output();
+            for piece in to_receiver.chunks(chunk) {
+                receiver.feed(piece);
+            }
+            if sender.is_finished() && receiver.is_finished() {
+                return;
+            }
+        }
+        panic!("transfer did not finish");
+    }
+
+    #[test]
+    fn test_transfer_between_sender_and_receiver() {
+        let src = test_dir("src");
+        let dst = test_dir("dst");
+        let content: Vec<u8> = (0..50_000u32).map(|i| (i * 7 % 256) as u8).collect();
+        std::fs::write(src.join("data.bin"), &content).unwrap();
+        std::fs::write(src.join("empty.txt"), b"").unwrap();
+        // 同名文件不被覆盖
+        std::fs::write(dst.join("data.bin"), b"old").unwrap();
+
+        let mut sender = ZmodemSession::send(vec![
+            src.join("data.bin"),
+            src.join("missing.txt"),
+            src.join("empty.txt"),
+        ]);
+        let mut receiver = ZmodemSession::receive(&dst);
+        run_transfer(&mut sender, &mut receiver, 333);
+
+        assert_eq!(std::fs::read(dst.join("data.bin.1")).unwrap(), content);
+        assert_eq!(std::fs::read(dst.join("data.bin")).unwrap(), b"old");
+        assert!(dst.join("empty.txt").exists());
+
+        let events = receiver.take_events();
+        let completed: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                ZmodemEvent::FileCompleted { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(completed, vec!["data.bin", "empty.txt"]);
+        assert!(events.contains(&ZmodemEvent::Finished));
+        assert!(sender
+            .take_events()
+            .iter()
+            .any(|e| matches!(e, ZmodemEvent::Error(msg) if msg.contains("missing.txt"))));
+    }
+
+    #[test]
+    fn test_receiver_leftover_and_cancel() {
+        let dst = test_dir("dst");
+        let mut receiver = ZmodemSession::receive(&dst);
+        receiver.feed(&Header::new(FrameType::RqInit).encode_hex());
+        assert!(receiver.take_output().starts_with(b"**\x18B01"));
+
+        // ZFIN 之后的 "OO" 被吞掉，其余字节交还给终端
+        receiver.feed(&Header::new(FrameType::Fin).encode_hex());
+        assert!(!receiver.is_finished());
+        receiver.feed(b"OO$ ");
+        assert!(receiver.is_finished());
+        assert_eq!(receiver.take_leftover(), b"$ ");
+
+        let mut receiver = ZmodemSession::receive(&dst);
+        receiver.feed(&[ZDLE; 5]);
+        assert!(receiver.is_finished());
+        assert!(matches!(
+            receiver.take_events().last(),
+            Some(ZmodemEvent::Error(_))
+        ));
+
+        // 远端先启动 rz，选择文件后才开始发送
+        let src = test_dir("pending");
+        std::fs::write(src.join("a.txt"), b"hello").unwrap();
+        let mut sender = ZmodemSession::awaiting_files();
+        let mut remote = ZmodemSession::receive(&dst);
+        remote.send_rinit();
+        sender.feed(&remote.take_output());
+        assert!(sender.needs_files());
+        assert!(sender.take_output().is_empty());
+        sender.provide_files(vec![src.join("a.txt")]);
+        assert!(!sender.needs_files());
+        run_transfer(&mut sender, &mut remote, 64);
+        assert_eq!(std::fs::read(dst.join("a.txt")).unwrap(), b"hello");
+
+        let mut sender = ZmodemSession::send(Vec::new());
+        sender.cancel();
+        assert!(sender.is_finished());
+        assert!(sender.take_output().starts_with(&[0x18; 8]));
+    }
+}
@@ -239,8 +239,8 @@ mod config_tests {
 
     #[test]
     fn test_config_manager_creation() {
-        let manager = get_test_manager();
-        assert!(true);
+        let _manager = get_test_manager();
+        // Implicitly checked by creation
     }
 
     #[test]
@@ -316,6 +316,31 @@ mod config_tests {
         }
     }
 
+    #[test]
+    fn test_telnet_connection_persistence() {
+        let manager = get_test_manager();
+
+        let mut config = AppConfig::default();
+        let mut switch = SshConfig::new(
+            "Lab Switch".to_string(),
+            "10.0.0.2".to_string(),
+            23,
+            String::new(),
+        );
+        switch.protocol = Protocol::Telnet;
+        config.connections.push(switch);
+
+        manager.save_config(&mut config).unwrap();
+        let loaded = manager.load_config().unwrap();
+        assert_eq!(loaded.connections[0].protocol, Protocol::Telnet);
+        assert!(loaded.connections[0].auth.is_none());
+
+        // 旧配置没有 protocol 字段时默认为 SSH
+        let legacy: SshConfig =
+            toml::from_str("name = \"old\"\nhost = \"h\"\nport = 22\nusername = \"u\"").unwrap();
+        assert_eq!(legacy.protocol, Protocol::Ssh);
+    }
+
     #[test]
     fn test_export_config_safe() {
         let manager = get_test_manager();
@@ -372,6 +397,236 @@ mod ssh_tests {
         let auth = AuthMethod::Password("testpass".to_string());
         let _result = session.connect(&auth);
     }
+
+    // Manual Test: SFTP throughput benchmark with simulated latency
+    // Uses the same testuser/testpass account as above. To simulate a long-haul link:
+    //   sudo tc qdisc add dev lo root netem delay 50ms   (100 ms RTT)
+    //   cargo test test_sftp_chunk_size_benchmark -- --ignored --nocapture
+    //   sudo tc qdisc del dev lo root
+    #[test]
+    #[ignore]
+    fn test_sftp_chunk_size_benchmark() {
+        use std::time::Instant;
+
+        let session = SshSession::new("localhost".to_string(), 22, "testuser".to_string());
+        session
+            .connect(&AuthMethod::Password("testpass".to_string()))
+            .expect("SSH server with testuser/testpass required");
+
+        let size = 8 * 1024 * 1024;
+        let dir = std::env::temp_dir().join(format!("ishell_sftp_bench_{}", rand::random::<u32>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("source.bin");
+        let data: Vec<u8> = (0..size).map(|_| rand::random::<u8>()).collect();
+        std::fs::write(&source, &data).unwrap();
+        let remote = format!("/tmp/ishell_sftp_bench_{}.bin", rand::random::<u32>());
+
+        let mut results = Vec::new();
+        for chunk_size in [8 * 1024, 256 * 1024, 4 * 1024 * 1024] {
+            let sftp = session.sftp().unwrap().with_chunk_size(chunk_size);
+
+            let started = Instant::now();
+            sftp.upload_file(&source, &remote, |_| {}).unwrap();
+            let upload = size as f64 / started.elapsed().as_secs_f64() / 1e6;
+
+            let target = dir.join(format!("download_{}.bin", chunk_size));
+            let started = Instant::now();
+            sftp.download_file(&remote, &target, |_| {}).unwrap();
+            let download = size as f64 / started.elapsed().as_secs_f64() / 1e6;
+            assert_eq!(std::fs::read(&target).unwrap(), data);
+
+            println!(
+                "chunk {:>5} KB: upload {:>7.2} MB/s, download {:>7.2} MB/s",
+                chunk_size / 1024,
+                upload,
+                download
+            );
+            results.push(upload + download);
+        }
+
+        let _ = session.sftp().unwrap().delete(&remote);
+        let _ = std::fs::remove_dir_all(&dir);
+        // 大块的在途请求更多，延迟越高差距越明显
+        assert!(results[2] > results[0], "large chunks should be faster: {:?}", results);
+    }
+
+    // Manual Test: atomic save-back used by remote editing
+    // Run: cargo test test_sftp_upload_atomic -- --ignored
+    #[test]
+    #[ignore]
+    fn test_sftp_upload_atomic() {
+        let session = SshSession::new("localhost".to_string(), 22, "testuser".to_string());
+        session
+            .connect(&AuthMethod::Password("testpass".to_string()))
+            .expect("SSH server with testuser/testpass required");
+        let sftp = session.sftp().unwrap();
+
+        let dir = format!("/tmp/ishell_atomic_{}", rand::random::<u32>());
+        sftp.create_dir(&dir).unwrap();
+        let remote = format!("{}/app.conf", dir);
+        let local = std::env::temp_dir().join(format!("ishell_atomic_{}.conf", rand::random::<u32>()));
+
+        std::fs::write(&local, "v1\n").unwrap();
+        sftp.upload_file(&local, &remote, |_| {}).unwrap();
+        sftp.chmod(&remote, 0o640).unwrap();
+        // OpenSSH 的 rename 不覆盖已有文件，最后一步由 mv -f 完成
+        std::fs::write(&local, "v2, a bit longer\n").unwrap();
+        sftp.upload_atomic(&session, &local, &remote).unwrap();
+
+        let stat = sftp.stat(&remote).unwrap();
+        assert_eq!(stat.size, 17);
+        assert_eq!(stat.permissions.as_deref().map(|p| &p[p.len() - 3..]), Some("640"));
+        let names: Vec<_> = sftp.list_dir(&dir).unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, vec!["app.conf"], "temporary files must be cleaned up");
+
+        // 通过符号链接保存时替换链接指向的文件，链接保留
+        let link = format!("{}/current.conf", dir);
+        sftp.symlink(&remote, &link).unwrap();
+        std::fs::write(&local, "v3\n").unwrap();
+        sftp.upload_atomic(&session, &local, &link).unwrap();
+        assert_eq!(sftp.readlink(&link).unwrap(), remote);
+        assert_eq!(sftp.stat(&remote).unwrap().size, 3);
+        let _ = sftp.delete(&link);
+
+        // 远程尚不存在的文件只允许属主访问
+        let fresh = format!("{}/new.conf", dir);
+        sftp.upload_atomic(&session, &local, &fresh).unwrap();
+        let stat = sftp.stat(&fresh).unwrap();
+        assert_eq!(stat.permissions.as_deref().map(|p| &p[p.len() - 3..]), Some("600"));
+        let _ = sftp.delete(&fresh);
+
+        let _ = sftp.delete(&dir);
+        let _ = std::fs::remove_file(&local);
+    }
+
+    // Run: cargo test test_sftp_read_range -- --ignored
+    #[test]
+    #[ignore]
+    fn test_sftp_read_range() {
+        let session = SshSession::new("localhost".to_string(), 22, "testuser".to_string());
+        session
+            .connect(&AuthMethod::Password("testpass".to_string()))
+            .expect("SSH server with testuser/testpass required");
+        let sftp = session.sftp().unwrap();
+
+        let remote = format!("/tmp/ishell_range_{}.log", rand::random::<u32>());
+        let local = std::env::temp_dir().join(format!("ishell_range_{}.log", rand::random::<u32>()));
+        std::fs::write(&local, "0123456789").unwrap();
+        sftp.upload_file(&local, &remote, |_| {}).unwrap();
+
+        assert_eq!(sftp.read_range(&remote, 3, 4).unwrap(), (b"3456".to_vec(), 10));
+        // 读到末尾时返回剩余部分
+        assert_eq!(sftp.read_range(&remote, 8, 4).unwrap(), (b"89".to_vec(), 10));
+
+        let _ = sftp.delete(&remote);
+        let _ = std::fs::remove_file(&local);
+    }
+
+    // Run: cargo test test_transfer_download_dir_symlink -- --ignored
+    #[test]
+    #[ignore]
+    fn test_transfer_download_dir_symlink() {
+        use ishell::transfer::{spawn_worker, TransferManager, TransferUpdate};
+        use std::sync::{Arc, Mutex};
+
+        let session = SshSession::new("localhost".to_string(), 22, "testuser".to_string());
+        session
+            .connect(&AuthMethod::Password("testpass".to_string()))
+            .expect("SSH server with testuser/testpass required");
+        let sftp = session.sftp().unwrap();
+
+        let dir = format!("/tmp/ishell_dirlink_{}", rand::random::<u32>());
+        let data = format!("{}/data", dir);
+        sftp.create_dir(&dir).unwrap();
+        sftp.create_dir(&data).unwrap();
+        let local = std::env::temp_dir().join(format!("ishell_dirlink_{}.txt", rand::random::<u32>()));
+        std::fs::write(&local, "hello").unwrap();
+        sftp.upload_file(&local, &format!("{}/a.txt", data), |_| {}).unwrap();
+        let link = format!("{}/link", dir);
+        sftp.symlink(&data, &link).unwrap();
+
+        // 未解析的链接以文件入队，工作线程发现指向目录后递归下载
+        let target = std::env::temp_dir().join(format!("ishell_dirlink_{}", rand::random::<u32>()));
+        let mut manager = TransferManager::default();
+        let id = manager.enqueue(
+            "local",
+            TransferDirection::Download,
+            false,
+            target.clone(),
+            link.clone(),
+            TransferOptions::default(),
+        );
+        let (item, control) = manager.start(id).unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        spawn_worker(item, Arc::new(Mutex::new(session)), control, 32 * 1024, false, tx);
+        loop {
+            match rx.blocking_recv().unwrap() {
+                SftpMessage::Transfer(TransferUpdate::Finished { .. }) => break,
+                SftpMessage::Transfer(TransferUpdate::Failed { error, .. }) => panic!("{}", error),
+                _ => {}
+            }
+        }
+        assert_eq!(std::fs::read_to_string(target.join("a.txt")).unwrap(), "hello");
+
+        let _ = sftp.delete(&dir);
+        let _ = std::fs::remove_dir_all(&target);
+        let _ = std::fs::remove_file(&local);
+    }
+
+    // Run: cargo test test_sftp_file_management -- --ignored
+    #[test]
+    #[ignore]
+    fn test_sftp_file_management() {
+        use ishell::file_ops::{FileOp, IdNames};
+
+        let session = SshSession::new("localhost".to_string(), 22, "testuser".to_string());
+        session
+            .connect(&AuthMethod::Password("testpass".to_string()))
+            .expect("SSH server with testuser/testpass required");
+        let sftp = session.sftp().unwrap();
+
+        let dir = format!("/tmp/ishell_ops_{}", rand::random::<u32>());
+        sftp.create_dir(&dir).unwrap();
+        let local = std::env::temp_dir().join(format!("ishell_ops_{}.txt", rand::random::<u32>()));
+        std::fs::write(&local, "hello").unwrap();
+        let file = format!("{}/a.txt", dir);
+        sftp.upload_file(&local, &file, |_| {}).unwrap();
+
+        let moved = format!("{}/b.txt", dir);
+        FileOp::Rename { from: file.clone(), to: moved.clone() }.run(&session).unwrap();
+        assert!(sftp.stat(&file).is_err());
+
+        let copy = format!("{}/c.txt", dir);
+        FileOp::Copy { from: moved.clone(), to: copy.clone() }.run(&session).unwrap();
+        assert_eq!(sftp.stat(&copy).unwrap().size, 5);
+
+        sftp.chmod(&copy, 0o640).unwrap();
+        assert_eq!(sftp.stat(&copy).unwrap().permissions.as_deref(), Some("100640"));
+
+        let link = format!("{}/link", dir);
+        sftp.symlink(&moved, &link).unwrap();
+        assert_eq!(sftp.readlink(&link).unwrap(), moved);
+        let dir_link = format!("{}/dir_link", dir);
+        sftp.symlink("/tmp", &dir_link).unwrap();
+
+        // 列表保留链接自身的类型，目标按需解析
+        let entries = sftp.list_dir(&dir).unwrap();
+        let find = |name: &str| entries.iter().find(|e| e.name == name).unwrap();
+        assert_eq!(find("link").kind, FileKind::Symlink);
+        assert_eq!(find("link").link_target, None);
+        assert_eq!(sftp.resolve_link(&link).unwrap(), (moved.clone(), false));
+        assert_eq!(sftp.resolve_link(&dir_link).unwrap(), ("/tmp".to_string(), true));
+        assert_eq!(find("c.txt").mode_string().as_deref(), Some("-rw-r-----"));
+        assert!(find("c.txt").uid.is_some() && find("c.txt").accessed.is_some());
+
+        let names = IdNames::fetch(&session).unwrap();
+        let uid = names.resolve_user("testuser").unwrap();
+        // 改为自己的 uid 总是允许的
+        sftp.chown(&copy, Some(uid), None).unwrap();
+
+        let _ = sftp.delete(&dir);
+        let _ = std::fs::remove_file(&local);
+    }
 }
 
 #[cfg(test)]